@@ -115,21 +115,16 @@ impl Context {
         self.with_timeout(Duration::from_millis(ms))
     }
 
+    /// 设置截止时间（与已有截止时间取较早者）
+    ///
+    /// 只记录截止时间、不启动定时任务：`is_cancelled` / `cancelled` / `run` 在使用时比较当前时间，
+    /// 因此每请求设置截止时间没有额外的 tokio 任务开销。
     pub fn with_deadline(&self, deadline: Instant) -> Self {
         let child = self.child();
         let effective = match child.inner.deadline {
             Some(d) if d < deadline => d,
             _ => deadline,
         };
-        if effective <= Instant::now() {
-            child.inner.cancel_token.cancel();
-        } else {
-            let token = child.inner.cancel_token.clone();
-            tokio::spawn(async move {
-                sleep_until(TokioInstant::from_std(effective)).await;
-                token.cancel();
-            });
-        }
         let mut inner = (*child.inner).clone();
         inner.deadline = Some(effective);
         Self {
@@ -245,18 +240,50 @@ impl Context {
     pub fn audit(&self) -> Option<&super::AuditContext> {
         self.get_data::<super::AuditContext>()
     }
+    /// 设置 W3C 追踪上下文，同时将 trace_id 对齐为 W3C trace-id
+    ///
+    /// 原 trace_id 去掉 `-` 后与 W3C trace-id 相同（UUID 形式）时保持不变。
+    pub fn with_trace_context(&self, trace: super::TraceContext) -> Self {
+        let same = self
+            .trace_id()
+            .replace('-', "")
+            .eq_ignore_ascii_case(trace.trace_id());
+        let ctx = if same {
+            self.clone()
+        } else {
+            self.with_trace_id(trace.trace_id())
+        };
+        ctx.insert_data(trace)
+    }
+    pub fn trace_context(&self) -> Option<&super::TraceContext> {
+        self.get_data::<super::TraceContext>()
+    }
 
     pub fn cancel(&self) {
         self.inner.cancel_token.cancel();
         debug!(request_id = %self.inner.request_id, "Context cancelled");
     }
 
+    /// 已被取消或已过截止时间
     pub fn is_cancelled(&self) -> bool {
-        self.inner.cancel_token.is_cancelled()
+        self.inner.cancel_token.is_cancelled() || self.deadline_passed()
     }
 
+    /// 等待取消或截止时间到达
     pub async fn cancelled(&self) {
-        self.inner.cancel_token.cancelled().await;
+        match self.inner.deadline {
+            Some(deadline) => {
+                tokio::select! {
+                    _ = self.inner.cancel_token.cancelled() => {}
+                    _ = sleep_until(TokioInstant::from_std(deadline)) => {}
+                }
+            }
+            None => self.inner.cancel_token.cancelled().await,
+        }
+    }
+
+    fn deadline_passed(&self) -> bool {
+        self.inner.deadline.is_some_and(|d| d <= Instant::now())
     }
 
     pub async fn run<F, T>(&self, fut: F) -> Option<T>
@@ -412,6 +439,20 @@ mod tests {
         assert!(ctx.is_cancelled());
     }
 
+    #[tokio::test]
+    async fn test_deadline_is_checked_lazily() {
+        let ctx = Context::root().with_timeout(Duration::from_millis(20));
+        let child = ctx.child().with_user_id("u1");
+        assert!(!child.is_cancelled());
+
+        tokio::time::timeout(Duration::from_secs(1), child.cancelled())
+            .await
+            .expect("cancelled() resolves at the deadline");
+        assert!(child.is_cancelled());
+        assert!(ctx.is_cancelled());
+        assert_eq!(ctx.remaining_time(), None);
+    }
+
     #[tokio::test]
     async fn test_cancelled_future() {
         let ctx = Context::root();
@@ -47,6 +47,15 @@ pub mod keys {
 
     /// 重试次数
     pub const RETRY_COUNT: &str = "x-retry-count";
+
+    /// W3C Trace Context：traceparent
+    pub const TRACEPARENT: &str = "traceparent";
+
+    /// W3C Trace Context：tracestate
+    pub const TRACESTATE: &str = "tracestate";
+
+    /// W3C Baggage
+    pub const BAGGAGE: &str = "baggage";
 }
 
 // -----------------------------------------------------------------------------
@@ -208,6 +217,87 @@ impl PartialEq for ActorContext {
 }
 impl Eq for ActorContext {}
 
+// -----------------------------------------------------------------------------
+// TraceContext（W3C Trace Context + Baggage）
+// -----------------------------------------------------------------------------
+
+/// 分布式追踪上下文（与 W3C Trace Context / Baggage 对齐）
+///
+/// `span_id` 为调用方（父）span；`Context::trace_id` 与 `trace_id` 保持一致。
+/// 编解码见 `utils::trace_context_from_headers` / `utils::trace_context_to_headers`。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceContext {
+    /// 32 位小写十六进制
+    pub trace_id: Arc<str>,
+    /// 16 位小写十六进制
+    pub span_id: Arc<str>,
+    pub sampled: bool,
+    /// tracestate 原文（厂商扩展，原样透传）
+    pub trace_state: Option<Arc<str>>,
+    /// baggage 键值对（保持原始顺序）
+    pub baggage: Arc<[(Arc<str>, Arc<str>)]>,
+}
+
+impl TraceContext {
+    pub fn new(trace_id: impl AsRef<str>, span_id: impl AsRef<str>, sampled: bool) -> Self {
+        Self {
+            trace_id: Arc::from(trace_id.as_ref()),
+            span_id: Arc::from(span_id.as_ref()),
+            sampled,
+            trace_state: None,
+            baggage: Arc::from([]),
+        }
+    }
+
+    pub fn with_trace_state(mut self, trace_state: impl AsRef<str>) -> Self {
+        let state = trace_state.as_ref().trim();
+        self.trace_state = (!state.is_empty()).then(|| Arc::from(state));
+        self
+    }
+
+    /// 设置 baggage 项（同名覆盖）
+    pub fn with_baggage(mut self, key: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        let key = key.as_ref();
+        let mut v: Vec<(Arc<str>, Arc<str>)> = self
+            .baggage
+            .iter()
+            .filter(|(k, _)| k.as_ref() != key)
+            .cloned()
+            .collect();
+        v.push((Arc::from(key), Arc::from(value.as_ref())));
+        self.baggage = Arc::from(v);
+        self
+    }
+
+    /// 替换调用方 span（发起下游调用前改为当前 span）
+    pub fn with_span_id(mut self, span_id: impl AsRef<str>) -> Self {
+        self.span_id = Arc::from(span_id.as_ref());
+        self
+    }
+
+    #[inline]
+    pub fn trace_id(&self) -> &str {
+        &self.trace_id
+    }
+
+    #[inline]
+    pub fn span_id(&self) -> &str {
+        &self.span_id
+    }
+
+    pub fn baggage_item(&self, key: &str) -> Option<&str> {
+        self.baggage
+            .iter()
+            .find(|(k, _)| k.as_ref() == key)
+            .map(|(_, v)| v.as_ref())
+    }
+
+    #[inline]
+    pub fn baggage(&self) -> &[(Arc<str>, Arc<str>)] {
+        &self.baggage
+    }
+}
+
 // -----------------------------------------------------------------------------
 // AuditContext（敏感操作记录）
 // -----------------------------------------------------------------------------
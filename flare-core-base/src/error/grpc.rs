@@ -116,17 +116,17 @@ fn map_error_code_to_grpc(code: ErrorCode) -> Code {
 impl From<FlareError> for Status {
     fn from(err: FlareError) -> Self {
         #[cfg(feature = "proto")]
-        {
+        let status = {
             let (code, track) = match &err {
                 FlareError::Localized { code, .. } => (map_error_code_to_grpc(*code), ""),
                 FlareError::System(_) => (Code::Internal, ""),
                 FlareError::Io(_) => (Code::Unavailable, ""),
             };
-            return build_status(code, to_error_detail(&err, track));
-        }
+            build_status(code, to_error_detail(&err, track))
+        };
 
         #[cfg(not(feature = "proto"))]
-        match err {
+        let status = match err {
             FlareError::Localized {
                 code,
                 reason,
@@ -138,7 +138,9 @@ impl From<FlareError> for Status {
             }
             FlareError::System(msg) => Status::internal(msg),
             FlareError::Io(msg) => Status::unavailable(msg),
-        }
+        };
+
+        status
     }
 }
 
@@ -27,7 +27,7 @@ pub mod utils;
 // Re-exports - Context
 pub use context::{
     ActorContext, ActorType, AuditContext, Context, ContextError, ContextExt, Ctx, ExtendedContext,
-    TaskControl, TypeMap,
+    TaskControl, TraceContext, TypeMap,
 };
 
 // Re-exports - Error
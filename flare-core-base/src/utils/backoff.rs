@@ -0,0 +1,37 @@
+//! 退避抖动 (重试、重投共用)
+
+use std::time::Duration;
+
+use rand::Rng;
+
+/// 对退避时间施加向下抖动：结果在 `[delay * (1 - jitter), delay]` 内均匀分布
+///
+/// `jitter` 会被限制在 `0.0..=1.0`；为 0 或 `delay` 为零时原样返回。
+pub fn jittered_backoff(delay: Duration, jitter: f64) -> Duration {
+    let jitter = jitter.clamp(0.0, 1.0);
+    if jitter <= 0.0 || delay.is_zero() {
+        return delay;
+    }
+    let factor = 1.0 - jitter * rand::thread_rng().gen_range(0.0..1.0);
+    delay.mul_f64(factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stays_within_jitter_window() {
+        let delay = Duration::from_millis(1000);
+        assert_eq!(jittered_backoff(delay, 0.0), delay);
+        assert_eq!(jittered_backoff(Duration::ZERO, 0.5), Duration::ZERO);
+        for _ in 0..100 {
+            let jittered = jittered_backoff(delay, 0.25);
+            assert!(jittered > Duration::from_millis(750) && jittered <= delay);
+        }
+        // 超出范围的比例按 1.0 处理
+        for _ in 0..100 {
+            assert!(jittered_backoff(delay, 4.0) <= delay);
+        }
+    }
+}
@@ -1,6 +1,6 @@
 //! Context 工具函数 (基础,不依赖 gRPC)
 
-use crate::context::{ActorContext, ActorType, Context, Ctx, keys};
+use crate::context::{ActorContext, ActorType, Context, Ctx, TraceContext, keys};
 use std::collections::HashMap;
 
 // -----------------------------------------------------------------------------
@@ -87,6 +87,10 @@ pub fn ctx_to_map(ctx: &Ctx) -> HashMap<String, String> {
         map.insert(keys::SESSION_ID.to_string(), s.to_string());
     }
 
+    for (key, value) in trace_context_to_headers(ctx) {
+        map.insert(key.to_string(), value);
+    }
+
     // Actor 信息
     if let Some(actor) = ctx.actor() {
         if !actor.actor_id().is_empty() {
@@ -147,6 +151,10 @@ pub fn map_to_ctx(map: &HashMap<String, String>) -> Ctx {
         ctx = ctx.with_session_id(v.as_str());
     }
 
+    if let Some(trace) = trace_context_from_headers(|key| map.get(key).map(String::as_str)) {
+        ctx = ctx.with_trace_context(trace);
+    }
+
     // Actor 信息
     if let Some(actor) = decode_actor_from_map(map) {
         ctx = ctx.with_actor(actor);
@@ -190,3 +198,271 @@ fn decode_actor_from_map(map: &HashMap<String, String>) -> Option<ActorContext>
 
     Some(actor)
 }
+
+// -----------------------------------------------------------------------------
+// W3C Trace Context / Baggage 编解码
+// -----------------------------------------------------------------------------
+
+/// baggage 头的最大长度（W3C 建议至少支持 8192 字节）
+const MAX_BAGGAGE_LEN: usize = 8192;
+
+/// 解析 `traceparent`：`{version}-{trace-id}-{parent-id}-{flags}`
+///
+/// 全 0 的 trace-id / parent-id 以及版本 `ff` 视为非法。
+pub fn parse_traceparent(value: &str) -> Option<TraceContext> {
+    let mut parts = value.trim().split('-');
+    let version = parts.next()?;
+    let trace_id = parts.next()?;
+    let span_id = parts.next()?;
+    let flags = parts.next()?;
+    // 版本 00 必须恰好 4 段，未来版本允许追加字段
+    if version.len() != 2 || !is_lower_hex(version) || version == "ff" {
+        return None;
+    }
+    if version == "00" && parts.next().is_some() {
+        return None;
+    }
+    if trace_id.len() != 32 || !is_lower_hex(trace_id) || is_all_zero(trace_id) {
+        return None;
+    }
+    if span_id.len() != 16 || !is_lower_hex(span_id) || is_all_zero(span_id) {
+        return None;
+    }
+    if flags.len() != 2 || !is_lower_hex(flags) {
+        return None;
+    }
+    let flags = u8::from_str_radix(flags, 16).ok()?;
+    Some(TraceContext::new(trace_id, span_id, flags & 0x01 == 0x01))
+}
+
+/// 编码 `traceparent`（版本 00）
+pub fn format_traceparent(trace: &TraceContext) -> String {
+    format!(
+        "00-{}-{}-{:02x}",
+        trace.trace_id(),
+        trace.span_id(),
+        u8::from(trace.sampled)
+    )
+}
+
+/// 解析 `baggage`：`key=value` 以逗号分隔，值做百分号解码，忽略 `;` 之后的属性
+pub fn parse_baggage(value: &str) -> Vec<(String, String)> {
+    if value.len() > MAX_BAGGAGE_LEN {
+        return Vec::new();
+    }
+    value
+        .split(',')
+        .filter_map(|member| {
+            let member = member.split(';').next()?.trim();
+            let (key, value) = member.split_once('=')?;
+            let key = key.trim();
+            if key.is_empty() {
+                return None;
+            }
+            Some((key.to_string(), percent_decode(value.trim())?))
+        })
+        .collect()
+}
+
+/// 编码 `baggage`，值中的非安全字符做百分号编码
+pub fn format_baggage(entries: &[(impl AsRef<str>, impl AsRef<str>)]) -> String {
+    entries
+        .iter()
+        .map(|(k, v)| format!("{}={}", k.as_ref(), percent_encode(v.as_ref())))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// 从请求头读取 W3C Trace Context 与 Baggage
+///
+/// `get` 按键名（小写）取头部值，HTTP headers、gRPC metadata、MQ headers 均可适配。
+/// 没有合法的 `traceparent` 时返回 None（tracestate / baggage 依附于 traceparent）。
+pub fn trace_context_from_headers<'a>(
+    get: impl Fn(&str) -> Option<&'a str>,
+) -> Option<TraceContext> {
+    let mut trace = parse_traceparent(get(keys::TRACEPARENT)?)?;
+    if let Some(state) = get(keys::TRACESTATE) {
+        trace = trace.with_trace_state(state);
+    }
+    if let Some(baggage) = get(keys::BAGGAGE) {
+        for (key, value) in parse_baggage(baggage) {
+            trace = trace.with_baggage(key, value);
+        }
+    }
+    Some(trace)
+}
+
+/// 生成下游调用需要的 W3C 头部（traceparent / tracestate / baggage）
+///
+/// 只在 Context 带 [`TraceContext`] 时输出：仅有 trace_id 时无法得知上游的采样决定与父 span，
+/// 不凭空生成 traceparent，trace_id 仍由 `x-trace-id` 传递。
+pub fn trace_context_to_headers(ctx: &Context) -> Vec<(&'static str, String)> {
+    let Some(trace) = ctx.trace_context() else {
+        return Vec::new();
+    };
+
+    let mut headers = vec![(keys::TRACEPARENT, format_traceparent(trace))];
+    if let Some(state) = &trace.trace_state {
+        headers.push((keys::TRACESTATE, state.to_string()));
+    }
+    if !trace.baggage().is_empty() {
+        headers.push((keys::BAGGAGE, format_baggage(trace.baggage())));
+    }
+    headers
+}
+
+/// 将 trace_id 转为 W3C trace-id（32 位小写十六进制，允许 UUID 的 `-`）
+pub fn w3c_trace_id(trace_id: &str) -> Option<String> {
+    let hex: String = trace_id
+        .chars()
+        .filter(|c| *c != '-')
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    (hex.len() == 32 && is_lower_hex(&hex) && !is_all_zero(&hex)).then_some(hex)
+}
+
+/// 生成 16 位十六进制 span id
+pub fn new_span_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..16].to_string()
+}
+
+fn is_lower_hex(s: &str) -> bool {
+    s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn is_all_zero(s: &str) -> bool {
+    s.bytes().all(|b| b == b'0')
+}
+
+fn percent_encode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for b in value.bytes() {
+        match b {
+            // baggage-octet 允许的可见字符，排除分隔符 `,` `;` `\` `"` 与 `%`
+            0x21 | 0x23..=0x24 | 0x26..=0x2B | 0x2D..=0x3A | 0x3C..=0x5B | 0x5D..=0x7E => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value.get(i + 1..i + 3)?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn traceparent_roundtrip() {
+        let trace = parse_traceparent(TRACEPARENT).expect("valid traceparent");
+        assert_eq!(trace.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(trace.span_id(), "00f067aa0ba902b7");
+        assert!(trace.sampled);
+        assert_eq!(format_traceparent(&trace), TRACEPARENT);
+    }
+
+    #[test]
+    fn rejects_invalid_traceparent() {
+        for value in [
+            "",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+        ] {
+            assert!(parse_traceparent(value).is_none(), "{value}");
+        }
+        // 未来版本允许追加字段
+        assert!(
+            parse_traceparent("01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-x")
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn baggage_roundtrip_with_percent_encoding() {
+        let entries = parse_baggage("userId=alice, region=us%20east;ttl=60,bad");
+        assert_eq!(
+            entries,
+            vec![
+                ("userId".to_string(), "alice".to_string()),
+                ("region".to_string(), "us east".to_string()),
+            ]
+        );
+        assert_eq!(format_baggage(&entries), "userId=alice,region=us%20east");
+    }
+
+    #[test]
+    fn map_roundtrip_keeps_trace_context() {
+        let mut map = HashMap::new();
+        map.insert(keys::TRACEPARENT.to_string(), TRACEPARENT.to_string());
+        map.insert(keys::TRACESTATE.to_string(), "vendor=abc".to_string());
+        map.insert(keys::BAGGAGE.to_string(), "tenant=t1".to_string());
+
+        let ctx = map_to_ctx(&map);
+        assert_eq!(ctx.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        let trace = ctx.trace_context().expect("trace context");
+        assert_eq!(trace.trace_state.as_deref(), Some("vendor=abc"));
+        assert_eq!(trace.baggage_item("tenant"), Some("t1"));
+
+        let encoded = ctx_to_map(&ctx);
+        assert_eq!(
+            encoded.get(keys::TRACEPARENT).map(String::as_str),
+            Some(TRACEPARENT)
+        );
+        assert_eq!(
+            encoded.get(keys::BAGGAGE).map(String::as_str),
+            Some("tenant=t1")
+        );
+    }
+
+    #[test]
+    fn trace_id_without_trace_context_writes_no_traceparent() {
+        let ctx = Context::with_request_id("req").with_trace_id("4bf92f3577b34da6a3ce929d0e0e4736");
+        assert!(trace_context_to_headers(&ctx).is_empty());
+        assert_eq!(
+            ctx_to_map(&Arc::new(ctx))
+                .get(keys::TRACE_ID)
+                .map(String::as_str),
+            Some("4bf92f3577b34da6a3ce929d0e0e4736")
+        );
+    }
+
+    #[test]
+    fn uuid_trace_context_keeps_original_trace_id() {
+        let ctx =
+            Context::with_request_id("req").with_trace_id("4bf92f35-77b3-4da6-a3ce-929d0e0e4736");
+        let trace = parse_traceparent(TRACEPARENT).expect("valid");
+
+        // 回到带 UUID trace_id 的 Context 时保持原 trace_id
+        let back = ctx.with_trace_context(trace);
+        assert_eq!(back.trace_id(), "4bf92f35-77b3-4da6-a3ce-929d0e0e4736");
+    }
+
+    #[test]
+    fn non_hex_trace_id_writes_no_traceparent() {
+        let ctx = Context::with_request_id("req").with_trace_id("trace-789");
+        assert!(trace_context_to_headers(&ctx).is_empty());
+    }
+}
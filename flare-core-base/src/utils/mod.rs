@@ -1,8 +1,13 @@
 //! 工具函数模块 (基础)
 
+mod backoff;
 mod context;
 
+pub use backoff::jittered_backoff;
+
 pub use context::{
-    ctx_to_map, extract_session_id_from_ctx, map_to_ctx, require_request_id_from_ctx,
-    require_tenant_id_from_ctx, require_user_id_from_ctx,
+    ctx_to_map, extract_session_id_from_ctx, format_baggage, format_traceparent, map_to_ctx,
+    new_span_id, parse_baggage, parse_traceparent, require_request_id_from_ctx,
+    require_tenant_id_from_ctx, require_user_id_from_ctx, trace_context_from_headers,
+    trace_context_to_headers, w3c_trace_id,
 };
@@ -2,11 +2,12 @@
 //!
 //! `flare-core-infra` collects reusable infrastructure helpers that are shared
 //! across services: token validation, authenticated principals, KV storage
-//! traits, metrics, and tracing subscriber setup.
+//! traits, rate-limit stores, metrics, and tracing subscriber setup.
 
 pub mod auth;
 pub mod kv;
 pub mod metrics;
+pub mod ratelimit;
 pub mod telemetry;
 
 // KV re-exports.
@@ -18,5 +19,14 @@ pub use auth::{
     TokenValidationRequest, TokenValidator, TrustedIssuer,
 };
 
+// Rate limit re-exports.
+pub use ratelimit::{
+    InMemoryRateLimitStore, RateLimitDecision, RateLimitError, RateLimitQuota, RateLimitStore,
+    RedisRateLimitStore,
+};
+
 // Telemetry re-exports.
-pub use telemetry::{LoggingSubscriberOptions, init_fmt_subscriber};
+pub use telemetry::{
+    LoggingSubscriberOptions, ctx_with_current_span, init_fmt_subscriber, set_log_filter,
+    set_span_parent_from_ctx,
+};
@@ -0,0 +1,161 @@
+//! 进程内令牌桶存储
+
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+use super::{RateLimitDecision, RateLimitError, RateLimitQuota, RateLimitStore};
+
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// 进程内令牌桶
+///
+/// 令牌以浮点数累计，不会截断不足 1 个的补充量；
+/// 空闲超过 `idle_timeout` 的桶在下一次清扫时回收，内存占用随活跃键数有界。
+#[derive(Debug)]
+pub struct InMemoryRateLimitStore {
+    idle_timeout: Duration,
+    state: Mutex<BucketsState>,
+}
+
+#[derive(Debug)]
+struct BucketsState {
+    buckets: HashMap<String, Bucket>,
+    last_sweep: Instant,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl InMemoryRateLimitStore {
+    pub fn new() -> Self {
+        Self::with_idle_timeout(DEFAULT_IDLE_TIMEOUT)
+    }
+
+    /// 指定空闲桶回收时间
+    pub fn with_idle_timeout(idle_timeout: Duration) -> Self {
+        Self {
+            idle_timeout,
+            state: Mutex::new(BucketsState {
+                buckets: HashMap::new(),
+                last_sweep: Instant::now(),
+            }),
+        }
+    }
+
+    /// 同步申请一个令牌
+    pub fn try_acquire(&self, key: &str, quota: &RateLimitQuota) -> RateLimitDecision {
+        let rate = f64::from(quota.requests_per_second);
+        let burst = f64::from(quota.burst);
+        let now = Instant::now();
+
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if now.duration_since(state.last_sweep) >= self.idle_timeout {
+            let idle_timeout = self.idle_timeout;
+            state
+                .buckets
+                .retain(|_, b| now.duration_since(b.updated_at) < idle_timeout);
+            state.last_sweep = now;
+        }
+
+        let bucket = state.buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: burst,
+            updated_at: now,
+        });
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(burst);
+        bucket.updated_at = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+        quota.decision(allowed, bucket.tokens)
+    }
+
+    /// 当前跟踪的键数量
+    pub fn len(&self) -> usize {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .buckets
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for InMemoryRateLimitStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for InMemoryRateLimitStore {
+    async fn acquire(
+        &self,
+        key: &str,
+        quota: &RateLimitQuota,
+    ) -> Result<RateLimitDecision, RateLimitError> {
+        Ok(self.try_acquire(key, quota))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enforces_burst_and_reports_retry_after() {
+        let store = InMemoryRateLimitStore::new();
+        let quota = RateLimitQuota::new(1, 2);
+
+        assert!(store.try_acquire("k", &quota).allowed);
+        let second = store.try_acquire("k", &quota);
+        assert!(second.allowed);
+        assert_eq!(second.remaining, 0);
+
+        let rejected = store.try_acquire("k", &quota);
+        assert!(!rejected.allowed);
+        assert!(rejected.retry_after > Duration::ZERO);
+        assert!(rejected.retry_after <= Duration::from_secs(1));
+        assert_eq!(rejected.limit, 2);
+
+        assert!(store.try_acquire("other", &quota).allowed);
+    }
+
+    #[test]
+    fn fractional_refill_is_not_truncated() {
+        let store = InMemoryRateLimitStore::new();
+        let quota = RateLimitQuota::new(4, 1);
+        assert!(store.try_acquire("c", &quota).allowed);
+        assert!(!store.try_acquire("c", &quota).allowed);
+
+        // 每次只补充 0.6 个令牌，累计后应可放行
+        std::thread::sleep(Duration::from_millis(150));
+        assert!(!store.try_acquire("c", &quota).allowed);
+        std::thread::sleep(Duration::from_millis(150));
+        assert!(store.try_acquire("c", &quota).allowed);
+    }
+
+    #[test]
+    fn evicts_idle_buckets() {
+        let store = InMemoryRateLimitStore::with_idle_timeout(Duration::from_millis(30));
+        let quota = RateLimitQuota::new(10, 1);
+        store.try_acquire("a", &quota);
+        store.try_acquire("b", &quota);
+        assert_eq!(store.len(), 2);
+
+        std::thread::sleep(Duration::from_millis(40));
+        store.try_acquire("c", &quota);
+        assert_eq!(store.len(), 1);
+    }
+}
@@ -0,0 +1,86 @@
+//! 限流存储抽象
+//!
+//! gRPC / HTTP 限流中间件通过 [`RateLimitStore`] 判定请求是否放行：
+//! - [`InMemoryRateLimitStore`]：进程内令牌桶，适合单实例；
+//! - [`RedisRateLimitStore`]：Redis Lua 脚本原子执行令牌桶，多实例共享同一配额，
+//!   避免 N 个网关放行 N 倍流量。
+
+pub mod memory;
+pub mod redis;
+
+pub use memory::InMemoryRateLimitStore;
+pub use redis::RedisRateLimitStore;
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+/// 令牌桶配额
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitQuota {
+    /// 每秒补充的令牌数
+    pub requests_per_second: u32,
+    /// 桶容量（允许的突发请求数）
+    pub burst: u32,
+}
+
+impl RateLimitQuota {
+    /// 创建配额，0 会被规范为 1
+    pub fn new(requests_per_second: u32, burst: u32) -> Self {
+        Self {
+            requests_per_second: requests_per_second.max(1),
+            burst: burst.max(1),
+        }
+    }
+
+    /// 按剩余令牌数（可含小数）计算判定结果
+    pub fn decision(&self, allowed: bool, tokens: f64) -> RateLimitDecision {
+        let rate = f64::from(self.requests_per_second);
+        let burst = f64::from(self.burst);
+        let tokens = tokens.clamp(0.0, burst);
+        RateLimitDecision {
+            allowed,
+            limit: self.burst,
+            remaining: tokens.floor() as u32,
+            retry_after: if allowed {
+                Duration::ZERO
+            } else {
+                Duration::from_secs_f64((1.0 - tokens).max(0.0) / rate)
+            },
+            reset_after: Duration::from_secs_f64((burst - tokens) / rate),
+        }
+    }
+}
+
+/// 一次限流判定的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitDecision {
+    /// 是否放行
+    pub allowed: bool,
+    /// 桶容量
+    pub limit: u32,
+    /// 剩余可用令牌（向下取整）
+    pub remaining: u32,
+    /// 被拒绝时距下一个令牌可用的时长（放行时为 0）
+    pub retry_after: Duration,
+    /// 距桶完全回满的时长
+    pub reset_after: Duration,
+}
+
+/// 限流存储错误
+#[derive(Debug, thiserror::Error)]
+pub enum RateLimitError {
+    #[error("rate limit backend error: {0}")]
+    Backend(String),
+}
+
+/// 限流存储 trait
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// 对 `key` 申请一个令牌
+    async fn acquire(
+        &self,
+        key: &str,
+        quota: &RateLimitQuota,
+    ) -> Result<RateLimitDecision, RateLimitError>;
+}
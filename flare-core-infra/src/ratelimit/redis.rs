@@ -0,0 +1,332 @@
+//! Redis 令牌桶存储
+//!
+//! 令牌桶状态保存在 Redis Hash 中，由 Lua 脚本原子地完成“补充 + 判定 + 扣减”，
+//! 所有实例共享同一配额。
+
+use async_trait::async_trait;
+use redis::Script;
+use redis::aio::ConnectionManager;
+
+use super::{RateLimitDecision, RateLimitError, RateLimitQuota, RateLimitStore};
+
+const DEFAULT_NAMESPACE: &str = "flare";
+
+/// 令牌桶脚本
+///
+/// - 使用 Redis 服务器时钟，实例间无需时钟同步；
+/// - 桶在回满所需时间后自动过期，空闲键不会常驻；
+/// - Lua 数字回传时会被截断为整数，因此令牌数以千分之一为单位返回。
+///
+/// KEYS[1] = 桶键，ARGV[1] = 每秒速率，ARGV[2] = 桶容量；返回 `{allowed, tokens * 1000}`。
+const TOKEN_BUCKET_SCRIPT: &str = r#"
+if redis.replicate_commands then redis.replicate_commands() end
+local rate = tonumber(ARGV[1])
+local burst = tonumber(ARGV[2])
+local t = redis.call('TIME')
+local now = tonumber(t[1]) * 1000000 + tonumber(t[2])
+local state = redis.call('HMGET', KEYS[1], 'tokens', 'ts')
+local tokens = tonumber(state[1])
+local ts = tonumber(state[2])
+if tokens == nil or ts == nil then
+  tokens = burst
+  ts = now
+end
+tokens = math.min(burst, tokens + math.max(0, now - ts) * rate / 1000000)
+local allowed = 0
+if tokens >= 1 then
+  tokens = tokens - 1
+  allowed = 1
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'ts', tostring(now))
+redis.call('PEXPIRE', KEYS[1], math.ceil((burst - tokens) / rate * 1000) + 1000)
+return {allowed, math.floor(tokens * 1000)}
+"#;
+
+/// Redis 令牌桶存储
+#[derive(Clone)]
+pub struct RedisRateLimitStore {
+    conn: ConnectionManager,
+    namespace: String,
+    script: Script,
+}
+
+impl RedisRateLimitStore {
+    pub fn new(conn: ConnectionManager) -> Self {
+        Self {
+            conn,
+            namespace: DEFAULT_NAMESPACE.to_string(),
+            script: Script::new(TOKEN_BUCKET_SCRIPT),
+        }
+    }
+
+    /// 根据 URL 建立连接
+    pub async fn from_url(url: impl AsRef<str>) -> Result<Self, RateLimitError> {
+        let client = redis::Client::open(url.as_ref()).map_err(|err| {
+            RateLimitError::Backend(format!("failed to open redis client: {err}"))
+        })?;
+        let conn = ConnectionManager::new(client)
+            .await
+            .map_err(|err| RateLimitError::Backend(format!("failed to connect redis: {err}")))?;
+        Ok(Self::new(conn))
+    }
+
+    /// 设置键前缀命名空间
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = namespace.into();
+        self
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for RedisRateLimitStore {
+    async fn acquire(
+        &self,
+        key: &str,
+        quota: &RateLimitQuota,
+    ) -> Result<RateLimitDecision, RateLimitError> {
+        let mut conn = self.conn.clone();
+        let (allowed, milli_tokens): (i64, i64) = self
+            .script
+            .key(bucket_key(&self.namespace, key))
+            .arg(quota.requests_per_second)
+            .arg(quota.burst)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|err| RateLimitError::Backend(err.to_string()))?;
+        Ok(decision_from_reply(quota, allowed, milli_tokens))
+    }
+}
+
+fn bucket_key(namespace: &str, key: &str) -> String {
+    format!("{namespace}:ratelimit:{key}")
+}
+
+fn decision_from_reply(
+    quota: &RateLimitQuota,
+    allowed: i64,
+    milli_tokens: i64,
+) -> RateLimitDecision {
+    quota.decision(allowed == 1, milli_tokens as f64 / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mlua::{Lua, Table, Value, Variadic};
+    use std::cell::{Cell, RefCell};
+    use std::collections::HashMap;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    type Hashes = HashMap<String, HashMap<String, String>>;
+
+    /// 进程内的 Redis 替身：用 Lua 5.1 执行真实的限流脚本，
+    /// `redis.call` 由内存 Hash 实现，`TIME` 读取可手动推进的时钟，`PEXPIRE` 按该时钟惰性过期。
+    struct FakeRedis {
+        lua: Lua,
+        now_micros: Rc<Cell<i64>>,
+        hashes: Rc<RefCell<Hashes>>,
+        expires_at: Rc<RefCell<HashMap<String, i64>>>,
+    }
+
+    impl FakeRedis {
+        fn new() -> Self {
+            let lua = Lua::new();
+            let now_micros = Rc::new(Cell::new(1_700_000_000_000_000));
+            let hashes = Rc::new(RefCell::new(Hashes::new()));
+            let expires_at = Rc::new(RefCell::new(HashMap::new()));
+
+            let call = {
+                let (now_micros, hashes, expires_at) =
+                    (now_micros.clone(), hashes.clone(), expires_at.clone());
+                lua.create_function(move |lua, args: Variadic<String>| {
+                    let now = now_micros.get();
+                    if let Some(key) = args.get(1)
+                        && expires_at.borrow().get(key).is_some_and(|at| *at <= now)
+                    {
+                        expires_at.borrow_mut().remove(key);
+                        hashes.borrow_mut().remove(key);
+                    }
+                    match args[0].to_ascii_uppercase().as_str() {
+                        "TIME" => {
+                            let reply =
+                                [(now / 1_000_000).to_string(), (now % 1_000_000).to_string()];
+                            Ok(Value::Table(lua.create_sequence_from(reply)?))
+                        }
+                        "HMGET" => {
+                            let hashes = hashes.borrow();
+                            let hash = hashes.get(&args[1]);
+                            let reply = lua.create_table()?;
+                            for (i, field) in args[2..].iter().enumerate() {
+                                // Redis 把 nil 回复转换为 Lua 的 false
+                                match hash.and_then(|h| h.get(field)) {
+                                    Some(value) => reply.set(i + 1, value.as_str())?,
+                                    None => reply.set(i + 1, false)?,
+                                }
+                            }
+                            Ok(Value::Table(reply))
+                        }
+                        "HSET" => {
+                            let mut hashes = hashes.borrow_mut();
+                            let hash = hashes.entry(args[1].clone()).or_default();
+                            for pair in args[2..].chunks(2) {
+                                hash.insert(pair[0].clone(), pair[1].clone());
+                            }
+                            Ok(Value::Integer(0))
+                        }
+                        "PEXPIRE" => {
+                            let ttl_ms: i64 = args[2].parse().map_err(mlua::Error::external)?;
+                            expires_at
+                                .borrow_mut()
+                                .insert(args[1].clone(), now + ttl_ms * 1_000);
+                            Ok(Value::Integer(1))
+                        }
+                        other => Err(mlua::Error::RuntimeError(format!(
+                            "unsupported command {other}"
+                        ))),
+                    }
+                })
+                .unwrap()
+            };
+            let redis = lua.create_table().unwrap();
+            redis.set("call", call).unwrap();
+            lua.globals().set("redis", redis).unwrap();
+
+            Self {
+                lua,
+                now_micros,
+                hashes,
+                expires_at,
+            }
+        }
+
+        fn advance(&self, d: Duration) {
+            self.now_micros
+                .set(self.now_micros.get() + d.as_micros() as i64);
+        }
+
+        /// 与 `RedisRateLimitStore::acquire` 相同的键与参数执行脚本
+        fn acquire(&self, key: &str, quota: &RateLimitQuota) -> RateLimitDecision {
+            let lua = &self.lua;
+            let keys = [bucket_key(DEFAULT_NAMESPACE, key)];
+            let argv = [
+                quota.requests_per_second.to_string(),
+                quota.burst.to_string(),
+            ];
+            lua.globals()
+                .set("KEYS", lua.create_sequence_from(keys).unwrap())
+                .unwrap();
+            lua.globals()
+                .set("ARGV", lua.create_sequence_from(argv).unwrap())
+                .unwrap();
+            let reply: Table = lua.load(TOKEN_BUCKET_SCRIPT).eval().unwrap();
+            // Redis 把 Lua 数字截断为整数回复
+            let allowed = reply.get::<_, f64>(1).unwrap() as i64;
+            let milli_tokens = reply.get::<_, f64>(2).unwrap() as i64;
+            decision_from_reply(quota, allowed, milli_tokens)
+        }
+
+        fn ttl(&self, key: &str) -> Option<Duration> {
+            let at = *self
+                .expires_at
+                .borrow()
+                .get(&bucket_key(DEFAULT_NAMESPACE, key))?;
+            Some(Duration::from_micros((at - self.now_micros.get()) as u64))
+        }
+
+        /// 桶是否仍存在（已到期的视为不存在）
+        fn contains(&self, key: &str) -> bool {
+            let key = bucket_key(DEFAULT_NAMESPACE, key);
+            let expired = self
+                .expires_at
+                .borrow()
+                .get(&key)
+                .is_some_and(|at| *at <= self.now_micros.get());
+            !expired && self.hashes.borrow().contains_key(&key)
+        }
+    }
+
+    #[test]
+    fn script_allows_burst_then_rejects() {
+        let redis = FakeRedis::new();
+        let quota = RateLimitQuota::new(1, 3);
+
+        for remaining in [2, 1, 0] {
+            let decision = redis.acquire("a", &quota);
+            assert!(decision.allowed);
+            assert_eq!(decision.remaining, remaining);
+        }
+        let rejected = redis.acquire("a", &quota);
+        assert!(!rejected.allowed);
+        assert_eq!(rejected.retry_after, Duration::from_secs(1));
+        assert_eq!(rejected.reset_after, Duration::from_secs(3));
+    }
+
+    #[test]
+    fn script_refills_fractional_tokens() {
+        let redis = FakeRedis::new();
+        let quota = RateLimitQuota::new(4, 1);
+        assert!(redis.acquire("a", &quota).allowed);
+        assert!(!redis.acquire("a", &quota).allowed);
+
+        // 每次只补充 0.6 个令牌，累计后应可放行
+        redis.advance(Duration::from_millis(150));
+        let partial = redis.acquire("a", &quota);
+        assert!(!partial.allowed);
+        assert_eq!(partial.retry_after, Duration::from_millis(100));
+        redis.advance(Duration::from_millis(150));
+        assert!(redis.acquire("a", &quota).allowed);
+
+        // 补充量不超过桶容量
+        redis.advance(Duration::from_secs(60));
+        assert!(redis.acquire("a", &quota).allowed);
+        assert!(!redis.acquire("a", &quota).allowed);
+    }
+
+    #[test]
+    fn script_isolates_keys() {
+        let redis = FakeRedis::new();
+        let quota = RateLimitQuota::new(1, 1);
+        assert!(redis.acquire("tenant:a", &quota).allowed);
+        assert!(!redis.acquire("tenant:a", &quota).allowed);
+
+        assert!(redis.acquire("tenant:b", &quota).allowed);
+        assert!(!redis.acquire("tenant:a", &quota).allowed);
+    }
+
+    #[test]
+    fn script_expires_bucket_once_refilled() {
+        let redis = FakeRedis::new();
+        let quota = RateLimitQuota::new(2, 4);
+        assert!(redis.acquire("a", &quota).allowed);
+        // 补回 1 个令牌需 500ms，另加 1s 余量
+        assert_eq!(redis.ttl("a"), Some(Duration::from_millis(1_500)));
+
+        for _ in 0..3 {
+            assert!(redis.acquire("a", &quota).allowed);
+        }
+        assert_eq!(redis.ttl("a"), Some(Duration::from_secs(3)));
+
+        redis.advance(Duration::from_millis(2_999));
+        assert!(redis.contains("a"));
+        redis.advance(Duration::from_millis(1));
+        assert!(!redis.contains("a"));
+        assert_eq!(redis.acquire("a", &quota).remaining, 3);
+    }
+
+    #[test]
+    fn decodes_script_reply() {
+        let quota = RateLimitQuota::new(2, 5);
+
+        let allowed = decision_from_reply(&quota, 1, 3_500);
+        assert!(allowed.allowed);
+        assert_eq!(allowed.remaining, 3);
+        assert_eq!(allowed.retry_after, Duration::ZERO);
+        assert_eq!(allowed.reset_after, Duration::from_millis(750));
+
+        let rejected = decision_from_reply(&quota, 0, 500);
+        assert!(!rejected.allowed);
+        assert_eq!(rejected.remaining, 0);
+        assert_eq!(rejected.retry_after, Duration::from_millis(250));
+    }
+}
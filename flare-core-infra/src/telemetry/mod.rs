@@ -1,13 +1,24 @@
 //! 进程级日志与 trace 订阅器初始化（`tracing-subscriber` + `tracing-log` + 可选 OTLP），与具体业务配置解耦。
 //!
 //! IM 等上层可把 TOML 中的日志字段映射为 [LoggingSubscriberOptions] 后调用 [init_fmt_subscriber]；
-//! 需要端到端 trace 时调用 [init_tracing_subscriber] 并传入 [OtlpTracingOptions]。
+//! 需要端到端 trace 时调用 [init_tracing_subscriber] 并传入 [OtlpTracingOptions]；
+//! 跨服务的 W3C `traceparent` 通过 [set_span_parent_from_ctx] / [ctx_with_current_span] 接入 OTel span。
+//! 由本模块安装的订阅器可在运行期间通过 [set_log_filter] 替换过滤指令（如运维端点调整日志级别）。
+
+mod propagation;
+
+pub use propagation::{ctx_with_current_span, set_span_parent_from_ctx};
 
 use std::error::Error;
+use std::sync::OnceLock;
 
-use tracing_subscriber::{EnvFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt};
+use tracing_subscriber::{EnvFilter, Registry, fmt, layer::SubscriberExt, reload};
 
 type TelemetryInitResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+type FilterLayer = reload::Layer<EnvFilter, Registry>;
+
+/// 全局订阅器的过滤层句柄；仅当订阅器由本模块安装时存在
+static LOG_FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
 
 /// 与典型 YAML/TOML 日志段对齐的 fmt 层选项（不依赖任何应用配置类型）
 #[derive(Debug, Clone)]
@@ -73,7 +84,7 @@ fn stdout_is_terminal() -> bool {
 /// 安装全局 fmt subscriber：`log` crate 事件经 `tracing-log` 汇入同一流。
 ///
 /// * `options` — `None` 时使用 [LoggingSubscriberOptions::default]。
-/// * 已存在全局 subscriber 时静默跳过（`try_init`），避免测试/重复启动 panic。
+/// * 已存在全局 subscriber 时静默跳过，避免测试/重复启动 panic。
 pub fn init_fmt_subscriber(options: Option<&LoggingSubscriberOptions>) {
     let _ = init_tracing_subscriber(options, None);
 }
@@ -85,6 +96,7 @@ pub fn init_tracing_subscriber(
     options: Option<&LoggingSubscriberOptions>,
     otlp: Option<&OtlpTracingOptions>,
 ) -> TelemetryInitResult<()> {
+    // log 桥接只在这里安装：订阅器随后用 set_global_default 安装，try_init 会再次安装 LogTracer 并因此失败
     let _ = tracing_log::LogTracer::init();
 
     let opts = options.cloned().unwrap_or_default();
@@ -93,9 +105,11 @@ pub fn init_tracing_subscriber(
         Err(_) => default_env_filter(opts.level.as_str()),
     };
 
+    let (env_filter, filter_handle) = reload::Layer::new(env_filter);
+
     let use_ansi = opts.with_ansi.unwrap_or_else(stdout_is_terminal);
-    if let Some(otlp) = otlp.filter(|options| options.enabled()) {
-        init_subscriber_with_otlp(&opts, env_filter, use_ansi, otlp)?;
+    let installed = if let Some(otlp) = otlp.filter(|options| options.enabled()) {
+        init_subscriber_with_otlp(&opts, env_filter, use_ansi, otlp)?
     } else {
         let fmt_layer = fmt::layer()
             .with_ansi(use_ansi)
@@ -103,22 +117,40 @@ pub fn init_tracing_subscriber(
             .with_thread_ids(opts.with_thread_ids)
             .with_file(opts.with_file)
             .with_line_number(opts.with_line_number);
-        let _ = tracing_subscriber::registry()
-            .with(env_filter)
-            .with(fmt_layer)
-            .try_init();
+        tracing::subscriber::set_global_default(
+            tracing_subscriber::registry()
+                .with(env_filter)
+                .with(fmt_layer),
+        )
+        .is_ok()
+    };
+    if installed {
+        let _ = LOG_FILTER.set(filter_handle);
     }
 
     Ok(())
 }
 
+/// 运行期间替换全局日志过滤指令（`RUST_LOG` 语法，如 `info,sqlx=warn`）。
+///
+/// 仅对 [init_fmt_subscriber] / [init_tracing_subscriber] 安装的订阅器生效；
+/// 订阅器由其他代码安装或指令无法解析时返回错误，原过滤器保持不变。
+pub fn set_log_filter(directives: &str) -> TelemetryInitResult<()> {
+    let handle = LOG_FILTER
+        .get()
+        .ok_or("global tracing subscriber was not installed by flare-core-infra")?;
+    let filter = EnvFilter::try_new(directives)?;
+    handle.reload(filter)?;
+    Ok(())
+}
+
 #[cfg(feature = "otel")]
 fn init_subscriber_with_otlp(
     opts: &LoggingSubscriberOptions,
-    env_filter: EnvFilter,
+    env_filter: FilterLayer,
     use_ansi: bool,
     otlp: &OtlpTracingOptions,
-) -> TelemetryInitResult<()> {
+) -> TelemetryInitResult<bool> {
     use opentelemetry::trace::TracerProvider as _;
     use opentelemetry_otlp::WithExportConfig as _;
     use opentelemetry_sdk::{Resource, trace::SdkTracerProvider};
@@ -145,36 +177,63 @@ fn init_subscriber_with_otlp(
         .with_file(opts.with_file)
         .with_line_number(opts.with_line_number);
     let otel_layer = tracing_opentelemetry::layer().with_tracer(tracer);
-    let _ = tracing_subscriber::registry()
-        .with(env_filter)
-        .with(fmt_layer)
-        .with(otel_layer)
-        .try_init();
+    let installed = tracing::subscriber::set_global_default(
+        tracing_subscriber::registry()
+            .with(env_filter)
+            .with(fmt_layer)
+            .with(otel_layer),
+    )
+    .is_ok();
 
-    Ok(())
+    Ok(installed)
 }
 
 #[cfg(not(feature = "otel"))]
 fn init_subscriber_with_otlp(
     opts: &LoggingSubscriberOptions,
-    env_filter: EnvFilter,
+    env_filter: FilterLayer,
     use_ansi: bool,
     otlp: &OtlpTracingOptions,
-) -> TelemetryInitResult<()> {
+) -> TelemetryInitResult<bool> {
     let fmt_layer = fmt::layer()
         .with_ansi(use_ansi)
         .with_target(opts.with_target)
         .with_thread_ids(opts.with_thread_ids)
         .with_file(opts.with_file)
         .with_line_number(opts.with_line_number);
-    let _ = tracing_subscriber::registry()
-        .with(env_filter)
-        .with(fmt_layer)
-        .try_init();
+    let installed = tracing::subscriber::set_global_default(
+        tracing_subscriber::registry()
+            .with(env_filter)
+            .with(fmt_layer),
+    )
+    .is_ok();
     tracing::warn!(
         service_name = %otlp.service_name,
         endpoint = %otlp.endpoint,
         "OTLP tracing requested but flare-core-infra was built without the `otel` feature"
     );
-    Ok(())
+    Ok(installed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing::Level;
+
+    // 全局订阅器每个进程只能安装一次，安装前后的行为放在同一个测试里
+    #[test]
+    fn log_filter_is_reloaded_only_on_own_subscriber_and_valid_directives() {
+        assert!(set_log_filter("info").is_err());
+
+        init_fmt_subscriber(None);
+        set_log_filter("warn").unwrap();
+        assert!(!tracing::enabled!(Level::INFO));
+        assert!(tracing::enabled!(Level::WARN));
+
+        set_log_filter("debug").unwrap();
+        assert!(tracing::enabled!(Level::DEBUG));
+
+        assert!(set_log_filter("flare=loud").is_err());
+        assert!(tracing::enabled!(Level::DEBUG));
+    }
 }
@@ -0,0 +1,131 @@
+//! W3C Trace Context 与 OpenTelemetry span 的关联
+//!
+//! `Ctx` 中的 [`TraceContext`] 由 gRPC / HTTP / MQ 编解码器从 `traceparent` 还原；
+//! 本模块负责把它接到 [`init_tracing_subscriber`](super::init_tracing_subscriber) 安装的 OTel 层上：
+//! - 服务端：[`set_span_parent_from_ctx`] 让请求 span 成为上游 span 的子 span；
+//! - 客户端：[`ctx_with_current_span`] 以当前 span 作为下游调用的父 span。
+//!
+//! flare-core-transport 的 gRPC `ContextLayer`、客户端拦截器与 HTTP `inject_trace_headers` 已自动调用，
+//! 自行编解码 Context 时需按下例手动衔接。未启用 `otel` feature 时两者均为空操作。
+//!
+//! ```rust,ignore
+//! let span = tracing::info_span!("handle_request");
+//! set_span_parent_from_ctx(&span, &ctx);
+//! async move {
+//!     let downstream_ctx = ctx_with_current_span(&ctx);
+//!     // encode_context_to_metadata(request.metadata_mut(), &downstream_ctx);
+//! }
+//! .instrument(span)
+//! .await;
+//! ```
+
+use flare_core_base::context::{Context, TraceContext};
+
+/// 将 Context 中的远端追踪上下文设为 `span` 的父 span（需在 span 首次进入前调用）
+#[cfg(feature = "otel")]
+pub fn set_span_parent_from_ctx(span: &tracing::Span, ctx: &Context) {
+    use opentelemetry::KeyValue;
+    use opentelemetry::baggage::BaggageExt as _;
+    use opentelemetry::trace::{
+        SpanContext, SpanId, TraceContextExt as _, TraceFlags, TraceId, TraceState,
+    };
+    use tracing_opentelemetry::OpenTelemetrySpanExt as _;
+
+    let Some(trace) = ctx.trace_context() else {
+        return;
+    };
+    let (Ok(trace_id), Ok(span_id)) = (
+        TraceId::from_hex(trace.trace_id()),
+        SpanId::from_hex(trace.span_id()),
+    ) else {
+        return;
+    };
+    let flags = if trace.sampled {
+        TraceFlags::SAMPLED
+    } else {
+        TraceFlags::default()
+    };
+    let state = trace
+        .trace_state
+        .as_deref()
+        .and_then(|state| state.parse::<TraceState>().ok())
+        .unwrap_or_default();
+
+    let mut parent = opentelemetry::Context::new()
+        .with_remote_span_context(SpanContext::new(trace_id, span_id, flags, true, state));
+    if !trace.baggage().is_empty() {
+        parent = parent.with_baggage(
+            trace
+                .baggage()
+                .iter()
+                .map(|(k, v)| KeyValue::new(k.to_string(), v.to_string())),
+        );
+    }
+    let _ = span.set_parent(parent);
+}
+
+/// 未启用 `otel` feature：空操作
+#[cfg(not(feature = "otel"))]
+pub fn set_span_parent_from_ctx(_span: &tracing::Span, _ctx: &Context) {}
+
+/// 以当前 span 更新 Context 的追踪上下文，用于发起下游调用
+///
+/// 当前没有有效的 OTel span 时原样返回；tracestate / baggage 在同一 trace 内保留。
+#[cfg(feature = "otel")]
+pub fn ctx_with_current_span(ctx: &Context) -> Context {
+    use opentelemetry::trace::TraceContextExt as _;
+    use tracing_opentelemetry::OpenTelemetrySpanExt as _;
+
+    let otel_ctx = tracing::Span::current().context();
+    let span = otel_ctx.span();
+    let span_context = span.span_context();
+    if !span_context.is_valid() {
+        return ctx.clone();
+    }
+
+    let trace_id = span_context.trace_id().to_string();
+    let span_id = span_context.span_id().to_string();
+    let trace = match ctx.trace_context() {
+        Some(existing) if existing.trace_id() == trace_id => existing.clone().with_span_id(span_id),
+        _ => TraceContext::new(trace_id, span_id, span_context.is_sampled()),
+    };
+    ctx.with_trace_context(trace)
+}
+
+/// 未启用 `otel` feature：原样返回
+#[cfg(not(feature = "otel"))]
+pub fn ctx_with_current_span(ctx: &Context) -> Context {
+    ctx.clone()
+}
+
+#[cfg(all(test, feature = "otel"))]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn handler_span_continues_remote_trace() {
+        let provider = SdkTracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+        let ctx = Context::with_request_id("req").with_trace_context(
+            TraceContext::new("4bf92f3577b34da6a3ce929d0e0e4736", "00f067aa0ba902b7", true)
+                .with_baggage("tenant", "t1"),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("handler");
+            set_span_parent_from_ctx(&span, &ctx);
+            let _entered = span.enter();
+
+            let downstream = ctx_with_current_span(&ctx);
+            let trace = downstream.trace_context().expect("trace context");
+            assert_eq!(trace.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+            assert_ne!(trace.span_id(), "00f067aa0ba902b7");
+            assert_eq!(trace.baggage_item("tenant"), Some("t1"));
+        });
+    }
+}
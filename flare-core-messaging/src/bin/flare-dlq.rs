@@ -0,0 +1,236 @@
+//! Kafka 死信检查与重放工具，基于 [DeadLetterReplayer]。
+//!
+//! ```bash
+//! # 只检查：列出匹配的死信，不发送、不提交 offset
+//! cargo run -p flare-core-messaging --features kafka --bin flare-dlq -- \
+//!     inspect --brokers localhost:9092 --dlq-topic orders.dlq --reason handler_error
+//!
+//! # 重放：发回原始 topic，审计记录追加到文件
+//! cargo run -p flare-core-messaging --features kafka --bin flare-dlq -- \
+//!     replay --brokers localhost:9092 --dlq-topic orders.dlq \
+//!     --error-contains timeout --audit replay-audit.jsonl
+//! ```
+//!
+//! 审计记录为 JSON Lines，默认写到 stdout；统计与说明文字走 stderr。
+//! 未指定 `--group` 时使用固定的 `flare-dlq-<dlq-topic>` 消费组：首次从最早 offset 扫描，
+//! `replay` 会确认扫描过的每条死信（包括不匹配的）并提交 offset，在第一次发送失败处停下；
+//! 再次运行从停下的位置继续，已重放的死信不会重复发送，也不会为每次运行遗留一个消费组。
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use flare_core_messaging::mq::consumer::replay::{
+    DeadLetterFilter, DeadLetterReplayer, JsonLinesReplayAudit, ReplayAuditSink,
+};
+use flare_core_messaging::mq::{
+    KafkaConsumerConfig, KafkaMessageFetcher, KafkaProducer, KafkaProducerConfig, MessageFetcher,
+    Producer,
+};
+
+const DEFAULT_IDLE_TIMEOUT_MS: u64 = 5_000;
+
+struct CliConfig {
+    brokers: Vec<String>,
+    group: String,
+}
+
+impl KafkaProducerConfig for CliConfig {
+    fn kafka_brokers(&self) -> Vec<String> {
+        self.brokers.clone()
+    }
+
+    fn kafka_client_id(&self) -> &str {
+        "flare-dlq"
+    }
+}
+
+impl KafkaConsumerConfig for CliConfig {
+    fn kafka_consumer_group(&self) -> &str {
+        &self.group
+    }
+}
+
+struct Options {
+    dry_run: bool,
+    brokers: Vec<String>,
+    dlq_topic: String,
+    group: Option<String>,
+    filter: DeadLetterFilter,
+    max_messages: Option<usize>,
+    idle_timeout: Duration,
+    audit: Option<String>,
+}
+
+#[tokio::main]
+async fn main() {
+    let options = match parse_args(std::env::args().skip(1).collect()) {
+        Ok(Some(options)) => options,
+        Ok(None) => return,
+        Err(message) => {
+            eprintln!("{message}\n");
+            print_usage();
+            std::process::exit(2);
+        }
+    };
+
+    let config = CliConfig {
+        brokers: options.brokers.clone(),
+        group: options
+            .group
+            .clone()
+            .unwrap_or_else(|| default_group(&options.dlq_topic)),
+    };
+    let producer: Arc<dyn Producer> = match KafkaProducer::new(&config) {
+        Ok(producer) => Arc::new(producer),
+        Err(err) => fail(&format!("创建 Kafka producer 失败：{err}")),
+    };
+    let mut fetcher = match KafkaMessageFetcher::new(&config, vec![options.dlq_topic.clone()]) {
+        Ok(fetcher) => fetcher,
+        Err(err) => fail(&format!("创建 Kafka consumer 失败：{err}")),
+    };
+    let audit: Arc<dyn ReplayAuditSink> = match &options.audit {
+        Some(path) => match JsonLinesReplayAudit::append_to(path) {
+            Ok(audit) => Arc::new(audit),
+            Err(err) => fail(&err.to_string()),
+        },
+        None => Arc::new(JsonLinesReplayAudit::new(std::io::stdout())),
+    };
+
+    let mut replayer = DeadLetterReplayer::new(producer, audit)
+        .with_filter(options.filter)
+        .with_dry_run(options.dry_run)
+        .with_idle_timeout(options.idle_timeout);
+    if let Some(max) = options.max_messages {
+        replayer = replayer.with_max_messages(max);
+    }
+
+    eprintln!(
+        "{} dlq_topic={} group={}",
+        if options.dry_run { "inspect" } else { "replay" },
+        options.dlq_topic,
+        config.group
+    );
+    let result = replayer.run(&mut fetcher).await;
+    // 出错时也提交已确认的部分，避免再次运行重复发送
+    if let Err(err) = fetcher.close().await {
+        fail(&format!("提交 offset 失败：{err}"));
+    }
+    match result {
+        Ok(summary) => eprintln!(
+            "scanned={} matched={} replayed={} skipped={} failed={}",
+            summary.scanned, summary.matched, summary.replayed, summary.skipped, summary.failed
+        ),
+        Err(err) => fail(&format!("重放失败：{err}")),
+    }
+}
+
+fn parse_args(args: Vec<String>) -> Result<Option<Options>, String> {
+    let dry_run = match args.first().map(String::as_str) {
+        Some("inspect") => true,
+        Some("replay") => false,
+        Some("-h" | "--help") | None => {
+            print_usage();
+            return Ok(None);
+        }
+        Some(other) => return Err(format!("未知子命令：{other}")),
+    };
+
+    let mut options = Options {
+        dry_run,
+        brokers: Vec::new(),
+        dlq_topic: String::new(),
+        group: None,
+        filter: DeadLetterFilter::new(),
+        max_messages: None,
+        idle_timeout: Duration::from_millis(DEFAULT_IDLE_TIMEOUT_MS),
+        audit: None,
+    };
+
+    let mut i = 1;
+    while i < args.len() {
+        let flag = args[i].as_str();
+        if matches!(flag, "-h" | "--help") {
+            print_usage();
+            return Ok(None);
+        }
+        i += 1;
+        let value = args
+            .get(i)
+            .cloned()
+            .ok_or_else(|| format!("{flag} 需要一个值"))?;
+        match flag {
+            "--brokers" => {
+                options.brokers = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|broker| !broker.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            }
+            "--dlq-topic" => options.dlq_topic = value,
+            "--group" => options.group = Some(value),
+            "--reason" => options.filter = options.filter.with_reason(value),
+            "--error-contains" => options.filter = options.filter.with_error_contains(value),
+            "--original-topic" => options.filter = options.filter.with_original_topic(value),
+            "--max" => match value.parse::<usize>() {
+                Ok(max) if max > 0 => options.max_messages = Some(max),
+                _ => return Err("--max 需要一个正整数".to_string()),
+            },
+            "--idle-timeout-ms" => match value.parse::<u64>() {
+                Ok(ms) if ms > 0 => options.idle_timeout = Duration::from_millis(ms),
+                _ => return Err("--idle-timeout-ms 需要一个正整数（毫秒）".to_string()),
+            },
+            "--audit" => options.audit = Some(value),
+            other => return Err(format!("未知参数：{other}")),
+        }
+        i += 1;
+    }
+
+    if options.brokers.is_empty() {
+        return Err("缺少 --brokers".to_string());
+    }
+    if options.dlq_topic.is_empty() {
+        return Err("缺少 --dlq-topic".to_string());
+    }
+    Ok(Some(options))
+}
+
+/// 未指定 `--group` 时的消费组，同一 DLQ 的多次运行共享
+fn default_group(dlq_topic: &str) -> String {
+    format!("flare-dlq-{dlq_topic}")
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{message}");
+    std::process::exit(1);
+}
+
+fn print_usage() {
+    eprintln!(
+        r#"Kafka 死信检查与重放
+
+用法:
+    flare-dlq <inspect|replay> --brokers <list> --dlq-topic <topic> [选项]
+
+子命令:
+    inspect   只筛选并输出审计记录，不发送、不提交 offset
+    replay    把匹配的死信发回原始 topic（重试计数归零），确认扫描过的死信并提交 offset
+
+选项:
+    --brokers <list>          Kafka 地址，逗号分隔（必填）
+    --dlq-topic <topic>       死信 topic（必填）
+    --group <id>              消费组（默认 flare-dlq-<dlq-topic>，首次从最早 offset 扫描）
+    --reason <reason>         按失败原因精确匹配，例如 handler_error
+    --error-contains <text>   按错误信息子串匹配
+    --original-topic <topic>  按原始 topic 匹配
+    --max <n>                 最多扫描的消息数
+    --idle-timeout-ms <ms>    无新消息多久后结束（默认 5000）
+    --audit <file>            审计记录追加写入文件（默认 stdout）
+    -h, --help                显示本帮助
+
+注意:
+    replay 也会确认不匹配筛选条件的死信；第一次发送失败时停止，
+    以同一消费组再次运行时从失败的死信继续，已重放的死信不会重复发送。
+    inspect 不提交 offset。如需用其他筛选条件从头扫描，请指定新的 --group。"#
+    );
+}
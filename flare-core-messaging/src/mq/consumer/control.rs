@@ -0,0 +1,377 @@
+//! 运行中消费者的暂停、恢复与再均衡回调
+//!
+//! [ConsumerHandle] 由 [ConsumerRuntime](super::ConsumerRuntime) 持有，克隆后交给运维接口或
+//! `ServiceRuntime::add_consumer_control`。暂停分三种来源：
+//!
+//! - 手动暂停全部 topic（[ConsumerHandle::pause]）
+//! - 手动暂停单个 topic（[ConsumerHandle::pause_topic]）
+//! - 健康检查失败（[ConsumerRuntime::with_health_check](super::ConsumerRuntime::with_health_check)）
+//!
+//! 支持按分区暂停的后端（Kafka、进程内 Broker）在 broker 侧暂停拉取并保持组成员身份；
+//! 其他后端由运行时停止拉取，或把已拉到的被暂停 topic 的消息暂存到恢复后再分发。
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use flare_core_runtime::control::{ConsumerControl, ConsumerControlStatus};
+use tokio::sync::watch;
+
+/// `(topic, partition)`
+pub type TopicPartition = (String, i32);
+
+/// 分区分配与回收回调
+///
+/// 回调在后端的拉取路径上同步执行，耗时会推迟再均衡完成；需要等待的工作应设置上限。
+pub trait RebalanceListener: Send + Sync {
+    /// 分区分配完成后调用
+    fn on_partitions_assigned(&self, _partitions: &[TopicPartition]) {}
+
+    /// 分区回收前调用；返回后后端会提交这些分区已连续完成的 offset
+    fn on_partitions_revoked(&self, _partitions: &[TopicPartition]) {}
+}
+
+/// 控制状态快照
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(super) struct ControlState {
+    pub(super) paused_all: bool,
+    pub(super) paused_topics: BTreeSet<String>,
+    pub(super) unhealthy: BTreeSet<String>,
+    pub(super) assigned: BTreeSet<TopicPartition>,
+}
+
+impl ControlState {
+    /// 是否整体停止拉取
+    pub(super) fn is_suspended(&self) -> bool {
+        self.paused_all || !self.unhealthy.is_empty()
+    }
+}
+
+/// 运行中消费者的控制句柄
+#[derive(Clone)]
+pub struct ConsumerHandle {
+    inner: Arc<HandleInner>,
+}
+
+struct HandleInner {
+    state: watch::Sender<ControlState>,
+    listeners: Mutex<Vec<Arc<dyn RebalanceListener>>>,
+}
+
+impl Default for ConsumerHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConsumerHandle {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(HandleInner {
+                state: watch::Sender::new(ControlState::default()),
+                listeners: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// 暂停全部 topic
+    pub fn pause(&self) {
+        self.update(|state| !std::mem::replace(&mut state.paused_all, true));
+    }
+
+    /// 解除 [Self::pause]；单独暂停的 topic 保持暂停
+    pub fn resume(&self) {
+        self.update(|state| std::mem::take(&mut state.paused_all));
+    }
+
+    pub fn pause_topic(&self, topic: impl Into<String>) {
+        let topic = topic.into();
+        self.update(|state| state.paused_topics.insert(topic));
+    }
+
+    pub fn resume_topic(&self, topic: &str) {
+        self.update(|state| state.paused_topics.remove(topic));
+    }
+
+    /// `topic` 当前是否不会被分发（含整体暂停）
+    pub fn is_paused(&self, topic: &str) -> bool {
+        let state = self.inner.state.borrow();
+        state.is_suspended() || state.paused_topics.contains(topic)
+    }
+
+    /// 是否整体停止拉取（手动暂停或健康检查失败）
+    pub fn is_suspended(&self) -> bool {
+        self.inner.state.borrow().is_suspended()
+    }
+
+    /// 当前分配到的分区
+    pub fn assigned_partitions(&self) -> Vec<TopicPartition> {
+        self.inner.state.borrow().assigned.iter().cloned().collect()
+    }
+
+    /// 追加再均衡回调
+    pub fn add_rebalance_listener(&self, listener: Arc<dyn RebalanceListener>) {
+        self.inner
+            .listeners
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(listener);
+    }
+
+    pub(super) fn subscribe(&self) -> watch::Receiver<ControlState> {
+        self.inner.state.subscribe()
+    }
+
+    /// 记录健康检查结果；返回状态是否变化
+    pub(super) fn set_health(&self, check: &str, healthy: bool) -> bool {
+        self.update(|state| {
+            if healthy {
+                state.unhealthy.remove(check)
+            } else {
+                state.unhealthy.insert(check.to_string())
+            }
+        })
+    }
+
+    fn update(&self, modify: impl FnOnce(&mut ControlState) -> bool) -> bool {
+        self.inner.state.send_if_modified(modify)
+    }
+
+    fn listeners(&self) -> Vec<Arc<dyn RebalanceListener>> {
+        self.inner
+            .listeners
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl RebalanceListener for ConsumerHandle {
+    fn on_partitions_assigned(&self, partitions: &[TopicPartition]) {
+        self.update(|state| {
+            state.assigned.extend(partitions.iter().cloned());
+            !partitions.is_empty()
+        });
+        for listener in self.listeners() {
+            listener.on_partitions_assigned(partitions);
+        }
+    }
+
+    fn on_partitions_revoked(&self, partitions: &[TopicPartition]) {
+        for listener in self.listeners() {
+            listener.on_partitions_revoked(partitions);
+        }
+        self.update(|state| {
+            for partition in partitions {
+                state.assigned.remove(partition);
+            }
+            !partitions.is_empty()
+        });
+    }
+}
+
+impl ConsumerControl for ConsumerHandle {
+    fn pause(&self, topic: Option<&str>) {
+        match topic {
+            Some(topic) => self.pause_topic(topic),
+            None => ConsumerHandle::pause(self),
+        }
+    }
+
+    fn resume(&self, topic: Option<&str>) {
+        match topic {
+            Some(topic) => self.resume_topic(topic),
+            None => {
+                ConsumerHandle::resume(self);
+                self.update(|state| {
+                    let changed = !state.paused_topics.is_empty();
+                    state.paused_topics.clear();
+                    changed
+                });
+            }
+        }
+    }
+
+    fn status(&self) -> ConsumerControlStatus {
+        let state = self.inner.state.borrow();
+        ConsumerControlStatus {
+            paused: state.paused_all,
+            paused_topics: state.paused_topics.iter().cloned().collect(),
+            unhealthy_checks: state.unhealthy.iter().cloned().collect(),
+            assigned_partitions: state.assigned.iter().cloned().collect(),
+        }
+    }
+}
+
+impl fmt::Debug for ConsumerHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConsumerHandle")
+            .field("state", &*self.inner.state.borrow())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mq::consumer::dispatcher::{Dispatcher, TopicDispatcher};
+    use crate::mq::consumer::runtime::ConsumerRuntime;
+    use crate::mq::memory::InMemoryBroker;
+    use crate::mq::memory::testing::{FlakyHandler, ctx, fast_config, wait_until};
+    use crate::mq::producer::Producer;
+    use flare_core_runtime::error::HealthError;
+    use flare_core_runtime::health::HealthCheck;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+    use tokio::sync::oneshot;
+
+    #[derive(Default)]
+    struct RecordingListener {
+        revoked: Mutex<Vec<TopicPartition>>,
+    }
+
+    impl RebalanceListener for RecordingListener {
+        fn on_partitions_revoked(&self, partitions: &[TopicPartition]) {
+            self.revoked.lock().unwrap().extend_from_slice(partitions);
+        }
+    }
+
+    #[test]
+    fn pause_sources_combine_and_notify_subscribers() {
+        let handle = ConsumerHandle::new();
+        let mut rx = handle.subscribe();
+
+        handle.pause_topic("orders");
+        assert!(rx.has_changed().unwrap());
+        assert!(handle.is_paused("orders"));
+        assert!(!handle.is_paused("payments"));
+
+        let _ = rx.borrow_and_update();
+        handle.pause_topic("orders");
+        assert!(!rx.has_changed().unwrap(), "repeated pause is not a change");
+
+        assert!(handle.set_health("database", false));
+        assert!(handle.is_paused("payments"));
+        handle.resume();
+        assert!(handle.is_suspended(), "health pause survives manual resume");
+        assert!(handle.set_health("database", true));
+        assert!(!handle.is_suspended());
+
+        ConsumerControl::resume(&handle, None);
+        let status = handle.status();
+        assert!(status.paused_topics.is_empty());
+        assert!(!status.is_suspended());
+    }
+
+    #[test]
+    fn rebalance_tracks_assignment_and_forwards_revocations() {
+        let handle = ConsumerHandle::new();
+        let listener = Arc::new(RecordingListener::default());
+        handle.add_rebalance_listener(listener.clone());
+
+        handle.on_partitions_assigned(&[("orders".to_string(), 0), ("orders".to_string(), 1)]);
+        handle.on_partitions_revoked(&[("orders".to_string(), 0)]);
+
+        assert_eq!(
+            handle.assigned_partitions(),
+            vec![("orders".to_string(), 1)]
+        );
+        assert_eq!(
+            *listener.revoked.lock().unwrap(),
+            vec![("orders".to_string(), 0)]
+        );
+    }
+
+    /// 结果可在测试中切换的健康检查
+    struct ToggleCheck(AtomicBool);
+
+    impl HealthCheck for ToggleCheck {
+        fn check(&self) -> Pin<Box<dyn Future<Output = Result<(), HealthError>> + Send + '_>> {
+            Box::pin(async move {
+                if self.0.load(Ordering::SeqCst) {
+                    Ok(())
+                } else {
+                    Err(HealthError::CheckFailed {
+                        name: "database".to_string(),
+                        reason: "connection refused".to_string(),
+                    })
+                }
+            })
+        }
+
+        fn name(&self) -> &str {
+            "database"
+        }
+    }
+
+    #[tokio::test]
+    async fn running_consumer_pauses_by_topic_and_on_failed_health_check() {
+        let broker = InMemoryBroker::new();
+        let flaky = || {
+            Arc::new(FlakyHandler {
+                fail_times: 0,
+                seen: Mutex::new(Vec::new()),
+            })
+        };
+        let mut dispatcher = TopicDispatcher::new();
+        dispatcher.register("orders".to_string(), flaky()).unwrap();
+        dispatcher
+            .register("payments".to_string(), flaky())
+            .unwrap();
+        let database = Arc::new(ToggleCheck(AtomicBool::new(true)));
+        let runtime = Arc::new(
+            ConsumerRuntime::new(
+                fast_config(0).with_health_check_interval(Duration::from_millis(10)),
+                Arc::new(dispatcher),
+            )
+            .with_health_check(database.clone()),
+        );
+        let handle = runtime.handle();
+        handle.pause_topic("orders");
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let mut fetcher = broker.fetcher("billing", ["orders", "payments"]);
+        let running = runtime.clone();
+        let task =
+            tokio::spawn(async move { running.run_with_shutdown(&mut fetcher, shutdown_rx).await });
+
+        for (topic, key) in [("orders", "o-1"), ("payments", "p-1")] {
+            broker
+                .send(&ctx(), topic, Some(key), b"{}".to_vec(), None)
+                .await
+                .unwrap();
+        }
+        wait_until(|| broker.lag("billing", "payments") == 0).await;
+        assert_eq!(
+            broker.lag("billing", "orders"),
+            1,
+            "paused topic is not fetched"
+        );
+
+        handle.resume_topic("orders");
+        wait_until(|| broker.lag("billing", "orders") == 0).await;
+
+        database.0.store(false, Ordering::SeqCst);
+        wait_until(|| handle.is_suspended()).await;
+        assert_eq!(
+            handle.status().unhealthy_checks,
+            vec!["database".to_string()]
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        broker
+            .send(&ctx(), "payments", Some("p-2"), b"{}".to_vec(), None)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(broker.lag("billing", "payments"), 1);
+
+        database.0.store(true, Ordering::SeqCst);
+        wait_until(|| broker.lag("billing", "payments") == 0).await;
+        assert!(!handle.is_suspended());
+
+        let _ = shutdown_tx.send(());
+        task.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,409 @@
+//! 延迟投递
+//!
+//! 消息头 [HEADER_RETRY_NOT_BEFORE_UNIX_MS] 指定的时间未到时，[ConsumerRuntime](super::ConsumerRuntime)
+//! 不会分发该消息：支持延迟 nack 的 broker（JetStream）交还 broker 延迟重投，
+//! 其余 broker 停放在进程内时间轮中，到期后再分发。停放期间继续拉取后续消息，不阻塞分区。
+//! 停放数量有上限，达到后暂停拉取，直到有消息到期。
+//!
+//! 重试退避同理：支持原生重投但不支持延迟 nack 的 broker（如内存 broker）先把确认句柄
+//! 停放到同一时间轮，到期后再 nack。
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::mpsc;
+
+use super::failure::{HEADER_RETRY_NOT_BEFORE_UNIX_MS, unix_epoch_millis};
+use super::types::{Message, MessageAck};
+
+/// 时间轮默认刻度
+pub(crate) const DEFAULT_TICK: Duration = Duration::from_millis(10);
+/// 时间轮默认槽数（一圈约 5 秒，更长的延迟按圈数等待）
+pub(crate) const DEFAULT_SLOTS: usize = 512;
+
+/// 读取 not-before 头，返回距今的剩余时间；未设置或已到期时返回 `None`
+pub fn not_before_delay(headers: &HashMap<String, String>) -> Option<Duration> {
+    let not_before = headers
+        .get(HEADER_RETRY_NOT_BEFORE_UNIX_MS)?
+        .parse::<u64>()
+        .ok()?;
+    let now = unix_epoch_millis();
+    (not_before > now).then(|| Duration::from_millis(not_before - now))
+}
+
+/// 停放在拉取循环时间轮上的条目
+pub(crate) enum Parked {
+    /// 到期后重新分发
+    Dispatch(Box<Message>),
+    /// 到期后 nack，由 broker 重投
+    Nack(Arc<dyn MessageAck>),
+}
+
+/// 处理任务把条目交回拉取循环停放；拉取循环退出后交回失败
+#[derive(Clone)]
+pub(crate) struct Deferrals(mpsc::UnboundedSender<(Instant, Parked)>);
+
+impl Deferrals {
+    pub(crate) fn channel() -> (Self, mpsc::UnboundedReceiver<(Instant, Parked)>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Self(tx), rx)
+    }
+
+    /// `due` 时重新分发 `message`
+    pub(crate) fn dispatch_at(&self, due: Instant, message: Message) -> bool {
+        self.0
+            .send((due, Parked::Dispatch(Box::new(message))))
+            .is_ok()
+    }
+
+    /// `delay` 后 nack
+    pub(crate) fn nack_after(&self, delay: Duration, ack: Arc<dyn MessageAck>) -> bool {
+        self.0
+            .send((Instant::now() + delay, Parked::Nack(ack)))
+            .is_ok()
+    }
+}
+
+/// 哈希时间轮
+///
+/// 插入与推进均摊 O(1)；到期精度为一个刻度，且受调用 [TimerWheel::pop_due] 的频率影响。
+pub(crate) struct TimerWheel<T> {
+    tick: Duration,
+    start: Instant,
+    current_tick: u64,
+    slots: Vec<Vec<(u64, T)>>,
+    ready: VecDeque<T>,
+    len: usize,
+}
+
+impl<T> TimerWheel<T> {
+    pub(crate) fn new(tick: Duration, slots: usize) -> Self {
+        Self::starting_at(tick, slots, Instant::now())
+    }
+
+    fn starting_at(tick: Duration, slots: usize, start: Instant) -> Self {
+        Self {
+            tick: tick.max(Duration::from_millis(1)),
+            start,
+            current_tick: 0,
+            slots: (0..slots.max(1)).map(|_| Vec::new()).collect(),
+            ready: VecDeque::new(),
+            len: 0,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn insert(&mut self, deadline: Instant, item: T) {
+        self.len += 1;
+        let deadline_tick = self.tick_ceil(deadline);
+        if deadline_tick <= self.current_tick {
+            self.ready.push_back(item);
+            return;
+        }
+        let slot = (deadline_tick % self.slots.len() as u64) as usize;
+        self.slots[slot].push((deadline_tick, item));
+    }
+
+    /// 推进到 `now` 并取出一个到期项
+    pub(crate) fn pop_due(&mut self, now: Instant) -> Option<T> {
+        if self.ready.is_empty() && self.len > 0 {
+            self.advance(now);
+        }
+        let item = self.ready.pop_front()?;
+        self.len -= 1;
+        Some(item)
+    }
+
+    /// 取出全部项，不论是否到期
+    pub(crate) fn drain(&mut self) -> Vec<T> {
+        let mut items: Vec<T> = self.ready.drain(..).collect();
+        for slot in &mut self.slots {
+            items.extend(slot.drain(..).map(|(_, item)| item));
+        }
+        self.len = 0;
+        items
+    }
+
+    /// 最早一项的到期时间（按刻度取整），供调用方定时唤醒；为空时返回 `None`
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        if self.len == 0 {
+            return None;
+        }
+        if !self.ready.is_empty() {
+            return Some(self.instant_at(self.current_tick));
+        }
+        // 先按槽位顺序查找本圈内到期的项，找不到时再取全部项的最小值
+        let slot_count = self.slots.len() as u64;
+        let in_round = (1..=slot_count).find_map(|step| {
+            let deadline_tick = self.current_tick + step;
+            let slot = &self.slots[(deadline_tick % slot_count) as usize];
+            slot.iter()
+                .any(|(tick, _)| *tick <= deadline_tick)
+                .then_some(deadline_tick)
+        });
+        let deadline_tick = in_round.or_else(|| {
+            self.slots
+                .iter()
+                .flat_map(|slot| slot.iter().map(|(tick, _)| *tick))
+                .min()
+        })?;
+        Some(self.instant_at(deadline_tick))
+    }
+
+    fn instant_at(&self, tick: u64) -> Instant {
+        self.start + self.tick * tick.min(u32::MAX as u64) as u32
+    }
+
+    fn advance(&mut self, now: Instant) {
+        let now_tick = self.tick_floor(now);
+        if now_tick <= self.current_tick {
+            return;
+        }
+        let slot_count = self.slots.len() as u64;
+        let steps = (now_tick - self.current_tick).min(slot_count);
+        for step in 1..=steps {
+            let slot = ((self.current_tick + step) % slot_count) as usize;
+            let entries = std::mem::take(&mut self.slots[slot]);
+            for (deadline_tick, item) in entries {
+                if deadline_tick <= now_tick {
+                    self.ready.push_back(item);
+                } else {
+                    self.slots[slot].push((deadline_tick, item));
+                }
+            }
+        }
+        self.current_tick = now_tick;
+    }
+
+    fn tick_floor(&self, at: Instant) -> u64 {
+        (at.saturating_duration_since(self.start).as_nanos() / self.tick.as_nanos()) as u64
+    }
+
+    fn tick_ceil(&self, at: Instant) -> u64 {
+        let elapsed = at.saturating_duration_since(self.start).as_nanos();
+        elapsed.div_ceil(self.tick.as_nanos()) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mq::consumer::dispatcher::{Dispatcher, TopicDispatcher};
+    use crate::mq::consumer::failure::{
+        Backoff, ProducerRetryPublisher, RetryForwarderHandler, RetryPolicy, RetryTiers,
+    };
+    use crate::mq::consumer::runtime::ConsumerRuntime;
+    use crate::mq::memory::InMemoryBroker;
+    use crate::mq::memory::testing::{FlakyHandler, ctx, fast_config, wait_until};
+    use crate::mq::producer::Producer;
+    use std::sync::{Arc, Mutex, PoisonError};
+    use tokio::sync::oneshot;
+
+    #[test]
+    fn wheel_releases_items_in_deadline_ticks_across_rounds() {
+        let start = Instant::now();
+        let tick = Duration::from_millis(10);
+        let mut wheel = TimerWheel::starting_at(tick, 4, start);
+
+        wheel.insert(start + Duration::from_millis(25), "short");
+        // 超过一圈（40ms）：同槽位但需等下一圈
+        wheel.insert(start + Duration::from_millis(65), "long");
+        wheel.insert(start, "now");
+        assert_eq!(wheel.len(), 3);
+
+        assert_eq!(wheel.pop_due(start), Some("now"));
+        assert_eq!(wheel.pop_due(start + Duration::from_millis(20)), None);
+        assert_eq!(
+            wheel.pop_due(start + Duration::from_millis(30)),
+            Some("short")
+        );
+        assert_eq!(wheel.pop_due(start + Duration::from_millis(60)), None);
+        assert_eq!(
+            wheel.pop_due(start + Duration::from_millis(500)),
+            Some("long")
+        );
+        assert_eq!(wheel.len(), 0);
+    }
+
+    #[test]
+    fn wheel_reports_earliest_deadline() {
+        let start = Instant::now();
+        let tick = Duration::from_millis(10);
+        let mut wheel = TimerWheel::starting_at(tick, 4, start);
+        assert_eq!(wheel.next_deadline(), None);
+
+        // 下一圈的项不应被同槽位误判为本圈到期
+        wheel.insert(start + Duration::from_millis(65), "long");
+        assert_eq!(
+            wheel.next_deadline(),
+            Some(start + Duration::from_millis(70))
+        );
+        wheel.insert(start + Duration::from_millis(25), "short");
+        assert_eq!(
+            wheel.next_deadline(),
+            Some(start + Duration::from_millis(30))
+        );
+
+        assert_eq!(
+            wheel.pop_due(start + Duration::from_millis(30)),
+            Some("short")
+        );
+        assert_eq!(
+            wheel.next_deadline(),
+            Some(start + Duration::from_millis(70))
+        );
+        wheel.insert(start, "now");
+        assert_eq!(
+            wheel.next_deadline(),
+            Some(start + Duration::from_millis(30))
+        );
+    }
+
+    #[test]
+    fn not_before_delay_ignores_past_and_invalid_values() {
+        let mut headers = HashMap::new();
+        assert_eq!(not_before_delay(&headers), None);
+
+        headers.insert(HEADER_RETRY_NOT_BEFORE_UNIX_MS.to_string(), "1".to_string());
+        assert_eq!(not_before_delay(&headers), None);
+
+        headers.insert(
+            HEADER_RETRY_NOT_BEFORE_UNIX_MS.to_string(),
+            "not-a-number".to_string(),
+        );
+        assert_eq!(not_before_delay(&headers), None);
+
+        headers.insert(
+            HEADER_RETRY_NOT_BEFORE_UNIX_MS.to_string(),
+            u64::MAX.to_string(),
+        );
+        assert!(not_before_delay(&headers).is_some());
+    }
+
+    #[tokio::test]
+    async fn delayed_retry_is_parked_without_blocking_partition() {
+        let broker = InMemoryBroker::new();
+        let producer: Arc<dyn Producer> = Arc::new(broker.clone());
+        let handler = Arc::new(FlakyHandler {
+            fail_times: 1,
+            seen: Mutex::new(Vec::new()),
+        });
+        let delay = Duration::from_millis(300);
+        let tiers = RetryTiers::new([Duration::from_millis(500)]);
+        let retry_topic = tiers.topics("orders").remove(0);
+        assert_eq!(retry_topic, "orders.retry.500ms");
+
+        let mut dispatcher = TopicDispatcher::new();
+        dispatcher
+            .register("orders".to_string(), handler.clone())
+            .unwrap();
+        dispatcher
+            .register(
+                retry_topic.clone(),
+                Arc::new(RetryForwarderHandler::new(producer.clone())),
+            )
+            .unwrap();
+        let runtime = ConsumerRuntime::new(fast_config(3), Arc::new(dispatcher))
+            .with_retry_publisher(Arc::new(ProducerRetryPublisher::tiered(
+                producer,
+                tiers,
+                RetryPolicy::new(3, true).with_backoff(Backoff::fixed(delay)),
+            )));
+
+        let started = std::time::Instant::now();
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let mut fetcher = broker.fetcher("order-service", ["orders", retry_topic.as_str()]);
+        let handle =
+            tokio::spawn(async move { runtime.run_with_shutdown(&mut fetcher, shutdown_rx).await });
+
+        broker
+            .send(&ctx(), "orders", None, b"first".to_vec(), None)
+            .await
+            .unwrap();
+        wait_until(|| broker.records(&retry_topic).len() == 1).await;
+
+        // 重试消息停放期间，同分区的新消息照常处理（首次投递同样失败，各自进入重试）
+        broker
+            .send(&ctx(), "orders", None, b"second".to_vec(), None)
+            .await
+            .unwrap();
+        wait_until(|| {
+            handler
+                .seen
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .len()
+                == 2
+        })
+        .await;
+        assert_eq!(broker.records("orders").len(), 2);
+
+        wait_until(|| broker.records("orders").len() == 4).await;
+        wait_until(|| broker.lag("order-service", "orders") == 0).await;
+        assert!(started.elapsed() >= delay);
+        let _ = shutdown_tx.send(());
+        handle.await.unwrap().unwrap();
+
+        assert_eq!(
+            *handler.seen.lock().unwrap_or_else(PoisonError::into_inner),
+            vec![0, 0, 1, 1]
+        );
+        assert_eq!(broker.in_flight("order-service"), 0);
+    }
+
+    #[tokio::test]
+    async fn native_retry_backoff_is_parked_before_nack() {
+        let broker = InMemoryBroker::new();
+        let handler = Arc::new(FlakyHandler {
+            fail_times: 1,
+            seen: Mutex::new(Vec::new()),
+        });
+        let delay = Duration::from_millis(300);
+        let mut dispatcher = TopicDispatcher::new();
+        dispatcher
+            .register("orders".to_string(), handler.clone())
+            .unwrap();
+        let runtime = ConsumerRuntime::new(fast_config(3), Arc::new(dispatcher))
+            .with_retry_policy(RetryPolicy::new(3, true).with_backoff(Backoff::fixed(delay)));
+        let seen = || {
+            handler
+                .seen
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone()
+        };
+
+        let started = std::time::Instant::now();
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let mut fetcher = broker.fetcher("order-service", ["orders"]);
+        let handle =
+            tokio::spawn(async move { runtime.run_with_shutdown(&mut fetcher, shutdown_rx).await });
+
+        broker
+            .send(&ctx(), "orders", None, b"first".to_vec(), None)
+            .await
+            .unwrap();
+        wait_until(|| seen().len() == 1).await;
+
+        // 内存 broker 不支持延迟 nack：退避期间不重投，同分区的新消息照常处理
+        broker
+            .send(&ctx(), "orders", None, b"second".to_vec(), None)
+            .await
+            .unwrap();
+        wait_until(|| seen().len() == 2).await;
+        assert_eq!(seen(), vec![0, 0]);
+        assert!(started.elapsed() < delay);
+
+        wait_until(|| seen().len() == 4).await;
+        wait_until(|| broker.lag("order-service", "orders") == 0).await;
+        assert!(started.elapsed() >= delay);
+        assert_eq!(seen(), vec![0, 0, 1, 1]);
+
+        shutdown_tx.send(()).unwrap();
+        handle.await.unwrap().unwrap();
+    }
+}
@@ -35,6 +35,11 @@ pub trait Dispatcher: Send + Sync {
 
     /// 获取所有注册的主题
     fn topics(&self) -> Vec<String>;
+
+    /// 处理 `topic` 的 handler 名称，用作指标标签；未知时返回 `None`
+    fn handler_name(&self, _topic: &str) -> Option<String> {
+        None
+    }
 }
 
 /// 基于 Topic 的分发器
@@ -165,6 +170,11 @@ impl Dispatcher for TopicDispatcher {
     fn topics(&self) -> Vec<String> {
         self.handlers.keys().cloned().collect()
     }
+
+    fn handler_name(&self, topic: &str) -> Option<String> {
+        self.find_handler(topic)
+            .map(|handler| handler.name().to_string())
+    }
 }
 
 /// 基于注册表的分发器
@@ -280,4 +290,10 @@ impl Dispatcher for RegistryDispatcher {
     fn topics(&self) -> Vec<String> {
         self.registry.list()
     }
+
+    fn handler_name(&self, topic: &str) -> Option<String> {
+        self.registry
+            .get(topic)
+            .map(|handler| handler.name().to_string())
+    }
 }
@@ -3,10 +3,12 @@ use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use async_trait::async_trait;
+use flare_core_base::utils::jittered_backoff;
 
+use super::delay::not_before_delay;
 use super::handler::MessageHandler;
 use super::types::MessageResult;
-use super::types::{ConsumerError, ContentType, Message};
+use super::types::{ConsumerError, ConsumerErrorKind, ContentType, Message};
 use crate::mq::producer::Producer;
 
 pub const HEADER_RETRY_COUNT: &str = "x-flare-retry-count";
@@ -22,6 +24,8 @@ pub const HEADER_RETRY_NOT_BEFORE_UNIX_MS: &str = "x-flare-retry-not-before-unix
 pub enum FailureAction {
     Retry,
     DeadLetter,
+    /// 不重试且未启用 DLQ：term 原消息并记录错误
+    Drop,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -29,6 +33,8 @@ pub struct FailureContext {
     pub reason: String,
     pub error: Option<String>,
     pub retry_count: u32,
+    /// 重试前的等待时间，由 [RetryPolicy::backoff_for] 计算
+    pub delay: Option<Duration>,
 }
 
 impl FailureContext {
@@ -41,26 +47,177 @@ impl FailureContext {
             reason: reason.into(),
             error: error.map(Into::into),
             retry_count,
+            delay: None,
         }
     }
 
     pub fn from_error(reason: &'static str, error: &ConsumerError, retry_count: u32) -> Self {
         Self::new(reason, Some(error.to_string()), retry_count)
     }
+
+    pub fn with_delay(mut self, delay: Option<Duration>) -> Self {
+        self.delay = delay;
+        self
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// 重试退避
+#[derive(Debug, Clone, Default)]
+pub enum Backoff {
+    /// 立即重试
+    #[default]
+    None,
+    /// 固定间隔
+    Fixed(Duration),
+    /// `initial * multiplier^retry_count`，不超过 `max`
+    Exponential {
+        initial: Duration,
+        multiplier: f64,
+        max: Duration,
+    },
+    /// 第 n 次重试取第 n 项，超出部分沿用最后一项
+    Schedule(Vec<Duration>),
+}
+
+impl Backoff {
+    pub fn fixed(delay: Duration) -> Self {
+        Self::Fixed(delay)
+    }
+
+    /// 倍数为 2 的指数退避
+    pub fn exponential(initial: Duration, max: Duration) -> Self {
+        Self::Exponential {
+            initial,
+            multiplier: 2.0,
+            max,
+        }
+    }
+
+    pub fn schedule(delays: impl IntoIterator<Item = Duration>) -> Self {
+        Self::Schedule(delays.into_iter().collect())
+    }
+
+    /// 已重试 `retry_count` 次后的等待时间（不含抖动）
+    pub fn delay_for(&self, retry_count: u32) -> Duration {
+        match self {
+            Self::None => Duration::ZERO,
+            Self::Fixed(delay) => *delay,
+            Self::Exponential {
+                initial,
+                multiplier,
+                max,
+            } => {
+                let exponent = retry_count.min(i32::MAX as u32) as i32;
+                let secs = initial.as_secs_f64() * multiplier.max(1.0).powi(exponent);
+                Duration::try_from_secs_f64(secs).unwrap_or(*max).min(*max)
+            }
+            Self::Schedule(delays) => delays
+                .get(retry_count as usize)
+                .or_else(|| delays.last())
+                .copied()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+// 浮点字段按位比较，保证相等关系自反，从而可以实现 `Eq`
+impl PartialEq for Backoff {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::None, Self::None) => true,
+            (Self::Fixed(a), Self::Fixed(b)) => a == b,
+            (
+                Self::Exponential {
+                    initial: a_initial,
+                    multiplier: a_multiplier,
+                    max: a_max,
+                },
+                Self::Exponential {
+                    initial: b_initial,
+                    multiplier: b_multiplier,
+                    max: b_max,
+                },
+            ) => {
+                a_initial == b_initial
+                    && a_multiplier.to_bits() == b_multiplier.to_bits()
+                    && a_max == b_max
+            }
+            (Self::Schedule(a), Self::Schedule(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Backoff {}
+
+/// 按错误类型覆盖的重试上限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryLimit {
+    /// 不重试，直接进入 DLQ；未启用 DLQ 时 term 丢弃
+    Never,
+    /// 最多重试 n 次
+    Limited(u32),
+    /// 无限重试
+    Unlimited,
+}
+
+#[derive(Debug, Clone)]
 pub struct RetryPolicy {
     pub max_retries: u32,
     pub enable_dlq: bool,
+    pub backoff: Backoff,
+    /// 抖动比例 `0.0..=1.0`：实际延迟在 `[delay * (1 - jitter), delay]` 内均匀分布
+    pub jitter: f64,
+    /// 按 [ConsumerErrorKind] 覆盖 `max_retries` 与 [ConsumerError::is_retryable]
+    pub error_limits: HashMap<ConsumerErrorKind, RetryLimit>,
+}
+
+impl PartialEq for RetryPolicy {
+    fn eq(&self, other: &Self) -> bool {
+        self.max_retries == other.max_retries
+            && self.enable_dlq == other.enable_dlq
+            && self.backoff == other.backoff
+            && self.jitter.to_bits() == other.jitter.to_bits()
+            && self.error_limits == other.error_limits
+    }
 }
 
+impl Eq for RetryPolicy {}
+
 impl RetryPolicy {
     pub fn new(max_retries: u32, enable_dlq: bool) -> Self {
         Self {
             max_retries,
             enable_dlq,
+            backoff: Backoff::None,
+            jitter: 0.0,
+            error_limits: HashMap::new(),
+        }
+    }
+
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// 例如 `Deserialization` 设为 [RetryLimit::Never]、`Connection` 设为 [RetryLimit::Unlimited]
+    pub fn with_error_limit(mut self, kind: ConsumerErrorKind, limit: RetryLimit) -> Self {
+        self.error_limits.insert(kind, limit);
+        self
+    }
+
+    /// 已重试 `retry_count` 次的消息下一次重试前的等待时间（含抖动）
+    pub fn backoff_for(&self, retry_count: u32) -> Option<Duration> {
+        let delay = self.backoff.delay_for(retry_count);
+        if delay.is_zero() {
+            return None;
         }
+        Some(jittered_backoff(delay, self.jitter)).filter(|delay| !delay.is_zero())
     }
 
     pub fn action_for_nack(&self, retry_count: u32) -> FailureAction {
@@ -68,12 +225,13 @@ impl RetryPolicy {
     }
 
     pub fn action_for_error(&self, error: &ConsumerError, retry_count: u32) -> FailureAction {
-        if error.is_retryable() {
-            self.retry_or_dead_letter(retry_count)
-        } else if self.enable_dlq {
-            FailureAction::DeadLetter
-        } else {
-            FailureAction::Retry
+        match self.error_limits.get(&error.kind()) {
+            Some(RetryLimit::Never) if self.enable_dlq => FailureAction::DeadLetter,
+            Some(RetryLimit::Never) => FailureAction::Drop,
+            Some(RetryLimit::Limited(max_retries)) => self.retry_within(*max_retries, retry_count),
+            Some(RetryLimit::Unlimited) => FailureAction::Retry,
+            None if error.is_retryable() => self.retry_or_dead_letter(retry_count),
+            None => self.action_for_dead_letter(),
         }
     }
 
@@ -86,16 +244,61 @@ impl RetryPolicy {
     }
 
     fn retry_or_dead_letter(&self, retry_count: u32) -> FailureAction {
-        if retry_count < self.max_retries {
+        self.retry_within(self.max_retries, retry_count)
+    }
+
+    fn retry_within(&self, max_retries: u32, retry_count: u32) -> FailureAction {
+        if retry_count < max_retries {
             FailureAction::Retry
-        } else if self.enable_dlq {
-            FailureAction::DeadLetter
         } else {
-            FailureAction::Retry
+            self.action_for_dead_letter()
         }
     }
 }
 
+/// 按 topic 选择的重试策略，未配置的 topic 使用默认策略
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicies {
+    default: RetryPolicy,
+    topics: HashMap<String, RetryPolicy>,
+}
+
+impl RetryPolicies {
+    pub fn new(default: RetryPolicy) -> Self {
+        Self {
+            default,
+            topics: HashMap::new(),
+        }
+    }
+
+    pub fn with_topic(mut self, topic: impl Into<String>, policy: RetryPolicy) -> Self {
+        self.topics.insert(topic.into(), policy);
+        self
+    }
+
+    pub fn set_default(&mut self, policy: RetryPolicy) {
+        self.default = policy;
+    }
+
+    pub fn insert(&mut self, topic: impl Into<String>, policy: RetryPolicy) {
+        self.topics.insert(topic.into(), policy);
+    }
+
+    pub fn default_policy(&self) -> &RetryPolicy {
+        &self.default
+    }
+
+    pub fn for_topic(&self, topic: &str) -> &RetryPolicy {
+        self.topics.get(topic).unwrap_or(&self.default)
+    }
+}
+
+impl From<RetryPolicy> for RetryPolicies {
+    fn from(policy: RetryPolicy) -> Self {
+        Self::new(policy)
+    }
+}
+
 impl From<&super::runtime::ConsumerConfig> for RetryPolicy {
     fn from(config: &super::runtime::ConsumerConfig) -> Self {
         Self::new(config.max_retries, config.enable_dlq)
@@ -149,10 +352,82 @@ impl FailureTopic {
     }
 }
 
+/// 分级重试 topic
+///
+/// 按延迟把重试消息分流到 `{topic}.retry.5s`、`{topic}.retry.1m`、`{topic}.retry.10m` 等 topic，
+/// 同一 topic 内延迟相近，消费端停放的消息数量可控。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryTiers {
+    tiers: Vec<Duration>,
+}
+
+impl Default for RetryTiers {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl RetryTiers {
+    /// 自定义分级；零值会被忽略
+    pub fn new(tiers: impl IntoIterator<Item = Duration>) -> Self {
+        let mut tiers: Vec<Duration> = tiers.into_iter().filter(|d| !d.is_zero()).collect();
+        tiers.sort();
+        tiers.dedup();
+        Self { tiers }
+    }
+
+    /// `5s` / `1m` / `10m`
+    pub fn standard() -> Self {
+        Self::new([
+            Duration::from_secs(5),
+            Duration::from_secs(60),
+            Duration::from_secs(600),
+        ])
+    }
+
+    /// 不小于 `delay` 的最短一级；超过最长一级时取最长一级
+    pub fn select(&self, delay: Duration) -> Option<Duration> {
+        self.tiers
+            .iter()
+            .find(|tier| **tier >= delay)
+            .or_else(|| self.tiers.last())
+            .copied()
+    }
+
+    /// `delay` 对应的重试 topic；未配置分级时返回 `None`
+    pub fn topic_for(&self, original_topic: &str, delay: Duration) -> Option<String> {
+        self.select(delay)
+            .map(|tier| format!("{original_topic}.retry.{}", tier_label(tier)))
+    }
+
+    /// 全部分级 topic，便于为每级注册 [RetryForwarderHandler]
+    pub fn topics(&self, original_topic: &str) -> Vec<String> {
+        self.tiers
+            .iter()
+            .map(|tier| format!("{original_topic}.retry.{}", tier_label(*tier)))
+            .collect()
+    }
+}
+
+fn tier_label(tier: Duration) -> String {
+    let secs = tier.as_secs();
+    if tier.subsec_nanos() != 0 {
+        format!("{}ms", tier.as_millis())
+    } else if secs.is_multiple_of(3600) {
+        format!("{}h", secs / 3600)
+    } else if secs.is_multiple_of(60) {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{secs}s")
+    }
+}
+
 pub struct ProducerRetryPublisher {
     producer: Arc<dyn Producer>,
     topic: FailureTopic,
     not_before_delay: Option<Duration>,
+    retry_policy: Option<RetryPolicy>,
+    tiers: Option<RetryTiers>,
 }
 
 impl ProducerRetryPublisher {
@@ -161,13 +436,47 @@ impl ProducerRetryPublisher {
             producer,
             topic,
             not_before_delay: None,
+            retry_policy: None,
+            tiers: None,
         }
     }
 
+    /// 按 `policy` 的退避表选择延迟，并发往对应的分级重试 topic
+    pub fn tiered(producer: Arc<dyn Producer>, tiers: RetryTiers, policy: RetryPolicy) -> Self {
+        Self::new(producer, FailureTopic::Original)
+            .with_tiers(tiers)
+            .with_retry_policy(policy)
+    }
+
+    /// 固定的 not-before 延迟
     pub fn with_not_before_delay(mut self, delay: Duration) -> Self {
         self.not_before_delay = Some(delay);
         self
     }
+
+    /// [FailureContext::delay] 为空时按重试次数从 `policy` 取退避，优先于 [Self::with_not_before_delay]
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// 有延迟时按延迟选择分级 topic，替代 `topic`
+    pub fn with_tiers(mut self, tiers: RetryTiers) -> Self {
+        self.tiers = Some(tiers);
+        self
+    }
+
+    /// 依次取运行时算好的 [FailureContext::delay]、自身策略的退避、固定延迟
+    fn delay_for(&self, failure: &FailureContext) -> Option<Duration> {
+        failure
+            .delay
+            .or_else(|| {
+                self.retry_policy
+                    .as_ref()
+                    .and_then(|policy| policy.backoff_for(failure.retry_count))
+            })
+            .or(self.not_before_delay)
+    }
 }
 
 #[async_trait]
@@ -177,9 +486,13 @@ impl RetryPublisher for ProducerRetryPublisher {
         message: &Message,
         failure: FailureContext,
     ) -> Result<(), ConsumerError> {
-        let topic = self.topic.resolve(&message.context.topic);
+        let delay = self.delay_for(&failure);
+        let topic = delay
+            .zip(self.tiers.as_ref())
+            .and_then(|(delay, tiers)| tiers.topic_for(&message.context.topic, delay))
+            .unwrap_or_else(|| self.topic.resolve(&message.context.topic));
         let mut headers = failure_headers(message, &failure, failure.retry_count.saturating_add(1));
-        if let Some(delay) = self.not_before_delay {
+        if let Some(delay) = delay {
             headers.insert(
                 HEADER_RETRY_NOT_BEFORE_UNIX_MS.to_string(),
                 unix_epoch_millis()
@@ -250,22 +563,6 @@ impl RetryForwarderHandler {
         self.name = name.into();
         self
     }
-
-    async fn wait_until_not_before(message: &Message) {
-        let Some(not_before) = message
-            .context
-            .headers
-            .get(HEADER_RETRY_NOT_BEFORE_UNIX_MS)
-            .and_then(|value| value.parse::<u64>().ok())
-        else {
-            return;
-        };
-
-        let now = unix_epoch_millis();
-        if not_before > now {
-            tokio::time::sleep(Duration::from_millis(not_before - now)).await;
-        }
-    }
 }
 
 #[async_trait]
@@ -284,10 +581,12 @@ impl MessageHandler for RetryForwarderHandler {
             )));
         };
 
-        Self::wait_until_not_before(&message).await;
-
+        // ConsumerRuntime 只分发已到期的消息；未经其延迟的调用方在此保留 not-before 头，
+        // 由原 topic 的消费者停放，而不是阻塞重试 topic 的分区
         let mut headers = message.context.headers.clone();
-        headers.remove(HEADER_RETRY_NOT_BEFORE_UNIX_MS);
+        if not_before_delay(&headers).is_none() {
+            headers.remove(HEADER_RETRY_NOT_BEFORE_UNIX_MS);
+        }
         self.producer
             .send(
                 &message.context.ctx,
@@ -341,7 +640,7 @@ impl ConsumerFailurePublishers {
     }
 }
 
-fn unix_epoch_millis() -> u64 {
+pub(super) fn unix_epoch_millis() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
@@ -473,6 +772,97 @@ mod tests {
         );
     }
 
+    #[test]
+    fn retry_policy_error_limits_override_retryability() {
+        let policy = RetryPolicy::new(2, true)
+            .with_error_limit(ConsumerErrorKind::Deserialization, RetryLimit::Never)
+            .with_error_limit(ConsumerErrorKind::Connection, RetryLimit::Unlimited)
+            .with_error_limit(ConsumerErrorKind::Configuration, RetryLimit::Limited(1));
+
+        let deserialization = ConsumerError::Deserialization("bad json".to_string());
+        let connection = ConsumerError::Connection("reset".to_string());
+        let configuration = ConsumerError::Configuration("missing".to_string());
+        assert_eq!(
+            policy.action_for_error(&deserialization, 0),
+            FailureAction::DeadLetter
+        );
+        assert_eq!(
+            policy.action_for_error(&connection, 1_000),
+            FailureAction::Retry
+        );
+        assert_eq!(
+            policy.action_for_error(&configuration, 0),
+            FailureAction::Retry
+        );
+        assert_eq!(
+            policy.action_for_error(&configuration, 1),
+            FailureAction::DeadLetter
+        );
+    }
+
+    #[test]
+    fn never_retry_limit_without_dlq_drops_instead_of_retrying() {
+        let policy = RetryPolicy::new(2, false)
+            .with_error_limit(ConsumerErrorKind::Deserialization, RetryLimit::Never);
+
+        let deserialization = ConsumerError::Deserialization("bad json".to_string());
+        assert_eq!(
+            policy.action_for_error(&deserialization, 0),
+            FailureAction::Drop
+        );
+        // 其他错误仍按原有语义重试
+        assert_eq!(
+            policy.action_for_error(&ConsumerError::Connection("reset".to_string()), 5),
+            FailureAction::Retry
+        );
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_with_cap_and_jitter() {
+        let backoff = Backoff::exponential(Duration::from_millis(100), Duration::from_secs(1));
+        assert_eq!(backoff.delay_for(0), Duration::from_millis(100));
+        assert_eq!(backoff.delay_for(3), Duration::from_millis(800));
+        assert_eq!(backoff.delay_for(4), Duration::from_secs(1));
+        assert_eq!(backoff.delay_for(u32::MAX), Duration::from_secs(1));
+
+        let policy = RetryPolicy::new(3, true)
+            .with_backoff(Backoff::fixed(Duration::from_millis(200)))
+            .with_jitter(0.5);
+        for _ in 0..32 {
+            let delay = policy.backoff_for(0).expect("non-zero backoff");
+            assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(200));
+        }
+        assert_eq!(RetryPolicy::new(3, true).backoff_for(0), None);
+
+        let policies = RetryPolicies::new(RetryPolicy::new(3, true))
+            .with_topic("payments", RetryPolicy::new(10, true));
+        assert_eq!(policies.for_topic("payments").max_retries, 10);
+        assert_eq!(policies.for_topic("orders").max_retries, 3);
+    }
+
+    #[test]
+    fn retry_policy_equality_covers_backoff_and_jitter() {
+        fn assert_eq_impl<T: Eq>() {}
+        assert_eq_impl::<RetryPolicy>();
+
+        let policy = RetryPolicy::new(3, true)
+            .with_backoff(Backoff::exponential(
+                Duration::from_millis(100),
+                Duration::from_secs(1),
+            ))
+            .with_jitter(0.2);
+        assert_eq!(policy, policy.clone());
+        assert_ne!(policy, policy.clone().with_jitter(0.3));
+        assert_ne!(
+            policy,
+            policy.clone().with_backoff(Backoff::Exponential {
+                initial: Duration::from_millis(100),
+                multiplier: 3.0,
+                max: Duration::from_secs(1),
+            })
+        );
+    }
+
     #[test]
     fn failure_headers_preserve_original_identity_and_increment_retry() {
         let ctx = Arc::new(Context::with_request_id("req-1"));
@@ -544,6 +934,72 @@ mod tests {
         );
     }
 
+    #[test]
+    fn retry_tiers_pick_smallest_tier_covering_backoff() {
+        let policy = RetryPolicy::new(5, true).with_backoff(Backoff::schedule([
+            Duration::from_secs(2),
+            Duration::from_secs(30),
+            Duration::from_secs(3600),
+        ]));
+        let tiers = RetryTiers::standard();
+
+        assert_eq!(policy.backoff_for(0), Some(Duration::from_secs(2)));
+        assert_eq!(policy.backoff_for(9), Some(Duration::from_secs(3600)));
+        assert_eq!(RetryPolicy::new(1, true).backoff_for(0), None);
+
+        let topic = |retry_count| {
+            tiers
+                .topic_for("flare.main", policy.backoff_for(retry_count).unwrap())
+                .unwrap()
+        };
+        assert_eq!(topic(0), "flare.main.retry.5s");
+        assert_eq!(topic(1), "flare.main.retry.1m");
+        assert_eq!(topic(2), "flare.main.retry.10m");
+        assert_eq!(
+            tiers.topics("flare.main"),
+            vec![
+                "flare.main.retry.5s",
+                "flare.main.retry.1m",
+                "flare.main.retry.10m"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn tiered_retry_publisher_routes_by_backoff_schedule() {
+        let producer = Arc::new(RecordingProducer::default());
+        let policy = RetryPolicy::new(3, true).with_backoff(Backoff::schedule([
+            Duration::from_secs(1),
+            Duration::from_secs(45),
+        ]));
+        let publisher =
+            ProducerRetryPublisher::tiered(producer.clone(), RetryTiers::standard(), policy);
+
+        let ctx = Arc::new(Context::with_request_id("req-tier"));
+        let message = Message::new(
+            vec![1],
+            ContentType::Raw,
+            MessageContext::new(ctx, "flare.main".to_string()),
+        );
+        for retry_count in [0, 1] {
+            publisher
+                .publish_retry(
+                    &message,
+                    FailureContext::new("nack", None::<String>, retry_count),
+                )
+                .await
+                .expect("retry publish succeeds");
+        }
+
+        let sends = producer.sends.lock().expect("lock sends");
+        assert_eq!(sends[0].topic, "flare.main.retry.5s");
+        assert_eq!(sends[1].topic, "flare.main.retry.1m");
+        let not_before: u64 = sends[1].headers[HEADER_RETRY_NOT_BEFORE_UNIX_MS]
+            .parse()
+            .unwrap();
+        assert!(not_before >= unix_epoch_millis() + 40_000);
+    }
+
     #[tokio::test]
     async fn retry_forwarder_publishes_to_original_topic() {
         let producer = Arc::new(RecordingProducer::default());
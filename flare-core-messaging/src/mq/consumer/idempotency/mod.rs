@@ -0,0 +1,647 @@
+//! 消费幂等存储
+//!
+//! [ConsumerRuntime](super::ConsumerRuntime) 在分发前检查、成功后记录幂等键（`topic:message_id`）。
+//! 两种模式：
+//!
+//! - **检查后记录**（默认）：`is_consumed` → 处理 → `record_consumed`；并发重投可能都执行 handler。
+//! - **先声明后提交**（`ConsumerConfig::with_idempotency_claim`）：`try_claim` 以随机 [ClaimToken]
+//!   抢占处理权，成功后 `commit_claim` 提交，失败时 `release_claim` 释放；声明在租约到期后自动失效，
+//!   避免进程崩溃后永久占用。提交与释放都校验令牌：租约过期后被其他投递接管的声明不会被原持有者删除或覆盖。
+//!
+//! 实现：[NoopIdempotencyStore]、带 TTL/容量上限的 [InMemoryIdempotencyStore]，
+//! 以及 `redis` / `postgres` feature 下的 `RedisIdempotencyStore`、`PostgresIdempotencyStore`。
+
+#[cfg(feature = "postgres")]
+mod postgres;
+#[cfg(feature = "redis")]
+mod redis;
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use super::delay::Deferrals;
+use super::types::{ConsumerError, Message};
+
+#[cfg(feature = "postgres")]
+pub use self::postgres::PostgresIdempotencyStore;
+#[cfg(feature = "redis")]
+pub use self::redis::RedisIdempotencyStore;
+
+/// 已消费记录的默认保留时间
+pub const DEFAULT_IDEMPOTENCY_TTL: Duration = Duration::from_secs(24 * 3600);
+/// 内存存储的默认容量上限
+pub const DEFAULT_IN_MEMORY_IDEMPOTENCY_CAPACITY: usize = 100_000;
+
+/// 处理中消息重新检查的最长间隔；租约较短时取租约的四分之一
+const MAX_IN_PROGRESS_RECHECK: Duration = Duration::from_secs(1);
+/// 处理中消息重新检查的最短间隔
+const MIN_IN_PROGRESS_RECHECK: Duration = Duration::from_millis(10);
+
+/// 声明的持有者令牌，每次投递随机生成
+///
+/// 存储随声明保存令牌，[IdempotencyStore::commit_claim] / [IdempotencyStore::release_claim]
+/// 只作用于令牌一致的声明。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClaimToken(uuid::Uuid);
+
+impl ClaimToken {
+    pub fn new() -> Self {
+        Self(uuid::Uuid::new_v4())
+    }
+}
+
+impl Default for ClaimToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Display for ClaimToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// [IdempotencyStore::try_claim] 的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClaimOutcome {
+    /// 已取得处理权
+    Claimed,
+    /// 已成功消费过
+    Consumed,
+    /// 其他投递正在处理
+    InProgress,
+}
+
+/// Generic consumer idempotency port.
+///
+/// Implementations should treat keys as already namespaced by the runtime
+/// (`topic:message_id` or broker offset fallback). The default no-op store keeps
+/// existing behavior for callers that have not wired Redis/DB yet.
+#[async_trait::async_trait]
+pub trait IdempotencyStore: Send + Sync {
+    async fn is_consumed(&self, key: &str) -> Result<bool, ConsumerError>;
+    async fn record_consumed(&self, key: &str) -> Result<(), ConsumerError>;
+
+    /// 原子地以 `token` 声明处理权，声明在 `lease` 后过期
+    ///
+    /// 默认实现退化为 `is_consumed` 检查，不提供互斥。
+    async fn try_claim(
+        &self,
+        key: &str,
+        token: &ClaimToken,
+        lease: Duration,
+    ) -> Result<ClaimOutcome, ConsumerError> {
+        let _ = (token, lease);
+        if self.is_consumed(key).await? {
+            Ok(ClaimOutcome::Consumed)
+        } else {
+            Ok(ClaimOutcome::Claimed)
+        }
+    }
+
+    /// 提交 `token` 持有的声明，记录为已消费
+    ///
+    /// 声明已被其他令牌接管时保持不变，由新持有者决定结果；声明已过期且无人接管时仍记录已消费，
+    /// 避免已成功处理的消息被再次处理。默认实现直接调用 `record_consumed`。
+    async fn commit_claim(&self, key: &str, token: &ClaimToken) -> Result<(), ConsumerError> {
+        let _ = token;
+        self.record_consumed(key).await
+    }
+
+    /// 释放 `token` 持有且尚未提交的声明，使重投可以立即处理
+    async fn release_claim(&self, key: &str, token: &ClaimToken) -> Result<(), ConsumerError> {
+        let _ = (key, token);
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct NoopIdempotencyStore;
+
+#[async_trait::async_trait]
+impl IdempotencyStore for NoopIdempotencyStore {
+    async fn is_consumed(&self, _key: &str) -> Result<bool, ConsumerError> {
+        Ok(false)
+    }
+
+    async fn record_consumed(&self, _key: &str) -> Result<(), ConsumerError> {
+        Ok(())
+    }
+}
+
+/// 进程内幂等存储
+///
+/// 记录在 `ttl` 后过期；超过 `capacity` 时按写入顺序淘汰最旧的记录。
+/// 默认 TTL 为 [DEFAULT_IDEMPOTENCY_TTL]，容量为 [DEFAULT_IN_MEMORY_IDEMPOTENCY_CAPACITY]。
+pub struct InMemoryIdempotencyStore {
+    ttl: Option<Duration>,
+    capacity: Option<usize>,
+    state: Mutex<InMemoryState>,
+}
+
+#[derive(Default)]
+struct InMemoryState {
+    entries: HashMap<String, InMemoryEntry>,
+    /// 写入顺序；`seq` 与条目不一致的项已过时
+    order: VecDeque<(String, u64)>,
+    seq: u64,
+}
+
+struct InMemoryEntry {
+    consumed: bool,
+    /// 未提交声明的持有者
+    token: Option<ClaimToken>,
+    expires_at: Option<Instant>,
+    seq: u64,
+}
+
+impl InMemoryState {
+    fn live(&mut self, key: &str, now: Instant) -> Option<&InMemoryEntry> {
+        if self
+            .entries
+            .get(key)
+            .is_some_and(|entry| entry.expires_at.is_some_and(|at| at <= now))
+        {
+            self.entries.remove(key);
+        }
+        self.entries.get(key)
+    }
+
+    fn insert(
+        &mut self,
+        key: &str,
+        token: Option<ClaimToken>,
+        ttl: Option<Duration>,
+        now: Instant,
+    ) {
+        self.seq += 1;
+        self.entries.insert(
+            key.to_string(),
+            InMemoryEntry {
+                consumed: token.is_none(),
+                token,
+                expires_at: ttl.map(|ttl| now + ttl),
+                seq: self.seq,
+            },
+        );
+        self.order.push_back((key.to_string(), self.seq));
+    }
+
+    fn is_current(&self, key: &str, seq: u64) -> bool {
+        self.entries.get(key).is_some_and(|entry| entry.seq == seq)
+    }
+
+    fn prune(&mut self, capacity: Option<usize>, now: Instant) {
+        while let Some((key, seq)) = self.order.front() {
+            let expired = match self.entries.get(key) {
+                Some(entry) if entry.seq == *seq => entry.expires_at.is_some_and(|at| at <= now),
+                _ => true,
+            };
+            let over_capacity = capacity.is_some_and(|capacity| self.entries.len() > capacity);
+            if !expired && !over_capacity {
+                break;
+            }
+            if let Some((key, seq)) = self.order.pop_front()
+                && self.is_current(&key, seq)
+            {
+                self.entries.remove(&key);
+            }
+        }
+
+        // 更新/释放留下的过时项过多时压缩
+        if self.order.len() > self.entries.len() * 2 + 64 {
+            let entries = &self.entries;
+            self.order
+                .retain(|(key, seq)| entries.get(key).is_some_and(|entry| entry.seq == *seq));
+        }
+    }
+}
+
+impl Default for InMemoryIdempotencyStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InMemoryIdempotencyStore {
+    pub fn new() -> Self {
+        Self {
+            ttl: Some(DEFAULT_IDEMPOTENCY_TTL),
+            capacity: Some(DEFAULT_IN_MEMORY_IDEMPOTENCY_CAPACITY),
+            state: Mutex::new(InMemoryState::default()),
+        }
+    }
+
+    /// 不过期、不限容量（仅用于测试）
+    pub fn unbounded() -> Self {
+        Self {
+            ttl: None,
+            capacity: None,
+            state: Mutex::new(InMemoryState::default()),
+        }
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity.max(1));
+        self
+    }
+
+    /// 当前记录数（含未过期的声明）
+    pub fn len(&self) -> usize {
+        let mut state = self.lock();
+        state.prune(self.capacity, Instant::now());
+        state.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> MutexGuard<'_, InMemoryState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[async_trait::async_trait]
+impl IdempotencyStore for InMemoryIdempotencyStore {
+    async fn is_consumed(&self, key: &str) -> Result<bool, ConsumerError> {
+        let mut state = self.lock();
+        Ok(state
+            .live(key, Instant::now())
+            .is_some_and(|entry| entry.consumed))
+    }
+
+    async fn record_consumed(&self, key: &str) -> Result<(), ConsumerError> {
+        let now = Instant::now();
+        let mut state = self.lock();
+        state.insert(key, None, self.ttl, now);
+        state.prune(self.capacity, now);
+        Ok(())
+    }
+
+    async fn try_claim(
+        &self,
+        key: &str,
+        token: &ClaimToken,
+        lease: Duration,
+    ) -> Result<ClaimOutcome, ConsumerError> {
+        let now = Instant::now();
+        let mut state = self.lock();
+        match state.live(key, now).map(|entry| entry.consumed) {
+            Some(true) => Ok(ClaimOutcome::Consumed),
+            Some(false) => Ok(ClaimOutcome::InProgress),
+            None => {
+                state.insert(key, Some(*token), Some(lease), now);
+                state.prune(self.capacity, now);
+                Ok(ClaimOutcome::Claimed)
+            }
+        }
+    }
+
+    async fn commit_claim(&self, key: &str, token: &ClaimToken) -> Result<(), ConsumerError> {
+        let now = Instant::now();
+        let mut state = self.lock();
+        if state
+            .live(key, now)
+            .is_some_and(|entry| entry.token != Some(*token))
+        {
+            return Ok(());
+        }
+        state.insert(key, None, self.ttl, now);
+        state.prune(self.capacity, now);
+        Ok(())
+    }
+
+    async fn release_claim(&self, key: &str, token: &ClaimToken) -> Result<(), ConsumerError> {
+        let mut state = self.lock();
+        if state
+            .live(key, Instant::now())
+            .is_some_and(|entry| entry.token == Some(*token))
+        {
+            state.entries.remove(key);
+        }
+        Ok(())
+    }
+}
+
+/// 一次投递的幂等键及其声明令牌，从分发前检查一直携带到提交或释放
+#[derive(Debug, Clone)]
+pub(crate) struct IdempotencyClaim {
+    key: String,
+    token: ClaimToken,
+}
+
+impl IdempotencyClaim {
+    pub(crate) fn new(key: String) -> Self {
+        Self {
+            key,
+            token: ClaimToken::new(),
+        }
+    }
+
+    pub(crate) fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+/// 运行时使用的幂等门：按配置选择“检查后记录”或“先声明后提交”
+#[derive(Clone)]
+pub(crate) struct IdempotencyGate {
+    store: Arc<dyn IdempotencyStore>,
+    claim_lease: Option<Duration>,
+    /// 处理中的消息交回拉取循环，到期后重新检查
+    deferrals: Option<Deferrals>,
+}
+
+impl IdempotencyGate {
+    pub(crate) fn new(store: Arc<dyn IdempotencyStore>, claim_lease: Option<Duration>) -> Self {
+        Self {
+            store,
+            claim_lease,
+            deferrals: None,
+        }
+    }
+
+    pub(crate) fn with_deferrals(mut self, deferrals: Deferrals) -> Self {
+        self.deferrals = Some(deferrals);
+        self
+    }
+
+    /// 其他投递持有声明时，等待多久后重新检查
+    pub(crate) fn recheck_delay(&self) -> Duration {
+        (self.claim_lease.unwrap_or_default() / 4)
+            .clamp(MIN_IN_PROGRESS_RECHECK, MAX_IN_PROGRESS_RECHECK)
+    }
+
+    /// 交回拉取循环延迟重新检查；未接入拉取循环或循环已退出时返回 `false`
+    pub(crate) fn defer(&self, message: Message) -> bool {
+        let Some(deferrals) = self.deferrals.as_ref() else {
+            return false;
+        };
+        deferrals.dispatch_at(Instant::now() + self.recheck_delay(), message)
+    }
+
+    /// 分发前调用
+    pub(crate) async fn begin(
+        &self,
+        claim: &IdempotencyClaim,
+    ) -> Result<ClaimOutcome, ConsumerError> {
+        match self.claim_lease {
+            Some(lease) => self.store.try_claim(&claim.key, &claim.token, lease).await,
+            None if self.store.is_consumed(&claim.key).await? => Ok(ClaimOutcome::Consumed),
+            None => Ok(ClaimOutcome::Claimed),
+        }
+    }
+
+    /// 处理成功后调用
+    pub(crate) async fn commit(&self, claim: &IdempotencyClaim) -> Result<(), ConsumerError> {
+        match self.claim_lease {
+            Some(_) => self.store.commit_claim(&claim.key, &claim.token).await,
+            None => self.store.record_consumed(&claim.key).await,
+        }
+    }
+
+    /// 处理失败后调用；释放失败只记录日志，声明会在租约到期后失效
+    pub(crate) async fn abandon(&self, claim: &IdempotencyClaim) {
+        if self.claim_lease.is_none() {
+            return;
+        }
+        if let Err(err) = self.store.release_claim(&claim.key, &claim.token).await {
+            tracing::warn!(
+                idempotency_key = %claim.key,
+                error = %err,
+                "Failed to release idempotency claim"
+            );
+        }
+    }
+}
+
+/// 所有 [IdempotencyStore] 实现共用的契约测试
+///
+/// 每个用例使用随机键，可在共享的 Redis / Postgres 上重复执行。
+#[cfg(test)]
+pub(crate) mod conformance {
+    use super::*;
+
+    /// [consumed_record_expires] 要求存储以该保留时间创建
+    pub(crate) const SHORT_TTL: Duration = Duration::from_millis(200);
+
+    fn unique_key() -> String {
+        format!("conformance:{}", uuid::Uuid::new_v4())
+    }
+
+    /// 声明互斥，释放后可重新声明，提交后不可再声明且释放不影响已消费记录
+    pub(crate) async fn claim_commit_release(store: &dyn IdempotencyStore) {
+        let key = unique_key();
+        let lease = Duration::from_secs(30);
+        let first = ClaimToken::new();
+        let second = ClaimToken::new();
+
+        assert!(!store.is_consumed(&key).await.unwrap());
+        assert_eq!(
+            store.try_claim(&key, &first, lease).await.unwrap(),
+            ClaimOutcome::Claimed
+        );
+        assert_eq!(
+            store.try_claim(&key, &second, lease).await.unwrap(),
+            ClaimOutcome::InProgress
+        );
+        assert!(!store.is_consumed(&key).await.unwrap());
+
+        // 非持有者的释放不生效
+        store.release_claim(&key, &second).await.unwrap();
+        assert_eq!(
+            store.try_claim(&key, &second, lease).await.unwrap(),
+            ClaimOutcome::InProgress
+        );
+
+        store.release_claim(&key, &first).await.unwrap();
+        assert_eq!(
+            store.try_claim(&key, &second, lease).await.unwrap(),
+            ClaimOutcome::Claimed
+        );
+
+        store.commit_claim(&key, &second).await.unwrap();
+        store.release_claim(&key, &second).await.unwrap();
+        assert!(store.is_consumed(&key).await.unwrap());
+        assert_eq!(
+            store
+                .try_claim(&key, &ClaimToken::new(), lease)
+                .await
+                .unwrap(),
+            ClaimOutcome::Consumed
+        );
+
+        // 未声明直接提交（检查后记录模式）
+        let other = unique_key();
+        store.record_consumed(&other).await.unwrap();
+        assert!(store.is_consumed(&other).await.unwrap());
+    }
+
+    /// 声明在租约到期后可被其他投递接管
+    pub(crate) async fn claim_lease_expires(store: &dyn IdempotencyStore) {
+        let key = unique_key();
+        assert_eq!(
+            store
+                .try_claim(&key, &ClaimToken::new(), Duration::from_millis(100))
+                .await
+                .unwrap(),
+            ClaimOutcome::Claimed
+        );
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(
+            store
+                .try_claim(&key, &ClaimToken::new(), Duration::from_secs(30))
+                .await
+                .unwrap(),
+            ClaimOutcome::Claimed
+        );
+    }
+
+    /// 租约过期后被接管时，原持有者的释放与提交都不影响新声明
+    pub(crate) async fn stale_owner_cannot_touch_new_claim(store: &dyn IdempotencyStore) {
+        let key = unique_key();
+        let stale = ClaimToken::new();
+        let owner = ClaimToken::new();
+        let lease = Duration::from_secs(30);
+
+        store
+            .try_claim(&key, &stale, Duration::from_millis(100))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(
+            store.try_claim(&key, &owner, lease).await.unwrap(),
+            ClaimOutcome::Claimed
+        );
+
+        store.release_claim(&key, &stale).await.unwrap();
+        assert_eq!(
+            store
+                .try_claim(&key, &ClaimToken::new(), lease)
+                .await
+                .unwrap(),
+            ClaimOutcome::InProgress
+        );
+
+        store.commit_claim(&key, &stale).await.unwrap();
+        assert!(!store.is_consumed(&key).await.unwrap());
+
+        store.commit_claim(&key, &owner).await.unwrap();
+        assert!(store.is_consumed(&key).await.unwrap());
+    }
+
+    /// 已消费记录在保留时间（[SHORT_TTL]）后失效
+    pub(crate) async fn consumed_record_expires(store: &dyn IdempotencyStore) {
+        let key = unique_key();
+        store.record_consumed(&key).await.unwrap();
+        assert!(store.is_consumed(&key).await.unwrap());
+
+        tokio::time::sleep(SHORT_TTL + Duration::from_millis(150)).await;
+        assert!(!store.is_consumed(&key).await.unwrap());
+        assert_eq!(
+            store
+                .try_claim(&key, &ClaimToken::new(), Duration::from_secs(30))
+                .await
+                .unwrap(),
+            ClaimOutcome::Claimed
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn in_memory_store_conforms_to_contract() {
+        let store = InMemoryIdempotencyStore::new();
+        conformance::claim_commit_release(&store).await;
+        conformance::claim_lease_expires(&store).await;
+        conformance::stale_owner_cannot_touch_new_claim(&store).await;
+        conformance::consumed_record_expires(
+            &InMemoryIdempotencyStore::new().with_ttl(conformance::SHORT_TTL),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn noop_store_never_deduplicates() {
+        let store = NoopIdempotencyStore;
+        store.record_consumed("k").await.unwrap();
+        assert!(!store.is_consumed("k").await.unwrap());
+        assert_eq!(
+            store
+                .try_claim("k", &ClaimToken::new(), Duration::from_secs(1))
+                .await
+                .unwrap(),
+            ClaimOutcome::Claimed
+        );
+    }
+
+    #[tokio::test]
+    async fn gate_uses_claims_only_when_lease_configured() {
+        let store = Arc::new(InMemoryIdempotencyStore::new());
+        let delivery = || IdempotencyClaim::new("a".to_string());
+        let check_then_record = IdempotencyGate::new(store.clone(), None);
+        assert_eq!(
+            check_then_record.begin(&delivery()).await.unwrap(),
+            ClaimOutcome::Claimed
+        );
+        // 未声明，重复投递仍可进入
+        assert_eq!(
+            check_then_record.begin(&delivery()).await.unwrap(),
+            ClaimOutcome::Claimed
+        );
+        check_then_record.commit(&delivery()).await.unwrap();
+        assert_eq!(
+            check_then_record.begin(&delivery()).await.unwrap(),
+            ClaimOutcome::Consumed
+        );
+
+        let claiming = IdempotencyGate::new(store.clone(), Some(Duration::from_secs(30)));
+        let first = IdempotencyClaim::new("b".to_string());
+        let second = IdempotencyClaim::new("b".to_string());
+        assert_eq!(claiming.begin(&first).await.unwrap(), ClaimOutcome::Claimed);
+        assert_eq!(
+            claiming.begin(&second).await.unwrap(),
+            ClaimOutcome::InProgress
+        );
+        // 只有持有声明的投递能释放
+        claiming.abandon(&second).await;
+        assert_eq!(
+            claiming.begin(&second).await.unwrap(),
+            ClaimOutcome::InProgress
+        );
+        claiming.abandon(&first).await;
+        assert_eq!(
+            claiming.begin(&second).await.unwrap(),
+            ClaimOutcome::Claimed
+        );
+    }
+
+    #[tokio::test]
+    async fn in_memory_store_expires_and_evicts_oldest() {
+        let store = InMemoryIdempotencyStore::new()
+            .with_ttl(Duration::from_millis(30))
+            .with_capacity(2);
+
+        store.record_consumed("a").await.unwrap();
+        store.record_consumed("b").await.unwrap();
+        store.record_consumed("c").await.unwrap();
+        assert_eq!(store.len(), 2);
+        assert!(!store.is_consumed("a").await.unwrap());
+        assert!(store.is_consumed("c").await.unwrap());
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!store.is_consumed("c").await.unwrap());
+        assert!(store.is_empty());
+    }
+}
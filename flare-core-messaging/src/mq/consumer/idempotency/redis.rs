@@ -0,0 +1,214 @@
+//! Redis 幂等存储
+//!
+//! 每个键保存 `claimed:<令牌>` 或 `consumed`：声明用 `SET NX PX`；提交与释放由 Lua 脚本
+//! 比较令牌，只作用于本次投递持有的声明，租约过期后被其他投递接管的声明保持不变。
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use redis::Script;
+use redis::aio::ConnectionManager;
+
+use super::{ClaimOutcome, ClaimToken, DEFAULT_IDEMPOTENCY_TTL, IdempotencyStore};
+use crate::mq::consumer::types::ConsumerError;
+
+const DEFAULT_NAMESPACE: &str = "flare";
+const CLAIMED_PREFIX: &str = "claimed:";
+const CONSUMED: &str = "consumed";
+
+/// KEYS[1] = 幂等键，ARGV[1] = 声明值；仅当值仍为本次声明时删除
+const RELEASE_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+  return redis.call('DEL', KEYS[1])
+end
+return 0
+"#;
+
+/// KEYS[1] = 幂等键，ARGV[1] = 声明值，ARGV[2] = 保留毫秒数；
+/// 值为本次声明或已过期时记为已消费，被其他声明接管时不覆盖
+const COMMIT_SCRIPT: &str = r#"
+local current = redis.call('GET', KEYS[1])
+if current == false or current == ARGV[1] then
+  redis.call('SET', KEYS[1], 'consumed', 'PX', ARGV[2])
+  return 1
+end
+return 0
+"#;
+
+/// Redis 幂等存储
+#[derive(Clone)]
+pub struct RedisIdempotencyStore {
+    conn: ConnectionManager,
+    namespace: String,
+    ttl: Duration,
+    release: Script,
+    commit: Script,
+}
+
+impl RedisIdempotencyStore {
+    pub fn new(conn: ConnectionManager) -> Self {
+        Self {
+            conn,
+            namespace: DEFAULT_NAMESPACE.to_string(),
+            ttl: DEFAULT_IDEMPOTENCY_TTL,
+            release: Script::new(RELEASE_SCRIPT),
+            commit: Script::new(COMMIT_SCRIPT),
+        }
+    }
+
+    /// 根据 URL 建立连接
+    pub async fn from_url(url: impl AsRef<str>) -> Result<Self, ConsumerError> {
+        let client = redis::Client::open(url.as_ref()).map_err(|err| {
+            ConsumerError::Idempotency(format!("failed to open redis client: {err}"))
+        })?;
+        let conn = ConnectionManager::new(client)
+            .await
+            .map_err(|err| ConsumerError::Idempotency(format!("failed to connect redis: {err}")))?;
+        Ok(Self::new(conn))
+    }
+
+    /// 设置键前缀命名空间
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = namespace.into();
+        self
+    }
+
+    /// 已消费记录的保留时间
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    fn redis_key(&self, key: &str) -> String {
+        format!("{}:idempotency:{}", self.namespace, key)
+    }
+}
+
+#[async_trait]
+impl IdempotencyStore for RedisIdempotencyStore {
+    async fn is_consumed(&self, key: &str) -> Result<bool, ConsumerError> {
+        let mut conn = self.conn.clone();
+        let value: Option<String> = redis::cmd("GET")
+            .arg(self.redis_key(key))
+            .query_async(&mut conn)
+            .await
+            .map_err(redis_error)?;
+        Ok(value.as_deref() == Some(CONSUMED))
+    }
+
+    async fn record_consumed(&self, key: &str) -> Result<(), ConsumerError> {
+        let mut conn = self.conn.clone();
+        redis::cmd("SET")
+            .arg(self.redis_key(key))
+            .arg(CONSUMED)
+            .arg("PX")
+            .arg(millis(self.ttl))
+            .query_async::<()>(&mut conn)
+            .await
+            .map_err(redis_error)
+    }
+
+    async fn try_claim(
+        &self,
+        key: &str,
+        token: &ClaimToken,
+        lease: Duration,
+    ) -> Result<ClaimOutcome, ConsumerError> {
+        let mut conn = self.conn.clone();
+        let redis_key = self.redis_key(key);
+        let claimed: Option<String> = redis::cmd("SET")
+            .arg(&redis_key)
+            .arg(claim_value(token))
+            .arg("NX")
+            .arg("PX")
+            .arg(millis(lease))
+            .query_async(&mut conn)
+            .await
+            .map_err(redis_error)?;
+        if claimed.is_some() {
+            return Ok(ClaimOutcome::Claimed);
+        }
+
+        let current: Option<String> = redis::cmd("GET")
+            .arg(&redis_key)
+            .query_async(&mut conn)
+            .await
+            .map_err(redis_error)?;
+        Ok(claim_outcome_from_value(current.as_deref()))
+    }
+
+    async fn commit_claim(&self, key: &str, token: &ClaimToken) -> Result<(), ConsumerError> {
+        let mut conn = self.conn.clone();
+        self.commit
+            .key(self.redis_key(key))
+            .arg(claim_value(token))
+            .arg(millis(self.ttl))
+            .invoke_async::<i64>(&mut conn)
+            .await
+            .map(|_| ())
+            .map_err(redis_error)
+    }
+
+    async fn release_claim(&self, key: &str, token: &ClaimToken) -> Result<(), ConsumerError> {
+        let mut conn = self.conn.clone();
+        self.release
+            .key(self.redis_key(key))
+            .arg(claim_value(token))
+            .invoke_async::<i64>(&mut conn)
+            .await
+            .map(|_| ())
+            .map_err(redis_error)
+    }
+}
+
+fn claim_value(token: &ClaimToken) -> String {
+    format!("{CLAIMED_PREFIX}{token}")
+}
+
+/// `SET NX` 失败后读取现值：`consumed` 为已消费，其余（包括刚好过期）视为处理中
+fn claim_outcome_from_value(value: Option<&str>) -> ClaimOutcome {
+    match value {
+        Some(CONSUMED) => ClaimOutcome::Consumed,
+        _ => ClaimOutcome::InProgress,
+    }
+}
+
+fn millis(duration: Duration) -> u64 {
+    (duration.as_millis() as u64).max(1)
+}
+
+fn redis_error(err: redis::RedisError) -> ConsumerError {
+    ConsumerError::Idempotency(format!("redis: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mq::consumer::idempotency::conformance;
+
+    #[tokio::test]
+    #[ignore] // 需要运行中的 Redis 实例
+    async fn conforms_to_idempotency_store_contract() {
+        let store = RedisIdempotencyStore::from_url("redis://127.0.0.1:6379")
+            .await
+            .unwrap()
+            .with_namespace("flare-test");
+        conformance::claim_commit_release(&store).await;
+        conformance::claim_lease_expires(&store).await;
+        conformance::stale_owner_cannot_touch_new_claim(&store).await;
+        conformance::consumed_record_expires(&store.with_ttl(conformance::SHORT_TTL)).await;
+    }
+
+    #[test]
+    fn claim_outcome_maps_existing_value() {
+        assert_eq!(
+            claim_outcome_from_value(Some(CONSUMED)),
+            ClaimOutcome::Consumed
+        );
+        assert_eq!(
+            claim_outcome_from_value(Some(&claim_value(&ClaimToken::new()))),
+            ClaimOutcome::InProgress
+        );
+        assert_eq!(claim_outcome_from_value(None), ClaimOutcome::InProgress);
+    }
+}
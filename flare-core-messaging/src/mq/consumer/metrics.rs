@@ -0,0 +1,610 @@
+//! 消费者运行时指标
+//!
+//! [ConsumerMetrics] 注册到调用方提供的 [Registry]，通过 [ConsumerConfig::with_metrics](super::ConsumerConfig::with_metrics)
+//! 交给 `ConsumerRuntime`。多个运行时可共享同一实例，用 [ConsumerMetrics::for_consumer] 区分 `consumer` 标签。
+//!
+//! | 指标 | 类型 | 标签 |
+//! |------|------|------|
+//! | `mq_consumer_messages_total` | counter | consumer, topic, handler, outcome |
+//! | `mq_consumer_handler_duration_seconds` | histogram | consumer, topic, handler |
+//! | `mq_consumer_in_flight` | gauge | consumer, topic |
+//! | `mq_consumer_permits` / `mq_consumer_permits_in_use` | gauge | consumer |
+//! | `mq_consumer_permit_waits_total` | counter | consumer |
+//! | `mq_consumer_lag` | gauge | consumer, topic, partition |
+//!
+//! `outcome` 取值：`processed`、`failed`、`retried`、`dead_lettered`、`dropped`、`skipped`。
+//! 未配置指标时运行时仍维护 [ConsumerStats] 计数。
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use prometheus::{
+    HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+};
+
+use super::dispatcher::Dispatcher;
+use super::failure::FailureAction;
+use super::runtime::ConsumerStats;
+
+const DEFAULT_CONSUMER: &str = "default";
+
+/// 分区级消费延迟：broker 最新 offset 与消费组提交位置之差
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsumerLag {
+    pub topic: String,
+    pub partition: i32,
+    pub lag: i64,
+}
+
+impl ConsumerLag {
+    pub fn new(topic: impl Into<String>, partition: i32, lag: i64) -> Self {
+        Self {
+            topic: topic.into(),
+            partition,
+            lag,
+        }
+    }
+}
+
+/// 消费者 Prometheus 指标
+#[derive(Clone)]
+pub struct ConsumerMetrics {
+    consumer: String,
+    messages_total: IntCounterVec,
+    handler_duration_seconds: HistogramVec,
+    in_flight: IntGaugeVec,
+    permits: IntGaugeVec,
+    permits_in_use: IntGaugeVec,
+    permit_waits_total: IntCounterVec,
+    lag: IntGaugeVec,
+}
+
+impl ConsumerMetrics {
+    /// 创建并注册到 `registry`；同一 registry 只能注册一次，多个运行时请克隆后 [Self::for_consumer]
+    pub fn new(registry: &Registry) -> prometheus::Result<Self> {
+        let metrics = Self::unregistered();
+        registry.register(Box::new(metrics.messages_total.clone()))?;
+        registry.register(Box::new(metrics.handler_duration_seconds.clone()))?;
+        registry.register(Box::new(metrics.in_flight.clone()))?;
+        registry.register(Box::new(metrics.permits.clone()))?;
+        registry.register(Box::new(metrics.permits_in_use.clone()))?;
+        registry.register(Box::new(metrics.permit_waits_total.clone()))?;
+        registry.register(Box::new(metrics.lag.clone()))?;
+        Ok(metrics)
+    }
+
+    /// 共享同一组指标，`consumer` 标签取 `name`
+    pub fn for_consumer(&self, name: impl Into<String>) -> Self {
+        Self {
+            consumer: name.into(),
+            ..self.clone()
+        }
+    }
+
+    pub fn consumer(&self) -> &str {
+        &self.consumer
+    }
+
+    fn unregistered() -> Self {
+        let messages_total = IntCounterVec::new(
+            Opts::new(
+                "mq_consumer_messages_total",
+                "Messages handled by the MQ consumer runtime by consumer, topic, handler, and outcome.",
+            ),
+            &["consumer", "topic", "handler", "outcome"],
+        )
+        .expect("consumer messages metric is valid");
+        let handler_duration_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "mq_consumer_handler_duration_seconds",
+                "MQ handler latency by consumer, topic, and handler.",
+            )
+            .buckets(vec![
+                0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+            ]),
+            &["consumer", "topic", "handler"],
+        )
+        .expect("consumer handler duration metric is valid");
+        let in_flight = IntGaugeVec::new(
+            Opts::new(
+                "mq_consumer_in_flight",
+                "Messages currently being handled by consumer and topic.",
+            ),
+            &["consumer", "topic"],
+        )
+        .expect("consumer in-flight metric is valid");
+        let permits = IntGaugeVec::new(
+            Opts::new(
+                "mq_consumer_permits",
+                "Concurrency permits configured for the consumer runtime.",
+            ),
+            &["consumer"],
+        )
+        .expect("consumer permits metric is valid");
+        let permits_in_use = IntGaugeVec::new(
+            Opts::new(
+                "mq_consumer_permits_in_use",
+                "Concurrency permits held by fetched messages that are not yet settled.",
+            ),
+            &["consumer"],
+        )
+        .expect("consumer permits in use metric is valid");
+        let permit_waits_total = IntCounterVec::new(
+            Opts::new(
+                "mq_consumer_permit_waits_total",
+                "Times the fetch loop waited because every concurrency permit was in use.",
+            ),
+            &["consumer"],
+        )
+        .expect("consumer permit waits metric is valid");
+        let lag = IntGaugeVec::new(
+            Opts::new(
+                "mq_consumer_lag",
+                "Messages between the broker high watermark and the committed position, by consumer, topic, and partition.",
+            ),
+            &["consumer", "topic", "partition"],
+        )
+        .expect("consumer lag metric is valid");
+
+        Self {
+            consumer: DEFAULT_CONSUMER.to_string(),
+            messages_total,
+            handler_duration_seconds,
+            in_flight,
+            permits,
+            permits_in_use,
+            permit_waits_total,
+            lag,
+        }
+    }
+}
+
+impl fmt::Debug for ConsumerMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConsumerMetrics")
+            .field("consumer", &self.consumer)
+            .finish_non_exhaustive()
+    }
+}
+
+/// 单条消息的处理结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Outcome {
+    /// handler 返回 Ack 且已确认
+    Processed,
+    /// handler 返回错误、Nack 或 DeadLetter
+    Failed,
+    /// 按重试策略重试
+    Retried,
+    /// 已转入 DLQ
+    DeadLettered,
+    /// 不重试也未进 DLQ，已 term 丢弃
+    Dropped,
+    /// 幂等检查判定为重复或正在处理，未分发
+    Skipped,
+}
+
+impl Outcome {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Processed => "processed",
+            Self::Failed => "failed",
+            Self::Retried => "retried",
+            Self::DeadLettered => "dead_lettered",
+            Self::Dropped => "dropped",
+            Self::Skipped => "skipped",
+        }
+    }
+}
+
+/// 指标标签：topic 与处理它的 handler
+#[derive(Debug, Clone)]
+pub(super) struct MessageLabels {
+    pub(super) topic: String,
+    pub(super) handler: String,
+}
+
+/// 运行时内部的指标句柄：始终维护 [ConsumerStats]，配置了 [ConsumerMetrics] 时同时导出
+#[derive(Clone, Default)]
+pub(super) struct RuntimeMetrics {
+    inner: Arc<RuntimeMetricsInner>,
+}
+
+#[derive(Default)]
+struct RuntimeMetricsInner {
+    exporter: Option<ConsumerMetrics>,
+    total: AtomicU64,
+    processed: AtomicU64,
+    failed: AtomicU64,
+    retried: AtomicU64,
+    dead_lettered: AtomicU64,
+    /// 上次导出的 lag 分区，分区回收后移除对应序列
+    lag_partitions: Mutex<HashSet<(String, i32)>>,
+}
+
+impl RuntimeMetrics {
+    pub(super) fn new(exporter: Option<ConsumerMetrics>) -> Self {
+        Self {
+            inner: Arc::new(RuntimeMetricsInner {
+                exporter,
+                ..Default::default()
+            }),
+        }
+    }
+
+    pub(super) fn is_exporting(&self) -> bool {
+        self.inner.exporter.is_some()
+    }
+
+    /// 未导出指标时不查找 handler 名称
+    pub(super) fn labels(&self, dispatcher: &dyn Dispatcher, topic: &str) -> MessageLabels {
+        let handler = if self.is_exporting() {
+            dispatcher
+                .handler_name(topic)
+                .unwrap_or_else(|| "unknown".to_string())
+        } else {
+            String::new()
+        };
+        MessageLabels {
+            topic: topic.to_string(),
+            handler,
+        }
+    }
+
+    pub(super) fn record(&self, labels: &MessageLabels, outcome: Outcome) {
+        let inner = &self.inner;
+        let counter = match outcome {
+            Outcome::Processed => Some(&inner.processed),
+            Outcome::Failed => Some(&inner.failed),
+            Outcome::Retried => Some(&inner.retried),
+            Outcome::DeadLettered => Some(&inner.dead_lettered),
+            Outcome::Skipped | Outcome::Dropped => None,
+        };
+        if let Some(counter) = counter {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+        if matches!(outcome, Outcome::Processed | Outcome::Failed) {
+            inner.total.fetch_add(1, Ordering::Relaxed);
+        }
+        if let Some(exporter) = &inner.exporter {
+            exporter
+                .messages_total
+                .with_label_values(&[
+                    exporter.consumer.as_str(),
+                    &labels.topic,
+                    &labels.handler,
+                    outcome.as_str(),
+                ])
+                .inc();
+        }
+    }
+
+    /// 记录一次失败，失败动作执行成功时再记重试或 DLQ
+    pub(super) fn record_failure<T, E>(
+        &self,
+        labels: &MessageLabels,
+        action: FailureAction,
+        applied: &Result<T, E>,
+    ) {
+        self.record(labels, Outcome::Failed);
+        if applied.is_ok() {
+            self.record(
+                labels,
+                match action {
+                    FailureAction::Retry => Outcome::Retried,
+                    FailureAction::DeadLetter => Outcome::DeadLettered,
+                    FailureAction::Drop => Outcome::Dropped,
+                },
+            );
+        }
+    }
+
+    pub(super) fn observe_handler(&self, labels: &MessageLabels, elapsed: Duration) {
+        if let Some(exporter) = &self.inner.exporter {
+            exporter
+                .handler_duration_seconds
+                .with_label_values(&[exporter.consumer.as_str(), &labels.topic, &labels.handler])
+                .observe(elapsed.as_secs_f64());
+        }
+    }
+
+    /// 分发期间计入 `mq_consumer_in_flight`，guard 释放时扣减
+    pub(super) fn in_flight(&self, labels: &MessageLabels) -> InFlightGuard {
+        let gauge = self.inner.exporter.as_ref().map(|exporter| {
+            exporter
+                .in_flight
+                .with_label_values(&[exporter.consumer.as_str(), &labels.topic])
+        });
+        if let Some(gauge) = &gauge {
+            gauge.inc();
+        }
+        InFlightGuard { gauge }
+    }
+
+    pub(super) fn permit_wait(&self) {
+        if let Some(exporter) = &self.inner.exporter {
+            exporter
+                .permit_waits_total
+                .with_label_values(&[exporter.consumer.as_str()])
+                .inc();
+        }
+    }
+
+    pub(super) fn permits(&self, capacity: usize, available: usize) {
+        if let Some(exporter) = &self.inner.exporter {
+            let consumer = [exporter.consumer.as_str()];
+            exporter
+                .permits
+                .with_label_values(&consumer)
+                .set(capacity as i64);
+            exporter
+                .permits_in_use
+                .with_label_values(&consumer)
+                .set(capacity.saturating_sub(available) as i64);
+        }
+    }
+
+    pub(super) fn export_lag(&self, lags: &[ConsumerLag]) {
+        let Some(exporter) = &self.inner.exporter else {
+            return;
+        };
+        let consumer = exporter.consumer.as_str();
+        let current: HashSet<(String, i32)> = lags
+            .iter()
+            .map(|lag| (lag.topic.clone(), lag.partition))
+            .collect();
+        for lag in lags {
+            exporter
+                .lag
+                .with_label_values(&[consumer, &lag.topic, &lag.partition.to_string()])
+                .set(lag.lag);
+        }
+        let mut previous = self
+            .inner
+            .lag_partitions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        for (topic, partition) in previous.difference(&current) {
+            exporter
+                .lag
+                .remove_label_values(&[consumer, topic, &partition.to_string()])
+                .ok();
+        }
+        *previous = current;
+    }
+
+    pub(super) fn stats(&self) -> ConsumerStats {
+        let inner = &self.inner;
+        ConsumerStats {
+            total_messages: inner.total.load(Ordering::Relaxed),
+            processed_messages: inner.processed.load(Ordering::Relaxed),
+            failed_messages: inner.failed.load(Ordering::Relaxed),
+            retried_messages: inner.retried.load(Ordering::Relaxed),
+            dlq_messages: inner.dead_lettered.load(Ordering::Relaxed),
+        }
+    }
+}
+
+pub(super) struct InFlightGuard {
+    gauge: Option<IntGauge>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if let Some(gauge) = &self.gauge {
+            gauge.dec();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mq::consumer::dispatcher::TopicDispatcher;
+    use crate::mq::consumer::failure::{FailureTopic, ProducerDeadLetterPublisher};
+    use crate::mq::consumer::runtime::ConsumerRuntime;
+    use crate::mq::memory::InMemoryBroker;
+    use crate::mq::memory::testing::{AlwaysNack, ctx, fast_config, wait_until};
+    use crate::mq::producer::Producer;
+    use tokio::sync::oneshot;
+
+    fn labels() -> MessageLabels {
+        MessageLabels {
+            topic: "orders".to_string(),
+            handler: "order_handler".to_string(),
+        }
+    }
+
+    #[test]
+    fn runtime_metrics_update_stats_and_injected_registry() {
+        let registry = Registry::new();
+        let metrics = ConsumerMetrics::new(&registry)
+            .unwrap()
+            .for_consumer("orders-service");
+        let runtime = RuntimeMetrics::new(Some(metrics.clone()));
+        let labels = labels();
+
+        runtime.record(&labels, Outcome::Processed);
+        runtime.record_failure(&labels, FailureAction::Retry, &Ok::<(), ()>(()));
+        runtime.record_failure(&labels, FailureAction::DeadLetter, &Err::<(), ()>(()));
+        {
+            let _guard = runtime.in_flight(&labels);
+            assert_eq!(
+                metrics
+                    .in_flight
+                    .with_label_values(&["orders-service", "orders"])
+                    .get(),
+                1
+            );
+        }
+
+        let stats = runtime.stats();
+        assert_eq!(stats.total_messages, 3);
+        assert_eq!(stats.processed_messages, 1);
+        assert_eq!(stats.failed_messages, 2);
+        assert_eq!(stats.retried_messages, 1);
+        assert_eq!(stats.dlq_messages, 0);
+
+        let count = |outcome: &str| {
+            metrics
+                .messages_total
+                .with_label_values(&["orders-service", "orders", "order_handler", outcome])
+                .get()
+        };
+        assert_eq!(count("processed"), 1);
+        assert_eq!(count("failed"), 2);
+        assert_eq!(count("retried"), 1);
+        assert_eq!(count("dead_lettered"), 0);
+        assert_eq!(
+            metrics
+                .in_flight
+                .with_label_values(&["orders-service", "orders"])
+                .get(),
+            0
+        );
+        assert!(
+            registry
+                .gather()
+                .iter()
+                .any(|family| family.name() == "mq_consumer_messages_total")
+        );
+        assert!(
+            prometheus::default_registry()
+                .gather()
+                .iter()
+                .all(|family| family.name() != "mq_consumer_messages_total")
+        );
+    }
+
+    #[test]
+    fn export_lag_drops_partitions_no_longer_reported() {
+        let registry = Registry::new();
+        let metrics = ConsumerMetrics::new(&registry).unwrap();
+        let runtime = RuntimeMetrics::new(Some(metrics.clone()));
+
+        runtime.export_lag(&[
+            ConsumerLag::new("orders", 0, 5),
+            ConsumerLag::new("orders", 1, 2),
+        ]);
+        runtime.export_lag(&[ConsumerLag::new("orders", 0, 3)]);
+
+        let lag = registry
+            .gather()
+            .into_iter()
+            .find(|family| family.name() == "mq_consumer_lag")
+            .unwrap();
+        assert_eq!(lag.get_metric().len(), 1);
+        assert_eq!(
+            metrics
+                .lag
+                .with_label_values(&["default", "orders", "0"])
+                .get(),
+            3
+        );
+    }
+
+    /// 按名称与标签在 registry 中取样本值
+    fn sample(registry: &Registry, name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+        let family = registry
+            .gather()
+            .into_iter()
+            .find(|family| family.name() == name)?;
+        let is_gauge = family.get_field_type() == prometheus::proto::MetricType::GAUGE;
+        family
+            .get_metric()
+            .iter()
+            .find(|metric| {
+                labels.iter().all(|(key, value)| {
+                    metric
+                        .get_label()
+                        .iter()
+                        .any(|pair| pair.name() == *key && pair.value() == *value)
+                })
+            })
+            .map(|metric| {
+                if is_gauge {
+                    metric.get_gauge().value()
+                } else {
+                    metric.get_counter().value()
+                }
+            })
+    }
+
+    #[tokio::test]
+    async fn runtime_exports_outcomes_and_lag_to_injected_registry() {
+        let broker = InMemoryBroker::new();
+        let registry = Registry::new();
+        let metrics = ConsumerMetrics::new(&registry)
+            .unwrap()
+            .for_consumer("order-service");
+        let mut dispatcher = TopicDispatcher::new();
+        dispatcher
+            .register("orders".to_string(), Arc::new(AlwaysNack))
+            .unwrap();
+        let config = fast_config(1)
+            .with_metrics(metrics)
+            .with_lag_interval(Duration::from_millis(10));
+        let runtime = Arc::new(
+            ConsumerRuntime::new(config, Arc::new(dispatcher)).with_dead_letter_publisher(
+                Arc::new(ProducerDeadLetterPublisher::new(
+                    Arc::new(broker.clone()),
+                    FailureTopic::suffix(".dlq"),
+                )),
+            ),
+        );
+
+        broker
+            .send(&ctx(), "orders", Some("o-1"), b"order".to_vec(), None)
+            .await
+            .unwrap();
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let mut fetcher = broker.fetcher("order-service", ["orders"]);
+        let running = runtime.clone();
+        let handle =
+            tokio::spawn(async move { running.run_with_shutdown(&mut fetcher, shutdown_rx).await });
+
+        let lag_labels = [
+            ("consumer", "order-service"),
+            ("topic", "orders"),
+            ("partition", "0"),
+        ];
+        wait_until(|| !broker.records("orders.dlq").is_empty()).await;
+        wait_until(|| sample(&registry, "mq_consumer_lag", &lag_labels) == Some(0.0)).await;
+        let _ = shutdown_tx.send(());
+        handle.await.unwrap().unwrap();
+
+        let stats = runtime.stats();
+        assert_eq!(stats.total_messages, 2);
+        assert_eq!(stats.failed_messages, 2);
+        assert_eq!(stats.retried_messages, 1);
+        assert_eq!(stats.dlq_messages, 1);
+        assert_eq!(stats.success_rate(), 0.0);
+
+        let outcome = |outcome| {
+            sample(
+                &registry,
+                "mq_consumer_messages_total",
+                &[("handler", "always-nack"), ("outcome", outcome)],
+            )
+        };
+        assert_eq!(outcome("failed"), Some(2.0));
+        assert_eq!(outcome("retried"), Some(1.0));
+        assert_eq!(outcome("dead_lettered"), Some(1.0));
+        assert_eq!(
+            sample(&registry, "mq_consumer_in_flight", &[("topic", "orders")]),
+            Some(0.0)
+        );
+        assert_eq!(
+            sample(
+                &registry,
+                "mq_consumer_permits",
+                &[("consumer", "order-service")]
+            ),
+            Some(1.0)
+        );
+    }
+}
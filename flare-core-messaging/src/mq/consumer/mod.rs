@@ -8,28 +8,53 @@
 //! - **Dispatcher**: 按 topic 路由消息到对应 Handler
 //! - **Runtime**: 消费循环和并发控制
 //! - **Fetcher**: 消息获取器抽象接口
+//! - **Metrics**: 处理计数、handler 延迟、并发占用与消费延迟指标
+//! - **Control**: 运行中按 topic 暂停/恢复、健康检查自动暂停与分区再均衡回调
 //!
 //! 注意：具体的 MQ 实现（如 JetStream、NATS、RocketMQ）应该在各自的模块中实现。
 
 pub mod adapter;
+pub mod control;
+pub mod delay;
 pub mod dispatcher;
 pub mod failure;
 pub mod handler;
+pub mod idempotency;
+pub mod metrics;
+pub mod ordered;
+pub mod replay;
 pub mod runtime;
 pub mod task;
 pub mod types;
 
 // 重新导出核心类型
 pub use adapter::{MqConsumerAdapter, MqConsumerAdapterBuilder};
+pub use control::{ConsumerHandle, RebalanceListener, TopicPartition};
 pub use dispatcher::{Dispatcher, RegistryDispatcher, TopicDispatcher};
 pub use failure::{
-    ConsumerFailurePublishers, DeadLetterPublisher, FailureAction, FailureContext, FailureTopic,
-    ProducerDeadLetterPublisher, ProducerRetryPublisher, RetryForwarderHandler, RetryPolicy,
-    RetryPublisher,
+    Backoff, ConsumerFailurePublishers, DeadLetterPublisher, FailureAction, FailureContext,
+    FailureTopic, ProducerDeadLetterPublisher, ProducerRetryPublisher, RetryForwarderHandler,
+    RetryLimit, RetryPolicies, RetryPolicy, RetryPublisher, RetryTiers,
 };
 pub use handler::{HandlerRegistry, MessageHandler};
+#[cfg(feature = "postgres")]
+pub use idempotency::PostgresIdempotencyStore;
+#[cfg(feature = "redis")]
+pub use idempotency::RedisIdempotencyStore;
+pub use idempotency::{
+    ClaimOutcome, ClaimToken, IdempotencyStore, InMemoryIdempotencyStore, NoopIdempotencyStore,
+};
+pub use metrics::{ConsumerLag, ConsumerMetrics};
+pub use ordered::{KeyFailureMode, OrderedConfig, OrderedLaneStatus};
+pub use replay::{
+    DeadLetterFilter, DeadLetterReplayer, InMemoryReplayAudit, JsonLinesReplayAudit,
+    ReplayAuditSink, ReplayDecision, ReplayRecord, ReplaySummary,
+};
 pub use runtime::{
     ConsumerConfig, ConsumerRuntime, ConsumerRuntimeTask, ConsumerStats, MessageFetcher,
 };
 pub use task::{MqConsumer, MqConsumerTask};
-pub use types::{ConsumerError, ContentType, Message, MessageAck, MessageContext, MessageResult};
+pub use types::{
+    ConsumerError, ConsumerErrorKind, ContentType, Message, MessageAck, MessageContext,
+    MessageResult,
+};
@@ -0,0 +1,919 @@
+//! 顺序消费 lane
+//!
+//! 启用 [ConsumerConfig::ordered](super::ConsumerConfig::ordered) 后，消息按顺序键（消息 key，缺省为 topic）
+//! 哈希到固定数量的 lane。每个 lane 是一个有界队列加一个工作任务：同一 key 的消息串行处理，
+//! 不同 key 在 lane 内轮转，lane 之间并行。
+//!
+//! 某个 key 处理失败时按 [KeyFailureMode] 处理：阻塞该 key 直到失败消息重试成功或被重试策略转入 DLQ，
+//! 或直接把失败消息转入 DLQ 继续处理后续消息。幂等检查或确认失败的消息留在 lane 内稍后重试，
+//! 同样阻塞该 key。
+//!
+//! 被阻塞 key 的后续消息只暂存：不占 lane 容量，也释放并发许可，同一 lane 的其他 key 与拉取循环照常推进。
+//! 暂存消息另有与 [OrderedConfig::lane_capacity] 相同的上限，达到后 lane 才会反压拉取循环。
+//! key 的队列为空时立即回收。
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::mpsc::{self, error::TryRecvError, error::TrySendError};
+use tokio::task::JoinSet;
+use tokio::time::{Duration, Instant};
+
+use super::dispatcher::Dispatcher;
+use super::failure::{
+    DeadLetterPublisher, FailureAction, FailureContext, RetryPolicies, RetryPublisher,
+};
+use super::idempotency::{IdempotencyClaim, IdempotencyGate};
+use super::metrics::{MessageLabels, Outcome, RuntimeMetrics};
+use super::runtime::ConsumerRuntime;
+use super::types::{ConsumerError, Message, MessageResult};
+
+/// 阻塞重试的最小间隔，避免未配置退避时空转
+const MIN_BLOCKED_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// 顺序键处理失败时的行为
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyFailureMode {
+    /// 在 lane 内按重试策略退避重试，期间该 key 的后续消息等待；重试耗尽后转入 DLQ
+    #[default]
+    BlockUntilResolved,
+    /// 失败消息直接转入 DLQ，该 key 的后续消息继续处理；要求配置死信发布器
+    SkipToDeadLetter,
+}
+
+/// 顺序消费配置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderedConfig {
+    /// lane 数量；`None` 时取 `poll.concurrency`
+    pub lanes: Option<usize>,
+    /// 单个 lane 排队的最大任务数，超出后拉取循环等待
+    pub lane_capacity: usize,
+    pub on_key_failure: KeyFailureMode,
+}
+
+impl Default for OrderedConfig {
+    fn default() -> Self {
+        Self {
+            lanes: None,
+            lane_capacity: 256,
+            on_key_failure: KeyFailureMode::default(),
+        }
+    }
+}
+
+impl OrderedConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_lanes(mut self, lanes: usize) -> Self {
+        self.lanes = Some(lanes);
+        self
+    }
+
+    pub fn with_lane_capacity(mut self, capacity: usize) -> Self {
+        self.lane_capacity = capacity;
+        self
+    }
+
+    pub fn with_key_failure_mode(mut self, mode: KeyFailureMode) -> Self {
+        self.on_key_failure = mode;
+        self
+    }
+}
+
+/// 单个 lane 的运行状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderedLaneStatus {
+    pub lane: usize,
+    /// 已提交未完成的任务数（含被阻塞 key 的任务）
+    pub depth: usize,
+    pub capacity: usize,
+    /// 当前被阻塞的 key 数
+    pub blocked_keys: usize,
+    /// 因 lane 已满而让拉取循环等待的次数
+    pub backpressure_waits: u64,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct LaneCounters {
+    depth: AtomicUsize,
+    blocked_keys: AtomicUsize,
+    backpressure_waits: AtomicU64,
+}
+
+pub(super) fn lane_counters(lanes: usize) -> Arc<[LaneCounters]> {
+    (0..lanes).map(|_| LaneCounters::default()).collect()
+}
+
+pub(super) fn lane_status(counters: &[LaneCounters], capacity: usize) -> Vec<OrderedLaneStatus> {
+    counters
+        .iter()
+        .enumerate()
+        .map(|(lane, counters)| OrderedLaneStatus {
+            lane,
+            depth: counters.depth.load(Ordering::Relaxed),
+            capacity,
+            blocked_keys: counters.blocked_keys.load(Ordering::Relaxed),
+            backpressure_waits: counters.backpressure_waits.load(Ordering::Relaxed),
+        })
+        .collect()
+}
+
+/// lane 处理消息所需的运行时依赖
+pub(super) struct OrderedContext {
+    pub(super) dispatcher: Arc<dyn Dispatcher>,
+    pub(super) retry_policies: Arc<RetryPolicies>,
+    pub(super) retry_publisher: Option<Arc<dyn RetryPublisher>>,
+    pub(super) dead_letter_publisher: Option<Arc<dyn DeadLetterPublisher>>,
+    pub(super) idempotency: Option<IdempotencyGate>,
+    pub(super) on_key_failure: KeyFailureMode,
+    pub(super) metrics: RuntimeMetrics,
+}
+
+/// 同一 key 的一组消息；批量模式下整组交给 `dispatch_batch`
+struct LaneJob {
+    key: String,
+    messages: Vec<Message>,
+    /// handler 已成功但确认失败的消息及其幂等声明，重试时只补做确认
+    unsettled: Vec<(Message, IdempotencyClaim)>,
+    batched: bool,
+    /// key 被阻塞时释放
+    permit: Option<Arc<OwnedSemaphorePermit>>,
+}
+
+/// 需要在 lane 内稍后重试的部分
+struct LaneRetry {
+    messages: Vec<Message>,
+    unsettled: Vec<(Message, IdempotencyClaim)>,
+    delay: Duration,
+}
+
+pub(super) struct OrderedLanes {
+    senders: Vec<mpsc::Sender<LaneJob>>,
+    counters: Arc<[LaneCounters]>,
+    workers: JoinSet<()>,
+}
+
+impl OrderedLanes {
+    pub(super) fn spawn(
+        context: OrderedContext,
+        counters: Arc<[LaneCounters]>,
+        lane_capacity: usize,
+    ) -> Self {
+        let context = Arc::new(context);
+        let capacity = lane_capacity.max(1);
+        let mut workers = JoinSet::new();
+        let senders = (0..counters.len().max(1))
+            .map(|lane| {
+                let (tx, rx) = mpsc::channel(capacity);
+                let worker = LaneWorker {
+                    lane,
+                    capacity,
+                    context: context.clone(),
+                    counters: counters.clone(),
+                    keys: HashMap::new(),
+                    ready: VecDeque::new(),
+                    buffered: 0,
+                    parked: 0,
+                };
+                workers.spawn(worker.run(rx));
+                tx
+            })
+            .collect();
+        Self {
+            senders,
+            counters,
+            workers,
+        }
+    }
+
+    /// 按 key 分组后投递到各自的 lane；lane 已满时等待
+    pub(super) async fn submit(
+        &self,
+        messages: Vec<Message>,
+        batched: bool,
+        permit: OwnedSemaphorePermit,
+    ) {
+        let permit = Arc::new(permit);
+        for (key, messages) in group_by_key(messages) {
+            let lane = self.lane_for(&key);
+            let counters = &self.counters[lane];
+            counters.depth.fetch_add(1, Ordering::Relaxed);
+            let job = LaneJob {
+                key,
+                messages,
+                unsettled: Vec::new(),
+                batched,
+                permit: Some(permit.clone()),
+            };
+            let sender = &self.senders[lane];
+            let job = match sender.try_send(job) {
+                Ok(()) => continue,
+                Err(TrySendError::Full(job)) => job,
+                Err(TrySendError::Closed(_)) => {
+                    counters.depth.fetch_sub(1, Ordering::Relaxed);
+                    continue;
+                }
+            };
+            let waits = counters.backpressure_waits.fetch_add(1, Ordering::Relaxed) + 1;
+            tracing::debug!(
+                lane,
+                depth = counters.depth.load(Ordering::Relaxed),
+                blocked_keys = counters.blocked_keys.load(Ordering::Relaxed),
+                backpressure_waits = waits,
+                "Ordered lane is full, pausing fetch"
+            );
+            if sender.send(job).await.is_err() {
+                counters.depth.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// 停止接收新消息，处理完已排队且未被阻塞的任务后返回
+    pub(super) async fn close(mut self) {
+        self.senders.clear();
+        while let Some(result) = self.workers.join_next().await {
+            if let Err(e) = result {
+                tracing::error!(error = ?e, "Ordered lane worker join failed");
+            }
+        }
+    }
+
+    fn lane_for(&self, key: &str) -> usize {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        (hasher.finish() % self.senders.len() as u64) as usize
+    }
+}
+
+/// 顺序键：消息 key，缺省为 topic
+fn ordered_key(message: &Message) -> String {
+    message
+        .context
+        .key
+        .clone()
+        .unwrap_or_else(|| message.context.topic.clone())
+}
+
+/// 按 key 分组，保留 key 首次出现的顺序与组内顺序
+fn group_by_key(messages: Vec<Message>) -> Vec<(String, Vec<Message>)> {
+    let mut groups: Vec<(String, Vec<Message>)> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for message in messages {
+        let key = ordered_key(&message);
+        match index.entry(key) {
+            Entry::Occupied(entry) => groups[*entry.get()].1.push(message),
+            Entry::Vacant(entry) => {
+                groups.push((entry.key().clone(), vec![message]));
+                entry.insert(groups.len() - 1);
+            }
+        }
+    }
+    groups
+}
+
+struct KeyQueue {
+    jobs: VecDeque<LaneJob>,
+    blocked_until: Option<Instant>,
+}
+
+struct LaneWorker {
+    lane: usize,
+    capacity: usize,
+    context: Arc<OrderedContext>,
+    counters: Arc<[LaneCounters]>,
+    keys: HashMap<String, KeyQueue>,
+    /// 队首可执行的 key，轮转处理
+    ready: VecDeque<String>,
+    /// 未被阻塞 key 的任务数
+    buffered: usize,
+    /// 被阻塞 key 暂存的任务数
+    parked: usize,
+}
+
+impl LaneWorker {
+    async fn run(mut self, mut rx: mpsc::Receiver<LaneJob>) {
+        let mut closed = false;
+        loop {
+            while !closed && self.accepting() {
+                match rx.try_recv() {
+                    Ok(job) => self.enqueue(job),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => closed = true,
+                }
+            }
+            self.release_due(Instant::now());
+
+            if let Some(key) = self.ready.pop_front() {
+                self.run_head(key).await;
+                continue;
+            }
+            if closed {
+                break;
+            }
+
+            // 没有可执行的 key：等待新任务或最早的阻塞到期
+            let wake = self.next_wake();
+            tokio::select! {
+                job = rx.recv(), if self.accepting() => match job {
+                    Some(job) => self.enqueue(job),
+                    None => closed = true,
+                },
+                _ = tokio::time::sleep_until(wake.unwrap_or_else(|| Instant::now() + Duration::from_secs(3600))), if wake.is_some() => {}
+            }
+        }
+
+        if !self.keys.is_empty() {
+            let messages = self.buffered + self.parked;
+            tracing::info!(
+                lane = self.lane,
+                blocked_keys = self.keys.len(),
+                messages,
+                "Ordered lane stopped with blocked keys, messages left for broker redelivery"
+            );
+            let counters = &self.counters[self.lane];
+            counters.depth.fetch_sub(messages, Ordering::Relaxed);
+            counters.blocked_keys.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// 可执行任务与暂存任务都未达上限时继续接收
+    fn accepting(&self) -> bool {
+        self.buffered < self.capacity && self.parked < self.capacity
+    }
+
+    fn enqueue(&mut self, mut job: LaneJob) {
+        match self.keys.entry(job.key.clone()) {
+            Entry::Occupied(mut entry) => {
+                let queue = entry.get_mut();
+                if queue.blocked_until.is_some() {
+                    // 被阻塞 key 的后续消息只暂存，不占用并发许可
+                    job.permit = None;
+                    self.parked += 1;
+                } else {
+                    self.buffered += 1;
+                }
+                queue.jobs.push_back(job);
+            }
+            Entry::Vacant(entry) => {
+                self.buffered += 1;
+                self.ready.push_back(entry.key().clone());
+                entry.insert(KeyQueue {
+                    jobs: VecDeque::from([job]),
+                    blocked_until: None,
+                });
+            }
+        }
+    }
+
+    async fn run_head(&mut self, key: String) {
+        let Some(mut job) = self
+            .keys
+            .get_mut(&key)
+            .and_then(|queue| queue.jobs.pop_front())
+        else {
+            self.keys.remove(&key);
+            return;
+        };
+        let retry = self
+            .context
+            .attempt(
+                std::mem::take(&mut job.messages),
+                std::mem::take(&mut job.unsettled),
+                job.batched,
+            )
+            .await;
+
+        let counters = &self.counters[self.lane];
+        let Some(queue) = self.keys.get_mut(&key) else {
+            return;
+        };
+        match retry {
+            Some(LaneRetry {
+                messages,
+                unsettled,
+                delay,
+            }) => {
+                job.messages = messages;
+                job.unsettled = unsettled;
+                queue.jobs.push_front(job);
+                queue.blocked_until = Some(Instant::now() + delay);
+                for job in &mut queue.jobs {
+                    job.permit = None;
+                }
+                self.buffered -= queue.jobs.len();
+                self.parked += queue.jobs.len();
+                counters.blocked_keys.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!(
+                    lane = self.lane,
+                    key = %key,
+                    delay_ms = delay.as_millis() as u64,
+                    waiting = queue.jobs.len(),
+                    "Ordered key blocked until failed message is resolved"
+                );
+            }
+            None => {
+                self.buffered -= 1;
+                counters.depth.fetch_sub(1, Ordering::Relaxed);
+                if queue.jobs.is_empty() {
+                    self.keys.remove(&key);
+                } else {
+                    self.ready.push_back(key);
+                }
+            }
+        }
+    }
+
+    fn release_due(&mut self, now: Instant) {
+        for (key, queue) in &mut self.keys {
+            if queue.blocked_until.is_some_and(|until| until <= now) {
+                queue.blocked_until = None;
+                self.parked -= queue.jobs.len();
+                self.buffered += queue.jobs.len();
+                self.counters[self.lane]
+                    .blocked_keys
+                    .fetch_sub(1, Ordering::Relaxed);
+                self.ready.push_back(key.clone());
+            }
+        }
+    }
+
+    fn next_wake(&self) -> Option<Instant> {
+        self.keys
+            .values()
+            .filter_map(|queue| queue.blocked_until)
+            .min()
+    }
+}
+
+impl OrderedContext {
+    /// 处理一组同 key 消息，返回需要在 lane 内重试的部分与等待时间
+    async fn attempt(
+        &self,
+        messages: Vec<Message>,
+        unsettled: Vec<(Message, IdempotencyClaim)>,
+        batched: bool,
+    ) -> Option<LaneRetry> {
+        let gate = self.idempotency.as_ref();
+
+        // 先补做上次失败的确认；确认完成前不分发后续消息
+        let mut still_unsettled = Vec::new();
+        for (message, claim) in unsettled {
+            match self.settle_ack(&message, &claim).await {
+                Ok(()) => {
+                    let labels = self
+                        .metrics
+                        .labels(self.dispatcher.as_ref(), &message.context.topic);
+                    self.metrics.record(&labels, Outcome::Processed);
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        error = %err,
+                        message_id = %message.context.message_id,
+                        "Failed to acknowledge ordered message, retrying in lane"
+                    );
+                    still_unsettled.push((message, claim));
+                }
+            }
+        }
+        if !still_unsettled.is_empty() {
+            return Some(LaneRetry {
+                messages,
+                unsettled: still_unsettled,
+                delay: MIN_BLOCKED_RETRY_DELAY,
+            });
+        }
+
+        // 幂等检查失败的消息及其后续消息留在 lane 内重试，保持顺序
+        let mut admitted = Vec::with_capacity(messages.len());
+        let mut deferred = Vec::new();
+        let mut messages = messages.into_iter();
+        for message in messages.by_ref() {
+            let claim = IdempotencyClaim::new(ConsumerRuntime::idempotency_key(&message));
+            let labels = self
+                .metrics
+                .labels(self.dispatcher.as_ref(), &message.context.topic);
+            match ConsumerRuntime::admit(&message, &claim, gate).await {
+                Ok(true) => admitted.push((message, claim, labels)),
+                Ok(false) => self.metrics.record(&labels, Outcome::Skipped),
+                Err(err) => {
+                    tracing::warn!(
+                        error = %err,
+                        message_id = %message.context.message_id,
+                        "Idempotency check failed, retrying in lane"
+                    );
+                    deferred.push(message);
+                    break;
+                }
+            }
+        }
+        deferred.extend(messages);
+        if admitted.is_empty() {
+            return (!deferred.is_empty()).then_some(LaneRetry {
+                messages: deferred,
+                unsettled: Vec::new(),
+                delay: MIN_BLOCKED_RETRY_DELAY,
+            });
+        }
+
+        let outcomes = self.dispatch(&admitted, batched).await;
+        let mut retry = Vec::new();
+        let mut unsettled = Vec::new();
+        let mut delay = if deferred.is_empty() {
+            Duration::ZERO
+        } else {
+            MIN_BLOCKED_RETRY_DELAY
+        };
+        for ((mut message, claim, labels), outcome) in admitted.into_iter().zip(outcomes) {
+            let retry_count = message.context.retry_count;
+            let retry_policy = self.retry_policies.for_topic(&message.context.topic);
+            let (action, failure) = match outcome {
+                Ok(MessageResult::Ack) => {
+                    match self.settle_ack(&message, &claim).await {
+                        Ok(()) => self.metrics.record(&labels, Outcome::Processed),
+                        Err(err) => {
+                            tracing::warn!(
+                                error = %err,
+                                message_id = %message.context.message_id,
+                                "Failed to acknowledge ordered message, retrying in lane"
+                            );
+                            delay = delay.max(MIN_BLOCKED_RETRY_DELAY);
+                            unsettled.push((message, claim));
+                        }
+                    }
+                    continue;
+                }
+                Ok(MessageResult::Nack) => (
+                    retry_policy.action_for_nack(retry_count),
+                    FailureContext::new("handler_nack", None::<String>, retry_count),
+                ),
+                Ok(MessageResult::DeadLetter) => (
+                    FailureAction::DeadLetter,
+                    FailureContext::new("handler_dead_letter", None::<String>, retry_count),
+                ),
+                Err(err) => (
+                    retry_policy.action_for_error(&err, retry_count),
+                    FailureContext::from_error("handler_error", &err, retry_count),
+                ),
+            };
+            ConsumerRuntime::abandon(&claim, gate).await;
+
+            if action == FailureAction::Retry
+                && self.on_key_failure == KeyFailureMode::BlockUntilResolved
+            {
+                let backoff = retry_policy
+                    .backoff_for(retry_count)
+                    .unwrap_or_default()
+                    .max(MIN_BLOCKED_RETRY_DELAY);
+                delay = delay.max(backoff);
+                message.context.retry_count = retry_count.saturating_add(1);
+                self.metrics.record(&labels, Outcome::Failed);
+                self.metrics.record(&labels, Outcome::Retried);
+                retry.push(message);
+                continue;
+            }
+
+            // 转入 DLQ（或丢弃）后该 key 继续处理，其余重试方式都会打乱顺序
+            let action = match action {
+                FailureAction::Drop => FailureAction::Drop,
+                _ => FailureAction::DeadLetter,
+            };
+            let result = ConsumerRuntime::apply_failure_action(
+                Some(&message),
+                message.ack_handle.as_ref(),
+                &self.retry_publisher,
+                &self.dead_letter_publisher,
+                None,
+                retry_policy,
+                action,
+                failure,
+            )
+            .await;
+            self.metrics.record_failure(&labels, action, &result);
+            if let Err(err) = result {
+                tracing::warn!(
+                    error = %err,
+                    message_id = %message.context.message_id,
+                    "Failed to dead-letter ordered message"
+                );
+            }
+        }
+
+        retry.extend(deferred);
+        (!retry.is_empty() || !unsettled.is_empty()).then_some(LaneRetry {
+            messages: retry,
+            unsettled,
+            delay,
+        })
+    }
+
+    async fn dispatch(
+        &self,
+        admitted: &[(Message, IdempotencyClaim, MessageLabels)],
+        batched: bool,
+    ) -> Vec<Result<MessageResult, Arc<ConsumerError>>> {
+        if !batched {
+            let mut outcomes = Vec::with_capacity(admitted.len());
+            for (message, _, labels) in admitted {
+                let _in_flight = self.metrics.in_flight(labels);
+                let started_at = Instant::now();
+                let outcome = self.dispatcher.dispatch(message.clone()).await;
+                self.metrics.observe_handler(labels, started_at.elapsed());
+                outcomes.push(outcome.map_err(Arc::new));
+            }
+            return outcomes;
+        }
+
+        let messages = admitted
+            .iter()
+            .map(|(message, _, _)| message.clone())
+            .collect();
+        let _in_flight: Vec<_> = admitted
+            .iter()
+            .map(|(_, _, labels)| self.metrics.in_flight(labels))
+            .collect();
+        let started_at = Instant::now();
+        let dispatched = self.dispatcher.dispatch_batch(messages).await;
+        self.metrics
+            .observe_handler(&admitted[0].2, started_at.elapsed());
+        let error = match dispatched {
+            Ok(results) if results.len() == admitted.len() => {
+                return results.into_iter().map(Ok).collect();
+            }
+            Ok(results) => ConsumerError::Handler(format!(
+                "batch handler returned {} results for {} messages",
+                results.len(),
+                admitted.len()
+            )),
+            Err(err) => err,
+        };
+        let error = Arc::new(error);
+        admitted.iter().map(|_| Err(error.clone())).collect()
+    }
+
+    async fn settle_ack(
+        &self,
+        message: &Message,
+        claim: &IdempotencyClaim,
+    ) -> Result<(), ConsumerError> {
+        ConsumerRuntime::commit(claim, self.idempotency.as_ref()).await?;
+        if let Some(ack) = message.ack_handle.as_ref() {
+            ack.ack().await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mq::consumer::dispatcher::TopicDispatcher;
+    use crate::mq::consumer::failure::{FailureTopic, ProducerDeadLetterPublisher};
+    use crate::mq::consumer::handler::MessageHandler;
+    use crate::mq::consumer::idempotency::{IdempotencyStore, InMemoryIdempotencyStore};
+    use crate::mq::consumer::runtime::ConsumerConfig;
+    use crate::mq::memory::InMemoryBroker;
+    use crate::mq::memory::testing::{ctx, fast_config, wait_until};
+    use crate::mq::producer::Producer;
+    use async_trait::async_trait;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::{Mutex, PoisonError};
+    use tokio::sync::oneshot;
+
+    /// 首次投递时对 `fail_payload` 返回 Nack（`always` 时每次都 Nack），记录处理顺序
+    struct OrderRecorder {
+        fail_payload: &'static [u8],
+        always: bool,
+        seen: Mutex<Vec<String>>,
+    }
+
+    impl OrderRecorder {
+        fn new(fail_payload: &'static [u8], always: bool) -> Self {
+            Self {
+                fail_payload,
+                always,
+                seen: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn seen(&self) -> Vec<String> {
+            self.seen
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone()
+        }
+    }
+
+    #[async_trait]
+    impl MessageHandler for OrderRecorder {
+        async fn handle(&self, message: Message) -> Result<MessageResult, ConsumerError> {
+            self.seen
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(String::from_utf8_lossy(&message.payload).to_string());
+            if message.payload == self.fail_payload
+                && (self.always || message.context.retry_count == 0)
+            {
+                return Ok(MessageResult::Nack);
+            }
+            Ok(MessageResult::Ack)
+        }
+
+        fn name(&self) -> &str {
+            "order-recorder"
+        }
+    }
+
+    /// 只有 `a` 一个 key 失败时的默认投递顺序
+    const SENDS: &[(&str, &str)] = &[("a", "a1"), ("b", "b1"), ("a", "a2")];
+
+    /// 首次检查与首次记录各失败一次的幂等存储
+    struct FlakyStore {
+        inner: InMemoryIdempotencyStore,
+        fail_check: AtomicBool,
+        fail_record: AtomicBool,
+    }
+
+    #[async_trait]
+    impl IdempotencyStore for FlakyStore {
+        async fn is_consumed(&self, key: &str) -> Result<bool, ConsumerError> {
+            if self.fail_check.swap(false, Ordering::SeqCst) {
+                return Err(ConsumerError::Idempotency("store unavailable".to_string()));
+            }
+            self.inner.is_consumed(key).await
+        }
+
+        async fn record_consumed(&self, key: &str) -> Result<(), ConsumerError> {
+            if self.fail_record.swap(false, Ordering::SeqCst) {
+                return Err(ConsumerError::Idempotency("store unavailable".to_string()));
+            }
+            self.inner.record_consumed(key).await
+        }
+    }
+
+    fn ordered_config(mode: KeyFailureMode, concurrency: usize) -> ConsumerConfig {
+        fast_config(3).with_concurrency(concurrency).with_ordering(
+            OrderedConfig::new()
+                .with_lanes(1)
+                .with_key_failure_mode(mode),
+        )
+    }
+
+    fn ordered_runtime(
+        broker: &InMemoryBroker,
+        handler: Arc<OrderRecorder>,
+        config: ConsumerConfig,
+    ) -> ConsumerRuntime {
+        let mut dispatcher = TopicDispatcher::new();
+        dispatcher.register("orders".to_string(), handler).unwrap();
+        ConsumerRuntime::new(config, Arc::new(dispatcher)).with_dead_letter_publisher(Arc::new(
+            ProducerDeadLetterPublisher::new(
+                Arc::new(broker.clone()),
+                FailureTopic::suffix(".dlq"),
+            ),
+        ))
+    }
+
+    async fn run_ordered(
+        broker: &InMemoryBroker,
+        handler: &OrderRecorder,
+        runtime: ConsumerRuntime,
+        sends: &[(&str, &str)],
+        expected: usize,
+    ) -> Arc<ConsumerRuntime> {
+        for (key, payload) in sends {
+            broker
+                .send(
+                    &ctx(),
+                    "orders",
+                    Some(key),
+                    payload.as_bytes().to_vec(),
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+
+        let runtime = Arc::new(runtime);
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let mut fetcher = broker.fetcher("order-service", ["orders"]);
+        let running = runtime.clone();
+        let handle =
+            tokio::spawn(async move { running.run_with_shutdown(&mut fetcher, shutdown_rx).await });
+
+        wait_until(|| handler.seen().len() == expected).await;
+        wait_until(|| broker.lag("order-service", "orders") == 0).await;
+        let _ = shutdown_tx.send(());
+        handle.await.unwrap().unwrap();
+        runtime
+    }
+
+    #[tokio::test]
+    async fn ordered_failed_key_blocks_only_its_followers() {
+        let broker = InMemoryBroker::new();
+        let handler = Arc::new(OrderRecorder::new(b"a1", false));
+        let runtime = ordered_runtime(
+            &broker,
+            handler.clone(),
+            ordered_config(KeyFailureMode::BlockUntilResolved, 4),
+        );
+
+        let runtime = run_ordered(&broker, &handler, runtime, SENDS, 4).await;
+
+        // a1 失败后 a2 等待 a1 重试成功；同一 lane 的 b1 不受影响
+        assert_eq!(handler.seen(), vec!["a1", "b1", "a1", "a2"]);
+        assert!(broker.records("orders.dlq").is_empty());
+        let status = runtime.ordered_lane_status();
+        assert_eq!(status.len(), 1);
+        assert_eq!(status[0].depth, 0);
+        assert_eq!(status[0].blocked_keys, 0);
+    }
+
+    #[tokio::test]
+    async fn blocked_key_releases_permits_for_other_keys() {
+        let broker = InMemoryBroker::new();
+        let handler = Arc::new(OrderRecorder::new(b"a1", false));
+        // 只有一个并发许可：被阻塞的 a1 与暂存的 a2 若仍持有许可，b1 要等 a1 重试后才能拉取
+        let runtime = ordered_runtime(
+            &broker,
+            handler.clone(),
+            ordered_config(KeyFailureMode::BlockUntilResolved, 1),
+        );
+
+        run_ordered(
+            &broker,
+            &handler,
+            runtime,
+            &[("a", "a1"), ("a", "a2"), ("b", "b1")],
+            4,
+        )
+        .await;
+
+        assert_eq!(handler.seen(), vec!["a1", "b1", "a1", "a2"]);
+    }
+
+    #[tokio::test]
+    async fn idempotency_failures_retry_in_lane_instead_of_dropping() {
+        let broker = InMemoryBroker::new();
+        let handler = Arc::new(OrderRecorder::new(b"", false));
+        let store = Arc::new(FlakyStore {
+            inner: InMemoryIdempotencyStore::new(),
+            fail_check: AtomicBool::new(true),
+            fail_record: AtomicBool::new(true),
+        });
+        let runtime = ordered_runtime(
+            &broker,
+            handler.clone(),
+            ordered_config(KeyFailureMode::BlockUntilResolved, 1).with_idempotent(true),
+        )
+        .with_idempotency_store(store);
+
+        run_ordered(&broker, &handler, runtime, &[("a", "a1"), ("a", "a2")], 2).await;
+
+        // 检查失败的 a1 留在 lane 内重试；记录失败只补做确认，不会再次分发
+        assert_eq!(handler.seen(), vec!["a1", "a2"]);
+        assert!(broker.records("orders.dlq").is_empty());
+    }
+
+    #[tokio::test]
+    async fn skip_mode_without_dead_letter_publisher_is_rejected() {
+        let broker = InMemoryBroker::new();
+        let handler = Arc::new(OrderRecorder::new(b"a1", true));
+        let mut dispatcher = TopicDispatcher::new();
+        dispatcher.register("orders".to_string(), handler).unwrap();
+        let config = fast_config(3).with_ordering(
+            OrderedConfig::new().with_key_failure_mode(KeyFailureMode::SkipToDeadLetter),
+        );
+        let runtime = ConsumerRuntime::new(config, Arc::new(dispatcher));
+
+        let (_shutdown_tx, shutdown_rx) = oneshot::channel();
+        let mut fetcher = broker.fetcher("order-service", ["orders"]);
+        let result = runtime.run_with_shutdown(&mut fetcher, shutdown_rx).await;
+
+        assert!(matches!(result, Err(ConsumerError::Configuration(_))));
+    }
+
+    #[tokio::test]
+    async fn ordered_skip_mode_dead_letters_failed_message_and_continues() {
+        let broker = InMemoryBroker::new();
+        let handler = Arc::new(OrderRecorder::new(b"a1", true));
+        let runtime = ordered_runtime(
+            &broker,
+            handler.clone(),
+            ordered_config(KeyFailureMode::SkipToDeadLetter, 4),
+        );
+
+        run_ordered(&broker, &handler, runtime, SENDS, 3).await;
+
+        assert_eq!(handler.seen(), vec!["a1", "b1", "a2"]);
+        let dead = broker.records("orders.dlq");
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].payload, b"a1");
+        assert_eq!(broker.terminated("order-service").len(), 1);
+    }
+}
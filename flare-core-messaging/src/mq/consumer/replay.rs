@@ -0,0 +1,658 @@
+//! 死信重放
+//!
+//! [DeadLetterReplayer] 通过任意 [MessageFetcher] 读取 DLQ topic，按 [ProducerDeadLetterPublisher]
+//! 写入的失败头（原因、错误、原始 topic）筛选，把选中的消息重置重试计数后发回原始 topic。
+//! 每条扫描到的消息都会写一条 [ReplayRecord] 审计记录。
+//!
+//! [ProducerDeadLetterPublisher]: super::failure::ProducerDeadLetterPublisher
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde::Serialize;
+
+use super::failure::{
+    HEADER_FAILURE_ERROR, HEADER_FAILURE_REASON, HEADER_ORIGINAL_TOPIC, HEADER_RETRY_COUNT,
+    HEADER_RETRY_NOT_BEFORE_UNIX_MS, unix_epoch_millis,
+};
+use super::runtime::MessageFetcher;
+use super::types::{ConsumerError, Message};
+use crate::mq::producer::Producer;
+
+/// 重放来源 DLQ topic
+pub const HEADER_REPLAYED_FROM: &str = "x-flare-replayed-from";
+/// 重放时间（Unix 毫秒）
+pub const HEADER_REPLAYED_AT_UNIX_MS: &str = "x-flare-replayed-at-unix-ms";
+
+/// 按失败头筛选死信；未设置的条件视为匹配
+#[derive(Debug, Clone, Default)]
+pub struct DeadLetterFilter {
+    pub reason: Option<String>,
+    pub error_contains: Option<String>,
+    pub original_topic: Option<String>,
+}
+
+impl DeadLetterFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 失败原因精确匹配，例如 `handler_nack`、`handler_error`
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    /// 错误信息包含子串
+    pub fn with_error_contains(mut self, needle: impl Into<String>) -> Self {
+        self.error_contains = Some(needle.into());
+        self
+    }
+
+    pub fn with_original_topic(mut self, topic: impl Into<String>) -> Self {
+        self.original_topic = Some(topic.into());
+        self
+    }
+
+    pub fn matches(&self, headers: &HashMap<String, String>) -> bool {
+        let header = |name: &str| headers.get(name).map(String::as_str);
+        self.reason
+            .as_deref()
+            .is_none_or(|reason| header(HEADER_FAILURE_REASON) == Some(reason))
+            && self.error_contains.as_deref().is_none_or(|needle| {
+                header(HEADER_FAILURE_ERROR).is_some_and(|error| error.contains(needle))
+            })
+            && self
+                .original_topic
+                .as_deref()
+                .is_none_or(|topic| header(HEADER_ORIGINAL_TOPIC) == Some(topic))
+    }
+}
+
+/// 单条死信的处理结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayDecision {
+    /// 已发回原始 topic
+    Replayed,
+    /// dry-run：匹配但未发送
+    WouldReplay,
+    /// 不匹配筛选条件
+    Skipped,
+    /// 缺少原始 topic，无法重放
+    MissingOriginalTopic,
+    /// 发送失败
+    Failed,
+}
+
+/// 审计记录
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayRecord {
+    pub message_id: String,
+    pub dlq_topic: String,
+    pub partition: i32,
+    pub offset: i64,
+    pub original_topic: Option<String>,
+    pub reason: Option<String>,
+    pub error: Option<String>,
+    pub retry_count: u32,
+    pub decision: ReplayDecision,
+    pub detail: Option<String>,
+    pub recorded_at_unix_ms: u64,
+}
+
+/// 审计记录的去向
+#[async_trait]
+pub trait ReplayAuditSink: Send + Sync {
+    async fn record(&self, record: &ReplayRecord) -> Result<(), ConsumerError>;
+}
+
+/// 内存审计，便于测试与嵌入调用方
+#[derive(Default)]
+pub struct InMemoryReplayAudit {
+    records: Mutex<Vec<ReplayRecord>>,
+}
+
+impl InMemoryReplayAudit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn records(&self) -> Vec<ReplayRecord> {
+        self.records
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+#[async_trait]
+impl ReplayAuditSink for InMemoryReplayAudit {
+    async fn record(&self, record: &ReplayRecord) -> Result<(), ConsumerError> {
+        self.records
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(record.clone());
+        Ok(())
+    }
+}
+
+/// 以 JSON Lines 写入审计记录（文件或 stdout）
+pub struct JsonLinesReplayAudit {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl JsonLinesReplayAudit {
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Mutex::new(Box::new(writer)),
+        }
+    }
+
+    /// 追加写入 `path`，不存在时创建
+    pub fn append_to(path: impl AsRef<Path>) -> Result<Self, ConsumerError> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path.as_ref())
+            .map_err(|err| {
+                ConsumerError::Configuration(format!(
+                    "failed to open replay audit file {}: {err}",
+                    path.as_ref().display()
+                ))
+            })?;
+        Ok(Self::new(file))
+    }
+}
+
+#[async_trait]
+impl ReplayAuditSink for JsonLinesReplayAudit {
+    async fn record(&self, record: &ReplayRecord) -> Result<(), ConsumerError> {
+        let line = serde_json::to_string(record)
+            .map_err(|err| ConsumerError::Serialization(err.to_string()))?;
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        writeln!(writer, "{line}")
+            .and_then(|()| writer.flush())
+            .map_err(|err| ConsumerError::Handler(format!("failed to write replay audit: {err}")))
+    }
+}
+
+/// 一次重放的统计
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ReplaySummary {
+    pub scanned: usize,
+    pub matched: usize,
+    pub replayed: usize,
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+/// 死信重放器
+///
+/// 读取直到 `idle_timeout` 内没有新消息或达到 `max_messages`。非 dry-run 时每条处理完的死信
+/// （已重放、不匹配、缺少原始 topic）都会 ack，使 Kafka 的提交 offset 越过它们；发送失败时
+/// 不确认并结束本次运行，提交 offset 停在失败的死信之前，再次运行从它继续，已重放的死信不会
+/// 重复发送。dry-run 不 ack。结束后调用 [MessageFetcher::close] 提交已确认的部分。
+pub struct DeadLetterReplayer {
+    producer: Arc<dyn Producer>,
+    audit: Arc<dyn ReplayAuditSink>,
+    filter: DeadLetterFilter,
+    dry_run: bool,
+    max_messages: Option<usize>,
+    idle_timeout: Duration,
+}
+
+impl DeadLetterReplayer {
+    pub fn new(producer: Arc<dyn Producer>, audit: Arc<dyn ReplayAuditSink>) -> Self {
+        Self {
+            producer,
+            audit,
+            filter: DeadLetterFilter::default(),
+            dry_run: false,
+            max_messages: None,
+            idle_timeout: Duration::from_secs(5),
+        }
+    }
+
+    pub fn with_filter(mut self, filter: DeadLetterFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// 只筛选与审计，不发送也不 ack
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// 最多扫描的消息数
+    pub fn with_max_messages(mut self, max: usize) -> Self {
+        self.max_messages = Some(max);
+        self
+    }
+
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    pub async fn run<MF>(&self, fetcher: &mut MF) -> Result<ReplaySummary, ConsumerError>
+    where
+        MF: MessageFetcher + Send + ?Sized,
+    {
+        let mut summary = ReplaySummary::default();
+        let mut last_message_at = Instant::now();
+
+        while self.max_messages.is_none_or(|max| summary.scanned < max) {
+            let remaining = self.idle_timeout.saturating_sub(last_message_at.elapsed());
+            if remaining.is_zero() {
+                break;
+            }
+            let message = match tokio::time::timeout(remaining, fetcher.fetch()).await {
+                Err(_) => break,
+                Ok(Ok(Some(message))) => message,
+                Ok(Ok(None)) => {
+                    tokio::time::sleep(remaining.min(Duration::from_millis(50))).await;
+                    continue;
+                }
+                Ok(Err(err)) => return Err(err),
+            };
+            last_message_at = Instant::now();
+            summary.scanned += 1;
+
+            let (decision, detail) = self.handle(&message).await;
+            match decision {
+                ReplayDecision::Replayed | ReplayDecision::WouldReplay => {
+                    summary.matched += 1;
+                    if decision == ReplayDecision::Replayed {
+                        summary.replayed += 1;
+                    }
+                }
+                ReplayDecision::Skipped => summary.skipped += 1,
+                ReplayDecision::MissingOriginalTopic | ReplayDecision::Failed => {
+                    summary.matched += 1;
+                    summary.failed += 1;
+                }
+            }
+            self.audit
+                .record(&replay_record(&message, decision, detail))
+                .await?;
+
+            if self.dry_run {
+                continue;
+            }
+            if decision == ReplayDecision::Failed {
+                // 之后的死信留给下次运行，否则提交 offset 停在这里时会重复发送它们
+                tracing::warn!(
+                    message_id = %message.context.message_id,
+                    "Stopping replay at the first failed dead-letter message"
+                );
+                break;
+            }
+            if let Some(ack) = message.ack_handle.as_ref() {
+                ack.ack().await?;
+            }
+        }
+
+        tracing::info!(
+            scanned = summary.scanned,
+            matched = summary.matched,
+            replayed = summary.replayed,
+            skipped = summary.skipped,
+            failed = summary.failed,
+            dry_run = self.dry_run,
+            "Dead-letter replay finished"
+        );
+        Ok(summary)
+    }
+
+    async fn handle(&self, message: &Message) -> (ReplayDecision, Option<String>) {
+        let headers = &message.context.headers;
+        if !self.filter.matches(headers) {
+            return (ReplayDecision::Skipped, None);
+        }
+        let Some(original_topic) = headers
+            .get(HEADER_ORIGINAL_TOPIC)
+            .filter(|topic| !topic.trim().is_empty())
+        else {
+            return (ReplayDecision::MissingOriginalTopic, None);
+        };
+        if self.dry_run {
+            return (ReplayDecision::WouldReplay, None);
+        }
+
+        let result = self
+            .producer
+            .send(
+                &message.context.ctx,
+                original_topic,
+                message.context.key.as_deref(),
+                message.payload.clone(),
+                Some(replay_headers(message)),
+            )
+            .await;
+        if let Err(err) = result {
+            tracing::warn!(
+                message_id = %message.context.message_id,
+                original_topic = %original_topic,
+                error = %err,
+                "Failed to replay dead-letter message"
+            );
+            return (ReplayDecision::Failed, Some(err.to_string()));
+        }
+        (ReplayDecision::Replayed, None)
+    }
+}
+
+/// 去掉失败与延迟头、重置重试计数，并标记重放来源
+fn replay_headers(message: &Message) -> HashMap<String, String> {
+    let mut headers = message.context.headers.clone();
+    headers.remove(HEADER_FAILURE_REASON);
+    headers.remove(HEADER_FAILURE_ERROR);
+    headers.remove(HEADER_RETRY_NOT_BEFORE_UNIX_MS);
+    headers.insert(HEADER_RETRY_COUNT.to_string(), "0".to_string());
+    headers.insert(
+        HEADER_REPLAYED_FROM.to_string(),
+        message.context.topic.clone(),
+    );
+    headers.insert(
+        HEADER_REPLAYED_AT_UNIX_MS.to_string(),
+        unix_epoch_millis().to_string(),
+    );
+    headers
+}
+
+fn replay_record(
+    message: &Message,
+    decision: ReplayDecision,
+    detail: Option<String>,
+) -> ReplayRecord {
+    let header = |name: &str| message.context.headers.get(name).cloned();
+    ReplayRecord {
+        message_id: message.context.message_id.clone(),
+        dlq_topic: message.context.topic.clone(),
+        partition: message.context.partition,
+        offset: message.context.offset,
+        original_topic: header(HEADER_ORIGINAL_TOPIC),
+        reason: header(HEADER_FAILURE_REASON),
+        error: header(HEADER_FAILURE_ERROR),
+        retry_count: message.context.retry_count,
+        decision,
+        detail,
+        recorded_at_unix_ms: unix_epoch_millis(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mq::consumer::failure::DeadLetterPublisher;
+    use crate::mq::consumer::failure::{FailureContext, FailureTopic, ProducerDeadLetterPublisher};
+    use crate::mq::consumer::types::MessageAck;
+    use crate::mq::consumer::types::{ContentType, MessageContext};
+    use crate::mq::memory::InMemoryBroker;
+    use crate::mq::producer::{ProducerError, ProducerMessage};
+    use flare_core_base::context::{Context, Ctx};
+    use std::collections::BTreeSet;
+
+    async fn dead_letter(broker: &InMemoryBroker, topic: &str, reason: &str, error: &str) {
+        let publisher = ProducerDeadLetterPublisher::new(
+            Arc::new(broker.clone()),
+            FailureTopic::fixed("orders.dlq"),
+        );
+        let mut context = MessageContext::new(
+            Arc::new(Context::with_request_id("req-dlq")),
+            topic.to_string(),
+        );
+        context.message_id = format!("{topic}-{reason}");
+        context.key = Some("order-1".to_string());
+        let message = Message::new(b"payload".to_vec(), ContentType::Raw, context);
+        publisher
+            .publish_dead_letter(&message, FailureContext::new(reason, Some(error), 3))
+            .await
+            .unwrap();
+    }
+
+    async fn seed(broker: &InMemoryBroker) {
+        dead_letter(broker, "orders", "handler_error", "connection reset").await;
+        dead_letter(broker, "orders", "handler_dead_letter", "invalid order").await;
+        dead_letter(broker, "payments", "handler_error", "connection refused").await;
+    }
+
+    #[tokio::test]
+    async fn dry_run_reports_matches_without_publishing_or_acking() {
+        let broker = InMemoryBroker::new();
+        seed(&broker).await;
+        let audit = Arc::new(InMemoryReplayAudit::new());
+        let replayer = DeadLetterReplayer::new(Arc::new(broker.clone()), audit.clone())
+            .with_filter(DeadLetterFilter::new().with_error_contains("connection"))
+            .with_dry_run(true)
+            .with_idle_timeout(Duration::from_millis(100));
+
+        let mut fetcher = broker.fetcher("dlq-inspect", ["orders.dlq"]);
+        let summary = replayer.run(&mut fetcher).await.unwrap();
+
+        assert_eq!(summary.scanned, 3);
+        assert_eq!(summary.matched, 2);
+        assert_eq!(summary.replayed, 0);
+        assert_eq!(summary.skipped, 1);
+        assert!(broker.records("orders").is_empty());
+        assert!(broker.records("payments").is_empty());
+        assert_eq!(broker.in_flight("dlq-inspect"), 3);
+        let decisions: Vec<_> = audit.records().iter().map(|r| r.decision).collect();
+        assert_eq!(
+            decisions,
+            vec![
+                ReplayDecision::WouldReplay,
+                ReplayDecision::Skipped,
+                ReplayDecision::WouldReplay
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn replay_publishes_to_original_topic_with_reset_retry_count() {
+        let broker = InMemoryBroker::new();
+        seed(&broker).await;
+        let audit = Arc::new(InMemoryReplayAudit::new());
+        let replayer = DeadLetterReplayer::new(Arc::new(broker.clone()), audit.clone())
+            .with_filter(
+                DeadLetterFilter::new()
+                    .with_reason("handler_error")
+                    .with_original_topic("orders"),
+            )
+            .with_idle_timeout(Duration::from_millis(100));
+
+        let mut fetcher = broker.fetcher("dlq-replay", ["orders.dlq"]);
+        let summary = replayer.run(&mut fetcher).await.unwrap();
+
+        assert_eq!(summary.replayed, 1);
+        assert_eq!(summary.skipped, 2);
+        let replayed = broker.records("orders");
+        assert_eq!(replayed.len(), 1);
+        let headers = &replayed[0].headers;
+        assert_eq!(replayed[0].key.as_deref(), Some("order-1"));
+        assert_eq!(
+            headers.get(HEADER_RETRY_COUNT).map(String::as_str),
+            Some("0")
+        );
+        assert_eq!(
+            headers.get(HEADER_REPLAYED_FROM).map(String::as_str),
+            Some("orders.dlq")
+        );
+        assert!(!headers.contains_key(HEADER_FAILURE_REASON));
+        assert!(broker.records("payments").is_empty());
+        assert_eq!(broker.in_flight("dlq-replay"), 0);
+
+        let records = audit.records();
+        assert_eq!(records[0].decision, ReplayDecision::Replayed);
+        assert_eq!(records[0].error.as_deref(), Some("connection reset"));
+        assert_eq!(records[0].original_topic.as_deref(), Some("orders"));
+    }
+
+    /// 按 Kafka 的语义提交：只提交连续 ack 的前缀，close 后从提交位置重新拉取
+    struct CommittingFetcher {
+        log: Vec<Message>,
+        position: usize,
+        acked: Arc<Mutex<BTreeSet<usize>>>,
+    }
+
+    impl CommittingFetcher {
+        fn committed(&self) -> usize {
+            let acked = self.acked.lock().unwrap();
+            (0..self.log.len())
+                .find(|offset| !acked.contains(offset))
+                .unwrap_or(self.log.len())
+        }
+    }
+
+    #[async_trait]
+    impl MessageFetcher for CommittingFetcher {
+        async fn fetch(&mut self) -> Result<Option<Message>, ConsumerError> {
+            let Some(message) = self.log.get(self.position) else {
+                return Ok(None);
+            };
+            let ack = OffsetAck {
+                offset: self.position,
+                acked: self.acked.clone(),
+            };
+            self.position += 1;
+            Ok(Some(message.clone().with_ack_handle(Arc::new(ack))))
+        }
+
+        async fn close(&mut self) -> Result<(), ConsumerError> {
+            self.position = self.committed();
+            Ok(())
+        }
+    }
+
+    struct OffsetAck {
+        offset: usize,
+        acked: Arc<Mutex<BTreeSet<usize>>>,
+    }
+
+    #[async_trait]
+    impl MessageAck for OffsetAck {
+        async fn ack(&self) -> Result<(), ConsumerError> {
+            self.acked.lock().unwrap().insert(self.offset);
+            Ok(())
+        }
+
+        async fn nack(&self) -> Result<(), ConsumerError> {
+            Ok(())
+        }
+
+        async fn term(&self) -> Result<(), ConsumerError> {
+            self.ack().await
+        }
+    }
+
+    /// 记录每次发送；载荷为 `flaky` 的消息第一次发送失败
+    #[derive(Default)]
+    struct FlakyProducer {
+        sent: Mutex<Vec<String>>,
+        failed_once: Mutex<bool>,
+    }
+
+    #[async_trait]
+    impl Producer for FlakyProducer {
+        async fn send(
+            &self,
+            _ctx: &Ctx,
+            _topic: &str,
+            _key: Option<&str>,
+            payload: Vec<u8>,
+            _headers: Option<HashMap<String, String>>,
+        ) -> Result<(), ProducerError> {
+            let mut failed_once = self.failed_once.lock().unwrap();
+            if payload == b"flaky" && !*failed_once {
+                *failed_once = true;
+                return Err(ProducerError::Send("broker unavailable".into()));
+            }
+            self.sent
+                .lock()
+                .unwrap()
+                .push(String::from_utf8(payload).unwrap());
+            Ok(())
+        }
+
+        async fn send_batch(
+            &self,
+            _ctx: &Ctx,
+            _messages: Vec<ProducerMessage>,
+        ) -> Result<(), ProducerError> {
+            Ok(())
+        }
+
+        fn name(&self) -> &str {
+            "flaky"
+        }
+    }
+
+    fn dlq_message(payload: &str, reason: &str) -> Message {
+        let mut context = MessageContext::new(
+            Arc::new(Context::with_request_id("req-dlq")),
+            "orders.dlq".to_string(),
+        );
+        context.message_id = payload.to_string();
+        context
+            .headers
+            .insert(HEADER_FAILURE_REASON.to_string(), reason.to_string());
+        context
+            .headers
+            .insert(HEADER_ORIGINAL_TOPIC.to_string(), "orders".to_string());
+        Message::new(payload.as_bytes().to_vec(), ContentType::Raw, context)
+    }
+
+    #[tokio::test]
+    async fn rerun_does_not_publish_replayed_messages_twice() {
+        let producer = Arc::new(FlakyProducer::default());
+        let audit = Arc::new(InMemoryReplayAudit::new());
+        let replayer = DeadLetterReplayer::new(producer.clone(), audit.clone())
+            .with_filter(DeadLetterFilter::new().with_reason("handler_error"))
+            .with_idle_timeout(Duration::from_millis(100));
+        let mut fetcher = CommittingFetcher {
+            log: vec![
+                dlq_message("a", "handler_error"),
+                dlq_message("b", "handler_dead_letter"),
+                dlq_message("c", "handler_error"),
+                dlq_message("flaky", "handler_error"),
+                dlq_message("d", "handler_error"),
+            ],
+            position: 0,
+            acked: Arc::default(),
+        };
+
+        // 第一次运行：跳过的死信也确认，发送失败时停下
+        let first = replayer.run(&mut fetcher).await.unwrap();
+        fetcher.close().await.unwrap();
+        assert_eq!(first.replayed, 2);
+        assert_eq!(first.skipped, 1);
+        assert_eq!(first.failed, 1);
+        assert_eq!(fetcher.committed(), 3);
+
+        // 第二次运行从失败的死信继续
+        let second = replayer.run(&mut fetcher).await.unwrap();
+        fetcher.close().await.unwrap();
+        assert_eq!(second.replayed, 2);
+        assert_eq!(fetcher.committed(), 5);
+
+        assert_eq!(*producer.sent.lock().unwrap(), vec!["a", "c", "flaky", "d"]);
+        let decisions: Vec<_> = audit.records().iter().map(|r| r.decision).collect();
+        assert_eq!(
+            decisions,
+            vec![
+                ReplayDecision::Replayed,
+                ReplayDecision::Skipped,
+                ReplayDecision::Replayed,
+                ReplayDecision::Failed,
+                ReplayDecision::Replayed,
+                ReplayDecision::Replayed,
+            ]
+        );
+    }
+}
@@ -6,80 +6,65 @@
 //! 与 [super::task::MqConsumer] / `flare_core_runtime::ServiceRuntime` 集成：使用 [ConsumerRuntimeTask]，
 //! 通过 `ServiceRuntime::add_mq_consumer` 或 `ServiceRuntime::add_mq_consumer_runtime` 注册。
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, VecDeque};
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Instant;
 
 use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+use tokio::sync::mpsc;
 use tokio::sync::oneshot;
-use tokio::sync::{Mutex, Semaphore};
+use tokio::sync::watch;
 use tokio::task::JoinSet;
-use tokio::time::Duration;
+use tokio::time::{Duration, MissedTickBehavior};
+use tokio_util::sync::{CancellationToken, DropGuard};
 
 use flare_core_runtime::config::{PollWorkerConfig, RuntimeConfig};
+use flare_core_runtime::health::HealthCheck;
 use flare_core_runtime::task::TaskResult;
 
 use super::task::MqConsumer;
 
+use super::control::{ConsumerHandle, ControlState, RebalanceListener};
+use super::delay::{DEFAULT_SLOTS, DEFAULT_TICK, Deferrals, Parked, TimerWheel, not_before_delay};
 use super::dispatcher::Dispatcher;
 use super::failure::{
-    ConsumerFailurePublishers, DeadLetterPublisher, FailureAction, FailureContext, RetryPolicy,
-    RetryPublisher,
+    ConsumerFailurePublishers, DeadLetterPublisher, FailureAction, FailureContext, RetryPolicies,
+    RetryPolicy, RetryPublisher,
+};
+use super::idempotency::{ClaimOutcome, IdempotencyClaim, IdempotencyGate};
+use super::metrics::{ConsumerLag, ConsumerMetrics, MessageLabels, Outcome, RuntimeMetrics};
+use super::ordered::{
+    KeyFailureMode, LaneCounters, OrderedConfig, OrderedContext, OrderedLaneStatus, OrderedLanes,
+    lane_counters, lane_status,
 };
 use super::types::{ConsumerError, Message, MessageResult};
 
-type OrderedKeyLocks = Arc<Mutex<HashMap<String, Arc<Mutex<()>>>>>;
+pub use super::idempotency::{IdempotencyStore, InMemoryIdempotencyStore, NoopIdempotencyStore};
+
 type BatchAckHandle = (
     String,
     String,
+    IdempotencyClaim,
     u32,
     Option<Arc<dyn super::types::MessageAck>>,
     Option<Message>,
+    MessageLabels,
 );
-const ORDERED_BATCH_KEY_CONCURRENCY: usize = 64;
-
-/// Generic consumer idempotency port.
-///
-/// Implementations should treat keys as already namespaced by the runtime
-/// (`topic:message_id` or broker offset fallback). The default no-op store keeps
-/// existing behavior for callers that have not wired Redis/DB yet.
-#[async_trait::async_trait]
-pub trait IdempotencyStore: Send + Sync {
-    async fn is_consumed(&self, key: &str) -> Result<bool, ConsumerError>;
-    async fn record_consumed(&self, key: &str) -> Result<(), ConsumerError>;
-}
-
-#[derive(Default)]
-pub struct NoopIdempotencyStore;
 
-#[async_trait::async_trait]
-impl IdempotencyStore for NoopIdempotencyStore {
-    async fn is_consumed(&self, _key: &str) -> Result<bool, ConsumerError> {
-        Ok(false)
-    }
-
-    async fn record_consumed(&self, _key: &str) -> Result<(), ConsumerError> {
-        Ok(())
-    }
-}
+/// 默认的消费延迟采样间隔
+const DEFAULT_LAG_INTERVAL: Duration = Duration::from_secs(30);
 
-#[derive(Default)]
-pub struct InMemoryIdempotencyStore {
-    consumed: Mutex<HashSet<String>>,
-}
+/// 默认的健康检查间隔
+const DEFAULT_HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
 
-#[async_trait::async_trait]
-impl IdempotencyStore for InMemoryIdempotencyStore {
-    async fn is_consumed(&self, key: &str) -> Result<bool, ConsumerError> {
-        Ok(self.consumed.lock().await.contains(key))
-    }
+/// 被暂停 topic 暂存消息的上限；达到后停止拉取直到恢复
+const MAX_HELD_MESSAGES: usize = 1024;
 
-    async fn record_consumed(&self, key: &str) -> Result<(), ConsumerError> {
-        self.consumed.lock().await.insert(key.to_string());
-        Ok(())
-    }
-}
+/// 时间轮停放消息的上限；达到后停止拉取直到有消息到期
+const MAX_PARKED_MESSAGES: usize = 10_000;
 
 /// 消费者配置
 #[derive(Debug, Clone)]
@@ -92,14 +77,24 @@ pub struct ConsumerConfig {
     pub batch_timeout_ms: u64,
     /// 是否启用顺序消费
     pub ordered: bool,
+    /// 顺序消费的 lane 与失败处理配置
+    pub ordering: OrderedConfig,
     /// 是否启用幂等性检查
     pub idempotent: bool,
+    /// 幂等“先声明后提交”的租约；`None` 时为“检查后记录”
+    pub idempotency_claim_lease: Option<Duration>,
     /// 最大重试次数
     pub max_retries: u32,
     /// 是否启用 DLQ
     pub enable_dlq: bool,
     /// JetStream：覆盖 `JetStreamConsumerConfig::consumer_group`（`None` 时使用传入的 JetStream 配置）
     pub consumer_group_override: Option<String>,
+    /// 运行时 Prometheus 指标；`None` 时只维护 [ConsumerStats]
+    pub metrics: Option<ConsumerMetrics>,
+    /// 从 [MessageFetcher::lag] 采样消费延迟的间隔
+    pub lag_interval: Duration,
+    /// [ConsumerRuntime::with_health_check] 注册的检查执行间隔，同时作为单次检查的超时
+    pub health_check_interval: Duration,
 }
 
 impl Default for ConsumerConfig {
@@ -109,10 +104,15 @@ impl Default for ConsumerConfig {
             batch_size: 1,
             batch_timeout_ms: 1000,
             ordered: false,
+            ordering: OrderedConfig::default(),
             idempotent: true,
+            idempotency_claim_lease: None,
             max_retries: 3,
             enable_dlq: true,
             consumer_group_override: None,
+            metrics: None,
+            lag_interval: DEFAULT_LAG_INTERVAL,
+            health_check_interval: DEFAULT_HEALTH_CHECK_INTERVAL,
         }
     }
 }
@@ -153,41 +153,88 @@ impl ConsumerConfig {
         self
     }
 
+    /// 启用顺序消费并指定 lane 配置
+    pub fn with_ordering(mut self, ordering: OrderedConfig) -> Self {
+        self.ordered = true;
+        self.ordering = ordering;
+        self
+    }
+
+    fn ordered_lane_count(&self) -> usize {
+        self.ordering.lanes.unwrap_or(self.poll.concurrency).max(1)
+    }
+
     pub fn with_idempotent(mut self, idempotent: bool) -> Self {
         self.idempotent = idempotent;
         self
     }
 
+    /// 启用“先声明后提交”：分发前 `try_claim`，同一消息的并发重投不会同时执行 handler。
+    /// `lease` 应大于单条消息的最长处理时间。
+    pub fn with_idempotency_claim(mut self, lease: Duration) -> Self {
+        self.idempotent = true;
+        self.idempotency_claim_lease = Some(lease);
+        self
+    }
+
     /// JetStream：设置 `group.id` 覆盖（与 `JetStreamMessageFetcher::new_with_consumer_group` 一致）
     pub fn with_consumer_group(mut self, group_id: impl Into<String>) -> Self {
         self.consumer_group_override = Some(group_id.into());
         self
     }
+
+    /// 导出运行时指标；多个运行时共享时用 [ConsumerMetrics::for_consumer] 区分
+    pub fn with_metrics(mut self, metrics: ConsumerMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn with_lag_interval(mut self, interval: Duration) -> Self {
+        self.lag_interval = interval;
+        self
+    }
+
+    pub fn with_health_check_interval(mut self, interval: Duration) -> Self {
+        self.health_check_interval = interval;
+        self
+    }
 }
 
 /// 消费者运行时
 pub struct ConsumerRuntime {
     config: ConsumerConfig,
     dispatcher: Arc<dyn Dispatcher>,
-    key_locks: Arc<Mutex<HashMap<String, Arc<Mutex<()>>>>>,
-    retry_policy: RetryPolicy,
+    lane_counters: Arc<[LaneCounters]>,
+    retry_policies: Arc<RetryPolicies>,
     retry_publisher: Option<Arc<dyn RetryPublisher>>,
     dead_letter_publisher: Option<Arc<dyn DeadLetterPublisher>>,
     idempotency_store: Arc<dyn IdempotencyStore>,
+    metrics: RuntimeMetrics,
+    control: ConsumerHandle,
+    health_checks: Vec<Arc<dyn HealthCheck>>,
 }
 
 impl ConsumerRuntime {
     /// 创建新的消费者运行时
     pub fn new(config: ConsumerConfig, dispatcher: Arc<dyn Dispatcher>) -> Self {
-        let retry_policy = RetryPolicy::from(&config);
+        let retry_policies = Arc::new(RetryPolicies::new(RetryPolicy::from(&config)));
+        let lane_counters = lane_counters(if config.ordered {
+            config.ordered_lane_count()
+        } else {
+            0
+        });
+        let metrics = RuntimeMetrics::new(config.metrics.clone());
         Self {
             config,
             dispatcher,
-            key_locks: Arc::new(Mutex::new(HashMap::new())),
-            retry_policy,
+            lane_counters,
+            retry_policies,
             retry_publisher: None,
             dead_letter_publisher: None,
             idempotency_store: Arc::new(NoopIdempotencyStore),
+            metrics,
+            control: ConsumerHandle::new(),
+            health_checks: Vec::new(),
         }
     }
 
@@ -206,12 +253,149 @@ impl ConsumerRuntime {
         self
     }
 
+    /// 覆盖由 [ConsumerConfig] 推导的默认重试策略（例如设置退避、按错误类型的上限）
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        Arc::make_mut(&mut self.retry_policies).set_default(policy);
+        self
+    }
+
+    /// 为单个 topic 指定重试策略
+    pub fn with_topic_retry_policy(
+        mut self,
+        topic: impl Into<String>,
+        policy: RetryPolicy,
+    ) -> Self {
+        Arc::make_mut(&mut self.retry_policies).insert(topic, policy);
+        self
+    }
+
+    /// 整体替换按 topic 的重试策略
+    pub fn with_retry_policies(mut self, policies: RetryPolicies) -> Self {
+        self.retry_policies = Arc::new(policies);
+        self
+    }
+
     pub fn with_failure_publishers(mut self, publishers: ConsumerFailurePublishers) -> Self {
         self.retry_publisher = publishers.retry;
         self.dead_letter_publisher = publishers.dead_letter;
         self
     }
 
+    /// 依赖检查失败时暂停全部拉取，恢复后自动继续；按 [ConsumerConfig::health_check_interval] 执行
+    pub fn with_health_check(mut self, check: Arc<dyn HealthCheck>) -> Self {
+        self.health_checks.push(check);
+        self
+    }
+
+    /// 分区分配/回收回调，回收回调返回后才提交 offset
+    pub fn with_rebalance_listener(self, listener: Arc<dyn RebalanceListener>) -> Self {
+        self.control.add_rebalance_listener(listener);
+        self
+    }
+
+    /// 暂停/恢复句柄；可注册到 `ServiceRuntime::add_consumer_control`
+    pub fn handle(&self) -> ConsumerHandle {
+        self.control.clone()
+    }
+
+    fn idempotency_gate(&self, fetch: &FetchState) -> Option<IdempotencyGate> {
+        self.config.idempotent.then(|| {
+            IdempotencyGate::new(
+                self.idempotency_store.clone(),
+                self.config.idempotency_claim_lease,
+            )
+            .with_deferrals(fetch.deferrals.clone())
+        })
+    }
+
+    /// 启动以来的消息处理计数
+    pub fn stats(&self) -> ConsumerStats {
+        self.metrics.stats()
+    }
+
+    /// 顺序消费各 lane 的排队深度、被阻塞 key 数与背压次数；未启用顺序消费时为空
+    pub fn ordered_lane_status(&self) -> Vec<OrderedLaneStatus> {
+        lane_status(
+            &self.lane_counters,
+            self.config.ordering.lane_capacity.max(1),
+        )
+    }
+
+    /// 启用顺序消费时启动 lane；`SkipToDeadLetter` 未配置死信发布器时拒绝启动
+    fn ordered_lanes(
+        &self,
+        idempotency: &Option<IdempotencyGate>,
+    ) -> Result<Option<OrderedLanes>, ConsumerError> {
+        if !self.config.ordered {
+            return Ok(None);
+        }
+        if self.config.ordering.on_key_failure == KeyFailureMode::SkipToDeadLetter
+            && self.dead_letter_publisher.is_none()
+        {
+            return Err(ConsumerError::Configuration(
+                "ordered consumption with SkipToDeadLetter requires a dead letter publisher"
+                    .to_string(),
+            ));
+        }
+        Ok(Some(OrderedLanes::spawn(
+            OrderedContext {
+                dispatcher: self.dispatcher.clone(),
+                retry_policies: self.retry_policies.clone(),
+                retry_publisher: self.retry_publisher.clone(),
+                dead_letter_publisher: self.dead_letter_publisher.clone(),
+                idempotency: idempotency.clone(),
+                on_key_failure: self.config.ordering.on_key_failure,
+                metrics: self.metrics.clone(),
+            },
+            self.lane_counters.clone(),
+            self.config.ordering.lane_capacity,
+        )))
+    }
+
+    /// 顺序消费时投递到 lane（lane 已满时等待），否则直接派生处理任务
+    async fn submit(
+        &self,
+        messages: Vec<Message>,
+        permit: OwnedSemaphorePermit,
+        idempotency: &Option<IdempotencyGate>,
+        deferrals: &Deferrals,
+        lanes: Option<&OrderedLanes>,
+        tasks: &mut JoinSet<Result<(), ConsumerError>>,
+    ) {
+        let batched = self.config.batch_size > 1;
+        if let Some(lanes) = lanes {
+            lanes.submit(messages, batched, permit).await;
+            return;
+        }
+        if batched {
+            Self::spawn_batch_task(
+                messages,
+                permit,
+                &self.dispatcher,
+                &self.retry_policies,
+                &self.retry_publisher,
+                &self.dead_letter_publisher,
+                idempotency,
+                deferrals,
+                &self.metrics,
+                tasks,
+            );
+        } else if let Some(message) = messages.into_iter().next() {
+            Self::spawn_message_task(
+                message,
+                permit,
+                &self.dispatcher,
+                &self.retry_policies,
+                &self.retry_publisher,
+                &self.dead_letter_publisher,
+                idempotency,
+                deferrals,
+                &self.metrics,
+                tasks,
+            );
+        }
+    }
+
     /// 持续拉取并处理消息，直到进程退出或 `fetch` 侧永久失败（与 [Self::run_with_shutdown] 相对，用于独立 JetStream/NATS 入口）
     pub async fn run<MF>(&self, message_fetcher: &mut MF) -> Result<(), ConsumerError>
     where
@@ -223,62 +407,62 @@ impl ConsumerRuntime {
         let error_backoff = self.config.poll.error_backoff;
         let batch_size = self.config.batch_size.max(1);
         let batch_timeout = Duration::from_millis(self.config.batch_timeout_ms.max(1));
+        let mut fetch = self.fetch_state(message_fetcher);
+        let idempotency = self.idempotency_gate(&fetch);
+        let lanes = self.ordered_lanes(&idempotency)?;
+        let _health_watch = self.spawn_health_watch();
+        let mut next_lag_sample = Instant::now();
 
         loop {
-            let permit = Self::acquire_slot(&semaphore).await?;
-            match message_fetcher.fetch().await {
-                Ok(Some(message)) => {
-                    if batch_size > 1 {
-                        match Self::fetch_batch(message_fetcher, message, batch_size, batch_timeout)
-                            .await
-                        {
-                            Ok(messages) => {
-                                Self::spawn_batch_task(
-                                    messages,
-                                    permit,
-                                    &self.dispatcher,
-                                    &self.retry_policy,
-                                    &self.retry_publisher,
-                                    &self.dead_letter_publisher,
-                                    &self.idempotency_store,
-                                    &mut tasks,
-                                    self.config.ordered,
-                                    self.config.idempotent,
-                                    &self.key_locks,
-                                );
-                            }
-                            Err(e) => {
-                                drop(permit);
-                                tracing::error!(error = %e, "Failed to fetch message batch");
-                                tokio::time::sleep(error_backoff).await;
-                            }
+            let permit = self.acquire_slot(&semaphore).await?;
+            self.sample_lag(message_fetcher, &mut next_lag_sample).await;
+            if Self::sync_control(message_fetcher, &mut fetch).await {
+                drop(permit);
+                fetch.control_changed().await;
+                continue;
+            }
+            let messages = match Self::next_message(message_fetcher, &mut fetch).await {
+                Ok(Some(message)) if batch_size > 1 => {
+                    match Self::fetch_batch(
+                        message_fetcher,
+                        &mut fetch,
+                        message,
+                        batch_size,
+                        batch_timeout,
+                    )
+                    .await
+                    {
+                        Ok(messages) => messages,
+                        Err(e) => {
+                            drop(permit);
+                            tracing::error!(error = %e, "Failed to fetch message batch");
+                            tokio::time::sleep(error_backoff).await;
+                            continue;
                         }
-                    } else {
-                        Self::spawn_message_task(
-                            message,
-                            permit,
-                            &self.dispatcher,
-                            &self.retry_policy,
-                            &self.retry_publisher,
-                            &self.dead_letter_publisher,
-                            &self.idempotency_store,
-                            &mut tasks,
-                            self.config.ordered,
-                            self.config.idempotent,
-                            &self.key_locks,
-                        );
                     }
                 }
+                Ok(Some(message)) => vec![message],
                 Ok(None) => {
                     drop(permit);
-                    tokio::time::sleep(idle_backoff).await;
+                    fetch.idle(idle_backoff).await;
+                    continue;
                 }
                 Err(e) => {
                     drop(permit);
                     tracing::error!(error = %e, "Failed to fetch message");
                     tokio::time::sleep(error_backoff).await;
+                    continue;
                 }
-            }
+            };
+            self.submit(
+                messages,
+                permit,
+                &idempotency,
+                &fetch.deferrals,
+                lanes.as_ref(),
+                &mut tasks,
+            )
+            .await;
         }
     }
 
@@ -297,6 +481,11 @@ impl ConsumerRuntime {
         let error_backoff = self.config.poll.error_backoff;
         let batch_size = self.config.batch_size.max(1);
         let batch_timeout = Duration::from_millis(self.config.batch_timeout_ms.max(1));
+        let mut fetch = self.fetch_state(message_fetcher);
+        let idempotency = self.idempotency_gate(&fetch);
+        let lanes = self.ordered_lanes(&idempotency)?;
+        let health_watch = self.spawn_health_watch();
+        let mut next_lag_sample = Instant::now();
 
         loop {
             let permit = tokio::select! {
@@ -304,73 +493,83 @@ impl ConsumerRuntime {
                     tracing::info!("Shutdown signal received, stopping consumer");
                     break;
                 }
-                permit = Self::acquire_slot(&semaphore) => {
+                permit = self.acquire_slot(&semaphore) => {
                     permit?
                 }
             };
+            self.sample_lag(message_fetcher, &mut next_lag_sample).await;
+            if Self::sync_control(message_fetcher, &mut fetch).await {
+                drop(permit);
+                tokio::select! {
+                    _ = &mut shutdown_rx => {
+                        tracing::info!("Shutdown signal received, stopping consumer");
+                        break;
+                    }
+                    _ = fetch.control_changed() => continue,
+                }
+            }
 
-            tokio::select! {
+            let fetched = tokio::select! {
                 _ = &mut shutdown_rx => {
                     drop(permit);
                     tracing::info!("Shutdown signal received, stopping consumer");
                     break;
                 }
-                result = message_fetcher.fetch() => {
-                    match result {
-                        Ok(Some(message)) => {
-                            if batch_size > 1 {
-                                match Self::fetch_batch(message_fetcher, message, batch_size, batch_timeout).await {
-                                    Ok(messages) => {
-                                        Self::spawn_batch_task(
-                                            messages,
-                                            permit,
-                                            &self.dispatcher,
-                                            &self.retry_policy,
-                                            &self.retry_publisher,
-                                            &self.dead_letter_publisher,
-                                            &self.idempotency_store,
-                                            &mut tasks,
-                                            self.config.ordered,
-                                            self.config.idempotent,
-                                            &self.key_locks,
-                                        );
-                                    }
-                                    Err(e) => {
-                                        drop(permit);
-                                        tracing::error!(error = %e, "Failed to fetch message batch");
-                                        tokio::time::sleep(error_backoff).await;
-                                    }
-                                }
-                            } else {
-                                Self::spawn_message_task(
-                                    message,
-                                    permit,
-                                    &self.dispatcher,
-                                    &self.retry_policy,
-                                    &self.retry_publisher,
-                                    &self.dead_letter_publisher,
-                                    &self.idempotency_store,
-                                    &mut tasks,
-                                    self.config.ordered,
-                                    self.config.idempotent,
-                                    &self.key_locks,
-                                );
-                            }
-                        }
-                        Ok(None) => {
-                            drop(permit);
-                            tokio::time::sleep(idle_backoff).await;
-                        }
+                result = Self::next_message(message_fetcher, &mut fetch) => result,
+            };
+            let messages = match fetched {
+                Ok(Some(message)) if batch_size > 1 => {
+                    match Self::fetch_batch(
+                        message_fetcher,
+                        &mut fetch,
+                        message,
+                        batch_size,
+                        batch_timeout,
+                    )
+                    .await
+                    {
+                        Ok(messages) => messages,
                         Err(e) => {
                             drop(permit);
-                            tracing::error!(error = %e, "Failed to fetch message");
+                            tracing::error!(error = %e, "Failed to fetch message batch");
                             tokio::time::sleep(error_backoff).await;
+                            continue;
                         }
                     }
                 }
+                Ok(Some(message)) => vec![message],
+                Ok(None) => {
+                    drop(permit);
+                    tokio::select! {
+                        _ = &mut shutdown_rx => {
+                            tracing::info!("Shutdown signal received, stopping consumer");
+                            break;
+                        }
+                        _ = fetch.idle(idle_backoff) => continue,
+                    }
+                }
+                Err(e) => {
+                    drop(permit);
+                    tracing::error!(error = %e, "Failed to fetch message");
+                    tokio::time::sleep(error_backoff).await;
+                    continue;
+                }
+            };
+
+            // lane 已满时 submit 会等待，关闭信号仍需及时响应
+            tokio::select! {
+                _ = &mut shutdown_rx => {
+                    tracing::info!("Shutdown signal received, stopping consumer");
+                    break;
+                }
+                _ = self.submit(messages, permit, &idempotency, &fetch.deferrals, lanes.as_ref(), &mut tasks) => {}
             }
         }
 
+        drop(health_watch);
+        if let Some(lanes) = lanes {
+            lanes.close().await;
+        }
         while let Some(result) = tasks.join_next().await {
             match result {
                 Ok(Ok(())) => {}
@@ -379,22 +578,269 @@ impl ConsumerRuntime {
             }
         }
 
+        fetch.collect_deferred();
+        let mut parked = 0;
+        for entry in fetch.parked.drain() {
+            match entry {
+                // 退避中的重试立即交还 broker，不留在进程内
+                Parked::Nack(ack) => Self::nack_parked(ack.as_ref()).await,
+                Parked::Dispatch(_) => parked += 1,
+            }
+        }
+        if parked > 0 {
+            tracing::info!(
+                parked,
+                "Delayed messages left unacknowledged for broker redelivery"
+            );
+        }
+        if !fetch.held.is_empty() {
+            tracing::info!(
+                held = fetch.held.len(),
+                "Messages of paused topics left unacknowledged for broker redelivery"
+            );
+        }
+        if let Err(e) = message_fetcher.close().await {
+            tracing::warn!(error = %e, "Failed to close message fetcher");
+        }
         tracing::info!("Consumer runtime stopped gracefully");
         Ok(())
     }
 
+    /// 获取并发许可；许可全部被占用时计一次等待
     async fn acquire_slot(
+        &self,
         semaphore: &Arc<Semaphore>,
     ) -> Result<OwnedSemaphorePermit, ConsumerError> {
-        semaphore
+        let capacity = self.config.poll.concurrency.max(1);
+        if semaphore.available_permits() == 0 {
+            self.metrics.permit_wait();
+        }
+        let permit = semaphore
             .clone()
             .acquire_owned()
             .await
-            .map_err(|_| ConsumerError::Configuration("consumer semaphore closed".into()))
+            .map_err(|_| ConsumerError::Configuration("consumer semaphore closed".into()))?;
+        self.metrics
+            .permits(capacity, semaphore.available_permits());
+        Ok(permit)
+    }
+
+    /// 到达采样间隔时从拉取器读取消费延迟；未配置指标时不采样
+    async fn sample_lag<MF>(&self, message_fetcher: &mut MF, next_sample: &mut Instant)
+    where
+        MF: MessageFetcher + Send + ?Sized,
+    {
+        let now = Instant::now();
+        if !self.metrics.is_exporting() || now < *next_sample {
+            return;
+        }
+        *next_sample = now + self.config.lag_interval;
+        match message_fetcher.lag().await {
+            Ok(lags) => self.metrics.export_lag(&lags),
+            Err(e) => tracing::debug!(error = %e, "Failed to sample consumer lag"),
+        }
+    }
+
+    /// 拉取器、健康检查与运行时共用的拉取侧状态
+    fn fetch_state<MF>(&self, message_fetcher: &mut MF) -> FetchState
+    where
+        MF: MessageFetcher + Send + ?Sized,
+    {
+        message_fetcher.set_rebalance_listener(Arc::new(self.control.clone()));
+        FetchState::new(self.control.subscribe())
+    }
+
+    /// 周期执行健康检查并写入控制状态；返回的 guard 释放时停止
+    fn spawn_health_watch(&self) -> Option<DropGuard> {
+        if self.health_checks.is_empty() {
+            return None;
+        }
+        let token = CancellationToken::new();
+        let cancelled = token.clone();
+        let checks = self.health_checks.clone();
+        let control = self.control.clone();
+        let interval = self
+            .config
+            .health_check_interval
+            .max(Duration::from_millis(1));
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = cancelled.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                for check in &checks {
+                    let result = match tokio::time::timeout(interval, check.check()).await {
+                        Ok(result) => result.map_err(|e| e.to_string()),
+                        Err(_) => Err("timed out".to_string()),
+                    };
+                    if !control.set_health(check.name(), result.is_ok()) {
+                        continue;
+                    }
+                    match result {
+                        Ok(()) => tracing::info!(
+                            check = check.name(),
+                            "Health check recovered, resuming consumer"
+                        ),
+                        Err(e) => tracing::warn!(
+                            check = check.name(),
+                            error = %e,
+                            "Health check failed, pausing consumer"
+                        ),
+                    }
+                }
+            }
+        });
+        Some(token.drop_guard())
+    }
+
+    /// 把控制状态的变化同步给拉取器；返回 `true` 表示应整体停止拉取，
+    /// 由调用方等待下一次状态变化
+    async fn sync_control<MF>(message_fetcher: &mut MF, fetch: &mut FetchState) -> bool
+    where
+        MF: MessageFetcher + Send + ?Sized,
+    {
+        if fetch.synced && !fetch.control.has_changed().unwrap_or(false) {
+            return fetch.paused.is_suspended() && !fetch.native_pause;
+        }
+        let state = fetch.control.borrow_and_update().clone();
+        fetch.synced = true;
+        if state.is_suspended() != fetch.paused.is_suspended()
+            || state.paused_topics != fetch.paused.paused_topics
+        {
+            tracing::info!(
+                suspended = state.is_suspended(),
+                paused_topics = ?state.paused_topics,
+                unhealthy = ?state.unhealthy,
+                "Consumer pause state changed"
+            );
+        }
+        fetch.native_pause = match message_fetcher
+            .set_paused(state.is_suspended(), &state.paused_topics)
+            .await
+        {
+            Ok(native) => native,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to pause partitions on broker, pausing locally");
+                false
+            }
+        };
+        fetch.paused = state;
+        fetch.paused.is_suspended() && !fetch.native_pause
+    }
+
+    /// 取下一条可分发的消息：先取到期的停放消息，再取已恢复 topic 的暂存消息，最后从 broker 拉取；
+    /// 未到 not-before 时间的消息交给 [Self::defer_until_due]，被暂停 topic 的消息暂存到恢复。
+    /// 等待拉取期间停放消息到期会中断拉取；停放数达到 [MAX_PARKED_MESSAGES] 时暂停拉取。
+    /// 控制状态变化时返回 `None`，由调用方重新同步
+    async fn next_message<MF>(
+        message_fetcher: &mut MF,
+        fetch: &mut FetchState,
+    ) -> Result<Option<Message>, ConsumerError>
+    where
+        MF: MessageFetcher + Send + ?Sized,
+    {
+        loop {
+            fetch.collect_deferred();
+            match fetch.parked.pop_due(Instant::now()) {
+                Some(Parked::Dispatch(message)) => match fetch.hold_if_paused(*message) {
+                    Some(message) => return Ok(Some(message)),
+                    None => continue,
+                },
+                Some(Parked::Nack(ack)) => {
+                    Self::nack_parked(ack.as_ref()).await;
+                    continue;
+                }
+                None => {}
+            }
+            if let Some(message) = fetch.release_held() {
+                return Ok(Some(message));
+            }
+            if fetch.held.len() >= MAX_HELD_MESSAGES {
+                return Ok(None);
+            }
+            let next_due = fetch.parked.next_deadline();
+            let wake_at = tokio::time::Instant::from_std(next_due.unwrap_or_else(Instant::now));
+            if fetch.parked.len() >= MAX_PARKED_MESSAGES {
+                // 停放已满：暂停拉取，等最早的停放消息到期
+                tokio::select! {
+                    _ = tokio::time::sleep_until(wake_at) => continue,
+                    _ = fetch.control.changed() => {
+                        fetch.synced = false;
+                        return Ok(None);
+                    }
+                }
+            }
+            // 拉取可能长时间阻塞，停放消息到期时中断拉取先分发到期消息
+            let fetched = tokio::select! {
+                result = message_fetcher.fetch() => Some(result),
+                _ = fetch.control.changed() => None,
+                Some((due, parked)) = fetch.deferred.recv() => {
+                    fetch.parked.insert(due, parked);
+                    continue;
+                }
+                _ = tokio::time::sleep_until(wake_at), if next_due.is_some() => continue,
+            };
+            let Some(fetched) = fetched else {
+                fetch.synced = false;
+                return Ok(None);
+            };
+            let Some(message) = fetched? else {
+                return Ok(None);
+            };
+            let Some(message) = Self::defer_until_due(message, &mut fetch.parked).await? else {
+                // 已停放或交还 broker：源未取空，继续拉取而不是按空闲退避
+                continue;
+            };
+            if let Some(message) = fetch.hold_if_paused(message) {
+                return Ok(Some(message));
+            }
+        }
+    }
+
+    /// 已到期返回原消息；否则支持延迟 nack 的 broker 交还 broker，其余停放到时间轮
+    async fn defer_until_due(
+        message: Message,
+        parked: &mut TimerWheel<Parked>,
+    ) -> Result<Option<Message>, ConsumerError> {
+        let Some(delay) = not_before_delay(&message.context.headers) else {
+            return Ok(Some(message));
+        };
+
+        if let Some(ack) = message.ack_handle.as_ref()
+            && ack.supports_delayed_nack()
+        {
+            ack.nack_with_delay(delay).await?;
+            tracing::trace!(
+                message_id = %message.context.message_id,
+                delay_ms = delay.as_millis() as u64,
+                "Message not yet due, handed back to broker"
+            );
+            return Ok(None);
+        }
+
+        tracing::trace!(
+            message_id = %message.context.message_id,
+            delay_ms = delay.as_millis() as u64,
+            "Message not yet due, parked"
+        );
+        parked.insert(Instant::now() + delay, Parked::Dispatch(Box::new(message)));
+        Ok(None)
+    }
+
+    /// 退避到期后 nack，由 broker 重投
+    async fn nack_parked(ack: &dyn super::types::MessageAck) {
+        if let Err(e) = ack.nack().await {
+            tracing::warn!(error = %e, "Failed to nack message after retry backoff");
+        }
     }
 
     async fn fetch_batch<MF>(
         message_fetcher: &mut MF,
+        fetch: &mut FetchState,
         first: Message,
         batch_size: usize,
         batch_timeout: Duration,
@@ -415,7 +861,7 @@ impl ConsumerRuntime {
         while messages.len() < batch_size {
             tokio::select! {
                 _ = &mut timeout => break,
-                result = message_fetcher.fetch() => {
+                result = Self::next_message(message_fetcher, fetch) => {
                     match result? {
                         Some(message) => messages.push(message),
                         None => break,
@@ -432,54 +878,35 @@ impl ConsumerRuntime {
         message: Message,
         permit: OwnedSemaphorePermit,
         dispatcher: &Arc<dyn Dispatcher>,
-        retry_policy: &RetryPolicy,
+        retry_policies: &Arc<RetryPolicies>,
         retry_publisher: &Option<Arc<dyn RetryPublisher>>,
         dead_letter_publisher: &Option<Arc<dyn DeadLetterPublisher>>,
-        idempotency_store: &Arc<dyn IdempotencyStore>,
+        idempotency_store: &Option<IdempotencyGate>,
+        deferrals: &Deferrals,
+        metrics: &RuntimeMetrics,
         tasks: &mut JoinSet<Result<(), ConsumerError>>,
-        ordered: bool,
-        idempotent: bool,
-        key_locks: &OrderedKeyLocks,
     ) {
         let dispatcher = dispatcher.clone();
-        let key_locks = key_locks.clone();
-        let retry_policy = retry_policy.clone();
+        let retry_policies = retry_policies.clone();
         let retry_publisher = retry_publisher.clone();
         let dead_letter_publisher = dead_letter_publisher.clone();
-        let idempotency_store = idempotent.then(|| idempotency_store.clone());
+        let idempotency_store = idempotency_store.clone();
+        let deferrals = deferrals.clone();
+        let metrics = metrics.clone();
 
         tasks.spawn(async move {
             let _permit = permit;
-            if ordered {
-                let key = Self::ordered_key(&message);
-                let lock = {
-                    let mut locks = key_locks.lock().await;
-                    locks
-                        .entry(key)
-                        .or_insert_with(|| Arc::new(Mutex::new(())))
-                        .clone()
-                };
-                let _key_guard = lock.lock().await;
-                Self::process_message(
-                    dispatcher,
-                    message,
-                    retry_policy,
-                    retry_publisher,
-                    dead_letter_publisher,
-                    idempotency_store,
-                )
-                .await
-            } else {
-                Self::process_message(
-                    dispatcher,
-                    message,
-                    retry_policy,
-                    retry_publisher,
-                    dead_letter_publisher,
-                    idempotency_store,
-                )
-                .await
-            }
+            Self::process_message(
+                dispatcher,
+                message,
+                retry_policies,
+                retry_publisher,
+                dead_letter_publisher,
+                idempotency_store,
+                Some(deferrals),
+                metrics,
+            )
+            .await
         });
 
         if let Some(result) = tasks.try_join_next() {
@@ -496,46 +923,35 @@ impl ConsumerRuntime {
         messages: Vec<Message>,
         permit: OwnedSemaphorePermit,
         dispatcher: &Arc<dyn Dispatcher>,
-        retry_policy: &RetryPolicy,
+        retry_policies: &Arc<RetryPolicies>,
         retry_publisher: &Option<Arc<dyn RetryPublisher>>,
         dead_letter_publisher: &Option<Arc<dyn DeadLetterPublisher>>,
-        idempotency_store: &Arc<dyn IdempotencyStore>,
+        idempotency_store: &Option<IdempotencyGate>,
+        deferrals: &Deferrals,
+        metrics: &RuntimeMetrics,
         tasks: &mut JoinSet<Result<(), ConsumerError>>,
-        ordered: bool,
-        idempotent: bool,
-        key_locks: &OrderedKeyLocks,
     ) {
         let dispatcher = dispatcher.clone();
-        let key_locks = key_locks.clone();
-        let retry_policy = retry_policy.clone();
+        let retry_policies = retry_policies.clone();
         let retry_publisher = retry_publisher.clone();
         let dead_letter_publisher = dead_letter_publisher.clone();
-        let idempotency_store = idempotent.then(|| idempotency_store.clone());
+        let idempotency_store = idempotency_store.clone();
+        let deferrals = deferrals.clone();
+        let metrics = metrics.clone();
 
         tasks.spawn(async move {
             let _permit = permit;
-            if ordered {
-                Self::process_ordered_batch(
-                    dispatcher,
-                    messages,
-                    retry_policy,
-                    retry_publisher,
-                    dead_letter_publisher,
-                    idempotency_store,
-                    key_locks,
-                )
-                .await
-            } else {
-                Self::process_batch(
-                    dispatcher,
-                    messages,
-                    retry_policy,
-                    retry_publisher,
-                    dead_letter_publisher,
-                    idempotency_store,
-                )
-                .await
-            }
+            Self::process_batch(
+                dispatcher,
+                messages,
+                retry_policies,
+                retry_publisher,
+                dead_letter_publisher,
+                idempotency_store,
+                Some(deferrals),
+                metrics,
+            )
+            .await
         });
 
         if let Some(result) = tasks.try_join_next() {
@@ -547,141 +963,62 @@ impl ConsumerRuntime {
         }
     }
 
-    fn ordered_key(message: &Message) -> String {
-        message
-            .context
-            .key
-            .clone()
-            .unwrap_or_else(|| message.context.topic.clone())
-    }
-
-    async fn process_ordered_batch(
-        dispatcher: Arc<dyn Dispatcher>,
-        messages: Vec<Message>,
-        retry_policy: RetryPolicy,
-        retry_publisher: Option<Arc<dyn RetryPublisher>>,
-        dead_letter_publisher: Option<Arc<dyn DeadLetterPublisher>>,
-        idempotency_store: Option<Arc<dyn IdempotencyStore>>,
-        key_locks: Arc<Mutex<HashMap<String, Arc<Mutex<()>>>>>,
-    ) -> Result<(), ConsumerError> {
-        if messages.is_empty() {
-            return Ok(());
-        }
-
-        let mut keys = Vec::new();
-        let mut groups: HashMap<String, Vec<Message>> = HashMap::new();
-        for message in messages {
-            let key = Self::ordered_key(&message);
-            if !groups.contains_key(&key) {
-                keys.push(key.clone());
-            }
-            groups.entry(key).or_default().push(message);
-        }
-
-        let mut key_tasks = JoinSet::new();
-        let mut in_flight = 0usize;
-
-        for key in keys {
-            let Some(group) = groups.remove(&key) else {
-                continue;
-            };
-            let lock = {
-                let mut locks = key_locks.lock().await;
-                locks
-                    .entry(key)
-                    .or_insert_with(|| Arc::new(Mutex::new(())))
-                    .clone()
-            };
-            let dispatcher = dispatcher.clone();
-            let retry_policy = retry_policy.clone();
-            let retry_publisher = retry_publisher.clone();
-            let dead_letter_publisher = dead_letter_publisher.clone();
-            let idempotency_store = idempotency_store.clone();
-            key_tasks.spawn(async move {
-                let _key_guard = lock.lock().await;
-                Self::process_batch(
-                    dispatcher,
-                    group,
-                    retry_policy,
-                    retry_publisher,
-                    dead_letter_publisher,
-                    idempotency_store,
-                )
-                .await
-            });
-            in_flight += 1;
-
-            if in_flight >= ORDERED_BATCH_KEY_CONCURRENCY {
-                Self::join_ordered_batch_key_task(&mut key_tasks).await?;
-                in_flight -= 1;
-            }
-        }
-
-        while in_flight > 0 {
-            Self::join_ordered_batch_key_task(&mut key_tasks).await?;
-            in_flight -= 1;
-        }
-
-        Ok(())
-    }
-
-    async fn join_ordered_batch_key_task(
-        key_tasks: &mut JoinSet<Result<(), ConsumerError>>,
-    ) -> Result<(), ConsumerError> {
-        match key_tasks.join_next().await {
-            Some(Ok(result)) => result,
-            Some(Err(err)) => Err(ConsumerError::Handler(format!(
-                "ordered batch key task join failed: {err}"
-            ))),
-            None => Ok(()),
-        }
-    }
-
     /// 处理单条消息
+    #[allow(clippy::too_many_arguments)]
     async fn process_message(
         dispatcher: Arc<dyn Dispatcher>,
         message: Message,
-        retry_policy: RetryPolicy,
+        retry_policies: Arc<RetryPolicies>,
         retry_publisher: Option<Arc<dyn RetryPublisher>>,
         dead_letter_publisher: Option<Arc<dyn DeadLetterPublisher>>,
-        idempotency_store: Option<Arc<dyn IdempotencyStore>>,
+        idempotency_store: Option<IdempotencyGate>,
+        deferrals: Option<Deferrals>,
+        metrics: RuntimeMetrics,
     ) -> Result<(), ConsumerError> {
+        let retry_policy = retry_policies.for_topic(&message.context.topic);
         let message_id = message.context.message_id.clone();
-        let idempotency_key = Self::idempotency_key(&message);
+        let claim = IdempotencyClaim::new(Self::idempotency_key(&message));
         let retry_count = message.context.retry_count;
+        let labels = metrics.labels(dispatcher.as_ref(), &message.context.topic);
         let failure_message = if retry_publisher.is_some() || dead_letter_publisher.is_some() {
             Some(message.clone())
         } else {
             None
         };
 
-        if Self::is_duplicate(&message, idempotency_store.as_ref()).await? {
-            tracing::trace!(
-                message_id = %message_id,
-                "Duplicate message, skipping"
-            );
-            if let Some(ack) = message.ack_handle.as_ref() {
-                ack.ack().await?;
-            }
+        if !Self::admit(&message, &claim, idempotency_store.as_ref()).await? {
+            metrics.record(&labels, Outcome::Skipped);
             return Ok(());
         }
 
         // 分发消息
         let ack_handle = message.ack_handle.clone();
-        let result = match dispatcher.dispatch(message).await {
+        let dispatched = {
+            let _in_flight = metrics.in_flight(&labels);
+            let started_at = Instant::now();
+            let dispatched = dispatcher.dispatch(message).await;
+            metrics.observe_handler(&labels, started_at.elapsed());
+            dispatched
+        };
+        let result = match dispatched {
             Ok(result) => result,
             Err(err) => {
+                Self::abandon(&claim, idempotency_store.as_ref()).await;
                 let failure = FailureContext::from_error("handler_error", &err, retry_count);
                 let action = retry_policy.action_for_error(&err, retry_count);
-                Self::apply_failure_action(
+                let applied = Self::apply_failure_action(
                     failure_message.as_ref(),
                     ack_handle.as_ref(),
                     &retry_publisher,
                     &dead_letter_publisher,
+                    deferrals.as_ref(),
+                    retry_policy,
                     action,
                     failure,
                 )
-                .await?;
+                .await;
+                metrics.record_failure(&labels, action, &applied);
+                applied?;
                 tracing::warn!(
                     error = %err,
                     message_id = %message_id,
@@ -695,36 +1032,47 @@ impl ConsumerRuntime {
         // 处理结果
         match result {
             MessageResult::Ack => {
-                Self::record_consumed(&idempotency_key, idempotency_store.as_ref()).await?;
+                Self::commit(&claim, idempotency_store.as_ref()).await?;
                 if let Some(ack) = ack_handle.as_ref() {
                     ack.ack().await?;
                 }
+                metrics.record(&labels, Outcome::Processed);
                 tracing::trace!(message_id = %message_id, "Message acknowledged");
             }
             MessageResult::Nack => {
+                Self::abandon(&claim, idempotency_store.as_ref()).await;
                 let action = retry_policy.action_for_nack(retry_count);
-                Self::apply_failure_action(
+                let applied = Self::apply_failure_action(
                     failure_message.as_ref(),
                     ack_handle.as_ref(),
                     &retry_publisher,
                     &dead_letter_publisher,
+                    deferrals.as_ref(),
+                    retry_policy,
                     action,
                     FailureContext::new("handler_nack", None::<String>, retry_count),
                 )
-                .await?;
+                .await;
+                metrics.record_failure(&labels, action, &applied);
+                applied?;
                 tracing::warn!(message_id = %message_id, action = ?action, "Message nacked");
             }
             MessageResult::DeadLetter => {
+                Self::abandon(&claim, idempotency_store.as_ref()).await;
                 let action = retry_policy.action_for_dead_letter();
-                Self::apply_failure_action(
+                let applied = Self::apply_failure_action(
                     failure_message.as_ref(),
                     ack_handle.as_ref(),
                     &retry_publisher,
                     &dead_letter_publisher,
+                    deferrals.as_ref(),
+                    retry_policy,
                     action,
                     FailureContext::new("handler_dead_letter", None::<String>, retry_count),
                 )
-                .await?;
+                .await;
+                metrics.record_failure(&labels, action, &applied);
+                applied?;
                 tracing::error!(message_id = %message_id, action = ?action, "Message dead-lettered");
             }
         }
@@ -732,13 +1080,16 @@ impl ConsumerRuntime {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn process_batch(
         dispatcher: Arc<dyn Dispatcher>,
         messages: Vec<Message>,
-        retry_policy: RetryPolicy,
+        retry_policies: Arc<RetryPolicies>,
         retry_publisher: Option<Arc<dyn RetryPublisher>>,
         dead_letter_publisher: Option<Arc<dyn DeadLetterPublisher>>,
-        idempotency_store: Option<Arc<dyn IdempotencyStore>>,
+        idempotency_store: Option<IdempotencyGate>,
+        deferrals: Option<Deferrals>,
+        metrics: RuntimeMetrics,
     ) -> Result<(), ConsumerError> {
         if messages.is_empty() {
             return Ok(());
@@ -748,23 +1099,21 @@ impl ConsumerRuntime {
         let mut dispatch_messages = Vec::with_capacity(messages.len());
         let mut ack_handles = Vec::with_capacity(messages.len());
         for message in messages {
-            if Self::is_duplicate(&message, idempotency_store.as_ref()).await? {
-                tracing::trace!(
-                    message_id = %message.context.message_id,
-                    "Duplicate message in batch, acknowledging without dispatch"
-                );
-                if let Some(ack) = message.ack_handle.as_ref() {
-                    ack.ack().await?;
-                }
+            let claim = IdempotencyClaim::new(Self::idempotency_key(&message));
+            let labels = metrics.labels(dispatcher.as_ref(), &message.context.topic);
+            if !Self::admit(&message, &claim, idempotency_store.as_ref()).await? {
+                metrics.record(&labels, Outcome::Skipped);
                 continue;
             }
 
             ack_handles.push((
                 message.context.message_id.clone(),
-                Self::idempotency_key(&message),
+                message.context.topic.clone(),
+                claim,
                 message.context.retry_count,
                 message.ack_handle.clone(),
                 keep_failure_message.then(|| message.clone()),
+                labels,
             ));
             dispatch_messages.push(message);
         }
@@ -773,14 +1122,28 @@ impl ConsumerRuntime {
             return Ok(());
         }
 
-        let results = match dispatcher.dispatch_batch(dispatch_messages).await {
+        // 整批只有一次 handler 调用，耗时记在首条消息的 handler 上
+        let dispatched = {
+            let _in_flight: Vec<_> = ack_handles
+                .iter()
+                .map(|handle| metrics.in_flight(&handle.6))
+                .collect();
+            let started_at = Instant::now();
+            let dispatched = dispatcher.dispatch_batch(dispatch_messages).await;
+            metrics.observe_handler(&ack_handles[0].6, started_at.elapsed());
+            dispatched
+        };
+        let results = match dispatched {
             Ok(results) => results,
             Err(err) => {
                 Self::apply_error_to_all(
                     &ack_handles,
-                    &retry_policy,
+                    &retry_policies,
                     &retry_publisher,
                     &dead_letter_publisher,
+                    idempotency_store.as_ref(),
+                    deferrals.as_ref(),
+                    &metrics,
                     &err,
                 )
                 .await?;
@@ -797,9 +1160,12 @@ impl ConsumerRuntime {
             ));
             Self::apply_error_to_all(
                 &ack_handles,
-                &retry_policy,
+                &retry_policies,
                 &retry_publisher,
                 &dead_letter_publisher,
+                idempotency_store.as_ref(),
+                deferrals.as_ref(),
+                &metrics,
                 &error,
             )
             .await?;
@@ -811,43 +1177,59 @@ impl ConsumerRuntime {
         }
 
         let mut first_error = None;
-        for ((message_id, idempotency_key, retry_count, ack_handle, failure_message), result) in
-            ack_handles.iter().zip(results)
+        for (
+            (message_id, topic, claim, retry_count, ack_handle, failure_message, labels),
+            result,
+        ) in ack_handles.iter().zip(results)
         {
+            let retry_policy = retry_policies.for_topic(topic);
             let ack_result = match result {
                 MessageResult::Ack => {
-                    Self::record_consumed(idempotency_key, idempotency_store.as_ref()).await?;
-                    if let Some(ack) = ack_handle.as_ref() {
-                        ack.ack().await
-                    } else {
-                        Ok(())
+                    Self::commit(claim, idempotency_store.as_ref()).await?;
+                    let acked = match ack_handle.as_ref() {
+                        Some(ack) => ack.ack().await,
+                        None => Ok(()),
+                    };
+                    if acked.is_ok() {
+                        metrics.record(labels, Outcome::Processed);
                     }
+                    acked
                 }
                 MessageResult::Nack => {
                     tracing::warn!(message_id = %message_id, "Message nacked in batch");
+                    Self::abandon(claim, idempotency_store.as_ref()).await;
                     let action = retry_policy.action_for_nack(*retry_count);
-                    Self::apply_failure_action(
+                    let applied = Self::apply_failure_action(
                         failure_message.as_ref(),
                         ack_handle.as_ref(),
                         &retry_publisher,
                         &dead_letter_publisher,
+                        deferrals.as_ref(),
+                        retry_policy,
                         action,
                         FailureContext::new("handler_nack", None::<String>, *retry_count),
                     )
-                    .await
+                    .await;
+                    metrics.record_failure(labels, action, &applied);
+                    applied
                 }
                 MessageResult::DeadLetter => {
                     tracing::error!(message_id = %message_id, "Message sent to DLQ in batch");
+                    Self::abandon(claim, idempotency_store.as_ref()).await;
                     let action = retry_policy.action_for_dead_letter();
-                    Self::apply_failure_action(
+                    let applied = Self::apply_failure_action(
                         failure_message.as_ref(),
                         ack_handle.as_ref(),
                         &retry_publisher,
                         &dead_letter_publisher,
+                        deferrals.as_ref(),
+                        retry_policy,
                         action,
                         FailureContext::new("handler_dead_letter", None::<String>, *retry_count),
                     )
-                    .await
+                    .await;
+                    metrics.record_failure(labels, action, &applied);
+                    applied
                 }
             };
 
@@ -871,26 +1253,36 @@ impl ConsumerRuntime {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn apply_error_to_all(
         ack_handles: &[BatchAckHandle],
-        retry_policy: &RetryPolicy,
+        retry_policies: &RetryPolicies,
         retry_publisher: &Option<Arc<dyn RetryPublisher>>,
         dead_letter_publisher: &Option<Arc<dyn DeadLetterPublisher>>,
+        idempotency: Option<&IdempotencyGate>,
+        deferrals: Option<&Deferrals>,
+        metrics: &RuntimeMetrics,
         error: &ConsumerError,
     ) -> Result<(), ConsumerError> {
         let mut first_error = None;
-        for (message_id, _idempotency_key, retry_count, ack_handle, failure_message) in ack_handles
+        for (message_id, topic, claim, retry_count, ack_handle, failure_message, labels) in
+            ack_handles
         {
+            let retry_policy = retry_policies.for_topic(topic);
+            Self::abandon(claim, idempotency).await;
             let action = retry_policy.action_for_error(error, *retry_count);
             let result = Self::apply_failure_action(
                 failure_message.as_ref(),
                 ack_handle.as_ref(),
                 retry_publisher,
                 dead_letter_publisher,
+                deferrals,
+                retry_policy,
                 action,
                 FailureContext::from_error("batch_handler_error", error, *retry_count),
             )
             .await;
+            metrics.record_failure(labels, action, &result);
 
             if let Err(err) = result {
                 tracing::warn!(
@@ -910,16 +1302,26 @@ impl ConsumerRuntime {
         Ok(())
     }
 
-    async fn apply_failure_action(
+    #[allow(clippy::too_many_arguments)]
+    pub(super) async fn apply_failure_action(
         message: Option<&Message>,
         ack_handle: Option<&Arc<dyn super::types::MessageAck>>,
         retry_publisher: &Option<Arc<dyn RetryPublisher>>,
         dead_letter_publisher: &Option<Arc<dyn DeadLetterPublisher>>,
+        deferrals: Option<&Deferrals>,
+        retry_policy: &RetryPolicy,
         action: FailureAction,
         failure: FailureContext,
     ) -> Result<(), ConsumerError> {
         match action {
             FailureAction::Retry => {
+                let failure = match failure.delay {
+                    Some(_) => failure,
+                    None => {
+                        let delay = retry_policy.backoff_for(failure.retry_count);
+                        failure.with_delay(delay)
+                    }
+                };
                 if let Some(publisher) = retry_publisher {
                     let message = message.ok_or_else(|| {
                         ConsumerError::Retry(
@@ -933,9 +1335,19 @@ impl ConsumerRuntime {
                     return Ok(());
                 }
 
-                if let Some(ack) = ack_handle.as_ref()
+                if let Some(ack) = ack_handle
                     && ack.supports_native_retry()
                 {
+                    let Some(delay) = failure.delay else {
+                        return ack.nack().await;
+                    };
+                    if ack.supports_delayed_nack() {
+                        return ack.nack_with_delay(delay).await;
+                    }
+                    // broker 不支持延迟 nack：停放到拉取循环的时间轮，退避到期后再 nack
+                    if deferrals.is_some_and(|deferrals| deferrals.nack_after(delay, ack.clone())) {
+                        return Ok(());
+                    }
                     return ack.nack().await;
                 }
 
@@ -966,10 +1378,23 @@ impl ConsumerRuntime {
                 }
                 Ok(())
             }
+            FailureAction::Drop => {
+                tracing::error!(
+                    message_id = message.map(|message| message.context.message_id.as_str()),
+                    reason = %failure.reason,
+                    error = failure.error.as_deref(),
+                    retry_count = failure.retry_count,
+                    "Message is not retryable and dead-lettering is disabled, dropping it"
+                );
+                if let Some(ack) = ack_handle {
+                    ack.term().await?;
+                }
+                Ok(())
+            }
         }
     }
 
-    fn idempotency_key(message: &Message) -> String {
+    pub(super) fn idempotency_key(message: &Message) -> String {
         let message_id = message
             .context
             .headers
@@ -986,26 +1411,147 @@ impl ConsumerRuntime {
         )
     }
 
-    /// 检查消息是否重复（幂等性）
-    async fn is_duplicate(
+    /// 分发前的幂等检查，返回是否需要分发
+    ///
+    /// 已消费的消息直接 ack；其他投递正在处理时，支持延迟 nack 的 broker 延迟交还，
+    /// 否则保持未确认并交回拉取循环停放，到期后重新检查（offset 在此期间不会提交）。
+    /// 立即 nack 会让 broker 马上重投，在声明到期前反复空转，因此不使用。
+    pub(super) async fn admit(
         message: &Message,
-        store: Option<&Arc<dyn IdempotencyStore>>,
+        claim: &IdempotencyClaim,
+        gate: Option<&IdempotencyGate>,
     ) -> Result<bool, ConsumerError> {
-        let Some(store) = store else {
-            return Ok(false);
+        let Some(gate) = gate else {
+            return Ok(true);
+        };
+        match gate.begin(claim).await? {
+            ClaimOutcome::Claimed => Ok(true),
+            ClaimOutcome::Consumed => {
+                tracing::trace!(
+                    message_id = %message.context.message_id,
+                    "Duplicate message, skipping"
+                );
+                if let Some(ack) = message.ack_handle.as_ref() {
+                    ack.ack().await?;
+                }
+                Ok(false)
+            }
+            ClaimOutcome::InProgress => {
+                tracing::debug!(
+                    message_id = %message.context.message_id,
+                    idempotency_key = %claim.key(),
+                    "Message is being processed by another delivery, deferring"
+                );
+                match message.ack_handle.as_ref() {
+                    Some(ack) if ack.supports_native_retry() && ack.supports_delayed_nack() => {
+                        ack.nack_with_delay(gate.recheck_delay()).await?;
+                    }
+                    _ => {
+                        if !gate.defer(message.clone()) {
+                            tracing::debug!(
+                                message_id = %message.context.message_id,
+                                "Consumer loop stopped, message left for broker redelivery"
+                            );
+                        }
+                    }
+                }
+                Ok(false)
+            }
+        }
+    }
+
+    /// 记录消息已消费
+    pub(super) async fn commit(
+        claim: &IdempotencyClaim,
+        gate: Option<&IdempotencyGate>,
+    ) -> Result<(), ConsumerError> {
+        let Some(gate) = gate else {
+            return Ok(());
         };
-        store.is_consumed(&Self::idempotency_key(message)).await
+        gate.commit(claim).await
+    }
+
+    /// 处理失败时释放声明
+    pub(super) async fn abandon(claim: &IdempotencyClaim, gate: Option<&IdempotencyGate>) {
+        if let Some(gate) = gate {
+            gate.abandon(claim).await;
+        }
+    }
+}
+
+/// 单次运行内的拉取侧状态：延迟停放、被暂停 topic 的暂存与最近同步的控制状态
+struct FetchState {
+    parked: TimerWheel<Parked>,
+    /// 处理任务交回的条目（其他投递仍持有幂等声明的消息、等待退避后 nack 的确认句柄），
+    /// 收取后停放到时间轮
+    deferred: mpsc::UnboundedReceiver<(Instant, Parked)>,
+    deferrals: Deferrals,
+    held: VecDeque<Message>,
+    control: watch::Receiver<ControlState>,
+    paused: ControlState,
+    /// 拉取器是否已在 broker 侧暂停（见 [MessageFetcher::set_paused]）
+    native_pause: bool,
+    synced: bool,
+}
+
+impl FetchState {
+    fn new(control: watch::Receiver<ControlState>) -> Self {
+        let (deferrals, deferred) = Deferrals::channel();
+        Self {
+            parked: TimerWheel::new(DEFAULT_TICK, DEFAULT_SLOTS),
+            deferred,
+            deferrals,
+            held: VecDeque::new(),
+            control,
+            paused: ControlState::default(),
+            native_pause: false,
+            synced: false,
+        }
+    }
+
+    fn is_paused(&self, topic: &str) -> bool {
+        self.paused.is_suspended() || self.paused.paused_topics.contains(topic)
+    }
+
+    /// 把处理任务交回的条目停放到时间轮
+    fn collect_deferred(&mut self) {
+        while let Ok((due, parked)) = self.deferred.try_recv() {
+            self.parked.insert(due, parked);
+        }
+    }
+
+    /// 被暂停 topic 的消息暂存并返回 `None`
+    fn hold_if_paused(&mut self, message: Message) -> Option<Message> {
+        if self.is_paused(&message.context.topic) {
+            self.held.push_back(message);
+            return None;
+        }
+        Some(message)
+    }
+
+    /// 取出最早一条已恢复 topic 的暂存消息
+    fn release_held(&mut self) -> Option<Message> {
+        let index = self
+            .held
+            .iter()
+            .position(|message| !self.is_paused(&message.context.topic))?;
+        self.held.remove(index)
     }
 
-    /// 记录消息已消费
-    async fn record_consumed(
-        message_id: &str,
-        store: Option<&Arc<dyn IdempotencyStore>>,
-    ) -> Result<(), ConsumerError> {
-        let Some(store) = store else {
-            return Ok(());
-        };
-        store.record_consumed(message_id).await
+    /// 源已取空时退避；停放消息先到期时提前结束
+    async fn idle(&self, backoff: Duration) {
+        let mut wake_at = Instant::now() + backoff;
+        if let Some(due) = self.parked.next_deadline() {
+            wake_at = wake_at.min(due);
+        }
+        tokio::time::sleep_until(tokio::time::Instant::from_std(wake_at)).await;
+    }
+
+    /// 等待控制状态变化
+    async fn control_changed(&mut self) {
+        // 发送端由 ConsumerRuntime 持有，运行期间不会关闭
+        let _ = self.control.changed().await;
+        self.synced = false;
     }
 }
 
@@ -1043,6 +1589,11 @@ impl ConsumerRuntimeTask {
             fetcher,
         )
     }
+
+    /// 运行中消费者的暂停/恢复句柄
+    pub fn handle(&self) -> ConsumerHandle {
+        self.runtime.handle()
+    }
 }
 
 impl MqConsumer for ConsumerRuntimeTask {
@@ -1067,6 +1618,29 @@ impl MqConsumer for ConsumerRuntimeTask {
 pub trait MessageFetcher: Send {
     /// 获取下一条消息
     async fn fetch(&mut self) -> Result<Option<Message>, ConsumerError>;
+
+    /// 停止消费、在途消息处理完成后调用，用于提交或释放拉取器侧状态
+    async fn close(&mut self) -> Result<(), ConsumerError> {
+        Ok(())
+    }
+
+    /// 各分区的消费延迟，按 [ConsumerConfig::lag_interval] 采样；后端不支持时返回空
+    async fn lag(&mut self) -> Result<Vec<ConsumerLag>, ConsumerError> {
+        Ok(Vec::new())
+    }
+
+    /// 在 broker 侧暂停/恢复拉取：`all` 为整体暂停，否则暂停 `topics` 的全部分区；
+    /// 每次控制状态变化时以完整状态调用。返回 `false` 表示不支持，由运行时在本地暂停
+    async fn set_paused(
+        &mut self,
+        _all: bool,
+        _topics: &BTreeSet<String>,
+    ) -> Result<bool, ConsumerError> {
+        Ok(false)
+    }
+
+    /// 运行开始时注册分区分配/回收回调；没有分区概念的后端忽略
+    fn set_rebalance_listener(&mut self, _listener: Arc<dyn RebalanceListener>) {}
 }
 
 #[async_trait::async_trait]
@@ -1074,6 +1648,26 @@ impl MessageFetcher for Box<dyn MessageFetcher + Send> {
     async fn fetch(&mut self) -> Result<Option<Message>, ConsumerError> {
         self.as_mut().fetch().await
     }
+
+    async fn close(&mut self) -> Result<(), ConsumerError> {
+        self.as_mut().close().await
+    }
+
+    async fn lag(&mut self) -> Result<Vec<ConsumerLag>, ConsumerError> {
+        self.as_mut().lag().await
+    }
+
+    async fn set_paused(
+        &mut self,
+        all: bool,
+        topics: &BTreeSet<String>,
+    ) -> Result<bool, ConsumerError> {
+        self.as_mut().set_paused(all, topics).await
+    }
+
+    fn set_rebalance_listener(&mut self, listener: Arc<dyn RebalanceListener>) {
+        self.as_mut().set_rebalance_listener(listener);
+    }
 }
 
 /// 消息消费统计
@@ -1119,10 +1713,13 @@ impl ConsumerStats {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::mq::consumer::types::{ContentType, MessageAck, MessageContext};
+    use crate::mq::consumer::failure::RetryLimit;
+    use crate::mq::consumer::idempotency::ClaimToken;
+    use crate::mq::consumer::types::{ConsumerErrorKind, ContentType, MessageAck, MessageContext};
     use flare_core_base::context::Context;
     use std::collections::VecDeque;
     use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::sync::Mutex;
     use tokio::{sync::Notify, time::timeout};
 
     struct CountingAck {
@@ -1334,6 +1931,21 @@ mod tests {
         }
     }
 
+    /// 消息取完后一直阻塞，模拟无新消息时的长轮询
+    struct BlockingFetcher {
+        messages: VecDeque<Message>,
+    }
+
+    #[async_trait::async_trait]
+    impl MessageFetcher for BlockingFetcher {
+        async fn fetch(&mut self) -> Result<Option<Message>, ConsumerError> {
+            match self.messages.pop_front() {
+                Some(message) => Ok(Some(message)),
+                None => std::future::pending().await,
+            }
+        }
+    }
+
     struct CountingRetryPublisher {
         published: Arc<AtomicUsize>,
     }
@@ -1379,8 +1991,8 @@ mod tests {
         message
     }
 
-    fn default_retry_policy() -> RetryPolicy {
-        RetryPolicy::new(3, true)
+    fn default_retry_policy() -> Arc<RetryPolicies> {
+        Arc::new(RetryPolicy::new(3, true).into())
     }
 
     #[tokio::test]
@@ -1430,6 +2042,157 @@ mod tests {
             .unwrap();
     }
 
+    #[tokio::test]
+    async fn parked_message_is_dispatched_while_fetch_blocks() {
+        let acked = Arc::new(AtomicUsize::new(0));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let ack_handle: Arc<dyn MessageAck> = Arc::new(CountingAck {
+            acked: acked.clone(),
+            nacked: Arc::new(AtomicUsize::new(0)),
+            termed: Arc::new(AtomicUsize::new(0)),
+        });
+        let delay = Duration::from_millis(50);
+        let mut message = test_message("delayed", ack_handle);
+        message.context.headers.insert(
+            super::super::failure::HEADER_RETRY_NOT_BEFORE_UNIX_MS.to_string(),
+            (super::super::failure::unix_epoch_millis() + delay.as_millis() as u64).to_string(),
+        );
+        let mut fetcher = BlockingFetcher {
+            messages: VecDeque::from(vec![message]),
+        };
+        let runtime = ConsumerRuntime::new(
+            ConsumerConfig::new(),
+            Arc::new(CountingResultDispatcher {
+                result: MessageResult::Ack,
+                calls: calls.clone(),
+            }),
+        );
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let started = Instant::now();
+        let handle =
+            tokio::spawn(async move { runtime.run_with_shutdown(&mut fetcher, shutdown_rx).await });
+
+        // 第二次拉取永不返回，停放的消息仍须按时分发
+        timeout(Duration::from_secs(1), async {
+            while acked.load(Ordering::SeqCst) == 0 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("parked message should be dispatched while fetch is blocked");
+        // not-before 头按毫秒取整
+        assert!(started.elapsed() >= delay - Duration::from_millis(5));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        shutdown_tx.send(()).unwrap();
+        timeout(Duration::from_secs(1), handle)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+    }
+
+    fn not_before(mut message: Message, delay: Duration) -> Message {
+        message.context.headers.insert(
+            super::super::failure::HEADER_RETRY_NOT_BEFORE_UNIX_MS.to_string(),
+            (super::super::failure::unix_epoch_millis() + delay.as_millis() as u64).to_string(),
+        );
+        message
+    }
+
+    #[tokio::test]
+    async fn parking_does_not_wait_for_idle_backoff() {
+        let acked = Arc::new(AtomicUsize::new(0));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let ack_handle: Arc<dyn MessageAck> = Arc::new(CountingAck {
+            acked: acked.clone(),
+            nacked: Arc::new(AtomicUsize::new(0)),
+            termed: Arc::new(AtomicUsize::new(0)),
+        });
+        let far = Duration::from_secs(60);
+        let near = Duration::from_millis(50);
+        let mut fetcher = VecFetcher {
+            messages: VecDeque::from(vec![
+                not_before(test_message("far-1", ack_handle.clone()), far),
+                not_before(test_message("far-2", ack_handle.clone()), far),
+                test_message("due", ack_handle.clone()),
+                not_before(test_message("near", ack_handle), near),
+            ]),
+            fetches: Arc::new(AtomicUsize::new(0)),
+        };
+        let runtime = ConsumerRuntime::new(
+            ConsumerConfig::new()
+                .with_poll(PollWorkerConfig::default().with_idle_backoff(Duration::from_secs(2))),
+            Arc::new(CountingResultDispatcher {
+                result: MessageResult::Ack,
+                calls: calls.clone(),
+            }),
+        );
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let handle =
+            tokio::spawn(async move { runtime.run_with_shutdown(&mut fetcher, shutdown_rx).await });
+
+        // 停放后继续拉取，到期消息不等空闲退避
+        timeout(Duration::from_secs(1), async {
+            while acked.load(Ordering::SeqCst) == 0 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("due message behind parked ones should be dispatched without idling");
+        // 源取空后的退避在最早停放消息到期时结束
+        timeout(Duration::from_secs(1), async {
+            while acked.load(Ordering::SeqCst) < 2 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("idle backoff should end when the earliest parked message is due");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        shutdown_tx.send(()).unwrap();
+        timeout(Duration::from_secs(1), handle)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn never_retried_error_without_dlq_is_termed() {
+        let acked = Arc::new(AtomicUsize::new(0));
+        let nacked = Arc::new(AtomicUsize::new(0));
+        let termed = Arc::new(AtomicUsize::new(0));
+        let ack_handle: Arc<dyn MessageAck> = Arc::new(CountingAck {
+            acked: acked.clone(),
+            nacked: nacked.clone(),
+            termed: termed.clone(),
+        });
+        let message = test_message("poison", ack_handle.clone());
+        let policy = RetryPolicy::new(3, false)
+            .with_error_limit(ConsumerErrorKind::Deserialization, RetryLimit::Never);
+        let error = ConsumerError::Deserialization("bad json".to_string());
+        let action = policy.action_for_error(&error, 0);
+
+        ConsumerRuntime::apply_failure_action(
+            Some(&message),
+            Some(&ack_handle),
+            &None,
+            &None,
+            None,
+            &policy,
+            action,
+            FailureContext::from_error("handler_error", &error, 0),
+        )
+        .await
+        .expect("dropping a poison message should succeed");
+
+        assert_eq!(action, FailureAction::Drop);
+        assert_eq!(termed.load(Ordering::SeqCst), 1);
+        assert_eq!(nacked.load(Ordering::SeqCst), 0);
+        assert_eq!(acked.load(Ordering::SeqCst), 0);
+    }
+
     #[tokio::test]
     async fn process_batch_acks_all_messages_after_batch_success() {
         let acked = Arc::new(AtomicUsize::new(0));
@@ -1459,6 +2222,8 @@ mod tests {
             None,
             None,
             None,
+            None,
+            RuntimeMetrics::default(),
         )
         .await
         .expect("batch should succeed");
@@ -1498,6 +2263,8 @@ mod tests {
             None,
             None,
             None,
+            None,
+            RuntimeMetrics::default(),
         )
         .await
         .expect("batch failure should be handled by native retry");
@@ -1509,7 +2276,7 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn process_ordered_batch_groups_by_key_and_preserves_key_order() {
+    async fn ordered_lanes_group_batch_by_key_and_preserve_key_order() {
         let acked = Arc::new(AtomicUsize::new(0));
         let nacked = Arc::new(AtomicUsize::new(0));
         let termed = Arc::new(AtomicUsize::new(0));
@@ -1530,17 +2297,22 @@ mod tests {
             keyed_test_message("b2", "user-b", ack_handle),
         ];
 
-        ConsumerRuntime::process_ordered_batch(
-            dispatcher,
-            messages,
-            default_retry_policy(),
-            None,
-            None,
-            None,
-            Arc::new(Mutex::new(HashMap::new())),
-        )
-        .await
-        .expect("ordered batch should succeed");
+        let lanes = OrderedLanes::spawn(
+            OrderedContext {
+                dispatcher,
+                retry_policies: default_retry_policy(),
+                retry_publisher: None,
+                dead_letter_publisher: None,
+                idempotency: None,
+                on_key_failure: KeyFailureMode::BlockUntilResolved,
+                metrics: RuntimeMetrics::default(),
+            },
+            lane_counters(2),
+            8,
+        );
+        let permit = Arc::new(Semaphore::new(1)).acquire_owned().await.unwrap();
+        lanes.submit(messages, true, permit).await;
+        lanes.close().await;
 
         let mut actual = batches.lock().await.clone();
         actual.sort();
@@ -1581,7 +2353,9 @@ mod tests {
             default_retry_policy(),
             None,
             None,
-            Some(store),
+            Some(IdempotencyGate::new(store, None)),
+            None,
+            RuntimeMetrics::default(),
         )
         .await
         .expect("duplicate should be acknowledged without dispatch");
@@ -1616,7 +2390,9 @@ mod tests {
             default_retry_policy(),
             None,
             None,
-            Some(store.clone()),
+            Some(IdempotencyGate::new(store.clone(), None)),
+            None,
+            RuntimeMetrics::default(),
         )
         .await
         .expect("fresh message should dispatch and record idempotency");
@@ -1628,6 +2404,171 @@ mod tests {
         assert_eq!(termed.load(Ordering::SeqCst), 0);
     }
 
+    #[tokio::test]
+    async fn process_message_claim_defers_in_progress_and_releases_on_nack() {
+        let acked = Arc::new(AtomicUsize::new(0));
+        let nacked = Arc::new(AtomicUsize::new(0));
+        let termed = Arc::new(AtomicUsize::new(0));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let ack_handle: Arc<dyn MessageAck> = Arc::new(CountingAck {
+            acked: acked.clone(),
+            nacked: nacked.clone(),
+            termed: termed.clone(),
+        });
+        let message = test_message("claimed", ack_handle);
+        let key = ConsumerRuntime::idempotency_key(&message);
+        let lease = Duration::from_secs(30);
+        let store = Arc::new(InMemoryIdempotencyStore::default());
+        let gate = IdempotencyGate::new(store.clone(), Some(lease));
+
+        // 另一投递持有声明：不分发；不支持延迟 nack 时不立即 nack
+        let other = ClaimToken::new();
+        store.try_claim(&key, &other, lease).await.unwrap();
+        ConsumerRuntime::process_message(
+            Arc::new(CountingResultDispatcher {
+                result: MessageResult::Ack,
+                calls: calls.clone(),
+            }),
+            message.clone(),
+            default_retry_policy(),
+            None,
+            None,
+            Some(gate.clone()),
+            None,
+            RuntimeMetrics::default(),
+        )
+        .await
+        .expect("in-progress delivery should be deferred");
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert_eq!(nacked.load(Ordering::SeqCst), 0);
+
+        // 声明释放后处理失败：声明再次释放，重投可以立即处理
+        store.release_claim(&key, &other).await.unwrap();
+        ConsumerRuntime::process_message(
+            Arc::new(CountingResultDispatcher {
+                result: MessageResult::Nack,
+                calls: calls.clone(),
+            }),
+            message,
+            default_retry_policy(),
+            None,
+            None,
+            Some(gate),
+            None,
+            RuntimeMetrics::default(),
+        )
+        .await
+        .expect("nack should be handled by native retry");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(nacked.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            store
+                .try_claim(&key, &ClaimToken::new(), lease)
+                .await
+                .unwrap(),
+            ClaimOutcome::Claimed
+        );
+        assert_eq!(acked.load(Ordering::SeqCst), 0);
+        assert_eq!(termed.load(Ordering::SeqCst), 0);
+    }
+
+    /// 不支持原生重投的 broker（如 Kafka）
+    struct CommitOnlyAck(CountingAck);
+
+    #[async_trait::async_trait]
+    impl MessageAck for CommitOnlyAck {
+        async fn ack(&self) -> Result<(), ConsumerError> {
+            self.0.ack().await
+        }
+
+        async fn nack(&self) -> Result<(), ConsumerError> {
+            self.0.nack().await
+        }
+
+        async fn term(&self) -> Result<(), ConsumerError> {
+            self.0.term().await
+        }
+
+        fn supports_native_retry(&self) -> bool {
+            false
+        }
+    }
+
+    #[tokio::test]
+    async fn in_progress_message_is_reparked_without_native_retry() {
+        reparks_in_progress_message(|ack| Arc::new(CommitOnlyAck(ack))).await;
+    }
+
+    #[tokio::test]
+    async fn in_progress_message_is_reparked_without_delayed_nack() {
+        reparks_in_progress_message(|ack| Arc::new(ack)).await;
+    }
+
+    /// 不支持延迟 nack 时，被其他投递声明的消息停放重查，不 nack
+    async fn reparks_in_progress_message(
+        ack_handle: impl FnOnce(CountingAck) -> Arc<dyn MessageAck>,
+    ) {
+        let acked = Arc::new(AtomicUsize::new(0));
+        let nacked = Arc::new(AtomicUsize::new(0));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let ack_handle = ack_handle(CountingAck {
+            acked: acked.clone(),
+            nacked: nacked.clone(),
+            termed: Arc::new(AtomicUsize::new(0)),
+        });
+        let message = test_message("claimed", ack_handle);
+        let key = ConsumerRuntime::idempotency_key(&message);
+        let store = Arc::new(InMemoryIdempotencyStore::default());
+        let other = ClaimToken::new();
+        store
+            .try_claim(&key, &other, Duration::from_secs(30))
+            .await
+            .unwrap();
+
+        let mut fetcher = VecFetcher {
+            messages: VecDeque::from(vec![message]),
+            fetches: Arc::new(AtomicUsize::new(0)),
+        };
+        let runtime = ConsumerRuntime::new(
+            ConsumerConfig::new()
+                .with_poll(PollWorkerConfig::default().with_idle_backoff(Duration::from_millis(5)))
+                .with_idempotency_claim(Duration::from_millis(200)),
+            Arc::new(CountingResultDispatcher {
+                result: MessageResult::Ack,
+                calls: calls.clone(),
+            }),
+        )
+        .with_idempotency_store(store.clone());
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let handle =
+            tokio::spawn(async move { runtime.run_with_shutdown(&mut fetcher, shutdown_rx).await });
+
+        // 其他投递持有声明：既不分发也不确认，消息停放后反复重新检查
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert_eq!(acked.load(Ordering::SeqCst), 0);
+        assert_eq!(nacked.load(Ordering::SeqCst), 0);
+
+        // 声明释放后，停放的消息被重新声明并处理
+        store.release_claim(&key, &other).await.unwrap();
+        timeout(Duration::from_secs(1), async {
+            while acked.load(Ordering::SeqCst) == 0 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("re-parked message should be processed once the claim is released");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(store.is_consumed(&key).await.unwrap());
+
+        shutdown_tx.send(()).unwrap();
+        timeout(Duration::from_secs(1), handle)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+    }
+
     #[tokio::test]
     async fn process_message_publishes_retry_then_acks_original() {
         let acked = Arc::new(AtomicUsize::new(0));
@@ -1652,6 +2593,8 @@ mod tests {
             Some(retry_publisher),
             None,
             None,
+            None,
+            RuntimeMetrics::default(),
         )
         .await
         .expect("retry handoff should succeed");
@@ -1686,6 +2629,8 @@ mod tests {
             None,
             Some(dlq_publisher),
             None,
+            None,
+            RuntimeMetrics::default(),
         )
         .await
         .expect("DLQ handoff should succeed");
@@ -1716,6 +2661,8 @@ mod tests {
             None,
             None,
             None,
+            None,
+            RuntimeMetrics::default(),
         )
         .await
         .expect_err("missing DLQ publisher must not silently term the message");
@@ -5,7 +5,7 @@
 use std::collections::HashMap;
 use std::fmt;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use flare_core_base::context::Ctx;
 
@@ -137,7 +137,39 @@ pub enum ConsumerError {
     NoHandler(String),
 }
 
+/// [ConsumerError] 的变体种类，用于按错误类型配置策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConsumerErrorKind {
+    Handler,
+    Serialization,
+    Deserialization,
+    Connection,
+    Timeout,
+    DeadLetter,
+    Retry,
+    Shutdown,
+    Configuration,
+    Idempotency,
+    NoHandler,
+}
+
 impl ConsumerError {
+    pub fn kind(&self) -> ConsumerErrorKind {
+        match self {
+            ConsumerError::Handler(_) => ConsumerErrorKind::Handler,
+            ConsumerError::Serialization(_) => ConsumerErrorKind::Serialization,
+            ConsumerError::Deserialization(_) => ConsumerErrorKind::Deserialization,
+            ConsumerError::Connection(_) => ConsumerErrorKind::Connection,
+            ConsumerError::Timeout(_) => ConsumerErrorKind::Timeout,
+            ConsumerError::DeadLetter(_) => ConsumerErrorKind::DeadLetter,
+            ConsumerError::Retry(_) => ConsumerErrorKind::Retry,
+            ConsumerError::Shutdown => ConsumerErrorKind::Shutdown,
+            ConsumerError::Configuration(_) => ConsumerErrorKind::Configuration,
+            ConsumerError::Idempotency(_) => ConsumerErrorKind::Idempotency,
+            ConsumerError::NoHandler(_) => ConsumerErrorKind::NoHandler,
+        }
+    }
+
     /// 是否可重试
     pub fn is_retryable(&self) -> bool {
         matches!(
@@ -319,4 +351,16 @@ pub trait MessageAck: Send + Sync {
     fn supports_native_retry(&self) -> bool {
         true
     }
+
+    /// 由 broker 在 `delay` 后重投（如 JetStream `Nak(Some(delay))`）
+    ///
+    /// 仅当 [Self::supports_delayed_nack] 为 true 时运行时才会调用；默认退化为 [Self::nack]。
+    async fn nack_with_delay(&self, delay: Duration) -> Result<(), ConsumerError> {
+        let _ = delay;
+        self.nack().await
+    }
+
+    fn supports_delayed_nack(&self) -> bool {
+        false
+    }
 }
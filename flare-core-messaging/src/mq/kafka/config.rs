@@ -56,4 +56,15 @@ pub trait KafkaConsumerConfig: KafkaProducerConfig {
     fn kafka_auto_offset_reset(&self) -> &str {
         "earliest"
     }
+
+    /// 连续完成 offset 的批量提交间隔
+    fn kafka_commit_interval_ms(&self) -> u64 {
+        1_000
+    }
+
+    /// 分区回收时等待在途消息完成的上限，超时未完成的消息由新的分区持有者重新消费；
+    /// 为 0 时不等待，只提交已连续完成的水位。等待期间让出拉取所在的 worker，需要多线程运行时（current_thread 上拉取会报配置错误）
+    fn kafka_revoke_drain_timeout_ms(&self) -> u64 {
+        0
+    }
 }
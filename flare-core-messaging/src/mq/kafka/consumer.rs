@@ -1,23 +1,223 @@
-use std::collections::HashMap;
-use std::sync::Arc;
-use std::time::Instant;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
 
-use rdkafka::ClientConfig;
-use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::consumer::{
+    BaseConsumer, CommitMode, Consumer, ConsumerContext, Rebalance, StreamConsumer,
+};
 use rdkafka::message::{BorrowedMessage, Headers, Message as KafkaMessage};
+use rdkafka::{ClientConfig, ClientContext};
 use rdkafka::{Offset, TopicPartitionList};
+use tokio::runtime::RuntimeFlavor;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
 use super::super::process_ack_metrics::record_process_ack;
 use super::config::KafkaConsumerConfig;
+use super::offsets::{OffsetCommit, OffsetTracker};
+use crate::mq::consumer::control::{RebalanceListener, TopicPartition};
 use crate::mq::consumer::dispatcher::Dispatcher;
 use crate::mq::consumer::failure::{ConsumerFailurePublishers, retry_count_from_headers};
 use crate::mq::consumer::{
-    ConsumerConfig, ConsumerError, ConsumerRuntimeTask, ContentType, Message, MessageAck,
-    MessageContext, MessageFetcher, MqConsumerTask,
+    ConsumerConfig, ConsumerError, ConsumerLag, ConsumerRuntimeTask, ContentType, Message,
+    MessageAck, MessageContext, MessageFetcher, MqConsumerTask,
 };
 
+type SharedOffsets = Arc<Mutex<OffsetTracker>>;
+
+/// 查询已提交 offset 与分区水位的超时
+const LAG_QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 分区回收时检查在途消息的间隔
+const REVOKE_DRAIN_POLL: Duration = Duration::from_millis(10);
+
+/// 回退被放弃 offset 时 seek 的超时
+const REWIND_SEEK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 运行时设置的暂停状态
+#[derive(Debug, Default)]
+struct PausedTopics {
+    all: bool,
+    topics: BTreeSet<String>,
+}
+
+impl PausedTopics {
+    fn contains(&self, topic: &str) -> bool {
+        self.all || self.topics.contains(topic)
+    }
+}
+
+/// 拉取器与再均衡回调共享的状态
+#[derive(Default)]
+struct RebalanceState {
+    listener: Mutex<Option<Arc<dyn RebalanceListener>>>,
+    paused: Mutex<PausedTopics>,
+}
+
+impl RebalanceState {
+    fn listener(&self) -> Option<Arc<dyn RebalanceListener>> {
+        self.listener
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+/// 分区分配后恢复暂停状态；分区回收前通知回调、等待在途消息并同步提交已完成的水位
+struct OffsetTrackingContext {
+    offsets: SharedOffsets,
+    rebalance: Arc<RebalanceState>,
+    revoke_drain: Duration,
+}
+
+impl OffsetTrackingContext {
+    /// 等待被回收分区的在途消息完成，最长 `revoke_drain`
+    ///
+    /// 回调运行在 `recv().await` 所在的 tokio worker 上：多线程运行时经 `block_in_place`
+    /// 把该 worker 上的其他任务交给别的线程，在途 handler 才能继续完成。
+    /// current_thread 运行时无法让出，[KafkaMessageFetcher] 拉取前已拒绝，这里只做兜底跳过。
+    fn drain(&self, partitions: &[TopicPartition]) {
+        if self.revoke_drain.is_zero() {
+            return;
+        }
+        match tokio::runtime::Handle::try_current().map(|handle| handle.runtime_flavor()) {
+            Ok(RuntimeFlavor::MultiThread) => {
+                tokio::task::block_in_place(|| self.wait_for_in_flight(partitions))
+            }
+            Ok(_) => tracing::warn!(
+                "Kafka revoke drain skipped on a current_thread runtime, in-flight messages will be redelivered"
+            ),
+            Err(_) => self.wait_for_in_flight(partitions),
+        }
+    }
+
+    fn wait_for_in_flight(&self, partitions: &[TopicPartition]) {
+        let deadline = Instant::now() + self.revoke_drain;
+        loop {
+            let in_flight = self
+                .offsets
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .in_flight(partitions.iter().map(|(topic, p)| (topic.as_str(), *p)));
+            if in_flight == 0 {
+                return;
+            }
+            if Instant::now() >= deadline {
+                tracing::warn!(
+                    in_flight,
+                    "Kafka partitions revoked with messages still in flight, they will be redelivered"
+                );
+                return;
+            }
+            std::thread::sleep(REVOKE_DRAIN_POLL);
+        }
+    }
+}
+
+impl ClientContext for OffsetTrackingContext {}
+
+impl ConsumerContext for OffsetTrackingContext {
+    fn pre_rebalance(&self, base_consumer: &BaseConsumer<Self>, rebalance: &Rebalance<'_>) {
+        let Rebalance::Revoke(partitions) = rebalance else {
+            return;
+        };
+        let revoked = topic_partitions(partitions);
+        if let Some(listener) = self.rebalance.listener() {
+            listener.on_partitions_revoked(&revoked);
+        }
+        self.drain(&revoked);
+
+        let commits = self
+            .offsets
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .revoke(revoked.iter().map(|(topic, p)| (topic.as_str(), *p)));
+        if commits.is_empty() {
+            return;
+        }
+        match commit_offsets(base_consumer, &commits, CommitMode::Sync) {
+            Ok(()) => tracing::info!(
+                partitions = commits.len(),
+                "Kafka offsets committed before partition revoke"
+            ),
+            Err(error) => tracing::warn!(
+                error = %error,
+                "Kafka offset commit before partition revoke failed"
+            ),
+        }
+    }
+
+    fn post_rebalance(&self, base_consumer: &BaseConsumer<Self>, rebalance: &Rebalance<'_>) {
+        let Rebalance::Assign(partitions) = rebalance else {
+            return;
+        };
+        let assigned = topic_partitions(partitions);
+        {
+            let paused = self
+                .rebalance
+                .paused
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            let mut tpl = TopicPartitionList::new();
+            for (topic, partition) in &assigned {
+                if paused.contains(topic) {
+                    tpl.add_partition(topic, *partition);
+                }
+            }
+            if tpl.count() > 0
+                && let Err(error) = base_consumer.pause(&tpl)
+            {
+                tracing::warn!(
+                    error = %error,
+                    "Failed to pause newly assigned Kafka partitions"
+                );
+            }
+        }
+        if let Some(listener) = self.rebalance.listener() {
+            listener.on_partitions_assigned(&assigned);
+        }
+    }
+}
+
+fn topic_partitions(partitions: &TopicPartitionList) -> Vec<TopicPartition> {
+    partitions
+        .elements()
+        .iter()
+        .map(|elem| (elem.topic().to_string(), elem.partition()))
+        .collect()
+}
+
+type TrackingConsumer = StreamConsumer<OffsetTrackingContext>;
+
+/// 分区回收等待会在 tokio worker 上阻塞，只有多线程运行时能让出该 worker
+fn ensure_drain_supported(revoke_drain: Duration) -> Result<(), ConsumerError> {
+    if revoke_drain.is_zero() {
+        return Ok(());
+    }
+    match tokio::runtime::Handle::try_current().map(|handle| handle.runtime_flavor()) {
+        Ok(RuntimeFlavor::CurrentThread) => Err(ConsumerError::Configuration(
+            "Kafka revoke drain requires a multi-thread tokio runtime, set kafka_revoke_drain_timeout_ms to 0 on current_thread runtimes"
+                .to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Kafka 消息拉取器
+///
+/// ack 只标记 offset 完成，后台任务按 [KafkaConsumerConfig::kafka_commit_interval_ms]
+/// 提交每个分区连续完成的最高 offset；分区回收与 [MessageFetcher::close] 时同步提交。
+/// 未 ack / term 就被丢弃的消息会放弃其 offset，下次拉取前回退到该位置重新拉取。
+/// [MessageFetcher::set_paused] 按分区暂停拉取，消费组成员身份不受影响。
+/// 启用 [KafkaConsumerConfig::kafka_revoke_drain_timeout_ms] 时必须运行在多线程运行时上。
 pub struct KafkaMessageFetcher {
-    consumer: Arc<StreamConsumer>,
+    consumer: Arc<TrackingConsumer>,
+    offsets: SharedOffsets,
+    rebalance: Arc<RebalanceState>,
+    revoke_drain: Duration,
+    commit_interval: Duration,
+    committer: Option<(CancellationToken, JoinHandle<()>)>,
 }
 
 impl KafkaMessageFetcher {
@@ -59,8 +259,15 @@ impl KafkaMessageFetcher {
             client.set(key, value);
         }
 
+        let offsets: SharedOffsets = Arc::new(Mutex::new(OffsetTracker::new()));
+        let rebalance = Arc::new(RebalanceState::default());
+        let revoke_drain = Duration::from_millis(config.kafka_revoke_drain_timeout_ms());
         let consumer = client
-            .create::<StreamConsumer>()
+            .create_with_context::<_, TrackingConsumer>(OffsetTrackingContext {
+                offsets: offsets.clone(),
+                rebalance: rebalance.clone(),
+                revoke_drain,
+            })
             .map_err(|e| ConsumerError::Configuration(e.to_string()))?;
         let topic_refs = topics.iter().map(String::as_str).collect::<Vec<_>>();
         consumer
@@ -75,9 +282,84 @@ impl KafkaMessageFetcher {
 
         Ok(Self {
             consumer: Arc::new(consumer),
+            offsets,
+            rebalance,
+            revoke_drain,
+            commit_interval: Duration::from_millis(config.kafka_commit_interval_ms().max(1)),
+            committer: None,
         })
     }
 
+    /// 首次拉取时在当前 tokio 运行时中启动提交任务
+    fn ensure_committer(&mut self) {
+        if self.committer.is_some() {
+            return;
+        }
+        let cancel = CancellationToken::new();
+        let stopped = cancel.clone();
+        let consumer = self.consumer.clone();
+        let offsets = self.offsets.clone();
+        let mut ticker = tokio::time::interval(self.commit_interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let handle = tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = stopped.cancelled() => break,
+                    _ = ticker.tick() => {
+                        let _ = flush_offsets(&*consumer, &offsets, CommitMode::Async);
+                    }
+                }
+            }
+        });
+        self.committer = Some((cancel, handle));
+    }
+
+    /// 把有消息被放弃的分区 seek 回被放弃的 offset；失败时留到下次拉取重试
+    async fn rewind_abandoned(&self) {
+        let rewinds = self
+            .offsets
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take_rewinds();
+        for (topic, partition, offset) in rewinds {
+            let consumer = self.consumer.clone();
+            let seek_topic = topic.clone();
+            let seeked = tokio::task::spawn_blocking(move || {
+                consumer
+                    .seek(
+                        &seek_topic,
+                        partition,
+                        Offset::Offset(offset),
+                        REWIND_SEEK_TIMEOUT,
+                    )
+                    .map_err(|e| e.to_string())
+            })
+            .await
+            .unwrap_or_else(|e| Err(e.to_string()));
+            match seeked {
+                Ok(()) => tracing::info!(
+                    topic = %topic,
+                    partition,
+                    offset,
+                    "Kafka partition rewound to abandoned offset"
+                ),
+                Err(error) => {
+                    tracing::warn!(
+                        topic = %topic,
+                        partition,
+                        offset,
+                        error = %error,
+                        "Failed to rewind Kafka partition, retrying on next fetch"
+                    );
+                    self.offsets
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .retry_rewind(&topic, partition, offset);
+                }
+            }
+        }
+    }
+
     fn decode_message(&self, msg: &BorrowedMessage<'_>) -> Result<Message, ConsumerError> {
         let payload = msg.payload().unwrap_or_default().to_vec();
         let topic = msg.topic().to_string();
@@ -125,10 +407,11 @@ impl KafkaMessageFetcher {
         Ok(
             Message::new(payload, content_type, context).with_ack_handle(Arc::new(
                 KafkaMessageAck {
-                    consumer: self.consumer.clone(),
+                    offsets: self.offsets.clone(),
                     topic,
                     partition: msg.partition(),
                     offset: msg.offset(),
+                    settled: AtomicBool::new(false),
                 },
             )),
         )
@@ -138,67 +421,235 @@ impl KafkaMessageFetcher {
 #[async_trait::async_trait]
 impl MessageFetcher for KafkaMessageFetcher {
     async fn fetch(&mut self) -> Result<Option<Message>, ConsumerError> {
-        match self.consumer.recv().await {
-            Ok(msg) => self.decode_message(&msg).map(Some),
-            Err(e) => Err(ConsumerError::Connection(e.to_string())),
+        ensure_drain_supported(self.revoke_drain)?;
+        self.ensure_committer();
+        loop {
+            self.rewind_abandoned().await;
+            let msg = self
+                .consumer
+                .recv()
+                .await
+                .map_err(|e| ConsumerError::Connection(e.to_string()))?;
+            let tracked = self
+                .offsets
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .track(msg.topic(), msg.partition(), msg.offset());
+            if tracked {
+                return self.decode_message(&msg).map(Some);
+            }
+        }
+    }
+
+    async fn close(&mut self) -> Result<(), ConsumerError> {
+        if let Some((cancel, handle)) = self.committer.take() {
+            cancel.cancel();
+            let _ = handle.await;
         }
+        flush_offsets(&*self.consumer, &self.offsets, CommitMode::Sync)
     }
+
+    async fn lag(&mut self) -> Result<Vec<ConsumerLag>, ConsumerError> {
+        let consumer = self.consumer.clone();
+        tokio::task::spawn_blocking(move || partition_lag(&*consumer))
+            .await
+            .map_err(|e| ConsumerError::Connection(e.to_string()))?
+    }
+
+    async fn set_paused(
+        &mut self,
+        all: bool,
+        topics: &BTreeSet<String>,
+    ) -> Result<bool, ConsumerError> {
+        // 持锁期间完成暂停/恢复，避免与再均衡中的分配回调交错
+        let mut paused = self
+            .rebalance
+            .paused
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *paused = PausedTopics {
+            all,
+            topics: topics.clone(),
+        };
+        let assignment = self
+            .consumer
+            .assignment()
+            .map_err(|e| ConsumerError::Connection(e.to_string()))?;
+        let mut pause = TopicPartitionList::new();
+        let mut resume = TopicPartitionList::new();
+        for elem in assignment.elements() {
+            let target = if paused.contains(elem.topic()) {
+                &mut pause
+            } else {
+                &mut resume
+            };
+            target.add_partition(elem.topic(), elem.partition());
+        }
+        if pause.count() > 0 {
+            self.consumer
+                .pause(&pause)
+                .map_err(|e| ConsumerError::Connection(e.to_string()))?;
+        }
+        if resume.count() > 0 {
+            self.consumer
+                .resume(&resume)
+                .map_err(|e| ConsumerError::Connection(e.to_string()))?;
+        }
+        Ok(true)
+    }
+
+    fn set_rebalance_listener(&mut self, listener: Arc<dyn RebalanceListener>) {
+        *self
+            .rebalance
+            .listener
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(listener);
+    }
+}
+
+impl Drop for KafkaMessageFetcher {
+    fn drop(&mut self) {
+        if let Some((cancel, _)) = &self.committer {
+            cancel.cancel();
+        }
+    }
+}
+
+/// 提交所有分区的待提交水位，并刷新未提交窗口指标
+fn flush_offsets<K>(
+    consumer: &K,
+    offsets: &Mutex<OffsetTracker>,
+    mode: CommitMode,
+) -> Result<(), ConsumerError>
+where
+    K: Consumer<OffsetTrackingContext>,
+{
+    let commits = {
+        let tracker = offsets.lock().unwrap_or_else(PoisonError::into_inner);
+        tracker.export_metrics();
+        tracker.pending_commits()
+    };
+    if commits.is_empty() {
+        return Ok(());
+    }
+
+    let started_at = Instant::now();
+    let result = commit_offsets(consumer, &commits, mode);
+    let outcome = if result.is_ok() { "success" } else { "error" };
+    record_process_ack("kafka", "commit", outcome, started_at.elapsed());
+
+    match &result {
+        Ok(()) => {
+            let mut tracker = offsets.lock().unwrap_or_else(PoisonError::into_inner);
+            tracker.mark_committed(&commits);
+            tracker.export_metrics();
+            tracing::trace!(
+                backend = "kafka",
+                partitions = commits.len(),
+                "Kafka consumer offsets committed"
+            );
+        }
+        Err(error) => {
+            tracing::warn!(
+                backend = "kafka",
+                partitions = commits.len(),
+                error = %error,
+                "Kafka consumer offset commit failed"
+            );
+        }
+    }
+    result
+}
+
+/// 已分配分区的高水位与消费组已提交 offset 之差；尚未提交的分区从低水位算起
+fn partition_lag<K>(consumer: &K) -> Result<Vec<ConsumerLag>, ConsumerError>
+where
+    K: Consumer<OffsetTrackingContext>,
+{
+    let committed = consumer
+        .committed(LAG_QUERY_TIMEOUT)
+        .map_err(|e| ConsumerError::Connection(e.to_string()))?;
+    committed
+        .elements()
+        .iter()
+        .map(|elem| {
+            let (low, high) = consumer
+                .fetch_watermarks(elem.topic(), elem.partition(), LAG_QUERY_TIMEOUT)
+                .map_err(|e| ConsumerError::Connection(e.to_string()))?;
+            let position = match elem.offset() {
+                Offset::Offset(offset) => offset,
+                _ => low,
+            };
+            Ok(ConsumerLag::new(
+                elem.topic(),
+                elem.partition(),
+                high.saturating_sub(position).max(0),
+            ))
+        })
+        .collect()
 }
 
+fn commit_offsets<K>(
+    consumer: &K,
+    commits: &[OffsetCommit],
+    mode: CommitMode,
+) -> Result<(), ConsumerError>
+where
+    K: Consumer<OffsetTrackingContext>,
+{
+    let mut tpl = TopicPartitionList::new();
+    for (topic, partition, offset) in commits {
+        tpl.add_partition_offset(topic, *partition, Offset::Offset(*offset))
+            .map_err(|e| ConsumerError::Connection(e.to_string()))?;
+    }
+    consumer
+        .commit(&tpl, mode)
+        .map_err(|e| ConsumerError::Connection(e.to_string()))
+}
+
+/// 消息的所有副本释放时仍未 ack / term，则放弃其 offset（见 [OffsetTracker::abandon]）
 struct KafkaMessageAck {
-    consumer: Arc<StreamConsumer>,
+    offsets: SharedOffsets,
     topic: String,
     partition: i32,
     offset: i64,
+    settled: AtomicBool,
 }
 
 impl KafkaMessageAck {
-    fn commit_offset(&self, operation: &'static str) -> Result<(), ConsumerError> {
+    /// 标记完成，由提交任务按连续水位提交
+    fn complete(&self, operation: &'static str) -> Result<(), ConsumerError> {
+        self.settled.store(true, Ordering::Release);
         let started_at = Instant::now();
-        let result = self.commit_offset_inner();
-        let outcome = if result.is_ok() { "success" } else { "error" };
+        let tracked = self
+            .offsets
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .complete(&self.topic, self.partition, self.offset);
+        let outcome = if tracked { "success" } else { "revoked" };
 
         record_process_ack("kafka", operation, outcome, started_at.elapsed());
 
-        match &result {
-            Ok(()) => {
-                tracing::trace!(
-                    backend = "kafka",
-                    operation,
-                    topic = %self.topic,
-                    partition = self.partition,
-                    offset = self.offset,
-                    commit_offset = self.offset.saturating_add(1),
-                    "Kafka consumer offset committed"
-                );
-            }
-            Err(error) => {
-                tracing::warn!(
-                    backend = "kafka",
-                    operation,
-                    topic = %self.topic,
-                    partition = self.partition,
-                    offset = self.offset,
-                    error = %error,
-                    "Kafka consumer offset commit failed"
-                );
-            }
+        if tracked {
+            tracing::trace!(
+                backend = "kafka",
+                operation,
+                topic = %self.topic,
+                partition = self.partition,
+                offset = self.offset,
+                "Kafka consumer offset marked complete"
+            );
+        } else {
+            tracing::debug!(
+                backend = "kafka",
+                operation,
+                topic = %self.topic,
+                partition = self.partition,
+                offset = self.offset,
+                "Kafka partition revoked before completion, message will be redelivered"
+            );
         }
-
-        result
-    }
-
-    fn commit_offset_inner(&self) -> Result<(), ConsumerError> {
-        let mut tpl = TopicPartitionList::new();
-        tpl.add_partition_offset(
-            &self.topic,
-            self.partition,
-            Offset::Offset(self.offset.saturating_add(1)),
-        )
-        .map_err(|e| ConsumerError::Connection(e.to_string()))?;
-        self.consumer
-            .commit(&tpl, CommitMode::Async)
-            .map_err(|e| ConsumerError::Connection(e.to_string()))
+        Ok(())
     }
 
     fn record_noop(&self, operation: &'static str) {
@@ -215,10 +666,33 @@ impl KafkaMessageAck {
     }
 }
 
+impl Drop for KafkaMessageAck {
+    fn drop(&mut self) {
+        if self.settled.load(Ordering::Acquire) {
+            return;
+        }
+        let abandoned = self
+            .offsets
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .abandon(&self.topic, self.partition, self.offset);
+        if abandoned {
+            record_process_ack("kafka", "abandon", "success", Duration::ZERO);
+            tracing::warn!(
+                backend = "kafka",
+                topic = %self.topic,
+                partition = self.partition,
+                offset = self.offset,
+                "Kafka message dropped without ack, partition will be rewound"
+            );
+        }
+    }
+}
+
 #[async_trait::async_trait]
 impl MessageAck for KafkaMessageAck {
     async fn ack(&self) -> Result<(), ConsumerError> {
-        self.commit_offset("ack")
+        self.complete("ack")
     }
 
     async fn nack(&self) -> Result<(), ConsumerError> {
@@ -229,7 +703,7 @@ impl MessageAck for KafkaMessageAck {
     }
 
     async fn term(&self) -> Result<(), ConsumerError> {
-        self.commit_offset("term")
+        self.complete("term")
     }
 
     fn supports_native_retry(&self) -> bool {
@@ -286,3 +760,92 @@ where
         Box::new(consumer),
     )])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ack_for(offsets: &SharedOffsets, offset: i64) -> KafkaMessageAck {
+        offsets
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .track("orders", 0, offset);
+        KafkaMessageAck {
+            offsets: offsets.clone(),
+            topic: "orders".to_string(),
+            partition: 0,
+            offset,
+            settled: AtomicBool::new(false),
+        }
+    }
+
+    #[tokio::test]
+    async fn dropping_unsettled_message_rewinds_partition() {
+        let offsets: SharedOffsets = Arc::new(Mutex::new(OffsetTracker::new()));
+        let first = ack_for(&offsets, 0);
+        let stalled = ack_for(&offsets, 1);
+        let later = ack_for(&offsets, 2);
+
+        first.ack().await.unwrap();
+        later.ack().await.unwrap();
+        drop(first);
+        drop(later);
+        // nack 不被支持，消息随后被丢弃：offset 1 不能让水位永久停滞
+        assert!(stalled.nack().await.is_err());
+        drop(stalled);
+
+        let mut tracker = offsets.lock().unwrap_or_else(PoisonError::into_inner);
+        assert_eq!(
+            tracker.pending_commits(),
+            vec![("orders".to_string(), 0, 1)]
+        );
+        assert_eq!(tracker.take_rewinds(), vec![("orders".to_string(), 0, 1)]);
+    }
+
+    fn drain_context(offsets: &SharedOffsets, revoke_drain: Duration) -> OffsetTrackingContext {
+        OffsetTrackingContext {
+            offsets: offsets.clone(),
+            rebalance: Arc::new(RebalanceState::default()),
+            revoke_drain,
+        }
+    }
+
+    // 单 worker：若 drain 直接阻塞该 worker，负责 ack 的任务永远得不到调度，只能等到超时
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn revoke_drain_lets_in_flight_handlers_finish() {
+        let offsets: SharedOffsets = Arc::new(Mutex::new(OffsetTracker::new()));
+        let in_flight = ack_for(&offsets, 0);
+        let context = drain_context(&offsets, Duration::from_secs(5));
+
+        let handler = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            in_flight.ack().await.unwrap();
+        });
+        let started = Instant::now();
+        tokio::spawn(async move { context.drain(&[("orders".to_string(), 0)]) })
+            .await
+            .unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(2));
+        handler.await.unwrap();
+        let tracker = offsets.lock().unwrap_or_else(PoisonError::into_inner);
+        assert_eq!(tracker.in_flight([("orders", 0)]), 0);
+        assert!(ensure_drain_supported(Duration::from_secs(5)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn revoke_drain_is_rejected_on_current_thread_runtime() {
+        assert!(matches!(
+            ensure_drain_supported(Duration::from_secs(5)),
+            Err(ConsumerError::Configuration(_))
+        ));
+        assert!(ensure_drain_supported(Duration::ZERO).is_ok());
+
+        // 兜底：即使绕过检查，drain 也不能阻塞唯一的线程
+        let offsets: SharedOffsets = Arc::new(Mutex::new(OffsetTracker::new()));
+        let _in_flight = ack_for(&offsets, 0);
+        let started = Instant::now();
+        drain_context(&offsets, Duration::from_secs(5)).drain(&[("orders".to_string(), 0)]);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
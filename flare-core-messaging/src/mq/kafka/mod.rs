@@ -2,6 +2,7 @@
 
 mod config;
 mod consumer;
+mod offsets;
 mod producer;
 
 pub use config::{KafkaConsumerConfig, KafkaProducerConfig};
@@ -9,4 +10,5 @@ pub use consumer::{
     KafkaMessageFetcher, build_kafka_consumer_tasks,
     build_kafka_consumer_tasks_with_failure_publishers,
 };
+pub use offsets::register_kafka_offset_metrics;
 pub use producer::{KafkaProducer, KafkaProducerBuilder};
@@ -0,0 +1,324 @@
+//! 分区 offset 水位
+//!
+//! 并发消费时消息完成顺序与 offset 顺序不一致。[OffsetTracker] 按分区记录已拉取的 offset，
+//! 只把连续完成的最高位置作为可提交水位，避免快消息越过仍在处理的慢消息提交。
+//!
+//! 未 ack / term 就被丢弃的消息（处理失败且无法交给重试/死信、任务 panic 等）通过
+//! [OffsetTracker::abandon] 放弃：该 offset 及之后的跟踪被清除，拉取器回退到该位置重新拉取，
+//! 水位不会因为永远不会完成的 offset 停滞。
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+use prometheus::{IntGaugeVec, Opts, Registry};
+
+type PartitionKey = (String, i32);
+
+/// 待提交的 `(topic, partition, 下一条要消费的 offset)`
+pub(crate) type OffsetCommit = (String, i32, i64);
+
+#[derive(Debug)]
+struct PartitionOffsets {
+    /// 已拉取未提交的 offset -> 是否已完成
+    pending: BTreeMap<i64, bool>,
+    /// 连续完成后的下一条 offset
+    watermark: i64,
+    /// 最近一次提交的 offset
+    committed: i64,
+    /// 已拉取的最大 offset 之后一位
+    fetched_end: i64,
+    /// 有消息被放弃时需要回退到的 offset
+    rewind: Option<i64>,
+}
+
+impl PartitionOffsets {
+    fn starting_at(offset: i64) -> Self {
+        Self {
+            pending: BTreeMap::new(),
+            watermark: offset,
+            committed: offset,
+            fetched_end: offset,
+            rewind: None,
+        }
+    }
+
+    fn advance(&mut self) {
+        while let Some(entry) = self.pending.first_entry() {
+            if !*entry.get() {
+                break;
+            }
+            self.watermark = entry.key().saturating_add(1);
+            entry.remove();
+        }
+    }
+
+    fn uncommitted(&self) -> i64 {
+        self.fetched_end.saturating_sub(self.committed)
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct OffsetTracker {
+    partitions: HashMap<PartitionKey, PartitionOffsets>,
+}
+
+impl OffsetTracker {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录拉取到的消息；分区等待回退且消息位于回退点之后时返回 `false`，
+    /// 该消息会在回退后重新拉取，调用方应丢弃
+    pub(crate) fn track(&mut self, topic: &str, partition: i32, offset: i64) -> bool {
+        let state = self
+            .partitions
+            .entry((topic.to_string(), partition))
+            .or_insert_with(|| PartitionOffsets::starting_at(offset));
+        if state.rewind.is_some_and(|rewind| offset >= rewind) {
+            return false;
+        }
+        if offset < state.watermark {
+            return true;
+        }
+        state.pending.entry(offset).or_insert(false);
+        state.fetched_end = state.fetched_end.max(offset.saturating_add(1));
+        true
+    }
+
+    /// 标记消息完成；分区已被回收或 offset 未被跟踪时返回 `false`
+    pub(crate) fn complete(&mut self, topic: &str, partition: i32, offset: i64) -> bool {
+        let Some(state) = self.partitions.get_mut(&(topic.to_string(), partition)) else {
+            return false;
+        };
+        let Some(done) = state.pending.get_mut(&offset) else {
+            return false;
+        };
+        *done = true;
+        state.advance();
+        true
+    }
+
+    /// 放弃未完成的消息：清除该 offset 及之后的跟踪并请求回退；
+    /// 已完成、未跟踪或分区已回收时返回 `false`
+    pub(crate) fn abandon(&mut self, topic: &str, partition: i32, offset: i64) -> bool {
+        let Some(state) = self.partitions.get_mut(&(topic.to_string(), partition)) else {
+            return false;
+        };
+        if state.pending.get(&offset) != Some(&false) {
+            return false;
+        }
+        // 之后的消息即使已完成也要随回退重新处理，否则水位会越过被放弃的 offset
+        state.pending.split_off(&offset);
+        state.rewind = Some(state.rewind.map_or(offset, |rewind| rewind.min(offset)));
+        true
+    }
+
+    /// 取出待回退的分区位置，由拉取器 seek 后重新拉取
+    pub(crate) fn take_rewinds(&mut self) -> Vec<OffsetCommit> {
+        self.partitions
+            .iter_mut()
+            .filter_map(|((topic, partition), state)| {
+                let rewind = state.rewind.take()?;
+                state.fetched_end = rewind;
+                Some((topic.clone(), *partition, rewind))
+            })
+            .collect()
+    }
+
+    /// seek 失败时恢复回退请求，下次拉取前重试
+    pub(crate) fn retry_rewind(&mut self, topic: &str, partition: i32, offset: i64) {
+        if let Some(state) = self.partitions.get_mut(&(topic.to_string(), partition)) {
+            state.rewind = Some(state.rewind.map_or(offset, |rewind| rewind.min(offset)));
+        }
+    }
+
+    /// 水位高于已提交位置的分区
+    pub(crate) fn pending_commits(&self) -> Vec<OffsetCommit> {
+        self.partitions
+            .iter()
+            .filter(|(_, state)| state.watermark > state.committed)
+            .map(|((topic, partition), state)| (topic.clone(), *partition, state.watermark))
+            .collect()
+    }
+
+    pub(crate) fn mark_committed(&mut self, commits: &[OffsetCommit]) {
+        for (topic, partition, offset) in commits {
+            if let Some(state) = self.partitions.get_mut(&(topic.clone(), *partition)) {
+                state.committed = state.committed.max(*offset);
+            }
+        }
+    }
+
+    /// 移除被回收的分区，返回其中尚未提交的水位
+    pub(crate) fn revoke<'a>(
+        &mut self,
+        partitions: impl IntoIterator<Item = (&'a str, i32)>,
+    ) -> Vec<OffsetCommit> {
+        partitions
+            .into_iter()
+            .filter_map(|(topic, partition)| {
+                let state = self.partitions.remove(&(topic.to_string(), partition))?;
+                uncommitted_gauge()
+                    .remove_label_values(&[topic, &partition.to_string()])
+                    .ok();
+                (state.watermark > state.committed)
+                    .then(|| (topic.to_string(), partition, state.watermark))
+            })
+            .collect()
+    }
+
+    /// 指定分区中已拉取、尚未完成的消息数
+    pub(crate) fn in_flight<'a>(
+        &self,
+        partitions: impl IntoIterator<Item = (&'a str, i32)>,
+    ) -> usize {
+        partitions
+            .into_iter()
+            .filter_map(|(topic, partition)| self.partitions.get(&(topic.to_string(), partition)))
+            .map(|state| state.pending.values().filter(|done| !**done).count())
+            .sum()
+    }
+
+    /// 已拉取但未提交的消息数（含已完成但被慢消息阻挡的部分）
+    pub(crate) fn uncommitted(&self, topic: &str, partition: i32) -> Option<i64> {
+        self.partitions
+            .get(&(topic.to_string(), partition))
+            .map(PartitionOffsets::uncommitted)
+    }
+
+    /// 把各分区未提交窗口写入 `mq_kafka_uncommitted_offsets`
+    pub(crate) fn export_metrics(&self) {
+        let gauge = uncommitted_gauge();
+        for ((topic, partition), state) in &self.partitions {
+            gauge
+                .with_label_values(&[topic.as_str(), &partition.to_string()])
+                .set(state.uncommitted());
+        }
+    }
+}
+
+static UNCOMMITTED_OFFSETS: OnceLock<IntGaugeVec> = OnceLock::new();
+
+/// 把 `mq_kafka_uncommitted_offsets` 同时注册到 `registry`
+///
+/// 该指标默认注册在 `prometheus::default_registry()`；运行时改用自定义 registry 导出时调用此函数。
+pub fn register_kafka_offset_metrics(registry: &Registry) -> prometheus::Result<()> {
+    registry.register(Box::new(uncommitted_gauge().clone()))
+}
+
+fn uncommitted_gauge() -> &'static IntGaugeVec {
+    UNCOMMITTED_OFFSETS.get_or_init(|| {
+        let gauge = IntGaugeVec::new(
+            Opts::new(
+                "mq_kafka_uncommitted_offsets",
+                "Kafka messages fetched but not yet covered by a committed offset, by topic and partition.",
+            ),
+            &["topic", "partition"],
+        )
+        .expect("uncommitted offsets metric is valid");
+        let _ = prometheus::default_registry().register(Box::new(gauge.clone()));
+        gauge
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watermark_stops_at_oldest_incomplete_offset() {
+        let mut tracker = OffsetTracker::new();
+        for offset in 7..=10 {
+            tracker.track("orders", 0, offset);
+        }
+
+        assert!(tracker.complete("orders", 0, 10));
+        assert!(tracker.pending_commits().is_empty());
+        assert_eq!(tracker.uncommitted("orders", 0), Some(4));
+
+        tracker.complete("orders", 0, 7);
+        tracker.complete("orders", 0, 9);
+        assert_eq!(
+            tracker.pending_commits(),
+            vec![("orders".to_string(), 0, 8)]
+        );
+
+        let commits = tracker.pending_commits();
+        tracker.mark_committed(&commits);
+        assert!(tracker.pending_commits().is_empty());
+        assert_eq!(tracker.uncommitted("orders", 0), Some(3));
+
+        tracker.complete("orders", 0, 8);
+        assert_eq!(
+            tracker.pending_commits(),
+            vec![("orders".to_string(), 0, 11)]
+        );
+    }
+
+    #[test]
+    fn revoke_returns_pending_watermark_and_ignores_late_completions() {
+        let mut tracker = OffsetTracker::new();
+        tracker.track("orders", 1, 0);
+        tracker.track("orders", 1, 2);
+        tracker.track("orders", 2, 5);
+        tracker.complete("orders", 1, 0);
+        assert_eq!(tracker.in_flight([("orders", 1), ("orders", 2)]), 2);
+
+        let commits = tracker.revoke([("orders", 1), ("orders", 2)]);
+        assert_eq!(commits, vec![("orders".to_string(), 1, 1)]);
+        assert!(!tracker.complete("orders", 1, 2));
+        assert_eq!(tracker.uncommitted("orders", 1), None);
+    }
+
+    #[test]
+    fn abandoned_offset_rewinds_instead_of_stalling_watermark() {
+        let mut tracker = OffsetTracker::new();
+        for offset in 0..4 {
+            assert!(tracker.track("orders", 0, offset));
+        }
+        tracker.complete("orders", 0, 0);
+        tracker.complete("orders", 0, 2);
+
+        // offset 1 永远不会 ack：放弃后清除 1 及之后的跟踪并回退
+        assert!(tracker.abandon("orders", 0, 1));
+        assert!(!tracker.abandon("orders", 0, 0));
+        assert_eq!(tracker.in_flight([("orders", 0)]), 0);
+        assert_eq!(
+            tracker.pending_commits(),
+            vec![("orders".to_string(), 0, 1)]
+        );
+
+        // 回退前已在途的后续消息被丢弃，迟到的完成不生效
+        assert!(!tracker.track("orders", 0, 4));
+        assert!(!tracker.complete("orders", 0, 3));
+        assert_eq!(tracker.take_rewinds(), vec![("orders".to_string(), 0, 1)]);
+        assert!(tracker.take_rewinds().is_empty());
+
+        // 回退后重新拉取，水位越过原先停滞的位置
+        for offset in 1..4 {
+            assert!(tracker.track("orders", 0, offset));
+            tracker.complete("orders", 0, offset);
+        }
+        assert_eq!(
+            tracker.pending_commits(),
+            vec![("orders".to_string(), 0, 4)]
+        );
+    }
+
+    #[test]
+    fn uncommitted_gauge_registers_with_custom_registry() {
+        let registry = Registry::new();
+        register_kafka_offset_metrics(&registry).unwrap();
+
+        let mut tracker = OffsetTracker::new();
+        tracker.track("metrics-test", 0, 0);
+        tracker.export_metrics();
+
+        assert!(
+            registry
+                .gather()
+                .iter()
+                .any(|family| family.name() == "mq_kafka_uncommitted_offsets")
+        );
+    }
+}
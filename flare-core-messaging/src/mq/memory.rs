@@ -0,0 +1,844 @@
+//! 进程内 MQ Broker
+//!
+//! 提供 [InMemoryBroker]：同时实现 [Producer] 与（通过 [InMemoryMessageFetcher]）[MessageFetcher]，
+//! 用于在不依赖外部服务的情况下对 `ConsumerRuntime`、`MqEventBus`、`RetryForwarderHandler`
+//! 做确定性的集成测试。
+//!
+//! ## 语义
+//!
+//! - **Topic / 分区**：首次发送时按默认分区数自动创建；有 key 时按 key 哈希选分区（同 key 有序），
+//!   无 key 时轮询。
+//! - **消费组**：每个消费组独立维护各分区的拉取位置与提交位点；同组多个 fetcher 竞争消费。
+//! - **Ack**：`ack` / `term` 结算消息并推进连续提交位点；`nack` 将消息放回本组重投队列，
+//!   下次投递时 `retry_count` 加一；`term` 的消息额外记录到 [InMemoryBroker::terminated]。
+//! - **暂停**：支持 `MessageFetcher::set_paused`，被暂停的 topic 不再投递（含重投队列）。
+//!
+//! ```rust,ignore
+//! use flare_server_core::mq::memory::InMemoryBroker;
+//!
+//! let broker = InMemoryBroker::new().with_default_partitions(4);
+//! broker.send(&ctx, "orders", Some("user-1"), payload, None).await?;
+//!
+//! let mut fetcher = broker.fetcher("order-service", ["orders"]);
+//! runtime.run_with_shutdown(&mut fetcher, shutdown_rx).await?;
+//! ```
+
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use async_trait::async_trait;
+use flare_core_base::context::Ctx;
+
+use super::consumer::failure::retry_count_from_headers;
+use super::consumer::{
+    ConsumerError, ConsumerLag, ContentType, Message, MessageAck, MessageContext, MessageFetcher,
+};
+use super::context::{merge_ctx_to_headers, mq_headers_to_ctx};
+use super::producer::{Producer, ProducerError, ProducerMessage};
+
+const HEADER_MESSAGE_ID: &str = "x-message-id";
+const HEADER_MESSAGE_KEY: &str = "x-message-key";
+
+/// Broker 中的一条消息快照
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InMemoryRecord {
+    pub topic: String,
+    pub partition: i32,
+    pub offset: i64,
+    pub message_id: String,
+    pub key: Option<String>,
+    pub payload: Vec<u8>,
+    pub headers: HashMap<String, String>,
+}
+
+type PartitionId = (String, i32);
+type RecordId = (String, i32, i64);
+
+#[derive(Default)]
+struct TopicState {
+    partitions: Vec<Vec<InMemoryRecord>>,
+    round_robin: usize,
+}
+
+#[derive(Default)]
+struct PartitionCursor {
+    /// 下一条待拉取的 offset
+    next: i64,
+    /// 连续已结算的下一个 offset（Kafka 语义的提交位点）
+    committed: i64,
+    /// 已结算但尚未连续的 offset
+    settled: BTreeSet<i64>,
+}
+
+impl PartitionCursor {
+    fn settle(&mut self, offset: i64) {
+        self.settled.insert(offset);
+        while self.settled.remove(&self.committed) {
+            self.committed += 1;
+        }
+    }
+}
+
+#[derive(Default)]
+struct GroupState {
+    cursors: HashMap<PartitionId, PartitionCursor>,
+    redelivery: VecDeque<RecordId>,
+    in_flight: HashSet<RecordId>,
+    deliveries: HashMap<RecordId, u32>,
+    terminated: Vec<InMemoryRecord>,
+}
+
+#[derive(Default)]
+struct BrokerState {
+    topics: HashMap<String, TopicState>,
+    groups: HashMap<String, GroupState>,
+}
+
+impl BrokerState {
+    fn record(&self, (topic, partition, offset): &RecordId) -> Option<&InMemoryRecord> {
+        self.topics
+            .get(topic)?
+            .partitions
+            .get(*partition as usize)?
+            .get(*offset as usize)
+    }
+}
+
+/// 进程内消息 Broker
+///
+/// 克隆后共享同一份状态，可同时作为生产者注入 `MqEventBus` / 失败发布器，
+/// 并通过 [Self::fetcher] 为消费组创建拉取器。
+#[derive(Clone)]
+pub struct InMemoryBroker {
+    state: Arc<Mutex<BrokerState>>,
+    default_partitions: usize,
+    name: String,
+}
+
+impl Default for InMemoryBroker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InMemoryBroker {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(BrokerState::default())),
+            default_partitions: 1,
+            name: "in-memory".to_string(),
+        }
+    }
+
+    /// 自动创建 topic 时使用的分区数
+    pub fn with_default_partitions(mut self, partitions: usize) -> Self {
+        self.default_partitions = partitions.max(1);
+        self
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// 显式创建 topic；已存在时保持原分区数
+    pub fn create_topic(&self, topic: impl Into<String>, partitions: usize) {
+        self.lock()
+            .topics
+            .entry(topic.into())
+            .or_insert_with(|| new_topic(partitions));
+    }
+
+    /// 为消费组创建拉取器；新消费组从最早 offset 开始消费
+    pub fn fetcher<I, T>(&self, group: impl Into<String>, topics: I) -> InMemoryMessageFetcher
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        InMemoryMessageFetcher {
+            broker: self.clone(),
+            group: group.into(),
+            topics: topics.into_iter().map(Into::into).collect(),
+            cursor: 0,
+            paused_all: false,
+            paused: BTreeSet::new(),
+        }
+    }
+
+    /// 发送消息并返回其分区与 offset
+    pub fn publish(
+        &self,
+        topic: &str,
+        key: Option<&str>,
+        payload: Vec<u8>,
+        mut headers: HashMap<String, String>,
+    ) -> (i32, i64) {
+        let message_id = headers
+            .get(HEADER_MESSAGE_ID)
+            .cloned()
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        headers.insert(HEADER_MESSAGE_ID.to_string(), message_id.clone());
+        if let Some(key) = key {
+            headers.insert(HEADER_MESSAGE_KEY.to_string(), key.to_string());
+        }
+
+        let mut state = self.lock();
+        let topic_state = state
+            .topics
+            .entry(topic.to_string())
+            .or_insert_with(|| new_topic(self.default_partitions));
+        let partition = match key {
+            Some(key) => (fnv1a(key.as_bytes()) % topic_state.partitions.len() as u64) as usize,
+            None => {
+                let partition = topic_state.round_robin % topic_state.partitions.len();
+                topic_state.round_robin = topic_state.round_robin.wrapping_add(1);
+                partition
+            }
+        };
+        let log = &mut topic_state.partitions[partition];
+        let offset = log.len() as i64;
+        log.push(InMemoryRecord {
+            topic: topic.to_string(),
+            partition: partition as i32,
+            offset,
+            message_id,
+            key: key.map(ToString::to_string),
+            payload,
+            headers,
+        });
+        (partition as i32, offset)
+    }
+
+    /// topic 中的全部消息（按分区、offset 排序）
+    pub fn records(&self, topic: &str) -> Vec<InMemoryRecord> {
+        self.lock()
+            .topics
+            .get(topic)
+            .map(|topic| topic.partitions.iter().flatten().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// topic 的分区数；topic 不存在时返回 `None`
+    pub fn partitions(&self, topic: &str) -> Option<usize> {
+        self.lock()
+            .topics
+            .get(topic)
+            .map(|topic| topic.partitions.len())
+    }
+
+    /// 消费组在分区上的提交位点（下一条待提交的 offset）；尚未提交时返回 `None`
+    pub fn committed_offset(&self, group: &str, topic: &str, partition: i32) -> Option<i64> {
+        self.lock()
+            .groups
+            .get(group)?
+            .cursors
+            .get(&(topic.to_string(), partition))
+            .map(|cursor| cursor.committed)
+            .filter(|committed| *committed > 0)
+    }
+
+    /// 消费组在 topic 上尚未结算（ack/term）的消息数
+    pub fn lag(&self, group: &str, topic: &str) -> usize {
+        let state = self.lock();
+        let Some(topic_state) = state.topics.get(topic) else {
+            return 0;
+        };
+        let group_state = state.groups.get(group);
+        topic_state
+            .partitions
+            .iter()
+            .enumerate()
+            .map(|(partition, log)| {
+                let settled = group_state
+                    .and_then(|group| group.cursors.get(&(topic.to_string(), partition as i32)))
+                    .map(|cursor| cursor.committed as usize + cursor.settled.len())
+                    .unwrap_or(0);
+                log.len().saturating_sub(settled)
+            })
+            .sum()
+    }
+
+    /// 消费组在各分区上日志末尾与提交位点之差（Kafka 语义的消费延迟）
+    pub fn partition_lag(&self, group: &str, topics: &[String]) -> Vec<ConsumerLag> {
+        let state = self.lock();
+        let group_state = state.groups.get(group);
+        topics
+            .iter()
+            .filter_map(|topic| Some((topic, state.topics.get(topic)?)))
+            .flat_map(|(topic, topic_state)| {
+                topic_state
+                    .partitions
+                    .iter()
+                    .enumerate()
+                    .map(move |(partition, log)| {
+                        let partition = partition as i32;
+                        let committed = group_state
+                            .and_then(|group| group.cursors.get(&(topic.clone(), partition)))
+                            .map(|cursor| cursor.committed)
+                            .unwrap_or(0);
+                        ConsumerLag::new(topic.as_str(), partition, log.len() as i64 - committed)
+                    })
+            })
+            .collect()
+    }
+
+    /// 消费组中已投递但尚未 ack/nack/term 的消息数
+    pub fn in_flight(&self, group: &str) -> usize {
+        self.lock()
+            .groups
+            .get(group)
+            .map(|group| group.in_flight.len())
+            .unwrap_or(0)
+    }
+
+    /// 消费组中被 `term` 的消息
+    pub fn terminated(&self, group: &str) -> Vec<InMemoryRecord> {
+        self.lock()
+            .groups
+            .get(group)
+            .map(|group| group.terminated.clone())
+            .unwrap_or_default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BrokerState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn next_delivery(
+        &self,
+        group: &str,
+        topics: &[String],
+        cursor: &mut usize,
+    ) -> Option<(InMemoryRecord, u32)> {
+        let mut guard = self.lock();
+        let state = &mut *guard;
+        let group_state = state.groups.entry(group.to_string()).or_default();
+
+        // 先投递本组 nack 的消息
+        if let Some(index) = group_state
+            .redelivery
+            .iter()
+            .position(|(topic, _, _)| topics.contains(topic))
+        {
+            let id = group_state.redelivery.remove(index)?;
+            let attempts = group_state.deliveries.get(&id).copied().unwrap_or(0);
+            group_state.in_flight.insert(id.clone());
+            let record = state.record(&id)?.clone();
+            return Some((record, attempts));
+        }
+
+        let partitions: Vec<PartitionId> = topics
+            .iter()
+            .filter_map(|topic| state.topics.get(topic).map(|t| (topic, t.partitions.len())))
+            .flat_map(|(topic, count)| (0..count as i32).map(move |p| (topic.clone(), p)))
+            .collect();
+        if partitions.is_empty() {
+            return None;
+        }
+
+        for step in 0..partitions.len() {
+            let index = (*cursor + step) % partitions.len();
+            let (topic, partition) = &partitions[index];
+            let log_len = state.topics[topic].partitions[*partition as usize].len() as i64;
+            let position = group_state
+                .cursors
+                .entry((topic.clone(), *partition))
+                .or_default();
+            if position.next < log_len {
+                let offset = position.next;
+                position.next += 1;
+                *cursor = index + 1;
+                let id = (topic.clone(), *partition, offset);
+                group_state.in_flight.insert(id.clone());
+                let record =
+                    state.topics[topic].partitions[*partition as usize][offset as usize].clone();
+                return Some((record, 0));
+            }
+        }
+        None
+    }
+
+    fn settle(&self, group: &str, id: &RecordId, terminated: bool) {
+        let mut guard = self.lock();
+        let state = &mut *guard;
+        let record = terminated.then(|| state.record(id).cloned()).flatten();
+        let group_state = state.groups.entry(group.to_string()).or_default();
+        group_state.in_flight.remove(id);
+        group_state.deliveries.remove(id);
+        group_state
+            .cursors
+            .entry((id.0.clone(), id.1))
+            .or_default()
+            .settle(id.2);
+        if let Some(record) = record {
+            group_state.terminated.push(record);
+        }
+    }
+
+    fn requeue(&self, group: &str, id: RecordId) {
+        let mut state = self.lock();
+        let group_state = state.groups.entry(group.to_string()).or_default();
+        group_state.in_flight.remove(&id);
+        *group_state.deliveries.entry(id.clone()).or_default() += 1;
+        group_state.redelivery.push_back(id);
+    }
+}
+
+#[async_trait]
+impl Producer for InMemoryBroker {
+    async fn send(
+        &self,
+        ctx: &Ctx,
+        topic: &str,
+        key: Option<&str>,
+        payload: Vec<u8>,
+        headers: Option<HashMap<String, String>>,
+    ) -> Result<(), ProducerError> {
+        let mut headers = headers.unwrap_or_default();
+        merge_ctx_to_headers(&mut headers, ctx);
+        let (partition, offset) = self.publish(topic, key, payload, headers);
+        tracing::trace!(
+            broker = %self.name,
+            topic = %topic,
+            partition,
+            offset,
+            "Message published to in-memory broker"
+        );
+        Ok(())
+    }
+
+    async fn send_batch(
+        &self,
+        ctx: &Ctx,
+        messages: Vec<ProducerMessage>,
+    ) -> Result<(), ProducerError> {
+        for message in messages {
+            self.send(
+                ctx,
+                &message.topic,
+                message.key.as_deref(),
+                message.payload,
+                Some(message.headers),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// 进程内 Broker 的消息获取器
+///
+/// 无可用消息时立即返回 `Ok(None)`，由 `ConsumerRuntime` 按 `idle_backoff` 退避。
+pub struct InMemoryMessageFetcher {
+    broker: InMemoryBroker,
+    group: String,
+    topics: Vec<String>,
+    cursor: usize,
+    paused_all: bool,
+    paused: BTreeSet<String>,
+}
+
+impl InMemoryMessageFetcher {
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
+    pub fn topics(&self) -> &[String] {
+        &self.topics
+    }
+
+    fn decode_message(&self, record: InMemoryRecord, redeliveries: u32) -> Message {
+        let ctx = mq_headers_to_ctx(&record.headers);
+        let content_type = record
+            .headers
+            .get("content-type")
+            .and_then(|v| ContentType::from_str(v))
+            .unwrap_or(ContentType::Raw);
+        let retry_count = retry_count_from_headers(&record.headers).max(redeliveries);
+        let ack = InMemoryMessageAck {
+            broker: self.broker.clone(),
+            group: self.group.clone(),
+            id: (record.topic.clone(), record.partition, record.offset),
+            settled: AtomicBool::new(false),
+        };
+
+        let context = MessageContext {
+            ctx,
+            message_id: record.message_id,
+            topic: record.topic,
+            partition: record.partition,
+            offset: record.offset,
+            key: record.key,
+            headers: record.headers,
+            started_at: std::time::Instant::now(),
+            retry_count,
+            metadata: HashMap::new(),
+        };
+        Message::new(record.payload, content_type, context).with_ack_handle(Arc::new(ack))
+    }
+}
+
+#[async_trait]
+impl MessageFetcher for InMemoryMessageFetcher {
+    async fn fetch(&mut self) -> Result<Option<Message>, ConsumerError> {
+        if self.paused_all {
+            return Ok(None);
+        }
+        let delivery = if self.paused.is_empty() {
+            self.broker
+                .next_delivery(&self.group, &self.topics, &mut self.cursor)
+        } else {
+            let active: Vec<String> = self
+                .topics
+                .iter()
+                .filter(|topic| !self.paused.contains(*topic))
+                .cloned()
+                .collect();
+            self.broker
+                .next_delivery(&self.group, &active, &mut self.cursor)
+        };
+        Ok(delivery.map(|(record, redeliveries)| self.decode_message(record, redeliveries)))
+    }
+
+    async fn lag(&mut self) -> Result<Vec<ConsumerLag>, ConsumerError> {
+        Ok(self.broker.partition_lag(&self.group, &self.topics))
+    }
+
+    async fn set_paused(
+        &mut self,
+        all: bool,
+        topics: &BTreeSet<String>,
+    ) -> Result<bool, ConsumerError> {
+        self.paused_all = all;
+        self.paused = topics.clone();
+        Ok(true)
+    }
+}
+
+struct InMemoryMessageAck {
+    broker: InMemoryBroker,
+    group: String,
+    id: RecordId,
+    settled: AtomicBool,
+}
+
+impl InMemoryMessageAck {
+    fn already_settled(&self, operation: &'static str) -> Result<(), ConsumerError> {
+        if self.settled.swap(true, Ordering::SeqCst) {
+            return Err(ConsumerError::Configuration(format!(
+                "in-memory message {}:{}:{} already settled, cannot {operation}",
+                self.id.0, self.id.1, self.id.2
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MessageAck for InMemoryMessageAck {
+    async fn ack(&self) -> Result<(), ConsumerError> {
+        self.already_settled("ack")?;
+        self.broker.settle(&self.group, &self.id, false);
+        Ok(())
+    }
+
+    async fn nack(&self) -> Result<(), ConsumerError> {
+        self.already_settled("nack")?;
+        self.broker.requeue(&self.group, self.id.clone());
+        Ok(())
+    }
+
+    async fn term(&self) -> Result<(), ConsumerError> {
+        self.already_settled("term")?;
+        self.broker.settle(&self.group, &self.id, true);
+        Ok(())
+    }
+}
+
+fn new_topic(partitions: usize) -> TopicState {
+    TopicState {
+        partitions: vec![Vec::new(); partitions.max(1)],
+        round_robin: 0,
+    }
+}
+
+/// FNV-1a，保证同一 key 在不同进程中落到相同分区
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// 基于 [InMemoryBroker] 的运行时集成测试共用工具
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::sync::{Arc, Mutex, PoisonError};
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use flare_core_base::context::{Context, Ctx};
+    use flare_core_runtime::config::PollWorkerConfig;
+
+    use crate::mq::consumer::{
+        ConsumerConfig, ConsumerError, Message, MessageHandler, MessageResult,
+    };
+
+    pub(crate) fn ctx() -> Ctx {
+        Arc::new(Context::with_request_id("req-memory").with_tenant_id("t1"))
+    }
+
+    pub(crate) fn fast_config(max_retries: u32) -> ConsumerConfig {
+        let mut config = ConsumerConfig::new()
+            .with_poll(
+                PollWorkerConfig::new()
+                    .with_concurrency(1)
+                    .with_idle_backoff(Duration::from_millis(5)),
+            )
+            .with_idempotent(false);
+        config.max_retries = max_retries;
+        config
+    }
+
+    pub(crate) struct AlwaysNack;
+
+    #[async_trait]
+    impl MessageHandler for AlwaysNack {
+        async fn handle(&self, _message: Message) -> Result<MessageResult, ConsumerError> {
+            Ok(MessageResult::Nack)
+        }
+
+        fn name(&self) -> &str {
+            "always-nack"
+        }
+    }
+
+    /// 前 `fail_times` 次投递返回 Nack，之后 Ack
+    pub(crate) struct FlakyHandler {
+        pub(crate) fail_times: u32,
+        pub(crate) seen: Mutex<Vec<u32>>,
+    }
+
+    #[async_trait]
+    impl MessageHandler for FlakyHandler {
+        async fn handle(&self, message: Message) -> Result<MessageResult, ConsumerError> {
+            self.seen
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(message.context.retry_count);
+            if message.context.retry_count < self.fail_times {
+                Ok(MessageResult::Nack)
+            } else {
+                Ok(MessageResult::Ack)
+            }
+        }
+
+        fn name(&self) -> &str {
+            "flaky"
+        }
+    }
+
+    pub(crate) async fn wait_until(mut condition: impl FnMut() -> bool) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !condition() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("condition reached before timeout");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::{AlwaysNack, FlakyHandler, ctx, fast_config, wait_until};
+    use super::*;
+    use crate::mq::consumer::ConsumerRuntime;
+    use crate::mq::consumer::dispatcher::{Dispatcher, TopicDispatcher};
+    use crate::mq::consumer::failure::{
+        FailureTopic, HEADER_ORIGINAL_TOPIC, HEADER_RETRY_COUNT, ProducerDeadLetterPublisher,
+        ProducerRetryPublisher, RetryForwarderHandler,
+    };
+    use tokio::sync::oneshot;
+
+    #[tokio::test]
+    async fn same_key_lands_on_same_partition_in_order() {
+        let broker = InMemoryBroker::new().with_default_partitions(4);
+        for i in 0..6u8 {
+            broker
+                .send(&ctx(), "orders", Some("user-1"), vec![i], None)
+                .await
+                .unwrap();
+        }
+
+        let records = broker.records("orders");
+        assert_eq!(records.len(), 6);
+        assert!(records.iter().all(|r| r.partition == records[0].partition));
+        let offsets: Vec<i64> = records.iter().map(|r| r.offset).collect();
+        assert_eq!(offsets, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(
+            records[0]
+                .headers
+                .get(HEADER_MESSAGE_KEY)
+                .map(String::as_str),
+            Some("user-1")
+        );
+        assert_eq!(
+            records[0].headers.get("x-tenant-id").map(String::as_str),
+            Some("t1")
+        );
+    }
+
+    #[tokio::test]
+    async fn consumer_groups_track_offsets_independently() {
+        let broker = InMemoryBroker::new();
+        for i in 0..3u8 {
+            broker
+                .send(&ctx(), "events", None, vec![i], None)
+                .await
+                .unwrap();
+        }
+
+        let mut a = broker.fetcher("group-a", ["events"]);
+        let mut b = broker.fetcher("group-b", ["events"]);
+        let first = a.fetch().await.unwrap().unwrap();
+        let second = a.fetch().await.unwrap().unwrap();
+        assert_eq!(first.context.ctx.tenant_id(), Some("t1"));
+
+        // 乱序 ack：位点只推进到连续已结算的位置
+        second.ack_handle.as_ref().unwrap().ack().await.unwrap();
+        assert_eq!(broker.committed_offset("group-a", "events", 0), None);
+        first.ack_handle.as_ref().unwrap().ack().await.unwrap();
+        assert_eq!(broker.committed_offset("group-a", "events", 0), Some(2));
+        assert_eq!(broker.lag("group-a", "events"), 1);
+
+        // group-b 不受 group-a 影响
+        let from_b = b.fetch().await.unwrap().unwrap();
+        assert_eq!(from_b.context.offset, 0);
+        assert_eq!(broker.lag("group-b", "events"), 3);
+    }
+
+    #[tokio::test]
+    async fn nack_redelivers_with_incremented_retry_and_term_drops() {
+        let broker = InMemoryBroker::new();
+        broker
+            .send(&ctx(), "jobs", None, b"job".to_vec(), None)
+            .await
+            .unwrap();
+        let mut fetcher = broker.fetcher("workers", ["jobs"]);
+
+        let first = fetcher.fetch().await.unwrap().unwrap();
+        assert_eq!(first.context.retry_count, 0);
+        let ack = first.ack_handle.clone().unwrap();
+        ack.nack().await.unwrap();
+        assert!(ack.ack().await.is_err(), "settled handle cannot be reused");
+
+        let second = fetcher.fetch().await.unwrap().unwrap();
+        assert_eq!(second.context.retry_count, 1);
+        assert_eq!(second.context.message_id, first.context.message_id);
+        second.ack_handle.as_ref().unwrap().term().await.unwrap();
+
+        assert!(fetcher.fetch().await.unwrap().is_none());
+        assert_eq!(broker.terminated("workers").len(), 1);
+        assert_eq!(broker.lag("workers", "jobs"), 0);
+        assert_eq!(broker.in_flight("workers"), 0);
+    }
+
+    #[tokio::test]
+    async fn runtime_native_retry_then_dead_letter_topic() {
+        let broker = InMemoryBroker::new();
+        let mut dispatcher = TopicDispatcher::new();
+        dispatcher
+            .register("orders".to_string(), Arc::new(AlwaysNack))
+            .unwrap();
+        let runtime = ConsumerRuntime::new(fast_config(2), Arc::new(dispatcher))
+            .with_dead_letter_publisher(Arc::new(ProducerDeadLetterPublisher::new(
+                Arc::new(broker.clone()),
+                FailureTopic::suffix(".dlq"),
+            )));
+
+        broker
+            .send(&ctx(), "orders", Some("o-1"), b"order".to_vec(), None)
+            .await
+            .unwrap();
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let mut fetcher = broker.fetcher("order-service", ["orders"]);
+        let handle =
+            tokio::spawn(async move { runtime.run_with_shutdown(&mut fetcher, shutdown_rx).await });
+
+        wait_until(|| !broker.records("orders.dlq").is_empty()).await;
+        wait_until(|| broker.lag("order-service", "orders") == 0).await;
+        let _ = shutdown_tx.send(());
+        handle.await.unwrap().unwrap();
+
+        let dead = broker.records("orders.dlq");
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].key.as_deref(), Some("o-1"));
+        assert_eq!(
+            dead[0].headers.get(HEADER_RETRY_COUNT).map(String::as_str),
+            Some("2")
+        );
+        assert_eq!(broker.terminated("order-service").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn runtime_retry_topic_is_forwarded_back_to_original() {
+        let broker = InMemoryBroker::new();
+        let producer: Arc<dyn Producer> = Arc::new(broker.clone());
+        let handler = Arc::new(FlakyHandler {
+            fail_times: 1,
+            seen: Mutex::new(Vec::new()),
+        });
+
+        let mut dispatcher = TopicDispatcher::new();
+        dispatcher
+            .register("orders".to_string(), handler.clone())
+            .unwrap();
+        dispatcher
+            .register(
+                "orders.retry".to_string(),
+                Arc::new(RetryForwarderHandler::new(producer.clone())),
+            )
+            .unwrap();
+        let runtime = ConsumerRuntime::new(fast_config(3), Arc::new(dispatcher))
+            .with_retry_publisher(Arc::new(ProducerRetryPublisher::new(
+                producer,
+                FailureTopic::suffix(".retry"),
+            )));
+
+        broker
+            .send(&ctx(), "orders", None, b"order".to_vec(), None)
+            .await
+            .unwrap();
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let mut fetcher = broker.fetcher("order-service", ["orders", "orders.retry"]);
+        let handle =
+            tokio::spawn(async move { runtime.run_with_shutdown(&mut fetcher, shutdown_rx).await });
+
+        wait_until(|| broker.records("orders").len() == 2).await;
+        wait_until(|| broker.lag("order-service", "orders") == 0).await;
+        let _ = shutdown_tx.send(());
+        handle.await.unwrap().unwrap();
+
+        let retry = broker.records("orders.retry");
+        assert_eq!(retry.len(), 1);
+        assert_eq!(
+            retry[0]
+                .headers
+                .get(HEADER_ORIGINAL_TOPIC)
+                .map(String::as_str),
+            Some("orders")
+        );
+        assert_eq!(
+            *handler.seen.lock().unwrap_or_else(PoisonError::into_inner),
+            vec![0, 1]
+        );
+        assert!(broker.terminated("order-service").is_empty());
+    }
+}
@@ -17,6 +17,8 @@
 //! - `consumer/`: 消费者核心框架（抽象层）
 //! - `jetstream/`: JetStream 具体实现（包含 Context 透传）
 //! - `nats/`: NATS JetStream 具体实现（包含 Context 透传）
+//! - `memory.rs`: 进程内 Broker，用于无外部依赖的集成测试
+//! - `outbox/`: Postgres 事务性 Outbox 与转发任务（`outbox` feature）
 //!
 //! ## 使用示例
 //!
@@ -86,21 +88,29 @@
 pub mod kafka;
 #[cfg(feature = "nats")]
 pub mod nats;
+#[cfg(feature = "outbox")]
+pub mod outbox;
 
 pub mod consumer;
 pub mod context;
+pub mod memory;
 #[cfg(any(feature = "kafka", feature = "nats"))]
 pub(crate) mod process_ack_metrics;
 pub mod producer;
+#[cfg(feature = "postgres")]
+pub(crate) mod sql;
 
 // 重新导出 Producer 相关类型
 pub use producer::{Producer, ProducerConfig, ProducerError, ProducerMessage};
 
+pub use memory::{InMemoryBroker, InMemoryMessageFetcher, InMemoryRecord};
+
 // 重新导出 Consumer 相关类型
 pub use consumer::{
-    ConsumerConfig, ConsumerError, ConsumerRuntime, ConsumerRuntimeTask, ConsumerStats,
-    ContentType, Dispatcher, HandlerRegistry, Message, MessageContext, MessageFetcher,
-    MessageHandler, MessageResult, MqConsumer, MqConsumerTask, RegistryDispatcher, TopicDispatcher,
+    ConsumerConfig, ConsumerError, ConsumerHandle, ConsumerLag, ConsumerMetrics, ConsumerRuntime,
+    ConsumerRuntimeTask, ConsumerStats, ContentType, Dispatcher, HandlerRegistry, Message,
+    MessageContext, MessageFetcher, MessageHandler, MessageResult, MqConsumer, MqConsumerTask,
+    RebalanceListener, RegistryDispatcher, TopicDispatcher,
 };
 
 #[cfg(feature = "nats")]
@@ -109,9 +119,15 @@ pub use nats::{
     NatsProducerBuilder, NatsProducerConfig, build_nats_consumer_tasks_with_failure_publishers,
 };
 
+#[cfg(any(feature = "kafka", feature = "nats"))]
+pub use process_ack_metrics::register_process_ack_metrics;
+
+#[cfg(feature = "outbox")]
+pub use outbox::{OutboxConfig, OutboxProducer, OutboxRelay, OutboxRelayConfig};
+
 #[cfg(feature = "kafka")]
 pub use kafka::{
     KafkaConsumerConfig, KafkaMessageFetcher, KafkaProducer, KafkaProducerBuilder,
     KafkaProducerConfig, build_kafka_consumer_tasks,
-    build_kafka_consumer_tasks_with_failure_publishers,
+    build_kafka_consumer_tasks_with_failure_publishers, register_kafka_offset_metrics,
 };
@@ -4,10 +4,11 @@
 
 use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use async_nats::jetstream;
 use async_nats::jetstream::Message as NatsMessage;
+use async_nats::jetstream::consumer::PullConsumer;
 use async_nats::jetstream::consumer::pull::Config as PullConfig;
 use async_nats::jetstream::consumer::pull::Stream as PullStream;
 use futures_util::StreamExt;
@@ -19,8 +20,8 @@ use super::config::{
 use crate::mq::consumer::dispatcher::Dispatcher;
 use crate::mq::consumer::failure::{ConsumerFailurePublishers, retry_count_from_headers};
 use crate::mq::consumer::{
-    ConsumerConfig, ConsumerError, ConsumerRuntimeTask, ContentType, Message, MessageAck,
-    MessageContext, MessageFetcher, MqConsumerTask,
+    ConsumerConfig, ConsumerError, ConsumerLag, ConsumerRuntimeTask, ContentType, Message,
+    MessageAck, MessageContext, MessageFetcher, MqConsumerTask,
 };
 
 pub fn context_from_nats_headers(
@@ -32,6 +33,8 @@ pub fn context_from_nats_headers(
 /// NATS JetStream 消息获取器
 pub struct NatsMessageFetcher {
     stream: PullStream,
+    /// 查询 `num_pending` 用于消费延迟指标
+    consumer: PullConsumer,
     manual_ack: bool,
 }
 
@@ -179,7 +182,11 @@ impl NatsMessageFetcher {
             batch_timeout_ms = config.batch_timeout_ms().max(1),
             "Subscribed to NATS subjects"
         );
-        let fetcher = Self { stream, manual_ack };
+        let fetcher = Self {
+            stream,
+            consumer,
+            manual_ack,
+        };
 
         Ok(fetcher)
     }
@@ -424,6 +431,20 @@ impl MessageFetcher for NatsMessageFetcher {
             }
         }
     }
+
+    /// JetStream consumer 的 `num_pending`，以 stream 名作为 topic、分区记为 0
+    async fn lag(&mut self) -> Result<Vec<ConsumerLag>, ConsumerError> {
+        let info = self
+            .consumer
+            .info()
+            .await
+            .map_err(|e| ConsumerError::Connection(e.to_string()))?;
+        Ok(vec![ConsumerLag::new(
+            info.stream_name.clone(),
+            0,
+            i64::try_from(info.num_pending).unwrap_or(i64::MAX),
+        )])
+    }
 }
 
 impl NatsMessageFetcher {
@@ -554,10 +575,10 @@ impl NatsMessageFetcher {
             // Attributes（前缀匹配）
             let prefix = "x-actor-attr-";
             for (key, value) in headers.iter() {
-                if let Some(attr_key) = key.strip_prefix(prefix) {
-                    if !value.is_empty() {
-                        actor = actor.with_attribute(attr_key, value);
-                    }
+                if let Some(attr_key) = key.strip_prefix(prefix)
+                    && !value.is_empty()
+                {
+                    actor = actor.with_attribute(attr_key, value);
                 }
             }
 
@@ -638,6 +659,21 @@ impl MessageAck for NatsMessageAck {
         result
     }
 
+    async fn nack_with_delay(&self, delay: Duration) -> Result<(), ConsumerError> {
+        let started_at = Instant::now();
+        let result = self
+            .message
+            .ack_with(jetstream::AckKind::Nak(Some(delay)))
+            .await
+            .map_err(|e| ConsumerError::Connection(e.to_string()));
+        record_nats_process_ack("nack", started_at, &result);
+        result
+    }
+
+    fn supports_delayed_nack(&self) -> bool {
+        true
+    }
+
     async fn term(&self) -> Result<(), ConsumerError> {
         let started_at = Instant::now();
         let result = self
@@ -0,0 +1,202 @@
+use std::time::Duration;
+
+use crate::mq::producer::ProducerError;
+use crate::mq::sql::{is_identifier, is_table_name, unqualified};
+
+pub const DEFAULT_OUTBOX_TABLE: &str = "flare_outbox";
+pub const DEFAULT_OUTBOX_CHANNEL: &str = "flare_outbox";
+
+/// Outbox 表与通知通道配置（[super::OutboxProducer] 与 [super::OutboxRelay] 必须一致）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboxConfig {
+    /// 表名，可带 schema（如 `app.outbox`）
+    pub table: String,
+    /// 写入后 `pg_notify` 的通道；`None` 时 relay 只按间隔轮询
+    pub notify_channel: Option<String>,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            table: DEFAULT_OUTBOX_TABLE.to_string(),
+            notify_channel: Some(DEFAULT_OUTBOX_CHANNEL.to_string()),
+        }
+    }
+}
+
+impl OutboxConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_table(mut self, table: impl Into<String>) -> Self {
+        self.table = table.into();
+        self
+    }
+
+    pub fn with_notify_channel(mut self, channel: impl Into<String>) -> Self {
+        self.notify_channel = Some(channel.into());
+        self
+    }
+
+    pub fn without_notify(mut self) -> Self {
+        self.notify_channel = None;
+        self
+    }
+
+    /// 表名与通道会拼进 SQL，只允许 `[A-Za-z_][A-Za-z0-9_]*`（表名可带一级 schema）
+    pub fn validate(&self) -> Result<(), ProducerError> {
+        if !is_table_name(&self.table) {
+            return Err(ProducerError::Configuration(format!(
+                "invalid outbox table name: {}",
+                self.table
+            )));
+        }
+        if let Some(channel) = &self.notify_channel
+            && !is_identifier(channel)
+        {
+            return Err(ProducerError::Configuration(format!(
+                "invalid outbox notify channel: {channel}"
+            )));
+        }
+        Ok(())
+    }
+
+    /// 建表、租约列与待投递索引 DDL
+    pub fn schema_sql(&self) -> Vec<String> {
+        let index_prefix = unqualified(&self.table);
+        vec![
+            format!(
+                "CREATE TABLE IF NOT EXISTS {table} (
+                    id BIGSERIAL PRIMARY KEY,
+                    dedupe_id TEXT NOT NULL UNIQUE,
+                    topic TEXT NOT NULL,
+                    message_key TEXT,
+                    payload BYTEA NOT NULL,
+                    headers JSONB NOT NULL DEFAULT '{{}}'::jsonb,
+                    attempts INTEGER NOT NULL DEFAULT 0,
+                    last_error TEXT,
+                    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+                    delivered_at TIMESTAMPTZ,
+                    locked_until TIMESTAMPTZ
+                )",
+                table = self.table
+            ),
+            // 早期版本建的表没有租约列
+            format!(
+                "ALTER TABLE {table} ADD COLUMN IF NOT EXISTS locked_until TIMESTAMPTZ",
+                table = self.table
+            ),
+            format!(
+                "CREATE INDEX IF NOT EXISTS {index_prefix}_pending_idx
+                    ON {table} (id) WHERE delivered_at IS NULL",
+                table = self.table
+            ),
+        ]
+    }
+}
+
+/// Outbox relay 轮询与清理参数
+#[derive(Debug, Clone)]
+pub struct OutboxRelayConfig {
+    /// 单次转发的最大行数
+    pub batch_size: usize,
+    /// 无通知时的轮询间隔
+    pub poll_interval: Duration,
+    /// 转发失败后的退避间隔
+    pub error_backoff: Duration,
+    /// 已投递行的保留时间，超过后由清理任务删除
+    pub retention: Duration,
+    /// 清理已投递行的间隔
+    pub cleanup_interval: Duration,
+    /// 认领一批后的租约时长，应大于转发一批所需时间；relay 崩溃后租约过期才会被其他实例重新认领
+    pub lease: Duration,
+}
+
+impl Default for OutboxRelayConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 100,
+            poll_interval: Duration::from_secs(1),
+            error_backoff: Duration::from_secs(1),
+            retention: Duration::from_secs(3600),
+            cleanup_interval: Duration::from_secs(60),
+            lease: Duration::from_secs(30),
+        }
+    }
+}
+
+impl OutboxRelayConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    pub fn with_error_backoff(mut self, backoff: Duration) -> Self {
+        self.error_backoff = backoff;
+        self
+    }
+
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    pub fn with_cleanup_interval(mut self, interval: Duration) -> Self {
+        self.cleanup_interval = interval;
+        self
+    }
+
+    pub fn with_lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_accepts_plain_and_schema_qualified_tables() {
+        assert!(OutboxConfig::new().validate().is_ok());
+        assert!(
+            OutboxConfig::new()
+                .with_table("app.outbox")
+                .validate()
+                .is_ok()
+        );
+        assert!(OutboxConfig::new().with_table("a.b.c").validate().is_err());
+        assert!(
+            OutboxConfig::new()
+                .with_table("outbox; DROP TABLE users")
+                .validate()
+                .is_err()
+        );
+        assert!(
+            OutboxConfig::new()
+                .with_notify_channel("bad-channel")
+                .validate()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn schema_sql_uses_unqualified_index_name() {
+        let sql = OutboxConfig::new().with_table("app.outbox").schema_sql();
+        assert!(sql[0].contains("CREATE TABLE IF NOT EXISTS app.outbox"));
+        assert!(sql[0].contains("locked_until TIMESTAMPTZ"));
+        assert!(sql[1].contains("ALTER TABLE app.outbox ADD COLUMN IF NOT EXISTS locked_until"));
+        assert!(sql[2].contains("outbox_pending_idx"));
+        assert!(sql[2].contains("ON app.outbox"));
+    }
+}
@@ -0,0 +1,25 @@
+//! Postgres 事务性 Outbox
+//!
+//! 业务先写库再调用 `Producer::send` 时，进程在两步之间崩溃会丢事件。Outbox 把消息与业务数据
+//! 写在同一个 `sqlx` 事务里，再由 [OutboxRelay] 按顺序转发到任意 [crate::mq::Producer]。
+//!
+//! - [OutboxProducer]：`enqueue` 写入调用方事务；也实现 `Producer`，可直接注入 `MqEventBus`
+//! - [OutboxRelay]：LISTEN/轮询未投递行并转发，至少一次投递，按 `x-message-id` 去重，定期清理
+//!
+//! ```rust,ignore
+//! use flare_server_core::mq::outbox::{OutboxProducer, OutboxRelay};
+//!
+//! let outbox = OutboxProducer::new(pool.clone());
+//! outbox.ensure_schema().await?;
+//!
+//! let runtime = ServiceRuntime::new("order-service")
+//!     .add_task(Box::new(OutboxRelay::new(pool, Arc::new(kafka_producer))));
+//! ```
+
+mod config;
+mod producer;
+mod relay;
+
+pub use config::{DEFAULT_OUTBOX_CHANNEL, DEFAULT_OUTBOX_TABLE, OutboxConfig, OutboxRelayConfig};
+pub use producer::OutboxProducer;
+pub use relay::OutboxRelay;
@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use flare_core_base::context::Ctx;
+use sqlx::types::Json;
+use sqlx::{PgConnection, PgPool};
+
+use super::config::OutboxConfig;
+use crate::mq::context::merge_ctx_to_headers;
+use crate::mq::producer::{Producer, ProducerError, ProducerMessage};
+
+const HEADER_MESSAGE_ID: &str = "x-message-id";
+
+/// Outbox 生产者
+///
+/// [Self::enqueue] 在调用方的 `sqlx` 事务中写入消息，与业务数据同时提交或回滚；
+/// 由 [super::OutboxRelay] 异步转发到真实 Broker。
+///
+/// 同时实现 [Producer]：每次 `send` 使用独立事务写入 outbox，可直接注入 `MqEventBus`。
+#[derive(Clone)]
+pub struct OutboxProducer {
+    pool: PgPool,
+    config: OutboxConfig,
+    name: String,
+}
+
+impl OutboxProducer {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            config: OutboxConfig::default(),
+            name: "postgres-outbox".to_string(),
+        }
+    }
+
+    pub fn with_config(mut self, config: OutboxConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn config(&self) -> &OutboxConfig {
+        &self.config
+    }
+
+    /// 创建 outbox 表与索引（幂等）
+    pub async fn ensure_schema(&self) -> Result<(), ProducerError> {
+        self.config.validate()?;
+        for statement in self.config.schema_sql() {
+            sqlx::query(&statement)
+                .execute(&self.pool)
+                .await
+                .map_err(db_error)?;
+        }
+        Ok(())
+    }
+
+    /// 在调用方事务中写入一条消息，返回去重 ID
+    ///
+    /// 去重 ID 取 `x-message-id` / `event_id` 头，缺省时生成 UUID；重复写入同一 ID 会被忽略。
+    ///
+    /// ```rust,ignore
+    /// let mut tx = pool.begin().await?;
+    /// sqlx::query("UPDATE orders SET status = 'paid' WHERE id = $1")
+    ///     .bind(order_id)
+    ///     .execute(&mut *tx)
+    ///     .await?;
+    /// outbox.enqueue(&mut tx, &ctx, ProducerMessage::new("orders.paid", payload)).await?;
+    /// tx.commit().await?;
+    /// ```
+    pub async fn enqueue(
+        &self,
+        conn: &mut PgConnection,
+        ctx: &Ctx,
+        message: ProducerMessage,
+    ) -> Result<String, ProducerError> {
+        self.config.validate()?;
+        let dedupe_id = self.insert(conn, ctx, message).await?;
+        self.notify(conn).await?;
+        Ok(dedupe_id)
+    }
+
+    /// 在调用方事务中批量写入，保持传入顺序
+    pub async fn enqueue_batch(
+        &self,
+        conn: &mut PgConnection,
+        ctx: &Ctx,
+        messages: Vec<ProducerMessage>,
+    ) -> Result<Vec<String>, ProducerError> {
+        self.config.validate()?;
+        let mut ids = Vec::with_capacity(messages.len());
+        for message in messages {
+            ids.push(self.insert(conn, ctx, message).await?);
+        }
+        if !ids.is_empty() {
+            self.notify(conn).await?;
+        }
+        Ok(ids)
+    }
+
+    async fn insert(
+        &self,
+        conn: &mut PgConnection,
+        ctx: &Ctx,
+        message: ProducerMessage,
+    ) -> Result<String, ProducerError> {
+        let (dedupe_id, headers) = outbox_headers(ctx, message.headers);
+        let sql = format!(
+            "INSERT INTO {} (dedupe_id, topic, message_key, payload, headers)
+             VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT (dedupe_id) DO NOTHING",
+            self.config.table
+        );
+        sqlx::query(&sql)
+            .bind(&dedupe_id)
+            .bind(&message.topic)
+            .bind(message.key.as_deref())
+            .bind(message.payload)
+            .bind(Json(headers))
+            .execute(&mut *conn)
+            .await
+            .map_err(db_error)?;
+        Ok(dedupe_id)
+    }
+
+    /// `pg_notify` 在事务提交时才会送达，回滚的写入不会唤醒 relay
+    async fn notify(&self, conn: &mut PgConnection) -> Result<(), ProducerError> {
+        let Some(channel) = &self.config.notify_channel else {
+            return Ok(());
+        };
+        sqlx::query("SELECT pg_notify($1, '')")
+            .bind(channel)
+            .execute(&mut *conn)
+            .await
+            .map_err(db_error)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Producer for OutboxProducer {
+    async fn send(
+        &self,
+        ctx: &Ctx,
+        topic: &str,
+        key: Option<&str>,
+        payload: Vec<u8>,
+        headers: Option<HashMap<String, String>>,
+    ) -> Result<(), ProducerError> {
+        let mut message =
+            ProducerMessage::new(topic, payload).with_headers(headers.unwrap_or_default());
+        message.key = key.map(ToString::to_string);
+        self.send_batch(ctx, vec![message]).await
+    }
+
+    async fn send_batch(
+        &self,
+        ctx: &Ctx,
+        messages: Vec<ProducerMessage>,
+    ) -> Result<(), ProducerError> {
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+        self.enqueue_batch(&mut tx, ctx, messages).await?;
+        tx.commit().await.map_err(db_error)
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// 合并 Context 头并确定去重 ID（同时写回 `x-message-id`，供下游 Broker 去重）
+fn outbox_headers(
+    ctx: &Ctx,
+    mut headers: HashMap<String, String>,
+) -> (String, HashMap<String, String>) {
+    merge_ctx_to_headers(&mut headers, ctx);
+    let dedupe_id = headers
+        .get(HEADER_MESSAGE_ID)
+        .or_else(|| headers.get("event_id"))
+        .filter(|id| !id.is_empty())
+        .cloned()
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    headers.insert(HEADER_MESSAGE_ID.to_string(), dedupe_id.clone());
+    (dedupe_id, headers)
+}
+
+pub(super) fn db_error(err: sqlx::Error) -> ProducerError {
+    match err {
+        sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed | sqlx::Error::Io(_) => {
+            ProducerError::Connection(format!("outbox: {err}"))
+        }
+        other => ProducerError::Send(format!("outbox: {other}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flare_core_base::context::Context;
+    use std::sync::Arc;
+
+    #[test]
+    fn outbox_headers_keep_business_id_and_context() {
+        let ctx: Ctx = Arc::new(Context::with_request_id("req-1").with_tenant_id("t1"));
+        let mut headers = HashMap::new();
+        headers.insert("event_id".to_string(), "evt-1".to_string());
+
+        let (dedupe_id, headers) = outbox_headers(&ctx, headers);
+
+        assert_eq!(dedupe_id, "evt-1");
+        assert_eq!(
+            headers.get(HEADER_MESSAGE_ID).map(String::as_str),
+            Some("evt-1")
+        );
+        assert_eq!(headers.get("x-tenant-id").map(String::as_str), Some("t1"));
+    }
+
+    #[test]
+    fn outbox_headers_generate_id_when_missing() {
+        let ctx: Ctx = Arc::new(Context::with_request_id("req-2"));
+        let (dedupe_id, headers) = outbox_headers(&ctx, HashMap::new());
+
+        assert!(!dedupe_id.is_empty());
+        assert_eq!(headers.get(HEADER_MESSAGE_ID), Some(&dedupe_id));
+    }
+}
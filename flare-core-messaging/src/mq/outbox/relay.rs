@@ -0,0 +1,603 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use flare_core_runtime::task::{Task, TaskResult};
+use sqlx::PgPool;
+use sqlx::postgres::PgListener;
+use sqlx::types::Json;
+use tokio::sync::oneshot;
+
+use super::config::{OutboxConfig, OutboxRelayConfig};
+use super::producer::db_error;
+use crate::mq::context::mq_headers_to_ctx;
+use crate::mq::producer::{Producer, ProducerError};
+
+#[derive(sqlx::FromRow)]
+struct OutboxRow {
+    id: i64,
+    topic: String,
+    message_key: Option<String>,
+    payload: Vec<u8>,
+    headers: Json<HashMap<String, String>>,
+}
+
+/// Outbox 转发任务
+///
+/// 按 `id` 顺序读取未投递的行并经任意 [Producer] 转发，成功后标记 `delivered_at`，
+/// 过期的已投递行由清理周期删除。投递语义为至少一次：下游按 `x-message-id` 去重。
+///
+/// 每批先在短事务中认领：事务级 advisory lock 串行化认领，并为本批行写入 `locked_until` 租约；
+/// 转发过程不持有事务与行锁。其他实例仍持有未过期租约时不认领，同一时刻只有一个 relay 转发，保证顺序。
+/// 某行转发失败时本批停止并释放剩余租约，后续行不会越过它；relay 崩溃时租约过期后由其他实例重新投递。
+///
+/// 实现 [Task]，可通过 `ServiceRuntime::add_task` 注册。
+pub struct OutboxRelay {
+    name: String,
+    dependencies: Vec<String>,
+    pool: PgPool,
+    producer: Arc<dyn Producer>,
+    outbox: OutboxConfig,
+    config: OutboxRelayConfig,
+}
+
+impl OutboxRelay {
+    pub fn new(pool: PgPool, producer: Arc<dyn Producer>) -> Self {
+        Self {
+            name: "outbox-relay".to_string(),
+            dependencies: Vec::new(),
+            pool,
+            producer,
+            outbox: OutboxConfig::default(),
+            config: OutboxRelayConfig::default(),
+        }
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn with_dependencies(mut self, deps: Vec<String>) -> Self {
+        self.dependencies = deps;
+        self
+    }
+
+    pub fn with_outbox_config(mut self, outbox: OutboxConfig) -> Self {
+        self.outbox = outbox;
+        self
+    }
+
+    pub fn with_relay_config(mut self, config: OutboxRelayConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// 转发一批消息，返回成功投递的行数
+    ///
+    /// 其他实例正在认领或持有租约时直接返回 `Ok(0)`；某行转发失败时在记录失败后返回该错误。
+    pub async fn relay_once(&self) -> Result<usize, ProducerError> {
+        self.outbox.validate()?;
+        let store = PgOutboxStore {
+            pool: &self.pool,
+            table: &self.outbox.table,
+        };
+        relay_batch(&self.name, &store, self.producer.as_ref(), &self.config).await
+    }
+
+    /// 删除超过保留时间的已投递行，返回删除行数
+    pub async fn cleanup(&self) -> Result<u64, ProducerError> {
+        self.outbox.validate()?;
+        let result = sqlx::query(&format!(
+            "DELETE FROM {} WHERE delivered_at IS NOT NULL
+             AND delivered_at < now() - make_interval(secs => $1)",
+            self.outbox.table
+        ))
+        .bind(self.config.retention.as_secs_f64())
+        .execute(&self.pool)
+        .await
+        .map_err(db_error)?;
+        Ok(result.rows_affected())
+    }
+
+    /// 持续转发直到收到关闭信号
+    ///
+    /// 配置了通知通道时 LISTEN 唤醒，监听失败则退化为按 `poll_interval` 轮询。
+    pub async fn run_with_shutdown(
+        &self,
+        mut shutdown_rx: oneshot::Receiver<()>,
+    ) -> Result<(), ProducerError> {
+        self.outbox.validate()?;
+        let mut listener = self.listen().await;
+        let mut last_cleanup = Instant::now();
+
+        loop {
+            let wait = match self.relay_once().await {
+                Ok(delivered) if delivered >= self.config.batch_size.max(1) => None,
+                Ok(_) => Some(self.config.poll_interval),
+                Err(_) => Some(self.config.error_backoff),
+            };
+
+            if last_cleanup.elapsed() >= self.config.cleanup_interval {
+                last_cleanup = Instant::now();
+                match self.cleanup().await {
+                    Ok(0) => {}
+                    Ok(removed) => {
+                        tracing::debug!(relay = %self.name, removed, "Outbox delivered rows cleaned")
+                    }
+                    Err(err) => {
+                        tracing::warn!(relay = %self.name, error = %err, "Outbox cleanup failed")
+                    }
+                }
+            }
+
+            // 满批说明还有积压，立即继续
+            let Some(wait) = wait else {
+                match shutdown_rx.try_recv() {
+                    Err(oneshot::error::TryRecvError::Empty) => continue,
+                    _ => break,
+                }
+            };
+
+            let notified = tokio::select! {
+                _ = &mut shutdown_rx => break,
+                _ = tokio::time::sleep(wait) => Ok(()),
+                result = wait_for_notification(&mut listener) => result,
+            };
+            if let Err(err) = notified {
+                tracing::warn!(
+                    relay = %self.name,
+                    error = %err,
+                    "Outbox listener failed, falling back to polling"
+                );
+                listener = None;
+            }
+        }
+
+        tracing::info!(relay = %self.name, "Outbox relay stopped");
+        Ok(())
+    }
+
+    async fn listen(&self) -> Option<PgListener> {
+        let channel = self.outbox.notify_channel.as_deref()?;
+        let result = async {
+            let mut listener = PgListener::connect_with(&self.pool).await?;
+            listener.listen(channel).await?;
+            Ok::<_, sqlx::Error>(listener)
+        }
+        .await;
+        match result {
+            Ok(listener) => Some(listener),
+            Err(err) => {
+                tracing::warn!(
+                    relay = %self.name,
+                    channel,
+                    error = %err,
+                    "Outbox LISTEN unavailable, using polling only"
+                );
+                None
+            }
+        }
+    }
+}
+
+/// relay 对 outbox 表的读写
+#[async_trait]
+trait OutboxStore: Send + Sync {
+    /// 认领下一批待投递行并写入租约；其他 relay 的租约仍有效时返回空批
+    async fn claim(&self, limit: usize, lease: Duration) -> Result<Vec<OutboxRow>, ProducerError>;
+
+    /// 标记已投递并清除租约
+    async fn mark_delivered(&self, ids: &[i64]) -> Result<(), ProducerError>;
+
+    /// 记录转发失败，并释放该行与本批未转发行的租约
+    async fn mark_failed(&self, id: i64, error: &str, unsent: &[i64]) -> Result<(), ProducerError>;
+}
+
+struct PgOutboxStore<'a> {
+    pool: &'a PgPool,
+    table: &'a str,
+}
+
+#[async_trait]
+impl OutboxStore for PgOutboxStore<'_> {
+    async fn claim(&self, limit: usize, lease: Duration) -> Result<Vec<OutboxRow>, ProducerError> {
+        let table = self.table;
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+
+        let locked: bool = sqlx::query_scalar("SELECT pg_try_advisory_xact_lock(hashtext($1))")
+            .bind(table)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(db_error)?;
+        if !locked {
+            return Ok(Vec::new());
+        }
+
+        let leased: bool = sqlx::query_scalar(&format!(
+            "SELECT EXISTS (SELECT 1 FROM {table}
+             WHERE delivered_at IS NULL AND locked_until > now())"
+        ))
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(db_error)?;
+        if leased {
+            return Ok(Vec::new());
+        }
+
+        let mut rows: Vec<OutboxRow> = sqlx::query_as(&format!(
+            "UPDATE {table} SET locked_until = now() + make_interval(secs => $2)
+             WHERE id IN (
+                 SELECT id FROM {table} WHERE delivered_at IS NULL ORDER BY id LIMIT $1
+             )
+             RETURNING id, topic, message_key, payload, headers"
+        ))
+        .bind(limit as i64)
+        .bind(lease.as_secs_f64())
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(db_error)?;
+        tx.commit().await.map_err(db_error)?;
+
+        rows.sort_by_key(|row| row.id);
+        Ok(rows)
+    }
+
+    async fn mark_delivered(&self, ids: &[i64]) -> Result<(), ProducerError> {
+        sqlx::query(&format!(
+            "UPDATE {} SET delivered_at = now(), attempts = attempts + 1, last_error = NULL,
+             locked_until = NULL WHERE id = ANY($1)",
+            self.table
+        ))
+        .bind(ids)
+        .execute(self.pool)
+        .await
+        .map_err(db_error)?;
+        Ok(())
+    }
+
+    async fn mark_failed(&self, id: i64, error: &str, unsent: &[i64]) -> Result<(), ProducerError> {
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+        sqlx::query(&format!(
+            "UPDATE {} SET attempts = attempts + 1, last_error = $2, locked_until = NULL
+             WHERE id = $1",
+            self.table
+        ))
+        .bind(id)
+        .bind(error)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+        if !unsent.is_empty() {
+            sqlx::query(&format!(
+                "UPDATE {} SET locked_until = NULL WHERE id = ANY($1)",
+                self.table
+            ))
+            .bind(unsent)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_error)?;
+        }
+        tx.commit().await.map_err(db_error)
+    }
+}
+
+/// 认领并转发一批，遇到失败即停止，保证后续行不越过失败行
+async fn relay_batch<S: OutboxStore>(
+    name: &str,
+    store: &S,
+    producer: &dyn Producer,
+    config: &OutboxRelayConfig,
+) -> Result<usize, ProducerError> {
+    let rows = store.claim(config.batch_size.max(1), config.lease).await?;
+
+    let mut delivered = Vec::with_capacity(rows.len());
+    let mut failure = None;
+    let mut rows = rows.into_iter();
+    for row in rows.by_ref() {
+        let Json(headers) = row.headers;
+        let ctx = mq_headers_to_ctx(&headers);
+        match producer
+            .send(
+                &ctx,
+                &row.topic,
+                row.message_key.as_deref(),
+                row.payload,
+                Some(headers),
+            )
+            .await
+        {
+            Ok(()) => delivered.push(row.id),
+            Err(err) => {
+                failure = Some((row.id, err));
+                break;
+            }
+        }
+    }
+
+    if !delivered.is_empty() {
+        store.mark_delivered(&delivered).await?;
+    }
+    match failure {
+        Some((id, err)) => {
+            let unsent: Vec<i64> = rows.map(|row| row.id).collect();
+            store.mark_failed(id, &err.to_string(), &unsent).await?;
+            tracing::warn!(
+                relay = %name,
+                outbox_id = id,
+                delivered = delivered.len(),
+                error = %err,
+                "Outbox relay failed to forward message"
+            );
+            Err(err)
+        }
+        None => {
+            if !delivered.is_empty() {
+                tracing::debug!(
+                    relay = %name,
+                    delivered = delivered.len(),
+                    "Outbox messages forwarded"
+                );
+            }
+            Ok(delivered.len())
+        }
+    }
+}
+
+async fn wait_for_notification(listener: &mut Option<PgListener>) -> Result<(), sqlx::Error> {
+    match listener {
+        Some(listener) => listener.recv().await.map(|_| ()),
+        None => std::future::pending().await,
+    }
+}
+
+impl Task for OutboxRelay {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn dependencies(&self) -> Vec<String> {
+        self.dependencies.clone()
+    }
+
+    fn run(
+        self: Box<Self>,
+        shutdown_rx: oneshot::Receiver<()>,
+    ) -> Pin<Box<dyn Future<Output = TaskResult> + Send>> {
+        Box::pin(async move {
+            self.run_with_shutdown(shutdown_rx)
+                .await
+                .map_err(Into::into)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mq::memory::InMemoryBroker;
+    use flare_core_base::context::Ctx;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[derive(Default)]
+    struct FakeRow {
+        id: i64,
+        payload: Vec<u8>,
+        attempts: u32,
+        last_error: Option<String>,
+        delivered: bool,
+        leased: bool,
+    }
+
+    /// 内存 outbox 表，租约只有“持有 / 已过期”两种状态
+    #[derive(Default)]
+    struct FakeStore {
+        rows: Mutex<Vec<FakeRow>>,
+    }
+
+    impl FakeStore {
+        fn with_payloads(payloads: &[&str]) -> Self {
+            let rows = payloads
+                .iter()
+                .enumerate()
+                .map(|(idx, payload)| FakeRow {
+                    id: idx as i64 + 1,
+                    payload: payload.as_bytes().to_vec(),
+                    ..FakeRow::default()
+                })
+                .collect();
+            Self {
+                rows: Mutex::new(rows),
+            }
+        }
+
+        fn expire_leases(&self) {
+            for row in self.rows.lock().unwrap().iter_mut() {
+                row.leased = false;
+            }
+        }
+
+        fn row<T>(&self, id: i64, f: impl FnOnce(&FakeRow) -> T) -> T {
+            f(self
+                .rows
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|r| r.id == id)
+                .unwrap())
+        }
+    }
+
+    #[async_trait]
+    impl OutboxStore for FakeStore {
+        async fn claim(
+            &self,
+            limit: usize,
+            _lease: Duration,
+        ) -> Result<Vec<OutboxRow>, ProducerError> {
+            let mut rows = self.rows.lock().unwrap();
+            if rows.iter().any(|r| !r.delivered && r.leased) {
+                return Ok(Vec::new());
+            }
+            Ok(rows
+                .iter_mut()
+                .filter(|r| !r.delivered)
+                .take(limit)
+                .map(|r| {
+                    r.leased = true;
+                    let mut headers = HashMap::new();
+                    headers.insert("x-message-id".to_string(), format!("msg-{}", r.id));
+                    OutboxRow {
+                        id: r.id,
+                        topic: "orders".to_string(),
+                        message_key: None,
+                        payload: r.payload.clone(),
+                        headers: Json(headers),
+                    }
+                })
+                .collect())
+        }
+
+        async fn mark_delivered(&self, ids: &[i64]) -> Result<(), ProducerError> {
+            for row in self.rows.lock().unwrap().iter_mut() {
+                if ids.contains(&row.id) {
+                    row.delivered = true;
+                    row.attempts += 1;
+                    row.last_error = None;
+                    row.leased = false;
+                }
+            }
+            Ok(())
+        }
+
+        async fn mark_failed(
+            &self,
+            id: i64,
+            error: &str,
+            unsent: &[i64],
+        ) -> Result<(), ProducerError> {
+            for row in self.rows.lock().unwrap().iter_mut() {
+                if row.id == id {
+                    row.attempts += 1;
+                    row.last_error = Some(error.to_string());
+                    row.leased = false;
+                } else if unsent.contains(&row.id) {
+                    row.leased = false;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// 开关打开时拒绝载荷为 `bad` 的消息，其余交给 InMemoryBroker
+    struct FlakyProducer {
+        broker: InMemoryBroker,
+        failing: AtomicBool,
+    }
+
+    #[async_trait]
+    impl Producer for FlakyProducer {
+        async fn send(
+            &self,
+            ctx: &Ctx,
+            topic: &str,
+            key: Option<&str>,
+            payload: Vec<u8>,
+            headers: Option<HashMap<String, String>>,
+        ) -> Result<(), ProducerError> {
+            if self.failing.load(Ordering::SeqCst) && payload == b"bad" {
+                return Err(ProducerError::Send("broker unavailable".into()));
+            }
+            self.broker.send(ctx, topic, key, payload, headers).await
+        }
+
+        async fn send_batch(
+            &self,
+            ctx: &Ctx,
+            messages: Vec<crate::mq::ProducerMessage>,
+        ) -> Result<(), ProducerError> {
+            self.broker.send_batch(ctx, messages).await
+        }
+
+        fn name(&self) -> &str {
+            "flaky"
+        }
+    }
+
+    fn payloads(broker: &InMemoryBroker) -> Vec<String> {
+        broker
+            .records("orders")
+            .into_iter()
+            .map(|r| String::from_utf8(r.payload).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn forwards_rows_in_order_and_marks_delivered() {
+        let store = FakeStore::with_payloads(&["a", "b", "c"]);
+        let broker = InMemoryBroker::new();
+        let config = OutboxRelayConfig::new().with_batch_size(2);
+
+        assert_eq!(relay_batch("t", &store, &broker, &config).await.unwrap(), 2);
+        assert_eq!(relay_batch("t", &store, &broker, &config).await.unwrap(), 1);
+        assert_eq!(relay_batch("t", &store, &broker, &config).await.unwrap(), 0);
+
+        assert_eq!(payloads(&broker), ["a", "b", "c"]);
+        let records = broker.records("orders");
+        assert_eq!(records[1].message_id, "msg-2");
+        assert!((1..=3).all(|id| store.row(id, |r| r.delivered && !r.leased)));
+    }
+
+    #[tokio::test]
+    async fn stops_at_failed_row_and_retries_it_first() {
+        let store = FakeStore::with_payloads(&["a", "bad", "c"]);
+        let producer = FlakyProducer {
+            broker: InMemoryBroker::new(),
+            failing: AtomicBool::new(true),
+        };
+        let config = OutboxRelayConfig::new();
+
+        assert!(relay_batch("t", &store, &producer, &config).await.is_err());
+        assert_eq!(payloads(&producer.broker), ["a"]);
+        store.row(2, |r| {
+            assert!(!r.delivered && !r.leased);
+            assert_eq!(r.attempts, 1);
+            assert!(
+                r.last_error
+                    .as_deref()
+                    .unwrap()
+                    .contains("broker unavailable")
+            );
+        });
+        // 失败行之后的行未被转发，租约已释放
+        store.row(3, |r| assert!(!r.delivered && !r.leased && r.attempts == 0));
+
+        producer.failing.store(false, Ordering::SeqCst);
+        assert_eq!(
+            relay_batch("t", &store, &producer, &config).await.unwrap(),
+            2
+        );
+        assert_eq!(payloads(&producer.broker), ["a", "bad", "c"]);
+        store.row(2, |r| assert!(r.delivered && r.last_error.is_none()));
+    }
+
+    #[tokio::test]
+    async fn redelivers_rows_after_lease_expires() {
+        let store = FakeStore::with_payloads(&["a", "b"]);
+        let broker = InMemoryBroker::new();
+        let config = OutboxRelayConfig::new();
+
+        // 另一个 relay 认领后崩溃：租约有效期间不重复转发
+        assert_eq!(store.claim(10, config.lease).await.unwrap().len(), 2);
+        assert_eq!(relay_batch("t", &store, &broker, &config).await.unwrap(), 0);
+        assert!(payloads(&broker).is_empty());
+
+        store.expire_leases();
+        assert_eq!(relay_batch("t", &store, &broker, &config).await.unwrap(), 2);
+        assert_eq!(payloads(&broker), ["a", "b"]);
+    }
+}
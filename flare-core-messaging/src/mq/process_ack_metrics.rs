@@ -1,7 +1,7 @@
 use std::sync::OnceLock;
 use std::time::Duration;
 
-use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, Opts};
+use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry};
 
 static PROCESS_ACK_METRICS: OnceLock<ProcessAckMetrics> = OnceLock::new();
 
@@ -69,6 +69,16 @@ fn metrics() -> &'static ProcessAckMetrics {
     })
 }
 
+/// 把 ack 指标同时注册到 `registry`
+///
+/// ack 指标默认注册在 `prometheus::default_registry()`，与运行时的 `PrometheusCollector::global()`
+/// 共用同一个导出端点；改用自定义 registry 导出时调用此函数。
+pub fn register_process_ack_metrics(registry: &Registry) -> prometheus::Result<()> {
+    let metrics = metrics();
+    registry.register(Box::new(metrics.total.clone()))?;
+    registry.register(Box::new(metrics.duration_seconds.clone()))
+}
+
 pub(crate) fn record_process_ack(
     backend: &'static str,
     operation: &'static str,
@@ -106,4 +116,19 @@ mod tests {
                 >= 0.01
         );
     }
+
+    #[test]
+    fn process_ack_metrics_are_exported_from_custom_registry() {
+        let registry = Registry::new();
+        register_process_ack_metrics(&registry).unwrap();
+
+        record_process_ack("nats", "nack", "success", Duration::from_millis(1));
+
+        assert!(
+            registry
+                .gather()
+                .iter()
+                .any(|family| family.name() == "mq_process_ack_total")
+        );
+    }
 }
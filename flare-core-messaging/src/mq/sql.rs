@@ -0,0 +1,22 @@
+//! Postgres 后端共用的 SQL 辅助函数
+
+/// 表名会拼进 SQL，只允许 `[A-Za-z_][A-Za-z0-9_]*`，可带一级 schema
+pub(crate) fn is_table_name(value: &str) -> bool {
+    let mut parts = value.split('.');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(table), None, None) => is_identifier(table),
+        (Some(schema), Some(table), None) => is_identifier(schema) && is_identifier(table),
+        _ => false,
+    }
+}
+
+pub(crate) fn is_identifier(value: &str) -> bool {
+    let mut chars = value.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// 去掉 schema 前缀，用于派生索引名
+pub(crate) fn unqualified(table: &str) -> &str {
+    table.rsplit('.').next().unwrap_or(table)
+}
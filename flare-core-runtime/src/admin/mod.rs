@@ -0,0 +1,20 @@
+//! 运维端点模块
+//!
+//! 启用 `RuntimeConfig::admin` 时由 `ServiceRuntime` 添加运维任务，默认监听 `127.0.0.1`。
+//! 配置 `AdminConfig::token` 后，除 `/livez`、`/readyz` 外的接口都要求 Bearer 令牌。提供：
+//!
+//! | 方法 | 路径 | 说明 |
+//! |------|------|------|
+//! | GET | `/livez` | 最近一轮健康检查全部通过时返回 200，否则 503 |
+//! | GET | `/readyz` | 所有任务 `Running` 且健康检查通过时返回 200，否则 503 |
+//! | GET | `/tasks` | 任务状态与消费者控制状态（JSON） |
+//! | GET | `/events` | 任务状态事件流（SSE） |
+//! | POST | `/shutdown` | 触发优雅停机 |
+//! | POST | `/consumers/{name}/pause?topic=` | 暂停消费者（省略 `topic` 时暂停全部） |
+//! | POST | `/consumers/{name}/resume?topic=` | 恢复消费者 |
+//! | POST | `/log-level?level=` | 替换日志过滤指令（需 `with_log_level_control`） |
+
+mod server;
+
+pub use server::LogLevelControl;
+pub(crate) use server::{AdminServer, HealthResults, ShutdownTrigger};
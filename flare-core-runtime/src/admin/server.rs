@@ -0,0 +1,642 @@
+//! 运维端点任务
+
+use crate::config::AdminConfig;
+use crate::control::{ConsumerControlStatus, ConsumerControls};
+use crate::health::HealthCheckResult;
+use crate::signal::ChannelSignal;
+use crate::state::{StateEvent, StateTracker};
+use crate::task::{Task, TaskResult, TaskState};
+use crate::utils::http::{self, HttpRequest, HttpResponse};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use tokio::sync::{RwLock, broadcast, oneshot, watch};
+use tracing::{info, warn};
+
+/// 替换日志过滤指令；返回错误时原过滤器保持不变
+///
+/// 使用 `flare-core-infra` 初始化日志时可直接转发给 `flare_core_infra::set_log_filter`。
+pub type LogLevelControl = Arc<dyn Fn(&str) -> Result<(), String> + Send + Sync>;
+
+/// 健康检查监控最近一轮的结果；尚未执行过时为 `None`
+pub(crate) type HealthResults = Arc<RwLock<Option<Vec<HealthCheckResult>>>>;
+
+/// 运维端点触发的停机请求
+///
+/// 发送端由运行时与运维任务共同持有，运维任务异常退出不会被当作停机信号。
+#[derive(Clone)]
+pub(crate) struct ShutdownTrigger(Arc<Mutex<Option<oneshot::Sender<()>>>>);
+
+impl ShutdownTrigger {
+    /// 创建触发器与交给 `CompositeSignal` 的停机信号
+    pub(crate) fn new() -> (Self, ChannelSignal) {
+        let (tx, rx) = oneshot::channel();
+        (
+            Self(Arc::new(Mutex::new(Some(tx)))),
+            ChannelSignal::new(AdminServer::TASK_NAME, rx),
+        )
+    }
+
+    /// 触发停机；已触发过时返回 false
+    fn trigger(&self) -> bool {
+        let tx = self.0.lock().unwrap_or_else(|e| e.into_inner()).take();
+        tx.is_some_and(|tx| tx.send(()).is_ok())
+    }
+}
+
+struct AdminState {
+    tracker: Arc<StateTracker>,
+    health: HealthResults,
+    controls: ConsumerControls,
+    shutdown: ShutdownTrigger,
+    log_level: Option<LogLevelControl>,
+    /// 除探针外的接口要求的访问令牌（只保存 SHA-256 摘要）
+    token_digest: Option<TokenDigest>,
+    /// 运维任务退出时关闭，结束仍在推送的事件流
+    closed: watch::Receiver<()>,
+}
+
+/// 运维端点任务
+pub(crate) struct AdminServer {
+    addr: SocketAddr,
+    state: AdminState,
+    closed_tx: watch::Sender<()>,
+}
+
+impl AdminServer {
+    /// 任务名称
+    pub(crate) const TASK_NAME: &'static str = "admin-server";
+
+    /// 在 `{config.host}:{config.port}` 上提供运维端点
+    pub(crate) fn new(
+        config: &AdminConfig,
+        tracker: Arc<StateTracker>,
+        health: HealthResults,
+        controls: ConsumerControls,
+        shutdown: ShutdownTrigger,
+        log_level: Option<LogLevelControl>,
+    ) -> Self {
+        let (closed_tx, closed) = watch::channel(());
+        Self {
+            addr: SocketAddr::new(config.host, config.port),
+            state: AdminState {
+                tracker,
+                health,
+                controls,
+                shutdown,
+                log_level,
+                token_digest: config.token.as_deref().map(token_digest),
+                closed,
+            },
+            closed_tx,
+        }
+    }
+
+    /// 在已绑定的监听器上提供服务，直到收到 shutdown 信号
+    async fn serve(
+        listener: TcpListener,
+        state: AdminState,
+        closed_tx: watch::Sender<()>,
+        shutdown_rx: oneshot::Receiver<()>,
+    ) -> std::io::Result<()> {
+        let state = Arc::new(state);
+        let result = http::serve(
+            listener,
+            move |request: HttpRequest| {
+                let state = Arc::clone(&state);
+                async move { state.handle(request).await }
+            },
+            shutdown_rx,
+        )
+        .await;
+        drop(closed_tx);
+        result
+    }
+}
+
+impl Task for AdminServer {
+    fn name(&self) -> &str {
+        Self::TASK_NAME
+    }
+
+    fn run(
+        self: Box<Self>,
+        shutdown_rx: oneshot::Receiver<()>,
+    ) -> Pin<Box<dyn Future<Output = TaskResult> + Send>> {
+        Box::pin(async move {
+            let listener = TcpListener::bind(self.addr)
+                .await
+                .map_err(|e| format!("failed to bind admin server on {}: {e}", self.addr))?;
+            info!(addr = %self.addr, "Admin server listening");
+            Self::serve(listener, self.state, self.closed_tx, shutdown_rx).await?;
+            Ok(())
+        })
+    }
+}
+
+/// 路由只接受的方法
+fn route_method(path: &str) -> Option<&'static str> {
+    match path {
+        "/livez" | "/readyz" | "/tasks" | "/events" => Some("GET"),
+        "/shutdown" | "/log-level" => Some("POST"),
+        _ => control_route(path).map(|_| "POST"),
+    }
+}
+
+/// 解析 `/consumers/{name}/pause` 与 `/consumers/{name}/resume`，返回名称与是否暂停
+fn control_route(path: &str) -> Option<(&str, bool)> {
+    let (name, action) = path.strip_prefix("/consumers/")?.rsplit_once('/')?;
+    let pause = match action {
+        "pause" => true,
+        "resume" => false,
+        _ => return None,
+    };
+    (!name.is_empty()).then_some((name, pause))
+}
+
+/// 探针供编排系统调用，不要求令牌
+fn requires_token(path: &str) -> bool {
+    !matches!(path, "/livez" | "/readyz")
+}
+
+type TokenDigest = [u8; 32];
+
+fn token_digest(token: &str) -> TokenDigest {
+    Sha256::digest(token.as_bytes()).into()
+}
+
+/// 比较令牌摘要：摘要定长且逐字节异或累积，耗时与令牌长度和首个不同字节的位置无关
+fn token_matches(expected: &TokenDigest, provided: &str) -> bool {
+    expected
+        .iter()
+        .zip(token_digest(provided).iter())
+        .fold(0, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+fn error(status: u16, message: impl Into<String>) -> HttpResponse {
+    let message: String = message.into();
+    HttpResponse::json(status, json!({ "error": message }).to_string())
+}
+
+fn control_status(status: &ConsumerControlStatus) -> Value {
+    json!({
+        "paused": status.paused,
+        "paused_topics": status.paused_topics,
+        "unhealthy_checks": status.unhealthy_checks,
+        "assigned_partitions": status
+            .assigned_partitions
+            .iter()
+            .map(|(topic, partition)| json!({ "topic": topic, "partition": partition }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn sse_event(event: &StateEvent) -> String {
+    let data = json!({
+        "task": event.task_name,
+        "from": event.old_state.to_string(),
+        "to": event.new_state.to_string(),
+        "error": event.error,
+    });
+    format!("event: state\ndata: {data}\n\n")
+}
+
+impl AdminState {
+    async fn handle(&self, request: HttpRequest) -> HttpResponse {
+        let path = request.path.as_str();
+        let Some(method) = route_method(path) else {
+            return HttpResponse::not_found();
+        };
+        if requires_token(path) && !self.authorized(&request) {
+            return error(401, "missing or invalid bearer token")
+                .with_header("WWW-Authenticate", "Bearer");
+        }
+        if request.method != method {
+            return error(405, format!("{path} only accepts {method}"));
+        }
+
+        match path {
+            "/livez" => self.livez().await,
+            "/readyz" => self.readyz().await,
+            "/tasks" => self.tasks().await,
+            "/events" => self.events(),
+            "/shutdown" => self.shutdown(),
+            "/log-level" => self.log_level(request.query("level")),
+            _ => match control_route(path) {
+                Some((name, pause)) => self.control(name, pause, request.query("topic")),
+                None => HttpResponse::not_found(),
+            },
+        }
+    }
+
+    fn authorized(&self, request: &HttpRequest) -> bool {
+        let Some(expected) = &self.token_digest else {
+            return true;
+        };
+        request
+            .header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|provided| token_matches(expected, provided.trim()))
+    }
+
+    /// 最近一轮健康检查的结果与是否全部通过
+    async fn health(&self) -> (bool, Vec<Value>) {
+        let results = self.health.read().await;
+        let results = results.as_deref().unwrap_or_default();
+        let checks = results
+            .iter()
+            .map(|result| {
+                json!({
+                    "name": result.name,
+                    "healthy": result.healthy,
+                    "error": result.error,
+                })
+            })
+            .collect();
+        (results.iter().all(|result| result.healthy), checks)
+    }
+
+    async fn livez(&self) -> HttpResponse {
+        let (healthy, checks) = self.health().await;
+        let status = if healthy { 200 } else { 503 };
+        HttpResponse::json(
+            status,
+            json!({ "healthy": healthy, "checks": checks }).to_string(),
+        )
+    }
+
+    async fn readyz(&self) -> HttpResponse {
+        let (healthy, _) = self.health().await;
+        let ready = self.tracker.all_ready().await;
+        let mut not_running: Vec<_> = self
+            .tracker
+            .get_all_states()
+            .await
+            .into_iter()
+            .filter(|info| info.state != TaskState::Running)
+            .map(|info| info.name)
+            .collect();
+        not_running.sort();
+        let status = if ready && healthy { 200 } else { 503 };
+        HttpResponse::json(
+            status,
+            json!({ "ready": ready, "healthy": healthy, "not_running": not_running }).to_string(),
+        )
+    }
+
+    async fn tasks(&self) -> HttpResponse {
+        let mut tasks = self.tracker.get_all_states().await;
+        tasks.sort_by(|a, b| a.name.cmp(&b.name));
+        let tasks: Vec<_> = tasks
+            .iter()
+            .map(|info| {
+                json!({
+                    "name": info.name,
+                    "state": info.state.to_string(),
+                    "state_age_secs": info.state_changed_at.elapsed().as_secs_f64(),
+                    "uptime_secs": info.started_at.map(|at| at.elapsed().as_secs_f64()),
+                    "restarts": info.restarts,
+                })
+            })
+            .collect();
+        let mut consumers = self.controls.statuses();
+        consumers.sort_by(|a, b| a.0.cmp(&b.0));
+        let consumers: Vec<_> = consumers
+            .iter()
+            .map(|(name, status)| {
+                let mut value = control_status(status);
+                value["name"] = json!(name);
+                value
+            })
+            .collect();
+        HttpResponse::json(
+            200,
+            json!({ "tasks": tasks, "consumers": consumers }).to_string(),
+        )
+    }
+
+    fn events(&self) -> HttpResponse {
+        let events = self.tracker.subscribe();
+        let closed = self.closed.clone();
+        let stream =
+            futures::stream::unfold((events, closed), |(mut events, mut closed)| async move {
+                let chunk = tokio::select! {
+                    event = events.recv() => match event {
+                        Ok(event) => sse_event(&event),
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            format!("event: lagged\ndata: {{\"skipped\":{skipped}}}\n\n")
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    },
+                    _ = closed.changed() => return None,
+                };
+                Some((chunk, (events, closed)))
+            });
+        HttpResponse::stream("text/event-stream", stream)
+    }
+
+    fn shutdown(&self) -> HttpResponse {
+        let status = if self.shutdown.trigger() {
+            warn!("Graceful shutdown requested via admin endpoint");
+            "requested"
+        } else {
+            "already requested"
+        };
+        HttpResponse::json(200, json!({ "shutdown": status }).to_string())
+    }
+
+    fn log_level(&self, level: Option<&str>) -> HttpResponse {
+        let Some(control) = &self.log_level else {
+            return error(501, "log level control is not configured");
+        };
+        let Some(level) = level.filter(|level| !level.is_empty()) else {
+            return error(400, "missing `level` query parameter");
+        };
+        match control(level) {
+            Ok(()) => {
+                info!(level = %level, "Log level changed via admin endpoint");
+                HttpResponse::json(200, json!({ "level": level }).to_string())
+            }
+            Err(e) => error(400, e),
+        }
+    }
+
+    fn control(&self, name: &str, pause: bool, topic: Option<&str>) -> HttpResponse {
+        let Some(control) = self.controls.get(name) else {
+            return error(404, format!("no consumer control named `{name}`"));
+        };
+        if pause {
+            control.pause(topic);
+        } else {
+            control.resume(topic);
+        }
+        info!(consumer = %name, topic = ?topic, pause, "Consumer control changed via admin endpoint");
+        let mut value = control_status(&control.status());
+        value["name"] = json!(name);
+        HttpResponse::json(200, value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control::ConsumerControl;
+    use crate::signal::ShutdownSignal;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    #[derive(Default)]
+    struct FlagControl {
+        paused: AtomicBool,
+    }
+
+    impl ConsumerControl for FlagControl {
+        fn pause(&self, _topic: Option<&str>) {
+            self.paused.store(true, Ordering::SeqCst);
+        }
+
+        fn resume(&self, _topic: Option<&str>) {
+            self.paused.store(false, Ordering::SeqCst);
+        }
+
+        fn status(&self) -> ConsumerControlStatus {
+            ConsumerControlStatus {
+                paused: self.paused.load(Ordering::SeqCst),
+                ..Default::default()
+            }
+        }
+    }
+
+    async fn request(addr: SocketAddr, method: &str, target: &str) -> String {
+        request_with(addr, method, target, "").await
+    }
+
+    async fn request_with(addr: SocketAddr, method: &str, target: &str, headers: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                format!("{method} {target} HTTP/1.1\r\nHost: test\r\n{headers}\r\n").as_bytes(),
+            )
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn serves_probes_tasks_and_controls() {
+        let tracker = Arc::new(StateTracker::new());
+        tracker.register_task("orders", TaskState::Pending).await;
+        let health: HealthResults = Arc::default();
+        let controls = ConsumerControls::new();
+        let consumer = Arc::new(FlagControl::default());
+        controls.register("orders", consumer.clone());
+        let (trigger, mut signal) = ShutdownTrigger::new();
+        let levels = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&levels);
+        let log_level: LogLevelControl = Arc::new(move |level: &str| {
+            recorded.lock().unwrap().push(level.to_string());
+            Ok(())
+        });
+        let server = AdminServer::new(
+            &AdminConfig::new(),
+            Arc::clone(&tracker),
+            Arc::clone(&health),
+            controls,
+            trigger,
+            Some(log_level),
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let serving = tokio::spawn(AdminServer::serve(
+            listener,
+            server.state,
+            server.closed_tx,
+            shutdown_rx,
+        ));
+
+        assert!(
+            request(addr, "GET", "/readyz")
+                .await
+                .starts_with("HTTP/1.1 503")
+        );
+        tracker.update_state("orders", TaskState::Starting).await;
+        tracker.update_state("orders", TaskState::Running).await;
+        assert!(
+            request(addr, "GET", "/readyz")
+                .await
+                .starts_with("HTTP/1.1 200")
+        );
+
+        *health.write().await = Some(vec![HealthCheckResult {
+            name: "db".to_string(),
+            healthy: false,
+            error: Some("connection refused".to_string()),
+        }]);
+        let livez = request(addr, "GET", "/livez").await;
+        assert!(livez.starts_with("HTTP/1.1 503"));
+        assert!(livez.contains("connection refused"));
+        assert!(
+            request(addr, "GET", "/readyz")
+                .await
+                .starts_with("HTTP/1.1 503")
+        );
+
+        let tasks = request(addr, "GET", "/tasks").await;
+        assert!(tasks.contains(r#""name":"orders""#));
+        assert!(tasks.contains(r#""state":"Running""#));
+
+        let paused = request(addr, "POST", "/consumers/orders/pause").await;
+        assert!(paused.starts_with("HTTP/1.1 200"));
+        assert!(consumer.paused.load(Ordering::SeqCst));
+        request(addr, "POST", "/consumers/orders/resume").await;
+        assert!(!consumer.paused.load(Ordering::SeqCst));
+        assert!(
+            request(addr, "POST", "/consumers/missing/pause")
+                .await
+                .starts_with("HTTP/1.1 404")
+        );
+        assert!(
+            request(addr, "GET", "/consumers/orders/pause")
+                .await
+                .starts_with("HTTP/1.1 405")
+        );
+
+        let changed = request(addr, "POST", "/log-level?level=info%2Csqlx%3Dwarn").await;
+        assert!(changed.starts_with("HTTP/1.1 200"));
+        assert_eq!(*levels.lock().unwrap(), vec!["info,sqlx=warn".to_string()]);
+
+        assert!(
+            request(addr, "POST", "/shutdown")
+                .await
+                .contains(r#""requested""#)
+        );
+        tokio::time::timeout(std::time::Duration::from_secs(1), signal.wait())
+            .await
+            .expect("shutdown signal is triggered");
+        assert!(
+            request(addr, "POST", "/shutdown")
+                .await
+                .contains("already requested")
+        );
+
+        shutdown_tx.send(()).unwrap();
+        serving.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn requires_bearer_token_except_for_probes() {
+        let (trigger, _signal) = ShutdownTrigger::new();
+        let server = AdminServer::new(
+            &AdminConfig::new().with_token("s3cret"),
+            Arc::new(StateTracker::new()),
+            Arc::default(),
+            ConsumerControls::new(),
+            trigger,
+            None,
+        );
+        assert_eq!(server.addr, SocketAddr::from(([127, 0, 0, 1], 9091)));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let serving = tokio::spawn(AdminServer::serve(
+            listener,
+            server.state,
+            server.closed_tx,
+            shutdown_rx,
+        ));
+        let with_token = |token: &str| format!("Authorization: Bearer {token}\r\n");
+
+        assert!(
+            request(addr, "GET", "/livez")
+                .await
+                .starts_with("HTTP/1.1 200")
+        );
+        let denied = request(addr, "GET", "/tasks").await;
+        assert!(denied.starts_with("HTTP/1.1 401"));
+        assert!(denied.contains("www-authenticate: Bearer\r\n"));
+        assert!(
+            request_with(addr, "POST", "/shutdown", &with_token("wrong"))
+                .await
+                .starts_with("HTTP/1.1 401")
+        );
+        assert!(
+            request_with(addr, "GET", "/tasks", &with_token("s3cret"))
+                .await
+                .starts_with("HTTP/1.1 200")
+        );
+
+        shutdown_tx.send(()).unwrap();
+        serving.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn streams_state_events_until_server_stops() {
+        let tracker = Arc::new(StateTracker::new());
+        tracker.register_task("grpc", TaskState::Pending).await;
+        let (trigger, _signal) = ShutdownTrigger::new();
+        let server = AdminServer::new(
+            &AdminConfig::new(),
+            Arc::clone(&tracker),
+            Arc::default(),
+            ConsumerControls::new(),
+            trigger,
+            None,
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let serving = tokio::spawn(AdminServer::serve(
+            listener,
+            server.state,
+            server.closed_tx,
+            shutdown_rx,
+        ));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /events HTTP/1.1\r\nHost: test\r\n\r\n")
+            .await
+            .unwrap();
+        let mut buf = vec![0u8; 1024];
+        let mut received = String::new();
+        while !received.contains("\r\n\r\n") {
+            let read = stream.read(&mut buf).await.unwrap();
+            received.push_str(&String::from_utf8_lossy(&buf[..read]));
+        }
+        assert!(received.contains("content-type: text/event-stream"));
+
+        tracker.update_state("grpc", TaskState::Starting).await;
+        // 事件以分块编码写出，每块以 CRLF 结尾
+        while !received.ends_with("\n\n\r\n") {
+            let read = stream.read(&mut buf).await.unwrap();
+            received.push_str(&String::from_utf8_lossy(&buf[..read]));
+        }
+        assert!(received.contains("event: state\ndata: {"));
+        assert!(received.contains(r#""task":"grpc""#));
+        assert!(received.contains(r#""to":"Starting""#));
+
+        shutdown_tx.send(()).unwrap();
+        serving.await.unwrap().unwrap();
+        let mut rest = String::new();
+        tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            stream.read_to_string(&mut rest),
+        )
+        .await
+        .expect("event stream ends when the admin server stops")
+        .unwrap();
+        assert!(rest.ends_with("0\r\n\r\n"));
+    }
+}
@@ -2,6 +2,8 @@
 //!
 //! 提供运行时配置，包括生命周期、任务启动、健康检查、指标等配置
 
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr};
 use std::time::Duration;
 
 /// 通用轮询型后台任务参数（与具体 Broker 无关）
@@ -163,10 +165,17 @@ impl HealthCheckConfig {
 }
 
 /// 指标配置
+///
+/// 默认关闭，需显式启用。启用时 `ServiceRuntime` 添加导出任务
+/// [MetricsExporter](crate::metrics::MetricsExporter)，导出 `with_metrics_collector` 设置的收集器；
+/// 未设置且开启 `prometheus` feature 时使用 `PrometheusCollector::global()`。
+/// `with_metrics_collector` 设置的收集器在禁用时仍会记录。
 #[derive(Debug, Clone)]
 pub struct MetricsConfig {
-    /// 是否启用指标收集（默认 true）
+    /// 是否启用指标收集与导出（默认 false）
     pub enabled: bool,
+    /// 指标监听地址（默认 `0.0.0.0`）
+    pub host: IpAddr,
     /// 指标暴露端口（默认 9090）
     pub port: u16,
     /// 指标暴露路径（默认 "/metrics"）
@@ -176,7 +185,8 @@ pub struct MetricsConfig {
 impl Default for MetricsConfig {
     fn default() -> Self {
         Self {
-            enabled: true,
+            enabled: false,
+            host: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
             port: 9090,
             path: "/metrics".to_string(),
         }
@@ -195,6 +205,12 @@ impl MetricsConfig {
         self
     }
 
+    /// 设置监听地址
+    pub fn with_host(mut self, host: IpAddr) -> Self {
+        self.host = host;
+        self
+    }
+
     /// 设置暴露端口
     pub fn with_port(mut self, port: u16) -> Self {
         self.port = port;
@@ -208,10 +224,81 @@ impl MetricsConfig {
     }
 }
 
+/// 运维端点配置
+///
+/// 启用时 `ServiceRuntime` 添加运维任务 `admin-server`，提供探针、任务状态、
+/// 状态事件流以及停机、暂停/恢复消费者、调整日志级别等控制接口。
+/// 默认只监听 `127.0.0.1`；设置 `token` 后除 `/livez`、`/readyz` 外的接口都要求
+/// `Authorization: Bearer <token>`。监听非回环地址时应同时设置 `token`。
+#[derive(Clone)]
+pub struct AdminConfig {
+    /// 是否启用运维端点（默认 false）
+    pub enabled: bool,
+    /// 运维端点监听地址（默认 `127.0.0.1`）
+    pub host: IpAddr,
+    /// 运维端点端口（默认 9091）
+    pub port: u16,
+    /// 访问令牌（默认不鉴权）
+    pub token: Option<String>,
+}
+
+impl fmt::Debug for AdminConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AdminConfig")
+            .field("enabled", &self.enabled)
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("token", &self.token.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+impl Default for AdminConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 9091,
+            token: None,
+        }
+    }
+}
+
+impl AdminConfig {
+    /// 创建默认配置
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 启用/禁用运维端点
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// 设置运维端点监听地址
+    pub fn with_host(mut self, host: IpAddr) -> Self {
+        self.host = host;
+        self
+    }
+
+    /// 设置运维端点端口
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// 设置访问令牌，请求需携带 `Authorization: Bearer <token>`
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+}
+
 /// 微服务运行时配置
 #[derive(Debug, Clone)]
 pub struct RuntimeConfig {
-    /// 关闭超时时间（默认 5 秒）
+    /// 关闭超时时间（默认 5 秒）；未设置 `Task::drain_timeout` 的任务按此等待退出
     pub shutdown_timeout: Duration,
     /// 任务启动配置
     pub task_startup: TaskStartupConfig,
@@ -219,6 +306,8 @@ pub struct RuntimeConfig {
     pub health_check: HealthCheckConfig,
     /// 指标配置
     pub metrics: MetricsConfig,
+    /// 运维端点配置
+    pub admin: AdminConfig,
     /// 默认轮询工作线程参数
     pub default_poll_worker: PollWorkerConfig,
 }
@@ -230,6 +319,7 @@ impl Default for RuntimeConfig {
             task_startup: TaskStartupConfig::default(),
             health_check: HealthCheckConfig::default(),
             metrics: MetricsConfig::default(),
+            admin: AdminConfig::default(),
             default_poll_worker: PollWorkerConfig::default(),
         }
     }
@@ -265,6 +355,12 @@ impl RuntimeConfig {
         self
     }
 
+    /// 设置运维端点配置
+    pub fn with_admin(mut self, config: AdminConfig) -> Self {
+        self.admin = config;
+        self
+    }
+
     /// 设置默认轮询工作线程参数
     pub fn with_default_poll_worker(mut self, config: PollWorkerConfig) -> Self {
         self.default_poll_worker = config;
@@ -0,0 +1,9 @@
+//! 消费者控制模块
+//!
+//! 提供暂停/恢复消费者的抽象与按名称索引的句柄集合，供运维接口通过 `ServiceRuntime` 访问。
+
+mod registry;
+mod r#trait;
+
+pub use registry::ConsumerControls;
+pub use r#trait::{ConsumerControl, ConsumerControlStatus};
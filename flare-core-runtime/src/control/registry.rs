@@ -0,0 +1,145 @@
+//! 消费者控制句柄集合
+
+use super::r#trait::{ConsumerControl, ConsumerControlStatus};
+use std::collections::BTreeMap;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// 按名称索引的 [ConsumerControl] 集合
+///
+/// 克隆后共享同一份注册表，可在 `ServiceRuntime` 启动前交给运维接口。
+#[derive(Clone, Default)]
+pub struct ConsumerControls {
+    controls: Arc<RwLock<BTreeMap<String, Arc<dyn ConsumerControl>>>>,
+}
+
+impl ConsumerControls {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 注册句柄；同名时覆盖
+    pub fn register(&self, name: impl Into<String>, control: Arc<dyn ConsumerControl>) {
+        self.controls
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(name.into(), control);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn ConsumerControl>> {
+        self.controls
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(name)
+            .cloned()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.controls
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    /// 所有消费者的控制状态，按名称排序
+    pub fn statuses(&self) -> Vec<(String, ConsumerControlStatus)> {
+        self.controls
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|(name, control)| (name.clone(), control.status()))
+            .collect()
+    }
+
+    /// 暂停所有消费者
+    pub fn pause_all(&self) {
+        for control in self.snapshot() {
+            control.pause(None);
+        }
+    }
+
+    /// 恢复所有消费者
+    pub fn resume_all(&self) {
+        for control in self.snapshot() {
+            control.resume(None);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.controls
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn snapshot(&self) -> Vec<Arc<dyn ConsumerControl>> {
+        self.controls
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .cloned()
+            .collect()
+    }
+}
+
+impl std::fmt::Debug for ConsumerControls {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConsumerControls")
+            .field("names", &self.names())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct FlagControl {
+        paused: Mutex<bool>,
+    }
+
+    impl ConsumerControl for FlagControl {
+        fn pause(&self, _topic: Option<&str>) {
+            *self.paused.lock().unwrap() = true;
+        }
+
+        fn resume(&self, _topic: Option<&str>) {
+            *self.paused.lock().unwrap() = false;
+        }
+
+        fn status(&self) -> ConsumerControlStatus {
+            ConsumerControlStatus {
+                paused: *self.paused.lock().unwrap(),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[test]
+    fn shared_registry_pauses_and_resumes_every_consumer() {
+        let controls = ConsumerControls::new();
+        let admin_view = controls.clone();
+        controls.register("orders", Arc::new(FlagControl::default()));
+        controls.register("payments", Arc::new(FlagControl::default()));
+
+        admin_view.pause_all();
+        assert_eq!(admin_view.names(), vec!["orders", "payments"]);
+        assert!(
+            controls
+                .statuses()
+                .iter()
+                .all(|(_, status)| status.is_suspended())
+        );
+
+        admin_view.get("orders").unwrap().resume(None);
+        assert!(!controls.get("orders").unwrap().status().paused);
+        assert!(controls.get("payments").unwrap().status().paused);
+    }
+}
@@ -0,0 +1,37 @@
+//! ConsumerControl trait 定义
+//!
+//! 运行中消费者的控制抽象，具体实现在 MQ 适配层（如 `flare-core-messaging` 的 `ConsumerHandle`）。
+
+/// 消费者当前的控制状态
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConsumerControlStatus {
+    /// 是否手动暂停了全部 topic
+    pub paused: bool,
+    /// 手动暂停的 topic
+    pub paused_topics: Vec<String>,
+    /// 未通过的健康检查；非空时消费者自动暂停
+    pub unhealthy_checks: Vec<String>,
+    /// 当前分配到的 `(topic, partition)`；不支持分区分配的后端为空
+    pub assigned_partitions: Vec<(String, i32)>,
+}
+
+impl ConsumerControlStatus {
+    /// 是否整体停止拉取（手动暂停或健康检查失败）
+    pub fn is_suspended(&self) -> bool {
+        self.paused || !self.unhealthy_checks.is_empty()
+    }
+}
+
+/// 运行中消费者的控制句柄
+///
+/// 暂停只影响新消息的拉取，已在处理中的消息继续完成。
+pub trait ConsumerControl: Send + Sync {
+    /// 暂停拉取；`topic` 为 `None` 时暂停全部 topic
+    fn pause(&self, topic: Option<&str>);
+
+    /// 恢复拉取；`topic` 为 `None` 时解除全部手动暂停
+    fn resume(&self, topic: Option<&str>);
+
+    /// 获取当前控制状态
+    fn status(&self) -> ConsumerControlStatus;
+}
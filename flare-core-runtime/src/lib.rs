@@ -11,7 +11,13 @@
 //! - Graceful shutdown with multiple signal sources and dependency ordering.
 //! - Service orchestration with task dependencies, health checks, and state
 //!   tracking.
+//! - Supervised tasks with restart policies, exponential backoff, and escalation
+//!   to shutdown when the restart budget is used up.
 //! - Extensible plugin hooks, middleware chains, and custom adapters.
+//! - Built-in `/metrics` exporter, with a Prometheus collector behind the
+//!   `prometheus` feature.
+//! - Optional admin endpoint with liveness/readiness probes, task states, an
+//!   SSE state event stream, and shutdown, pause/resume and log level controls.
 //!
 //! # Architecture
 //!
@@ -62,7 +68,9 @@
 //! ```
 
 // Module declarations.
+pub mod admin;
 pub mod config;
+pub mod control;
 pub mod error;
 pub mod health;
 pub mod metrics;
@@ -76,12 +84,16 @@ pub mod task;
 pub mod utils;
 
 // Re-exports
+pub use admin::LogLevelControl;
 pub use config::RuntimeConfig;
+pub use control::{ConsumerControl, ConsumerControlStatus, ConsumerControls};
 pub use error::{
     HealthError, MetricsError, MiddlewareError, PluginError, RegistryError, RuntimeError,
 };
 pub use health::{HealthCheck, HealthCheckResult, HealthChecker};
-pub use metrics::MetricsCollector;
+#[cfg(feature = "prometheus")]
+pub use metrics::PrometheusCollector;
+pub use metrics::{MetricsCollector, MetricsExporter};
 pub use middleware::{Middleware, MiddlewareChain};
 pub use plugin::{Plugin, PluginContext, PluginManager};
 pub use registry::{ServiceInfo, ServiceRegistry};
@@ -90,5 +102,8 @@ pub use signal::{
     ChannelSignal, CompositeSignal, CtrlCSignal, ShutdownSignal, UnixSignal, UnixSignalKind,
 };
 pub use state::{RuntimeEvent, StateEvent, StateTracker, TaskStateInfo};
-pub use task::{SpawnTask, Task, TaskManager, TaskResult, TaskState};
+pub use task::{
+    RestartPolicy, RestartStrategy, SpawnTask, SupervisedTask, Task, TaskManager, TaskResult,
+    TaskState,
+};
 pub use utils::topological_sort;
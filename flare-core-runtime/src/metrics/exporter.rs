@@ -0,0 +1,180 @@
+//! 指标导出任务
+//!
+//! 以 Prometheus 文本格式在 `MetricsConfig::host` / `port` / `path` 上暴露
+//! [MetricsCollector::export] 的结果。`ServiceRuntime` 在启用指标（默认关闭）且存在收集器时自动添加该任务。
+
+use super::MetricsCollector;
+use crate::config::MetricsConfig;
+use crate::task::{Task, TaskResult};
+use crate::utils::http::{self, HttpRequest, HttpResponse};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tracing::{info, warn};
+
+/// Prometheus 文本格式的 Content-Type
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// 指标导出任务
+///
+/// # 示例
+///
+/// ```rust,no_run
+/// use flare_core_runtime::config::MetricsConfig;
+/// use flare_core_runtime::metrics::MetricsExporter;
+/// # use flare_core_runtime::metrics::MetricsCollector;
+/// # use std::sync::Arc;
+/// # fn collector() -> Arc<dyn MetricsCollector> { unimplemented!() }
+///
+/// let exporter = MetricsExporter::new(&MetricsConfig::new().with_port(9464), collector());
+/// ```
+pub struct MetricsExporter {
+    addr: SocketAddr,
+    path: String,
+    collector: Arc<dyn MetricsCollector>,
+}
+
+impl MetricsExporter {
+    /// 任务名称
+    pub const TASK_NAME: &'static str = "metrics-exporter";
+
+    /// 在 `{config.host}:{config.port}{config.path}` 上导出
+    pub fn new(config: &MetricsConfig, collector: Arc<dyn MetricsCollector>) -> Self {
+        Self {
+            addr: SocketAddr::new(config.host, config.port),
+            path: config.path.clone(),
+            collector,
+        }
+    }
+
+    /// 设置监听地址（默认取自 `MetricsConfig`）
+    pub fn with_addr(mut self, addr: SocketAddr) -> Self {
+        self.addr = addr;
+        self
+    }
+
+    /// 在已绑定的监听器上导出，直到收到 shutdown 信号
+    async fn serve(
+        listener: TcpListener,
+        path: String,
+        collector: Arc<dyn MetricsCollector>,
+        shutdown_rx: oneshot::Receiver<()>,
+    ) -> std::io::Result<()> {
+        let path = Arc::new(path);
+        http::serve(
+            listener,
+            move |request: HttpRequest| {
+                let path = Arc::clone(&path);
+                let collector = Arc::clone(&collector);
+                async move {
+                    if request.path != *path {
+                        return HttpResponse::not_found();
+                    }
+                    if request.method != "GET" {
+                        return HttpResponse::text(405, "method not allowed\n");
+                    }
+                    match collector.export().await {
+                        Ok(body) => HttpResponse::new(200, CONTENT_TYPE, body),
+                        Err(e) => {
+                            warn!(error = %e, "Failed to export metrics");
+                            HttpResponse::text(500, format!("{e}\n"))
+                        }
+                    }
+                }
+            },
+            shutdown_rx,
+        )
+        .await
+    }
+}
+
+impl Task for MetricsExporter {
+    fn name(&self) -> &str {
+        Self::TASK_NAME
+    }
+
+    fn run(
+        self: Box<Self>,
+        shutdown_rx: oneshot::Receiver<()>,
+    ) -> Pin<Box<dyn Future<Output = TaskResult> + Send>> {
+        Box::pin(async move {
+            let listener = TcpListener::bind(self.addr)
+                .await
+                .map_err(|e| format!("failed to bind metrics exporter on {}: {e}", self.addr))?;
+            info!(addr = %self.addr, path = %self.path, "Metrics exporter listening");
+            Self::serve(listener, self.path, self.collector, shutdown_rx).await?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::MetricsError;
+    use crate::task::TaskState;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    struct StaticCollector;
+
+    #[async_trait::async_trait]
+    impl MetricsCollector for StaticCollector {
+        async fn record_task_startup(&self, _: &str, _: Duration) -> Result<(), MetricsError> {
+            Ok(())
+        }
+
+        async fn record_task_shutdown(&self, _: &str, _: Duration) -> Result<(), MetricsError> {
+            Ok(())
+        }
+
+        async fn record_task_state(&self, _: &str, _: TaskState) -> Result<(), MetricsError> {
+            Ok(())
+        }
+
+        async fn record_health_check(&self, _: &str, _: bool) -> Result<(), MetricsError> {
+            Ok(())
+        }
+
+        async fn export(&self) -> Result<String, MetricsError> {
+            Ok("runtime_up 1\n".to_string())
+        }
+    }
+
+    async fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(format!("GET {path} HTTP/1.1\r\nHost: test\r\n\r\n").as_bytes())
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn serves_collector_export_on_configured_path() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let server = tokio::spawn(MetricsExporter::serve(
+            listener,
+            "/metrics".to_string(),
+            Arc::new(StaticCollector),
+            shutdown_rx,
+        ));
+
+        let metrics = get(addr, "/metrics").await;
+        assert!(metrics.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(metrics.contains(CONTENT_TYPE));
+        assert!(metrics.ends_with("runtime_up 1\n"));
+        assert!(get(addr, "/other").await.starts_with("HTTP/1.1 404"));
+
+        shutdown_tx.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+}
@@ -1,7 +1,13 @@
 //! 指标收集模块
 //!
-//! 提供指标收集抽象和实现
+//! 提供指标收集抽象、导出任务，以及 `prometheus` feature 下的 Prometheus 实现
 
+mod exporter;
+#[cfg(feature = "prometheus")]
+mod prometheus;
 mod r#trait;
 
+pub use exporter::MetricsExporter;
+#[cfg(feature = "prometheus")]
+pub use prometheus::PrometheusCollector;
 pub use r#trait::MetricsCollector;
@@ -0,0 +1,292 @@
+//! Prometheus 指标收集器
+//!
+//! | 指标 | 类型 | 标签 |
+//! |------|------|------|
+//! | `runtime_task_state` | gauge | task, state |
+//! | `runtime_task_state_changes_total` | counter | task, state |
+//! | `runtime_task_startup_duration_seconds` | histogram | task |
+//! | `runtime_startup_duration_seconds` | gauge | - |
+//! | `runtime_task_shutdown_duration_seconds` | histogram | task |
+//! | `runtime_health_checks_total` | counter | check, outcome |
+//! | `runtime_health_check_up` | gauge | check |
+//!
+//! `runtime_task_state` 对任务当前状态取 1，其他状态取 0。
+//! [PrometheusCollector::global] 注册到 `prometheus::default_registry()`，
+//! MQ 的 ack 指标与 Kafka offset 指标也注册在这里，导出端点一次即可导出全部指标。
+
+use super::MetricsCollector;
+use crate::error::MetricsError;
+use crate::task::TaskState;
+use async_trait::async_trait;
+use prometheus::{
+    Encoder, Gauge, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+static GLOBAL_COLLECTOR: OnceLock<Arc<PrometheusCollector>> = OnceLock::new();
+
+const TASK_STATES: [TaskState; 8] = [
+    TaskState::Pending,
+    TaskState::Starting,
+    TaskState::Running,
+    TaskState::Stopping,
+    TaskState::Stopped,
+    TaskState::Failed,
+    TaskState::Restarting,
+    TaskState::Aborted,
+];
+
+/// 基于 Prometheus 的 [MetricsCollector]
+#[derive(Clone)]
+pub struct PrometheusCollector {
+    registry: Registry,
+    task_state: IntGaugeVec,
+    task_state_changes_total: IntCounterVec,
+    task_startup_duration_seconds: HistogramVec,
+    startup_duration_seconds: Gauge,
+    task_shutdown_duration_seconds: HistogramVec,
+    health_checks_total: IntCounterVec,
+    health_check_up: IntGaugeVec,
+}
+
+impl PrometheusCollector {
+    /// 创建并注册到 `registry`；同一 registry 只能注册一次
+    pub fn new(registry: Registry) -> prometheus::Result<Self> {
+        let collector = Self::unregistered(registry);
+        let registry = &collector.registry;
+        registry.register(Box::new(collector.task_state.clone()))?;
+        registry.register(Box::new(collector.task_state_changes_total.clone()))?;
+        registry.register(Box::new(collector.task_startup_duration_seconds.clone()))?;
+        registry.register(Box::new(collector.startup_duration_seconds.clone()))?;
+        registry.register(Box::new(collector.task_shutdown_duration_seconds.clone()))?;
+        registry.register(Box::new(collector.health_checks_total.clone()))?;
+        registry.register(Box::new(collector.health_check_up.clone()))?;
+        Ok(collector)
+    }
+
+    /// 进程内共享的收集器，注册到 `prometheus::default_registry()`
+    pub fn global() -> Arc<Self> {
+        GLOBAL_COLLECTOR
+            .get_or_init(|| {
+                Arc::new(
+                    Self::new(prometheus::default_registry().clone())
+                        .expect("runtime metrics are registered once"),
+                )
+            })
+            .clone()
+    }
+
+    /// 导出使用的 registry
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    fn unregistered(registry: Registry) -> Self {
+        let task_state = IntGaugeVec::new(
+            Opts::new(
+                "runtime_task_state",
+                "Current runtime task state, 1 for the active state and 0 otherwise.",
+            ),
+            &["task", "state"],
+        )
+        .expect("task state metric is valid");
+        let task_state_changes_total = IntCounterVec::new(
+            Opts::new(
+                "runtime_task_state_changes_total",
+                "Runtime task state transitions by task and target state.",
+            ),
+            &["task", "state"],
+        )
+        .expect("task state changes metric is valid");
+        let task_startup_duration_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "runtime_task_startup_duration_seconds",
+                "Time from task manager start until the task first entered Running, by task.",
+            )
+            .buckets(vec![
+                0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0,
+            ]),
+            &["task"],
+        )
+        .expect("task startup duration metric is valid");
+        let startup_duration_seconds = Gauge::new(
+            "runtime_startup_duration_seconds",
+            "Time from task manager start until all tasks passed the ready check.",
+        )
+        .expect("runtime startup duration metric is valid");
+        let task_shutdown_duration_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "runtime_task_shutdown_duration_seconds",
+                "Time from shutdown signal until the task exited or was aborted, by task.",
+            )
+            .buckets(vec![
+                0.001, 0.01, 0.05, 0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+            ]),
+            &["task"],
+        )
+        .expect("task shutdown duration metric is valid");
+        let health_checks_total = IntCounterVec::new(
+            Opts::new(
+                "runtime_health_checks_total",
+                "Runtime health check results by check and outcome.",
+            ),
+            &["check", "outcome"],
+        )
+        .expect("health checks metric is valid");
+        let health_check_up = IntGaugeVec::new(
+            Opts::new(
+                "runtime_health_check_up",
+                "Whether the last run of a runtime health check succeeded.",
+            ),
+            &["check"],
+        )
+        .expect("health check up metric is valid");
+
+        Self {
+            registry,
+            task_state,
+            task_state_changes_total,
+            task_startup_duration_seconds,
+            startup_duration_seconds,
+            task_shutdown_duration_seconds,
+            health_checks_total,
+            health_check_up,
+        }
+    }
+}
+
+fn state_label(state: TaskState) -> &'static str {
+    match state {
+        TaskState::Pending => "pending",
+        TaskState::Starting => "starting",
+        TaskState::Running => "running",
+        TaskState::Stopping => "stopping",
+        TaskState::Stopped => "stopped",
+        TaskState::Failed => "failed",
+        TaskState::Restarting => "restarting",
+        TaskState::Aborted => "aborted",
+    }
+}
+
+#[async_trait]
+impl MetricsCollector for PrometheusCollector {
+    async fn record_task_startup(
+        &self,
+        name: &str,
+        duration: Duration,
+    ) -> Result<(), MetricsError> {
+        self.task_startup_duration_seconds
+            .with_label_values(&[name])
+            .observe(duration.as_secs_f64());
+        Ok(())
+    }
+
+    async fn record_task_shutdown(
+        &self,
+        name: &str,
+        duration: Duration,
+    ) -> Result<(), MetricsError> {
+        self.task_shutdown_duration_seconds
+            .with_label_values(&[name])
+            .observe(duration.as_secs_f64());
+        Ok(())
+    }
+
+    async fn record_task_state(&self, name: &str, state: TaskState) -> Result<(), MetricsError> {
+        for candidate in TASK_STATES {
+            self.task_state
+                .with_label_values(&[name, state_label(candidate)])
+                .set(i64::from(candidate == state));
+        }
+        self.task_state_changes_total
+            .with_label_values(&[name, state_label(state)])
+            .inc();
+        Ok(())
+    }
+
+    async fn record_runtime_startup(&self, duration: Duration) -> Result<(), MetricsError> {
+        self.startup_duration_seconds.set(duration.as_secs_f64());
+        Ok(())
+    }
+
+    async fn record_health_check(&self, name: &str, success: bool) -> Result<(), MetricsError> {
+        let outcome = if success { "success" } else { "failure" };
+        self.health_checks_total
+            .with_label_values(&[name, outcome])
+            .inc();
+        self.health_check_up
+            .with_label_values(&[name])
+            .set(i64::from(success));
+        Ok(())
+    }
+
+    async fn export(&self) -> Result<String, MetricsError> {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .map_err(|e| MetricsError::ExportFailed(e.to_string()))?;
+        String::from_utf8(buffer).map_err(|e| MetricsError::ExportFailed(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn records_task_states_latencies_and_health_results() {
+        let collector = PrometheusCollector::new(Registry::new()).unwrap();
+
+        collector
+            .record_task_state("grpc", TaskState::Starting)
+            .await
+            .unwrap();
+        collector
+            .record_task_state("grpc", TaskState::Running)
+            .await
+            .unwrap();
+        collector
+            .record_task_startup("grpc", Duration::from_millis(20))
+            .await
+            .unwrap();
+        collector
+            .record_task_shutdown("grpc", Duration::from_millis(300))
+            .await
+            .unwrap();
+        collector
+            .record_runtime_startup(Duration::from_millis(120))
+            .await
+            .unwrap();
+        collector.record_health_check("db", false).await.unwrap();
+
+        let state = |state: &str| {
+            collector
+                .task_state
+                .with_label_values(&["grpc", state])
+                .get()
+        };
+        assert_eq!(state("running"), 1);
+        assert_eq!(state("starting"), 0);
+        assert_eq!(
+            collector
+                .task_startup_duration_seconds
+                .with_label_values(&["grpc"])
+                .get_sample_count(),
+            1
+        );
+        assert_eq!(collector.startup_duration_seconds.get(), 0.12);
+        assert_eq!(
+            collector.health_check_up.with_label_values(&["db"]).get(),
+            0
+        );
+
+        let exported = collector.export().await.unwrap();
+        assert!(exported.contains(r#"runtime_task_state{state="running",task="grpc"} 1"#));
+        assert!(
+            exported.contains(r#"runtime_health_checks_total{check="db",outcome="failure"} 1"#)
+        );
+        assert!(exported.contains("runtime_task_shutdown_duration_seconds_count{task=\"grpc\"} 1"));
+    }
+}
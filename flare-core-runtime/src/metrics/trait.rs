@@ -41,6 +41,9 @@ use std::time::Duration;
 pub trait MetricsCollector: Send + Sync {
     /// 记录任务启动耗时
     ///
+    /// 每个任务只记录一次：从 `TaskManager::start_all` 到任务首次进入 `Running`，
+    /// 包含运行前钩子以及首次运行前被中间件拒绝后的重启等待
+    ///
     /// # 参数
     ///
     /// * `name` - 任务名称
@@ -68,6 +71,18 @@ pub trait MetricsCollector: Send + Sync {
     /// * `state` - 任务状态
     async fn record_task_state(&self, name: &str, state: TaskState) -> Result<(), MetricsError>;
 
+    /// 记录运行时启动耗时：从启动任务到 `wait_for_ready` 确认全部任务就绪
+    ///
+    /// 未启用就绪检查时不调用；默认实现忽略
+    ///
+    /// # 参数
+    ///
+    /// * `duration` - 启动耗时
+    async fn record_runtime_startup(&self, duration: Duration) -> Result<(), MetricsError> {
+        let _ = duration;
+        Ok(())
+    }
+
     /// 记录健康检查结果
     ///
     /// # 参数
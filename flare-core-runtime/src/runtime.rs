@@ -6,20 +6,29 @@
 //! - 服务注册/注销
 //! - 健康检查
 //! - 状态监控
+//! - 运维端点（探针、任务状态、状态事件流与控制接口）
 
+use crate::admin::{AdminServer, HealthResults, LogLevelControl, ShutdownTrigger};
 use crate::config::RuntimeConfig;
+use crate::control::{ConsumerControl, ConsumerControls};
 use crate::error::HealthError;
 use crate::health::{HealthCheck, HealthChecker};
+use crate::metrics::{MetricsCollector, MetricsExporter};
+use crate::middleware::{Middleware, MiddlewareChain};
+use crate::plugin::{Plugin, PluginManager};
 use crate::registry::ServiceRegistry;
 use crate::signal::{CompositeSignal, CtrlCSignal, ShutdownSignal, UnixSignal, UnixSignalKind};
 use crate::state::StateTracker;
-use crate::task::{SpawnTask, Task, TaskManager};
+use crate::task::hooks::TaskHooks;
+use crate::task::{
+    RestartStrategy, SpawnTask, SupervisedTask, Task, TaskManager, TaskResult, TaskState,
+};
 use anyhow::Result;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::sync::{mpsc, oneshot};
-use tokio::task::JoinHandle;
-use tracing::{error, info, warn};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::task::{JoinHandle, JoinSet};
+use tracing::{debug, error, info, warn};
 
 /// 默认健康检查：监控任务失败状态
 struct TaskFailureHealthCheck {
@@ -70,6 +79,96 @@ struct HealthMonitorHandle {
     failure_rx: Option<mpsc::UnboundedReceiver<String>>,
 }
 
+struct StateMetricsHandle {
+    stop_tx: oneshot::Sender<()>,
+    join_handle: JoinHandle<()>,
+}
+
+/// 把任务状态变更转发给指标收集器；收到停止信号后处理完已排队的事件再退出
+fn spawn_state_metrics(
+    tracker: &StateTracker,
+    metrics: Arc<dyn MetricsCollector>,
+) -> StateMetricsHandle {
+    let mut events = tracker.subscribe();
+    let (stop_tx, mut stop_rx) = oneshot::channel::<()>();
+
+    let join_handle = tokio::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                event = events.recv() => event,
+                _ = &mut stop_rx => break,
+            };
+            match event {
+                Ok(event) => {
+                    record_task_state(metrics.as_ref(), &event.task_name, event.new_state).await
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!(skipped, "Task state metrics lagged behind state events");
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            }
+        }
+        while let Ok(event) = events.try_recv() {
+            record_task_state(metrics.as_ref(), &event.task_name, event.new_state).await;
+        }
+    });
+
+    StateMetricsHandle {
+        stop_tx,
+        join_handle,
+    }
+}
+
+async fn record_task_state(metrics: &dyn MetricsCollector, name: &str, state: TaskState) {
+    if let Err(e) = metrics.record_task_state(name, state).await {
+        debug!(task_name = %name, error = %e, "Failed to record task state");
+    }
+}
+
+/// 等待停机信号、健康检查失败或任务失败升级，任一先到即返回
+async fn wait_for_shutdown(
+    shutdown_signal: &mut CompositeSignal,
+    health_monitor: Option<&mut HealthMonitorHandle>,
+    escalations: Option<&mut mpsc::UnboundedReceiver<String>>,
+) {
+    let health_failure = async {
+        match health_monitor.and_then(|monitor| monitor.failure_rx.as_mut()) {
+            Some(failure_rx) => failure_rx.recv().await,
+            None => std::future::pending().await,
+        }
+    };
+    let escalation = async {
+        // TaskManager 持有发送端，接收端不会因发送端关闭而返回 None
+        match escalations {
+            Some(escalations) => escalations.recv().await,
+            None => std::future::pending().await,
+        }
+    };
+
+    tokio::select! {
+        _ = shutdown_signal.wait() => {
+            info!("Shutdown signal received");
+        }
+        failed = health_failure => {
+            warn!(failed_check = ?failed, "Health check threshold exceeded, triggering graceful shutdown");
+        }
+        task = escalation => {
+            warn!(task_name = ?task, "Task failure escalated, triggering graceful shutdown");
+        }
+    }
+}
+
+/// 未设置收集器时使用的内置收集器
+#[cfg(feature = "prometheus")]
+fn default_metrics_collector() -> Option<Arc<dyn MetricsCollector>> {
+    Some(crate::metrics::PrometheusCollector::global())
+}
+
+#[cfg(not(feature = "prometheus"))]
+fn default_metrics_collector() -> Option<Arc<dyn MetricsCollector>> {
+    None
+}
+
 /// 服务运行时
 ///
 /// 统一管理服务的生命周期，包括：
@@ -132,6 +231,18 @@ pub struct ServiceRuntime {
     health_checker: Option<HealthChecker>,
     /// 健康检查失败动作
     health_failure_action: HealthFailureAction,
+    /// 消费者控制句柄
+    consumer_controls: ConsumerControls,
+    /// 插件
+    plugins: PluginManager,
+    /// 任务中间件
+    middleware: MiddlewareChain,
+    /// 指标收集器（可选）
+    metrics: Option<Arc<dyn MetricsCollector>>,
+    /// 最近一轮健康检查结果（供运维端点读取）
+    health_results: HealthResults,
+    /// 日志级别控制（供运维端点调用）
+    log_level: Option<LogLevelControl>,
 }
 
 impl ServiceRuntime {
@@ -157,6 +268,12 @@ impl ServiceRuntime {
             config: RuntimeConfig::default(),
             health_checker: None,
             health_failure_action: HealthFailureAction::LogOnly,
+            consumer_controls: ConsumerControls::new(),
+            plugins: PluginManager::new(),
+            middleware: MiddlewareChain::new(),
+            metrics: None,
+            health_results: HealthResults::default(),
+            log_level: None,
         }
     }
 
@@ -182,6 +299,12 @@ impl ServiceRuntime {
             config: RuntimeConfig::default(),
             health_checker: None,
             health_failure_action: HealthFailureAction::LogOnly,
+            consumer_controls: ConsumerControls::new(),
+            plugins: PluginManager::new(),
+            middleware: MiddlewareChain::new(),
+            metrics: None,
+            health_results: HealthResults::default(),
+            log_level: None,
         }
     }
 
@@ -267,6 +390,57 @@ impl ServiceRuntime {
         self
     }
 
+    /// 注册插件
+    ///
+    /// 启动任务前调用 `on_startup`，任务每次运行前后调用 `on_task_start` / `on_task_stop`，
+    /// 任务失败或启动失败时调用 `on_error`，所有任务停止后调用 `on_shutdown`。
+    /// 插件钩子失败只记录日志，不会中止启动或停机。
+    pub fn with_plugin(mut self, plugin: Arc<dyn Plugin>) -> Self {
+        self.plugins.register(plugin);
+        self
+    }
+
+    /// 添加任务中间件
+    ///
+    /// 任务每次运行（含重启）前后按注册顺序调用；`before` 返回错误时本次运行不启动，
+    /// 按任务失败处理（重启策略与关键任务升级照常生效）
+    pub fn with_middleware(mut self, middleware: Arc<dyn Middleware>) -> Self {
+        self.middleware.add(middleware);
+        self
+    }
+
+    /// 设置指标收集器
+    ///
+    /// 记录任务启动与停机耗时、任务状态变更和健康检查结果；记录失败只输出 debug 日志。
+    /// 启用 `RuntimeConfig::metrics` 时由内置导出任务导出
+    pub fn with_metrics_collector(mut self, collector: Arc<dyn MetricsCollector>) -> Self {
+        self.metrics = Some(collector);
+        self
+    }
+
+    /// 设置日志级别控制，供运维端点 `POST /log-level?level=` 调用
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use flare_core_runtime::ServiceRuntime;
+    ///
+    /// let runtime = ServiceRuntime::new("my-service").with_log_level_control(|level| {
+    ///     // 例如转发给 flare_core_infra::set_log_filter
+    ///     if level.is_empty() {
+    ///         return Err("empty filter".to_string());
+    ///     }
+    ///     Ok(())
+    /// });
+    /// ```
+    pub fn with_log_level_control<F>(mut self, control: F) -> Self
+    where
+        F: Fn(&str) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.log_level = Some(Arc::new(control));
+        self
+    }
+
     /// 添加任务
     ///
     /// # 参数
@@ -334,6 +508,59 @@ impl ServiceRuntime {
         self
     }
 
+    /// 添加可重启任务
+    ///
+    /// `factory` 每次（重新）运行调用一次；任务退出后按 `restart` 重启，
+    /// 重启次数用尽且策略要求升级时触发运行时优雅停机
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use flare_core_runtime::ServiceRuntime;
+    /// use flare_core_runtime::task::RestartStrategy;
+    ///
+    /// let runtime = ServiceRuntime::new("my-service").add_supervised(
+    ///     "order-consumer",
+    ///     RestartStrategy::on_failure(),
+    ///     |shutdown_rx| async move {
+    ///         let _ = shutdown_rx.await;
+    ///         Ok(())
+    ///     },
+    /// );
+    /// ```
+    pub fn add_supervised<F, Fut>(
+        mut self,
+        name: impl Into<String>,
+        restart: RestartStrategy,
+        factory: F,
+    ) -> Self
+    where
+        F: Fn(oneshot::Receiver<()>) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<(), Box<dyn std::error::Error + Send + Sync>>>
+            + Send
+            + 'static,
+    {
+        self.task_manager.add_task(Box::new(
+            SupervisedTask::new(name, factory).with_restart(restart),
+        ));
+        self
+    }
+
+    /// 注册消费者控制句柄，供运维接口暂停/恢复
+    pub fn add_consumer_control(
+        self,
+        name: impl Into<String>,
+        control: Arc<dyn ConsumerControl>,
+    ) -> Self {
+        self.consumer_controls.register(name, control);
+        self
+    }
+
+    /// 获取消费者控制句柄集合（与运行时共享，`run` 之前取出即可在运行期间使用）
+    pub fn consumer_controls(&self) -> ConsumerControls {
+        self.consumer_controls.clone()
+    }
+
     /// 获取状态追踪器
     pub fn state_tracker(&self) -> Arc<StateTracker> {
         self.task_manager.state_tracker()
@@ -370,6 +597,8 @@ impl ServiceRuntime {
         }
 
         let service_name = self.service_name.clone();
+        let metrics = self.metrics.clone();
+        let health_results = Arc::clone(&self.health_results);
         let interval = self.config.health_check.interval;
         let timeout = self.config.health_check.timeout;
         let (stop_tx, mut stop_rx) = oneshot::channel::<()>();
@@ -395,6 +624,14 @@ impl ServiceRuntime {
                     _ = ticker.tick() => {
                         match tokio::time::timeout(timeout, checker.check_all()).await {
                             Ok(results) => {
+                                *health_results.write().await = Some(results.clone());
+                                if let Some(metrics) = &metrics {
+                                    for result in &results {
+                                        if let Err(e) = metrics.record_health_check(&result.name, result.healthy).await {
+                                            debug!(check = %result.name, error = %e, "Failed to record health check");
+                                        }
+                                    }
+                                }
                                 let unhealthy: Vec<_> = results.into_iter().filter(|r| !r.healthy).collect();
                                 if !unhealthy.is_empty() {
                                     let names: Vec<_> = unhealthy.into_iter().map(|r| r.name).collect();
@@ -425,6 +662,105 @@ impl ServiceRuntime {
         })
     }
 
+    /// 启用指标时确定收集器并添加导出任务；没有可用收集器（未设置且未启用 `prometheus` feature）时告警跳过
+    fn install_metrics_exporter(&mut self) {
+        if !self.config.metrics.enabled {
+            return;
+        }
+        if self.metrics.is_none() {
+            self.metrics = default_metrics_collector();
+        }
+        let Some(collector) = &self.metrics else {
+            warn!(
+                "Metrics are enabled but no MetricsCollector is set and the prometheus feature is disabled, metrics endpoint is not served"
+            );
+            return;
+        };
+        self.task_manager.add_task(Box::new(MetricsExporter::new(
+            &self.config.metrics,
+            Arc::clone(collector),
+        )));
+    }
+
+    /// 启用运维端点时添加运维任务，并把它的停机请求加入停机信号
+    ///
+    /// 返回的触发器需保持到停机完成，避免运维任务退出时被当作停机信号
+    fn install_admin_server(
+        &mut self,
+        signals: &mut Vec<Box<dyn ShutdownSignal>>,
+    ) -> Option<ShutdownTrigger> {
+        if !self.config.admin.enabled {
+            return None;
+        }
+        let (trigger, signal) = ShutdownTrigger::new();
+        signals.push(Box::new(signal));
+        self.task_manager.add_task(Box::new(AdminServer::new(
+            &self.config.admin,
+            self.task_manager.state_tracker(),
+            Arc::clone(&self.health_results),
+            self.consumer_controls.clone(),
+            trigger.clone(),
+            self.log_level.clone(),
+        )));
+        Some(trigger)
+    }
+
+    /// 把插件、中间件与指标收集器交给任务管理器
+    fn install_hooks(&mut self) -> Arc<TaskHooks> {
+        let hooks = Arc::new(TaskHooks::new(
+            self.service_name.clone(),
+            std::mem::take(&mut self.plugins),
+            std::mem::take(&mut self.middleware),
+            self.metrics.clone(),
+        ));
+        self.task_manager.set_hooks(Arc::clone(&hooks));
+        hooks
+    }
+
+    /// 启动所有任务并等待就绪；失败时通知插件
+    async fn start_tasks(
+        &mut self,
+        hooks: &TaskHooks,
+    ) -> Result<(JoinSet<TaskResult>, Vec<oneshot::Sender<()>>)> {
+        let started_at = std::time::Instant::now();
+        let (join_set, shutdown_txs) = match self.task_manager.start_all().await {
+            Ok(started) => started,
+            Err(e) => {
+                hooks.on_error(&e.to_string()).await;
+                return Err(anyhow::anyhow!("Failed to start tasks: {}", e));
+            }
+        };
+
+        if let Err(e) = self.task_manager.wait_for_ready().await {
+            hooks.on_error(&e.to_string()).await;
+            return Err(anyhow::anyhow!("Failed to wait for tasks ready: {}", e));
+        }
+        if self.config.task_startup.enable_ready_check
+            && let Some(metrics) = &self.metrics
+            && let Err(e) = metrics.record_runtime_startup(started_at.elapsed()).await
+        {
+            debug!(error = %e, "Failed to record runtime startup");
+        }
+
+        Ok((join_set, shutdown_txs))
+    }
+
+    /// 停止所有任务，记录完剩余的状态指标后通知插件
+    async fn stop_tasks(
+        &self,
+        join_set: JoinSet<TaskResult>,
+        shutdown_txs: Vec<oneshot::Sender<()>>,
+        hooks: &TaskHooks,
+        state_metrics: Option<StateMetricsHandle>,
+    ) {
+        self.task_manager.stop_all(join_set, shutdown_txs).await;
+        if let Some(state_metrics) = state_metrics {
+            let _ = state_metrics.stop_tx.send(());
+            let _ = state_metrics.join_handle.await;
+        }
+        hooks.on_shutdown().await;
+    }
+
     /// 运行服务（简单模式，不注册服务）
     ///
     /// 执行以下步骤：
@@ -459,54 +795,42 @@ impl ServiceRuntime {
             #[cfg(target_family = "unix")]
             signals.push(Box::new(UnixSignal::new(UnixSignalKind::Terminate)));
         }
+        let _admin_shutdown = self.install_admin_server(&mut signals);
 
         let mut shutdown_signal = CompositeSignal::from_signals(signals);
-
-        // 2. 启动所有任务
-        let (join_set, shutdown_txs) = self
-            .task_manager
-            .start_all()
-            .await
-            .map_err(|e| anyhow::anyhow!("Failed to start tasks: {}", e))?;
-
-        // 3. 等待所有任务就绪
-        self.task_manager
-            .wait_for_ready()
-            .await
-            .map_err(|e| anyhow::anyhow!("Failed to wait for tasks ready: {}", e))?;
-
-        // 4.1 启动健康检查监控（可选）
+        let mut escalations = self.task_manager.take_escalations();
+        self.install_metrics_exporter();
+        let hooks = self.install_hooks();
+        let state_metrics = self
+            .metrics
+            .clone()
+            .map(|metrics| spawn_state_metrics(&self.task_manager.state_tracker(), metrics));
+        hooks.on_startup().await;
+
+        // 2. 启动所有任务并等待就绪
+        let (join_set, shutdown_txs) = self.start_tasks(&hooks).await?;
+
+        // 3. 启动健康检查监控（可选）
         let mut health_monitor = self.start_health_monitor();
 
-        // 5. 等待停机信号或健康检查触发停机
+        // 4. 等待停机信号、健康检查或任务失败升级触发停机
         info!("Waiting for shutdown signal...");
-        if let Some(monitor) = health_monitor.as_mut() {
-            if let Some(failure_rx) = monitor.failure_rx.as_mut() {
-                tokio::select! {
-                    _ = shutdown_signal.wait() => {
-                        info!("Shutdown signal received");
-                    }
-                    failed = failure_rx.recv() => {
-                        warn!(failed_check = ?failed, "Health check threshold exceeded, triggering graceful shutdown");
-                    }
-                }
-            } else {
-                shutdown_signal.wait().await;
-                info!("Shutdown signal received");
-            }
-        } else {
-            shutdown_signal.wait().await;
-            info!("Shutdown signal received");
-        }
-
-        // 6. 停止健康检查监控
+        wait_for_shutdown(
+            &mut shutdown_signal,
+            health_monitor.as_mut(),
+            escalations.as_mut(),
+        )
+        .await;
+
+        // 5. 停止健康检查监控
         if let Some(monitor) = health_monitor {
             let _ = monitor.stop_tx.send(());
             let _ = monitor.join_handle.await;
         }
 
-        // 7. 停止所有任务
-        self.task_manager.stop_all(join_set, shutdown_txs).await;
+        // 6. 停止所有任务
+        self.stop_tasks(join_set, shutdown_txs, &hooks, state_metrics)
+            .await;
 
         info!(service_name = %self.service_name, "Service runtime stopped");
         Ok(())
@@ -575,22 +899,21 @@ impl ServiceRuntime {
             #[cfg(target_family = "unix")]
             signals.push(Box::new(UnixSignal::new(UnixSignalKind::Terminate)));
         }
+        let _admin_shutdown = self.install_admin_server(&mut signals);
         let mut shutdown_signal = CompositeSignal::from_signals(signals);
-
-        // 2. 启动所有任务
-        let (join_set, shutdown_txs) = self
-            .task_manager
-            .start_all()
-            .await
-            .map_err(|e| anyhow::anyhow!("Failed to start tasks: {}", e))?;
-
-        // 3. 等待所有任务就绪
-        self.task_manager
-            .wait_for_ready()
-            .await
-            .map_err(|e| anyhow::anyhow!("Failed to wait for tasks ready: {}", e))?;
-
-        // 4. 注册服务
+        let mut escalations = self.task_manager.take_escalations();
+        self.install_metrics_exporter();
+        let hooks = self.install_hooks();
+        let state_metrics = self
+            .metrics
+            .clone()
+            .map(|metrics| spawn_state_metrics(&self.task_manager.state_tracker(), metrics));
+        hooks.on_startup().await;
+
+        // 2. 启动所有任务并等待就绪
+        let (join_set, shutdown_txs) = self.start_tasks(&hooks).await?;
+
+        // 3. 注册服务
         info!("Registering service...");
         let registry = match register_fn(service_address).await {
             Ok(Some(reg)) => {
@@ -605,43 +928,33 @@ impl ServiceRuntime {
                 error!(error = %e, "❌ Service registration failed");
 
                 // 停止所有任务
-                self.task_manager.stop_all(join_set, shutdown_txs).await;
+                hooks.on_error(&e.to_string()).await;
+                self.stop_tasks(join_set, shutdown_txs, &hooks, state_metrics)
+                    .await;
 
                 return Err(anyhow::anyhow!("Service registration failed: {}", e));
             }
         };
 
-        // 4.1 启动健康检查监控（可选）
+        // 3.1 启动健康检查监控（可选）
         let mut health_monitor = self.start_health_monitor();
 
-        // 5. 等待停机信号或健康检查触发停机
+        // 4. 等待停机信号、健康检查或任务失败升级触发停机
         info!("Waiting for shutdown signal...");
-        if let Some(monitor) = health_monitor.as_mut() {
-            if let Some(failure_rx) = monitor.failure_rx.as_mut() {
-                tokio::select! {
-                    _ = shutdown_signal.wait() => {
-                        info!("Shutdown signal received");
-                    }
-                    failed = failure_rx.recv() => {
-                        warn!(failed_check = ?failed, "Health check threshold exceeded, triggering graceful shutdown");
-                    }
-                }
-            } else {
-                shutdown_signal.wait().await;
-                info!("Shutdown signal received");
-            }
-        } else {
-            shutdown_signal.wait().await;
-            info!("Shutdown signal received");
-        }
-
-        // 5.1 停止健康检查监控
+        wait_for_shutdown(
+            &mut shutdown_signal,
+            health_monitor.as_mut(),
+            escalations.as_mut(),
+        )
+        .await;
+
+        // 4.1 停止健康检查监控
         if let Some(monitor) = health_monitor {
             let _ = monitor.stop_tx.send(());
             let _ = monitor.join_handle.await;
         }
 
-        // 6. 注销服务
+        // 5. 注销服务
         if let Some(mut reg) = registry {
             info!("Deregistering service...");
             if let Err(e) = reg.shutdown().await {
@@ -651,8 +964,9 @@ impl ServiceRuntime {
             }
         }
 
-        // 7. 停止所有任务
-        self.task_manager.stop_all(join_set, shutdown_txs).await;
+        // 6. 停止所有任务
+        self.stop_tasks(join_set, shutdown_txs, &hooks, state_metrics)
+            .await;
 
         info!(service_name = %self.service_name, "Service runtime stopped");
         Ok(())
@@ -751,4 +1065,260 @@ mod tests {
         let (result, _) = tokio::join!(run, stop);
         result.unwrap();
     }
+
+    #[tokio::test]
+    async fn exhausted_restart_budget_triggers_graceful_shutdown() {
+        use crate::signal::ChannelSignal;
+
+        let (_shutdown_tx, shutdown_rx) = oneshot::channel();
+        let runtime =
+            ServiceRuntime::new("test-service")
+                .with_config(RuntimeConfig::new().with_task_startup(
+                    crate::config::TaskStartupConfig::new().with_ready_check(false),
+                ))
+                .add_spawn_with_shutdown("wait-for-shutdown", |shutdown_rx| async move {
+                    let _ = shutdown_rx.await;
+                    Ok(())
+                })
+                .add_supervised(
+                    "flaky-worker",
+                    RestartStrategy::on_failure()
+                        .with_backoff(Duration::from_millis(1), Duration::from_millis(1))
+                        .with_max_restarts(1, Duration::from_secs(60)),
+                    |_shutdown_rx| async { Err("crashed".into()) },
+                );
+
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            runtime.run_with_signals(vec![Box::new(ChannelSignal::new("never", shutdown_rx))]),
+        )
+        .await
+        .expect("escalation stops the runtime")
+        .unwrap();
+    }
+
+    #[derive(Default)]
+    struct RecordingHooks {
+        events: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl RecordingHooks {
+        fn push(&self, event: String) {
+            self.events.lock().unwrap().push(event);
+        }
+
+        fn events(&self) -> Vec<String> {
+            self.events.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Plugin for RecordingHooks {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        async fn on_startup(
+            &self,
+            _ctx: &crate::plugin::PluginContext,
+        ) -> Result<(), crate::error::PluginError> {
+            self.push("startup".to_string());
+            Ok(())
+        }
+
+        async fn on_task_stop(
+            &self,
+            ctx: &crate::plugin::PluginContext,
+        ) -> Result<(), crate::error::PluginError> {
+            self.push(format!("task_stop:{}", ctx.task_name.as_deref().unwrap()));
+            Ok(())
+        }
+
+        async fn on_error(
+            &self,
+            ctx: &crate::plugin::PluginContext,
+            _error: &str,
+        ) -> Result<(), crate::error::PluginError> {
+            self.push(format!("error:{}", ctx.task_name.as_deref().unwrap_or("-")));
+            Err(crate::error::PluginError::NotFound {
+                name: "ignored".to_string(),
+            })
+        }
+
+        async fn on_shutdown(
+            &self,
+            _ctx: &crate::plugin::PluginContext,
+        ) -> Result<(), crate::error::PluginError> {
+            self.push("shutdown".to_string());
+            Ok(())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Middleware for RecordingHooks {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        async fn before(&self, task_name: &str) -> Result<(), crate::error::MiddlewareError> {
+            if task_name == "guarded" {
+                return Err(crate::error::MiddlewareError::ExecutionFailed {
+                    name: "recording".to_string(),
+                    reason: "not allowed".to_string(),
+                });
+            }
+            Ok(())
+        }
+
+        async fn after(
+            &self,
+            task_name: &str,
+            _result: &TaskResult,
+        ) -> Result<(), crate::error::MiddlewareError> {
+            self.push(format!("after:{task_name}"));
+            Ok(())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl MetricsCollector for RecordingHooks {
+        async fn record_task_startup(
+            &self,
+            name: &str,
+            _duration: Duration,
+        ) -> Result<(), crate::error::MetricsError> {
+            self.push(format!("startup_metric:{name}"));
+            Ok(())
+        }
+
+        async fn record_task_shutdown(
+            &self,
+            name: &str,
+            _duration: Duration,
+        ) -> Result<(), crate::error::MetricsError> {
+            self.push(format!("shutdown_metric:{name}"));
+            Ok(())
+        }
+
+        async fn record_task_state(
+            &self,
+            name: &str,
+            state: TaskState,
+        ) -> Result<(), crate::error::MetricsError> {
+            self.push(format!("state:{name}:{state}"));
+            Ok(())
+        }
+
+        async fn record_health_check(
+            &self,
+            _name: &str,
+            _success: bool,
+        ) -> Result<(), crate::error::MetricsError> {
+            Ok(())
+        }
+
+        async fn export(&self) -> Result<String, crate::error::MetricsError> {
+            Ok(String::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn plugins_middleware_and_metrics_follow_task_lifecycle() {
+        use crate::signal::ChannelSignal;
+
+        let hooks = Arc::new(RecordingHooks::default());
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let runtime =
+            ServiceRuntime::new("test-service")
+                .with_config(RuntimeConfig::new().with_task_startup(
+                    crate::config::TaskStartupConfig::new().with_ready_check(false),
+                ))
+                .with_plugin(hooks.clone())
+                .with_middleware(hooks.clone())
+                .with_metrics_collector(hooks.clone())
+                .add_spawn_with_shutdown("worker", |shutdown_rx| async move {
+                    let _ = shutdown_rx.await;
+                    Ok(())
+                })
+                .add_spawn("guarded", async { Ok(()) });
+
+        let run = runtime.run_with_signals(vec![Box::new(ChannelSignal::new("test", shutdown_rx))]);
+        let stop = async move {
+            tokio::time::sleep(Duration::from_millis(25)).await;
+            shutdown_tx.send(()).unwrap();
+        };
+        let (result, _) = tokio::join!(run, stop);
+        result.unwrap();
+
+        let events = hooks.events();
+        let position = |event: &str| {
+            events
+                .iter()
+                .position(|e| e == event)
+                .unwrap_or_else(|| panic!("missing {event} in {events:?}"))
+        };
+        assert_eq!(position("startup"), 0);
+        position("startup_metric:worker");
+        assert!(position("shutdown_metric:worker") < position("after:worker"));
+        assert!(position("after:worker") < position("task_stop:worker"));
+        assert!(position("state:worker:Stopping") < position("state:worker:Stopped"));
+        position("error:guarded");
+        position("state:guarded:Failed");
+        assert!(
+            !events.iter().any(|e| e == "after:guarded"),
+            "rejected run has no after hook"
+        );
+        assert_eq!(events.last().map(String::as_str), Some("shutdown"));
+    }
+
+    #[tokio::test]
+    async fn admin_shutdown_request_stops_runtime() {
+        use crate::signal::ChannelSignal;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let (_shutdown_tx, shutdown_rx) = oneshot::channel();
+        let runtime = ServiceRuntime::new("test-service")
+            .with_config(
+                RuntimeConfig::new().with_admin(
+                    crate::config::AdminConfig::new()
+                        .with_enabled(true)
+                        .with_port(port),
+                ),
+            )
+            .add_spawn_with_shutdown("worker", |shutdown_rx| async move {
+                let _ = shutdown_rx.await;
+                Ok(())
+            });
+
+        let run =
+            runtime.run_with_signals(vec![Box::new(ChannelSignal::new("never", shutdown_rx))]);
+        let request_shutdown = async move {
+            let mut stream = loop {
+                match tokio::net::TcpStream::connect(("127.0.0.1", port)).await {
+                    Ok(stream) => break stream,
+                    Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+                }
+            };
+            stream
+                .write_all(b"POST /shutdown HTTP/1.1\r\nHost: test\r\n\r\n")
+                .await
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        };
+
+        let (result, response) = tokio::time::timeout(Duration::from_secs(5), async {
+            tokio::join!(run, request_shutdown)
+        })
+        .await
+        .expect("admin shutdown stops the runtime");
+        result.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+    }
 }
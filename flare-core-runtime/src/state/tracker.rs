@@ -20,6 +20,8 @@ pub struct TaskStateInfo {
     pub started_at: Option<Instant>,
     /// 运行时长（如果正在运行）
     pub running_duration: Option<Duration>,
+    /// 累计重启次数
+    pub restarts: u32,
 }
 
 impl TaskStateInfo {
@@ -31,6 +33,7 @@ impl TaskStateInfo {
             state_changed_at: Instant::now(),
             started_at: None,
             running_duration: None,
+            restarts: 0,
         }
     }
 
@@ -43,6 +46,10 @@ impl TaskStateInfo {
             self.started_at = Some(now);
         }
 
+        if new_state == TaskState::Restarting {
+            self.restarts += 1;
+        }
+
         // 计算运行时长
         if new_state == TaskState::Running {
             if let Some(started_at) = self.started_at {
@@ -227,6 +234,16 @@ impl StateTracker {
             .collect()
     }
 
+    /// 获取等待重启的任务
+    pub async fn get_restarting_tasks(&self) -> Vec<String> {
+        let tasks = self.tasks.read().await;
+        tasks
+            .values()
+            .filter(|info| info.state == TaskState::Restarting)
+            .map(|info| info.name.clone())
+            .collect()
+    }
+
     /// 获取运行中的任务数量
     pub async fn running_count(&self) -> usize {
         let tasks = self.tasks.read().await;
@@ -325,4 +342,27 @@ mod tests {
 
         assert!(tracker.all_ready().await);
     }
+
+    #[tokio::test]
+    async fn test_state_tracker_restarting_counts_restarts() {
+        let tracker = StateTracker::new();
+        tracker.register_task("task-1", TaskState::Pending).await;
+        tracker.update_state("task-1", TaskState::Starting).await;
+        tracker.update_state("task-1", TaskState::Running).await;
+
+        let event = tracker
+            .update_state_with_error("task-1", TaskState::Restarting, "boom")
+            .await
+            .unwrap();
+        assert_eq!(event.error.as_deref(), Some("boom"));
+        assert_eq!(tracker.get_restarting_tasks().await, vec!["task-1"]);
+        assert!(!tracker.all_ready().await);
+
+        tracker.update_state("task-1", TaskState::Starting).await;
+        tracker.update_state("task-1", TaskState::Running).await;
+
+        let info = tracker.get_state("task-1").await.unwrap();
+        assert_eq!(info.state, TaskState::Running);
+        assert_eq!(info.restarts, 1);
+    }
 }
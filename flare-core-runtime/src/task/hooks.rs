@@ -0,0 +1,354 @@
+//! 任务生命周期钩子
+//!
+//! 把 [PluginManager]、[MiddlewareChain] 与 [MetricsCollector] 接到任务的每次运行上。
+//! 错误语义：
+//!
+//! - 插件钩子失败只记录日志，不影响运行时与任务
+//! - 中间件 `before` 失败时本次运行不启动，按任务失败处理（重启策略、关键任务升级照常生效）
+//! - 中间件 `after` 与指标记录失败只记录日志
+
+use super::{TaskResult, TaskState};
+use crate::error::MiddlewareError;
+use crate::metrics::MetricsCollector;
+use crate::middleware::MiddlewareChain;
+use crate::plugin::{PluginContext, PluginManager};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
+
+/// 运行时注册的插件、中间件与指标收集器
+#[derive(Default)]
+pub(crate) struct TaskHooks {
+    runtime_name: String,
+    plugins: PluginManager,
+    middleware: MiddlewareChain,
+    metrics: Option<Arc<dyn MetricsCollector>>,
+}
+
+impl TaskHooks {
+    pub(crate) fn new(
+        runtime_name: impl Into<String>,
+        plugins: PluginManager,
+        middleware: MiddlewareChain,
+        metrics: Option<Arc<dyn MetricsCollector>>,
+    ) -> Self {
+        Self {
+            runtime_name: runtime_name.into(),
+            plugins,
+            middleware,
+            metrics,
+        }
+    }
+
+    fn context(&self) -> PluginContext {
+        PluginContext::new(&self.runtime_name)
+    }
+
+    fn task_context(&self, task_name: &str, state: TaskState) -> PluginContext {
+        self.context().with_task(task_name, state)
+    }
+
+    /// 运行时启动任务前
+    pub(crate) async fn on_startup(&self) {
+        let _ = self.plugins.on_startup(&self.context()).await;
+    }
+
+    /// 所有任务停止后
+    pub(crate) async fn on_shutdown(&self) {
+        let _ = self.plugins.on_shutdown(&self.context()).await;
+    }
+
+    /// 运行时级错误（启动失败、注册失败等）
+    pub(crate) async fn on_error(&self, error: &str) {
+        let _ = self.plugins.on_error(&self.context(), error).await;
+    }
+
+    /// 任务每次运行前：插件 `on_task_start`，然后中间件 `before`
+    pub(crate) async fn before_run(&self, task_name: &str) -> Result<(), MiddlewareError> {
+        let ctx = self.task_context(task_name, TaskState::Starting);
+        let _ = self.plugins.on_task_start(&ctx).await;
+        self.middleware.before(task_name).await
+    }
+
+    /// 任务每次运行结束后：中间件 `after`，然后插件 `on_task_stop`
+    pub(crate) async fn after_run(&self, task_name: &str, result: &TaskResult) {
+        // 各中间件的失败已由 MiddlewareChain 记录
+        let _ = self.middleware.after(task_name, result).await;
+        let state = match result {
+            Ok(()) => TaskState::Stopped,
+            Err(_) => TaskState::Failed,
+        };
+        let _ = self
+            .plugins
+            .on_task_stop(&self.task_context(task_name, state))
+            .await;
+    }
+
+    /// 任务运行失败或被中间件拒绝启动
+    pub(crate) async fn task_failed(&self, task_name: &str, error: &str) {
+        let ctx = self.task_context(task_name, TaskState::Failed);
+        let _ = self.plugins.on_error(&ctx, error).await;
+    }
+
+    /// 任务在停机时超过排空时间被中止
+    pub(crate) async fn task_aborted(&self, task_name: &str, drain_timeout: Duration) {
+        self.record_task_shutdown(task_name, drain_timeout).await;
+        let _ = self
+            .plugins
+            .on_task_stop(&self.task_context(task_name, TaskState::Aborted))
+            .await;
+    }
+
+    /// 任务从 [TaskManager::start_all](super::TaskManager::start_all) 到首次 `Running` 的耗时
+    pub(crate) async fn record_task_startup(&self, task_name: &str, duration: Duration) {
+        if let Some(metrics) = &self.metrics
+            && let Err(e) = metrics.record_task_startup(task_name, duration).await
+        {
+            debug!(task_name = %task_name, error = %e, "Failed to record task startup");
+        }
+    }
+
+    pub(crate) async fn record_task_shutdown(&self, task_name: &str, duration: Duration) {
+        if let Some(metrics) = &self.metrics
+            && let Err(e) = metrics.record_task_shutdown(task_name, duration).await
+        {
+            debug!(task_name = %task_name, error = %e, "Failed to record task shutdown");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{MetricsError, PluginError};
+    use crate::middleware::Middleware;
+    use crate::plugin::Plugin;
+    use crate::task::{RestartStrategy, SupervisedTask, TaskManager};
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    type Events = Arc<Mutex<Vec<String>>>;
+
+    struct RecordingPlugin {
+        events: Events,
+    }
+
+    #[async_trait]
+    impl Plugin for RecordingPlugin {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        async fn on_task_start(&self, ctx: &PluginContext) -> Result<(), PluginError> {
+            self.record(ctx, "start");
+            Ok(())
+        }
+
+        async fn on_task_stop(&self, ctx: &PluginContext) -> Result<(), PluginError> {
+            self.record(ctx, "stop");
+            Ok(())
+        }
+
+        async fn on_error(&self, ctx: &PluginContext, _error: &str) -> Result<(), PluginError> {
+            self.record(ctx, "error");
+            Ok(())
+        }
+    }
+
+    impl RecordingPlugin {
+        fn record(&self, ctx: &PluginContext, hook: &str) {
+            let task = ctx.task_name.as_deref().unwrap_or_default();
+            let state = ctx.task_state.map(|state| state.to_string());
+            self.events.lock().unwrap().push(format!(
+                "plugin {hook} {task} {}",
+                state.unwrap_or_default()
+            ));
+        }
+    }
+
+    /// 前 `rejections` 次 `before` 返回错误
+    struct GateMiddleware {
+        rejections: AtomicU32,
+        events: Events,
+    }
+
+    #[async_trait]
+    impl Middleware for GateMiddleware {
+        fn name(&self) -> &str {
+            "gate"
+        }
+
+        async fn before(&self, task_name: &str) -> Result<(), MiddlewareError> {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("middleware before {task_name}"));
+            let rejected = self
+                .rejections
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+            if rejected {
+                return Err(MiddlewareError::ExecutionFailed {
+                    name: "gate".to_string(),
+                    reason: "dependency not ready".to_string(),
+                });
+            }
+            Ok(())
+        }
+
+        async fn after(
+            &self,
+            task_name: &str,
+            _result: &TaskResult,
+        ) -> Result<(), MiddlewareError> {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("middleware after {task_name}"));
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct StartupRecorder {
+        startups: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl MetricsCollector for StartupRecorder {
+        async fn record_task_startup(&self, name: &str, _: Duration) -> Result<(), MetricsError> {
+            self.startups.lock().unwrap().push(name.to_string());
+            Ok(())
+        }
+
+        async fn record_task_shutdown(&self, _: &str, _: Duration) -> Result<(), MetricsError> {
+            Ok(())
+        }
+
+        async fn record_task_state(&self, _: &str, _: TaskState) -> Result<(), MetricsError> {
+            Ok(())
+        }
+
+        async fn record_health_check(&self, _: &str, _: bool) -> Result<(), MetricsError> {
+            Ok(())
+        }
+
+        async fn export(&self) -> Result<String, MetricsError> {
+            Ok(String::new())
+        }
+    }
+
+    fn hooks(
+        rejections: u32,
+        events: &Events,
+        metrics: Option<Arc<dyn MetricsCollector>>,
+    ) -> Arc<TaskHooks> {
+        let mut plugins = PluginManager::new();
+        plugins.register(Arc::new(RecordingPlugin {
+            events: events.clone(),
+        }));
+        let mut middleware = MiddlewareChain::new();
+        middleware.add(Arc::new(GateMiddleware {
+            rejections: AtomicU32::new(rejections),
+            events: events.clone(),
+        }));
+        Arc::new(TaskHooks::new("test-runtime", plugins, middleware, metrics))
+    }
+
+    #[tokio::test]
+    async fn run_hooks_wrap_middleware_inside_plugins() {
+        let events = Events::default();
+        let hooks = hooks(0, &events, None);
+
+        hooks.before_run("grpc").await.unwrap();
+        hooks.after_run("grpc", &Err("crashed".into())).await;
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                "plugin start grpc Starting",
+                "middleware before grpc",
+                "middleware after grpc",
+                "plugin stop grpc Failed",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn middleware_rejection_is_restarted_by_policy() {
+        let events = Events::default();
+        let metrics = Arc::new(StartupRecorder::default());
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = runs.clone();
+        let mut manager = TaskManager::new();
+        manager.set_hooks(hooks(1, &events, Some(metrics.clone())));
+        manager.add_task(Box::new(
+            SupervisedTask::new("consumer", move |shutdown_rx| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    let _ = shutdown_rx.await;
+                    Ok(())
+                }
+            })
+            .with_restart(
+                RestartStrategy::on_failure()
+                    .with_backoff(Duration::from_millis(1), Duration::from_millis(5)),
+            ),
+        ));
+        let tracker = manager.state_tracker();
+
+        let (mut join_set, shutdown_txs) = manager.start_all().await.expect("start_all");
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while runs.load(Ordering::SeqCst) == 0 {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("task started after restart");
+
+        // 被拒绝的那次运行计入重启，不调用任务；启动耗时在首次 Running 时只记录一次
+        let info = tracker.get_state("consumer").await.unwrap();
+        assert_eq!(info.state, TaskState::Running);
+        assert_eq!(info.restarts, 1);
+        assert_eq!(*metrics.startups.lock().unwrap(), ["consumer"]);
+        assert!(
+            events
+                .lock()
+                .unwrap()
+                .contains(&"plugin error consumer Failed".to_string())
+        );
+
+        for tx in shutdown_txs {
+            let _ = tx.send(());
+        }
+        assert!(join_set.join_next().await.unwrap().unwrap().is_ok());
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn middleware_rejection_without_restart_fails_task() {
+        let events = Events::default();
+        let mut manager = TaskManager::new();
+        manager.set_hooks(hooks(1, &events, None));
+        manager.add_task(Box::new(
+            SupervisedTask::new("worker", |_shutdown_rx| async { Ok(()) })
+                .with_restart(RestartStrategy::never()),
+        ));
+        let tracker = manager.state_tracker();
+
+        let (mut join_set, _shutdown_txs) = manager.start_all().await.expect("start_all");
+        let result = join_set.join_next().await.unwrap().unwrap();
+
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("dependency not ready")
+        );
+        assert_eq!(
+            tracker.get_state("worker").await.unwrap().state,
+            TaskState::Failed
+        );
+    }
+}
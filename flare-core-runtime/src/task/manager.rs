@@ -1,17 +1,19 @@
 //! 任务管理器实现
 //!
-//! 负责管理所有任务的生命周期，包括启动、停止、依赖管理等
+//! 负责管理所有任务的生命周期，包括启动、停止、依赖管理、失败重启等
 
+use super::hooks::TaskHooks;
+use super::supervisor::{RestartBudget, RestartPolicy};
 use super::{Task, TaskResult, TaskState};
 use crate::config::RuntimeConfig;
 use crate::error::RuntimeError;
 use crate::state::StateTracker;
 use crate::utils::topological_sort;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::oneshot;
-use tokio::task::JoinSet;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::{AbortHandle, Id, JoinSet};
 use tracing::{debug, error, info, warn};
 
 /// 任务管理器
@@ -22,7 +24,8 @@ use tracing::{debug, error, info, warn};
 ///
 /// - 任务注册和管理
 /// - 依赖排序和启动
-/// - 优雅停机
+/// - 按 [Task::restart_strategy] 重启退出的任务
+/// - 按依赖逆序优雅停机，每个任务独立的排空超时
 /// - 状态追踪
 ///
 /// # 示例
@@ -52,24 +55,41 @@ pub struct TaskManager {
     state_tracker: Arc<StateTracker>,
     /// 配置
     config: RuntimeConfig,
+    /// 需要停机的任务名（重启次数用尽或关键任务失败）
+    escalation_tx: mpsc::UnboundedSender<String>,
+    escalation_rx: Option<mpsc::UnboundedReceiver<String>>,
+    /// 最近一次 `start_all` 启动的任务（拓扑序），供 `stop_all` 逆序停机
+    started: Vec<StartedTask>,
+    /// 插件、中间件与指标钩子
+    hooks: Arc<TaskHooks>,
+}
+
+/// 已启动任务的停机信息
+struct StartedTask {
+    name: String,
+    dependencies: Vec<String>,
+    drain_timeout: Duration,
+    id: Id,
+    abort: AbortHandle,
 }
 
 impl TaskManager {
     /// 创建新的任务管理器
     pub fn new() -> Self {
-        Self {
-            tasks: Vec::new(),
-            state_tracker: Arc::new(StateTracker::new()),
-            config: RuntimeConfig::default(),
-        }
+        Self::with_config(RuntimeConfig::default())
     }
 
     /// 创建新的任务管理器（带配置）
     pub fn with_config(config: RuntimeConfig) -> Self {
+        let (escalation_tx, escalation_rx) = mpsc::unbounded_channel();
         Self {
             tasks: Vec::new(),
             state_tracker: Arc::new(StateTracker::new()),
             config,
+            escalation_tx,
+            escalation_rx: Some(escalation_rx),
+            started: Vec::new(),
+            hooks: Arc::new(TaskHooks::default()),
         }
     }
 
@@ -77,6 +97,10 @@ impl TaskManager {
         self.config = config;
     }
 
+    pub(crate) fn set_hooks(&mut self, hooks: Arc<TaskHooks>) {
+        self.hooks = hooks;
+    }
+
     #[cfg(test)]
     pub(crate) fn shutdown_timeout(&self) -> Duration {
         self.config.shutdown_timeout
@@ -98,6 +122,13 @@ impl TaskManager {
         Arc::clone(&self.state_tracker)
     }
 
+    /// 取出停机升级通知的接收端（只能取一次）
+    ///
+    /// 任务重启次数用尽且策略要求升级、或关键任务失败且不再重启时，发送该任务名
+    pub fn take_escalations(&mut self) -> Option<mpsc::UnboundedReceiver<String>> {
+        self.escalation_rx.take()
+    }
+
     /// 启动所有任务
     ///
     /// # 返回
@@ -113,6 +144,7 @@ impl TaskManager {
         &mut self,
     ) -> Result<(JoinSet<TaskResult>, Vec<oneshot::Sender<()>>), RuntimeError> {
         info!(task_count = self.tasks.len(), "Starting all tasks");
+        let started_at = Instant::now();
 
         // 1. 拓扑排序，确定启动顺序
         let sorted_tasks = self.sort_tasks()?;
@@ -127,6 +159,7 @@ impl TaskManager {
         // 3. 启动任务
         let mut join_set = JoinSet::new();
         let mut shutdown_txs = Vec::new();
+        self.started.clear();
 
         for task in sorted_tasks {
             let task_name = task.name().to_string();
@@ -138,36 +171,24 @@ impl TaskManager {
                 .update_state(&task_name, TaskState::Starting)
                 .await;
 
-            // 启动任务
-            let state_tracker = Arc::clone(&self.state_tracker);
-            let task_future = task.run(shutdown_rx);
+            let dependencies = task.dependencies();
+            let drain_timeout = task.drain_timeout().unwrap_or(self.config.shutdown_timeout);
 
-            join_set.spawn(async move {
-                // 更新状态为 Running
-                state_tracker
-                    .update_state(&task_name, TaskState::Running)
-                    .await;
-
-                // 执行任务
-                let result = task_future.await;
-
-                // 更新状态
-                match &result {
-                    Ok(_) => {
-                        debug!(task_name = %task_name, "Task completed");
-                        state_tracker
-                            .update_state(&task_name, TaskState::Stopped)
-                            .await;
-                    }
-                    Err(e) => {
-                        error!(task_name = %task_name, error = %e, "❌ Task failed");
-                        state_tracker
-                            .update_state_with_error(&task_name, TaskState::Failed, e.to_string())
-                            .await;
-                    }
-                }
-
-                result
+            // 启动任务
+            let abort = join_set.spawn(supervise(
+                task,
+                started_at,
+                shutdown_rx,
+                Arc::clone(&self.state_tracker),
+                self.escalation_tx.clone(),
+                Arc::clone(&self.hooks),
+            ));
+            self.started.push(StartedTask {
+                name: task_name,
+                dependencies,
+                drain_timeout,
+                id: abort.id(),
+                abort,
             });
         }
 
@@ -177,6 +198,10 @@ impl TaskManager {
 
     /// 停止所有任务
     ///
+    /// 按依赖逆序分层停机：先通知不被其他任务依赖的任务，这一层全部退出后再通知它们所依赖的任务。
+    /// 每个任务最多等待 [Task::drain_timeout]（默认 `shutdown_timeout`），超时的任务被强制中止，
+    /// 并在状态追踪器中记录为 [TaskState::Aborted]。
+    ///
     /// # 参数
     ///
     /// * `join_set` - 任务 JoinSet
@@ -188,6 +213,131 @@ impl TaskManager {
     ) {
         info!("Stopping all tasks");
 
+        if shutdown_txs.len() != self.started.len() {
+            // 不是本管理器 start_all 的返回值，无法对应到任务，整体停机
+            warn!(
+                senders = shutdown_txs.len(),
+                started = self.started.len(),
+                "Shutdown senders do not match started tasks, stopping all at once"
+            );
+            self.stop_together(join_set, shutdown_txs).await;
+            return;
+        }
+
+        let levels = shutdown_levels(&self.started);
+        let mut shutdown_txs: Vec<_> = shutdown_txs.into_iter().map(Some).collect();
+        let mut finished = HashSet::new();
+
+        for level in 0..=levels.iter().copied().max().unwrap_or(0) {
+            let signalled_at = Instant::now();
+            let mut pending = Vec::new();
+            for (index, task) in self.started.iter().enumerate() {
+                if levels[index] != level {
+                    continue;
+                }
+                if let Some(tx) = shutdown_txs[index].take() {
+                    let _ = tx.send(());
+                }
+                if finished.contains(&task.id) {
+                    continue;
+                }
+                self.state_tracker
+                    .update_state(&task.name, TaskState::Stopping)
+                    .await;
+                pending.push((index, signalled_at + task.drain_timeout));
+            }
+            debug!(
+                level,
+                tasks = pending.len(),
+                "Shutdown signalled to dependency level"
+            );
+            self.drain(&mut join_set, pending, &mut finished).await;
+        }
+
+        // start_all 之外加入 JoinSet 的任务
+        join_set.abort_all();
+        while join_set.join_next().await.is_some() {}
+        info!("All tasks completed");
+    }
+
+    /// 等待 `pending` 中的任务退出；超过各自期限仍未退出的任务被中止
+    async fn drain(
+        &self,
+        join_set: &mut JoinSet<TaskResult>,
+        mut pending: Vec<(usize, Instant)>,
+        finished: &mut HashSet<Id>,
+    ) {
+        while let Some(deadline) = pending.iter().map(|(_, deadline)| *deadline).min() {
+            let joined = tokio::time::timeout_at(
+                tokio::time::Instant::from_std(deadline),
+                join_set.join_next_with_id(),
+            )
+            .await;
+            match joined {
+                Ok(Some(result)) => {
+                    let id = match &result {
+                        Ok((id, _)) => *id,
+                        Err(e) => e.id(),
+                    };
+                    if !finished.insert(id) {
+                        // 已中止的任务
+                        continue;
+                    }
+                    let name = self.started_name(id);
+                    match result {
+                        Ok((_, Ok(()))) => debug!(task_name = name, "Task completed gracefully"),
+                        Ok((_, Err(e))) => {
+                            warn!(task_name = name, error = %e, "Task completed with error")
+                        }
+                        Err(e) => warn!(task_name = name, error = %e, "Task join error"),
+                    }
+                    pending.retain(|(index, _)| self.started[*index].id != id);
+                }
+                Ok(None) => return,
+                Err(_) => {
+                    let now = Instant::now();
+                    let (expired, waiting): (Vec<_>, Vec<_>) = pending
+                        .into_iter()
+                        .partition(|(_, deadline)| *deadline <= now);
+                    pending = waiting;
+                    for (index, _) in expired {
+                        let task = &self.started[index];
+                        warn!(
+                            task_name = %task.name,
+                            drain_timeout_ms = task.drain_timeout.as_millis() as u64,
+                            "Task shutdown timeout, aborting"
+                        );
+                        task.abort.abort();
+                        finished.insert(task.id);
+                        self.state_tracker
+                            .update_state_with_error(
+                                &task.name,
+                                TaskState::Aborted,
+                                format!("drain timeout {:?} exceeded", task.drain_timeout),
+                            )
+                            .await;
+                        self.hooks
+                            .task_aborted(&task.name, task.drain_timeout)
+                            .await;
+                    }
+                }
+            }
+        }
+    }
+
+    fn started_name(&self, id: Id) -> &str {
+        self.started
+            .iter()
+            .find(|task| task.id == id)
+            .map_or("unknown", |task| task.name.as_str())
+    }
+
+    /// 同时通知所有任务，在 `shutdown_timeout` 内等待全部退出
+    async fn stop_together(
+        &self,
+        mut join_set: JoinSet<TaskResult>,
+        shutdown_txs: Vec<oneshot::Sender<()>>,
+    ) {
         // 1. 发送 shutdown 信号
         for tx in shutdown_txs {
             let _ = tx.send(());
@@ -288,6 +438,184 @@ impl TaskManager {
     }
 }
 
+/// 每个任务的停机层级：不被依赖的任务为 0，其余为依赖它的任务的最大层级加一
+fn shutdown_levels(started: &[StartedTask]) -> Vec<usize> {
+    let mut levels = vec![0; started.len()];
+    // started 为拓扑序，依赖方总在被依赖方之后
+    for index in (0..started.len()).rev() {
+        let level = started[index + 1..]
+            .iter()
+            .zip(&levels[index + 1..])
+            .filter(|(dependent, _)| dependent.dependencies.contains(&started[index].name))
+            .map(|(_, level)| level + 1)
+            .max()
+            .unwrap_or(0);
+        levels[index] = level;
+    }
+    levels
+}
+
+/// 运行任务，并在退出后按重启策略重新构建运行，直到停机、不再重启或重启次数用尽
+///
+/// 每次运行使用独立的 shutdown 通道，外部 shutdown 信号转发给当前运行的实例；
+/// 每次运行前后调用插件与中间件钩子，中间件拒绝启动视为本次运行失败。
+/// 任务首次进入 Running 时记录从 `started_at`（[TaskManager::start_all]）起的启动耗时。
+/// 只有重启次数用尽才上报运行时停机（`escalate` 或关键任务）；不再重启的任务失败只标记为 Failed
+async fn supervise(
+    task: Box<dyn Task>,
+    started_at: Instant,
+    mut shutdown_rx: oneshot::Receiver<()>,
+    state_tracker: Arc<StateTracker>,
+    escalation_tx: mpsc::UnboundedSender<String>,
+    hooks: Arc<TaskHooks>,
+) -> TaskResult {
+    let task_name = task.name().to_string();
+    let critical = task.is_critical();
+    let mut budget = RestartBudget::new(task.restart_strategy());
+    let mut current = task;
+    let mut first_start = Some(started_at);
+
+    loop {
+        // run 会消费任务，先为可能的重启构建下一个实例
+        let next = match budget.strategy().policy {
+            RestartPolicy::Never => None,
+            _ => current.respawn(),
+        };
+
+        let (result, stopping) = match hooks.before_run(&task_name).await {
+            Ok(()) => {
+                // 更新状态为 Running
+                state_tracker
+                    .update_state(&task_name, TaskState::Running)
+                    .await;
+                if let Some(started_at) = first_start.take() {
+                    hooks
+                        .record_task_startup(&task_name, started_at.elapsed())
+                        .await;
+                }
+
+                // 执行任务
+                let (run_tx, run_rx) = oneshot::channel();
+                let mut run = current.run(run_rx);
+                let finished = tokio::select! {
+                    result = &mut run => Some(result),
+                    _ = &mut shutdown_rx => None,
+                };
+                let (result, stopping) = match finished {
+                    Some(result) => (result, false),
+                    None => {
+                        let stop_started = Instant::now();
+                        let _ = run_tx.send(());
+                        let result = run.await;
+                        hooks
+                            .record_task_shutdown(&task_name, stop_started.elapsed())
+                            .await;
+                        (result, true)
+                    }
+                };
+                hooks.after_run(&task_name, &result).await;
+                (result, stopping)
+            }
+            Err(e) => {
+                warn!(task_name = %task_name, error = %e, "Middleware rejected task start");
+                (Err(e.into()), false)
+            }
+        };
+        if let Err(e) = &result {
+            hooks.task_failed(&task_name, &e.to_string()).await;
+        }
+
+        if stopping || !budget.strategy().policy.should_restart(result.is_err()) {
+            complete(&state_tracker, &task_name, &result).await;
+            return result;
+        }
+        let Some(task) = next else {
+            warn!(task_name = %task_name, "Task has a restart policy but cannot be respawned");
+            complete(&state_tracker, &task_name, &result).await;
+            return result;
+        };
+        let Some(backoff) = budget.next_restart(Instant::now()) else {
+            let strategy = budget.strategy();
+            error!(
+                task_name = %task_name,
+                max_restarts = strategy.max_restarts,
+                window_ms = strategy.window.as_millis() as u64,
+                "❌ Task restart budget exhausted"
+            );
+            let reason = match &result {
+                Ok(()) => "restart budget exhausted".to_string(),
+                Err(e) => format!("restart budget exhausted: {e}"),
+            };
+            state_tracker
+                .update_state_with_error(&task_name, TaskState::Failed, reason.clone())
+                .await;
+            if strategy.escalate || critical {
+                let _ = escalation_tx.send(task_name.clone());
+            }
+            return Err(reason.into());
+        };
+
+        // 更新状态为 Restarting，退避期间仍响应停机
+        match &result {
+            Ok(()) => {
+                info!(
+                    task_name = %task_name,
+                    backoff_ms = backoff.as_millis() as u64,
+                    "Task exited, restarting"
+                );
+                state_tracker
+                    .update_state(&task_name, TaskState::Restarting)
+                    .await;
+            }
+            Err(e) => {
+                warn!(
+                    task_name = %task_name,
+                    backoff_ms = backoff.as_millis() as u64,
+                    error = %e,
+                    "Task failed, restarting"
+                );
+                state_tracker
+                    .update_state_with_error(&task_name, TaskState::Restarting, e.to_string())
+                    .await;
+            }
+        }
+        let stopped = tokio::select! {
+            _ = tokio::time::sleep(backoff) => false,
+            _ = &mut shutdown_rx => true,
+        };
+        if stopped {
+            debug!(task_name = %task_name, "Shutdown during restart backoff");
+            state_tracker
+                .update_state(&task_name, TaskState::Stopped)
+                .await;
+            return Ok(());
+        }
+
+        state_tracker
+            .update_state(&task_name, TaskState::Starting)
+            .await;
+        current = task;
+    }
+}
+
+/// 任务不再重启时记录终态
+async fn complete(state_tracker: &StateTracker, task_name: &str, result: &TaskResult) {
+    match result {
+        Ok(_) => {
+            debug!(task_name = %task_name, "Task completed");
+            state_tracker
+                .update_state(task_name, TaskState::Stopped)
+                .await;
+        }
+        Err(e) => {
+            error!(task_name = %task_name, error = %e, "❌ Task failed");
+            state_tracker
+                .update_state_with_error(task_name, TaskState::Failed, e.to_string())
+                .await;
+        }
+    }
+}
+
 impl Default for TaskManager {
     fn default() -> Self {
         Self::new()
@@ -297,7 +625,8 @@ impl Default for TaskManager {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::task::SpawnTask;
+    use crate::task::{RestartStrategy, SpawnTask, SupervisedTask};
+    use std::sync::atomic::{AtomicU32, Ordering};
 
     #[tokio::test]
     async fn test_task_manager_new() {
@@ -344,4 +673,171 @@ mod tests {
         }
         while join_set.join_next().await.is_some() {}
     }
+
+    #[tokio::test]
+    async fn failed_task_is_restarted_with_backoff_until_it_stays_up() {
+        let attempts = Arc::new(AtomicU32::new(0));
+        let counter = attempts.clone();
+        let mut manager = TaskManager::new();
+        manager.add_task(Box::new(
+            SupervisedTask::new("consumer", move |shutdown_rx| {
+                let attempt = counter.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if attempt < 2 {
+                        return Err("broker unavailable".into());
+                    }
+                    let _ = shutdown_rx.await;
+                    Ok(())
+                }
+            })
+            .with_restart(
+                RestartStrategy::on_failure()
+                    .with_backoff(Duration::from_millis(1), Duration::from_millis(5)),
+            ),
+        ));
+        let tracker = manager.state_tracker();
+
+        let (mut join_set, shutdown_txs) = manager.start_all().await.expect("start_all");
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while attempts.load(Ordering::SeqCst) < 3 {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("task restarted");
+
+        let info = tracker.get_state("consumer").await.unwrap();
+        assert_eq!(info.restarts, 2);
+        for tx in shutdown_txs {
+            let _ = tx.send(());
+        }
+        assert!(join_set.join_next().await.unwrap().unwrap().is_ok());
+        assert_eq!(
+            attempts.load(Ordering::SeqCst),
+            3,
+            "no restart after shutdown"
+        );
+    }
+
+    #[tokio::test]
+    async fn exhausted_restart_budget_fails_task_and_escalates() {
+        let mut manager = TaskManager::new();
+        manager.add_task(Box::new(
+            SupervisedTask::new("worker", |_shutdown_rx| async { Err("crashed".into()) })
+                .with_restart(
+                    RestartStrategy::always()
+                        .with_backoff(Duration::from_millis(1), Duration::from_millis(1))
+                        .with_max_restarts(2, Duration::from_secs(60)),
+                ),
+        ));
+        let mut escalations = manager.take_escalations().unwrap();
+        let tracker = manager.state_tracker();
+
+        let (mut join_set, _shutdown_txs) = manager.start_all().await.expect("start_all");
+        let result = join_set.join_next().await.unwrap().unwrap();
+
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("restart budget exhausted")
+        );
+        assert_eq!(escalations.recv().await.as_deref(), Some("worker"));
+        let info = tracker.get_state("worker").await.unwrap();
+        assert_eq!(info.state, TaskState::Failed);
+        assert_eq!(info.restarts, 2);
+    }
+
+    #[tokio::test]
+    async fn failed_critical_task_without_restart_does_not_escalate() {
+        let mut manager = TaskManager::new();
+        manager.add_task(Box::new(
+            SupervisedTask::new("worker", |_shutdown_rx| async { Err("crashed".into()) })
+                .with_restart(RestartStrategy::never())
+                .with_critical(true),
+        ));
+        let mut escalations = manager.take_escalations().unwrap();
+        let tracker = manager.state_tracker();
+
+        let (mut join_set, _shutdown_txs) = manager.start_all().await.expect("start_all");
+        assert!(join_set.join_next().await.unwrap().unwrap().is_err());
+
+        assert!(escalations.try_recv().is_err());
+        let info = tracker.get_state("worker").await.unwrap();
+        assert_eq!(info.state, TaskState::Failed);
+    }
+
+    #[tokio::test]
+    async fn stop_all_signals_dependents_before_their_dependencies() {
+        let order = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut manager = TaskManager::new();
+        for (name, deps) in [("db", vec![]), ("mq", vec!["db"]), ("grpc", vec!["mq"])] {
+            let order = order.clone();
+            manager.add_task(Box::new(
+                SupervisedTask::new(name, move |shutdown_rx| {
+                    let order = order.clone();
+                    async move {
+                        let _ = shutdown_rx.await;
+                        order.lock().unwrap().push(format!("{name} signalled"));
+                        tokio::time::sleep(Duration::from_millis(5)).await;
+                        order.lock().unwrap().push(format!("{name} stopped"));
+                        Ok(())
+                    }
+                })
+                .with_dependencies(deps.into_iter().map(String::from).collect()),
+            ));
+        }
+
+        let (join_set, shutdown_txs) = manager.start_all().await.expect("start_all");
+        manager.stop_all(join_set, shutdown_txs).await;
+
+        assert_eq!(
+            *order.lock().unwrap(),
+            [
+                "grpc signalled",
+                "grpc stopped",
+                "mq signalled",
+                "mq stopped",
+                "db signalled",
+                "db stopped",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn task_exceeding_drain_timeout_is_aborted_and_shutdown_continues() {
+        let db_signalled = Arc::new(AtomicU32::new(0));
+        let signalled = db_signalled.clone();
+        let mut manager = TaskManager::new();
+        manager.add_task(Box::new(SupervisedTask::new("db", move |shutdown_rx| {
+            let signalled = signalled.clone();
+            async move {
+                let _ = shutdown_rx.await;
+                signalled.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        })));
+        manager.add_task(Box::new(
+            SupervisedTask::new("stuck", |_shutdown_rx| std::future::pending())
+                .with_dependencies(vec!["db".to_string()])
+                .with_drain_timeout(Duration::from_millis(20)),
+        ));
+        let tracker = manager.state_tracker();
+
+        let (join_set, shutdown_txs) = manager.start_all().await.expect("start_all");
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            manager.stop_all(join_set, shutdown_txs),
+        )
+        .await
+        .expect("stop_all bounded by drain timeout");
+
+        let stuck = tracker.get_state("stuck").await.unwrap();
+        assert_eq!(stuck.state, TaskState::Aborted);
+        assert_eq!(db_signalled.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            tracker.get_state("db").await.unwrap().state,
+            TaskState::Stopped
+        );
+    }
 }
@@ -2,12 +2,15 @@
 //!
 //! 提供统一的 Task 抽象，所有运行时管理的任务都必须实现此 trait
 
+pub(crate) mod hooks;
 mod manager;
 mod spawn;
 mod state;
+mod supervisor;
 mod r#trait;
 
 pub use manager::TaskManager;
 pub use spawn::SpawnTask;
 pub use state::TaskState;
+pub use supervisor::{RestartPolicy, RestartStrategy, SupervisedTask};
 pub use r#trait::{Task, TaskResult};
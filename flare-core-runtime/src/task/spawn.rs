@@ -5,6 +5,7 @@
 use super::{Task, TaskResult};
 use std::future::Future;
 use std::pin::Pin;
+use std::time::Duration;
 
 type ShutdownReceiver = tokio::sync::oneshot::Receiver<()>;
 type TaskFuture = Pin<Box<dyn Future<Output = TaskResult> + Send>>;
@@ -68,6 +69,8 @@ pub struct SpawnTask {
     priority: i32,
     /// 是否关键任务
     critical: bool,
+    /// 停机排空超时
+    drain_timeout: Option<Duration>,
     /// Future 构建函数
     ///
     /// 使用闭包延迟构建 Future，以便在 run 时传入 shutdown_rx
@@ -101,6 +104,7 @@ impl SpawnTask {
             dependencies: Vec::new(),
             priority: 0,
             critical: false,
+            drain_timeout: None,
             future_fn: Box::new(move |_shutdown_rx| Box::pin(future)),
         }
     }
@@ -135,6 +139,7 @@ impl SpawnTask {
             dependencies: Vec::new(),
             priority: 0,
             critical: false,
+            drain_timeout: None,
             future_fn: Box::new(move |shutdown_rx| Box::pin(future_fn(shutdown_rx))),
         }
     }
@@ -195,6 +200,24 @@ impl SpawnTask {
         self.critical = critical;
         self
     }
+
+    /// 设置停机时等待任务退出的上限
+    ///
+    /// 超时后任务被强制中止，并在状态追踪器中记录为 `Aborted`
+    ///
+    /// # 示例
+    ///
+    /// ```rust
+    /// use flare_core_runtime::task::SpawnTask;
+    /// use std::time::Duration;
+    ///
+    /// let task = SpawnTask::new("my-task", async { Ok(()) })
+    ///     .with_drain_timeout(Duration::from_secs(10));
+    /// ```
+    pub fn with_drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = Some(timeout);
+        self
+    }
 }
 
 impl Task for SpawnTask {
@@ -221,6 +244,10 @@ impl Task for SpawnTask {
     fn is_critical(&self) -> bool {
         self.critical
     }
+
+    fn drain_timeout(&self) -> Option<Duration> {
+        self.drain_timeout
+    }
 }
 
 #[cfg(test)]
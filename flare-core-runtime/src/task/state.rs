@@ -17,12 +17,19 @@ pub enum TaskState {
     Stopped,
     /// 失败
     Failed,
+    /// 已退出，等待按重启策略重新运行
+    Restarting,
+    /// 停机时超过排空时间被强制中止
+    Aborted,
 }
 
 impl TaskState {
-    /// 检查是否为终态（Stopped 或 Failed）
+    /// 检查是否为终态（Stopped、Failed 或 Aborted）
     pub fn is_terminal(&self) -> bool {
-        matches!(self, TaskState::Stopped | TaskState::Failed)
+        matches!(
+            self,
+            TaskState::Stopped | TaskState::Failed | TaskState::Aborted
+        )
     }
 
     /// 检查是否可以转换到目标状态
@@ -35,10 +42,20 @@ impl TaskState {
             (Starting, Running) | (Starting, Failed) => true,
             // Running 可以转换到 Stopping 或 Failed
             (Running, Stopping) | (Running, Failed) => true,
+            // 退出后等待重启
+            (Running, Restarting) => true,
+            // 中间件拒绝启动后等待重启
+            (Starting, Restarting) => true,
+            // Restarting 重新启动，或在退避期间停机/放弃重启
+            (Restarting, Starting) | (Restarting, Stopped) | (Restarting, Failed) => true,
+            (Restarting, Stopping) => true,
+            // 未结束的任务在停机超时后被强制中止
+            (Starting, Aborted) | (Running, Aborted) | (Stopping, Aborted) => true,
+            (Restarting, Aborted) => true,
             // Stopping 可以转换到 Stopped 或 Failed
             (Stopping, Stopped) | (Stopping, Failed) => true,
             // 终态不能转换
-            (Stopped, _) | (Failed, _) => false,
+            (Stopped, _) | (Failed, _) | (Aborted, _) => false,
             // 其他转换无效
             _ => false,
         }
@@ -54,6 +71,8 @@ impl fmt::Display for TaskState {
             TaskState::Stopping => write!(f, "Stopping"),
             TaskState::Stopped => write!(f, "Stopped"),
             TaskState::Failed => write!(f, "Failed"),
+            TaskState::Restarting => write!(f, "Restarting"),
+            TaskState::Aborted => write!(f, "Aborted"),
         }
     }
 }
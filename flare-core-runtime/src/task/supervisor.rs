@@ -0,0 +1,338 @@
+//! 任务重启策略与可重启任务
+//!
+//! [TaskManager](super::TaskManager) 按 [Task::restart_strategy] 监督任务：退出后按策略重新构建并运行，
+//! 重启之间指数退避；时间窗口内重启次数用尽时任务标记为 `Failed`，并按配置触发运行时停机。
+
+use super::{Task, TaskResult};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+type ShutdownReceiver = tokio::sync::oneshot::Receiver<()>;
+type TaskFuture = Pin<Box<dyn Future<Output = TaskResult> + Send>>;
+type TaskFutureFactory = Arc<dyn Fn(ShutdownReceiver) -> TaskFuture + Send + Sync + 'static>;
+
+/// 任务退出后是否重启
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RestartPolicy {
+    /// 不重启
+    #[default]
+    Never,
+    /// 返回错误时重启
+    OnFailure,
+    /// 无论成功或失败都重启（收到停机信号时除外）
+    Always,
+}
+
+impl RestartPolicy {
+    /// 给定退出结果是否应重启
+    pub fn should_restart(&self, failed: bool) -> bool {
+        match self {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure => failed,
+            RestartPolicy::Always => true,
+        }
+    }
+}
+
+/// 重启策略：重启条件、指数退避与时间窗口内的重启上限
+#[derive(Debug, Clone)]
+pub struct RestartStrategy {
+    /// 重启条件
+    pub policy: RestartPolicy,
+    /// 首次重启前的等待（默认 1 秒）
+    pub initial_backoff: Duration,
+    /// 退避上限（默认 60 秒）
+    pub max_backoff: Duration,
+    /// 每次连续重启的退避倍数（默认 2.0）
+    pub multiplier: f64,
+    /// `window` 内允许的最大重启次数（默认 5 次）
+    pub max_restarts: u32,
+    /// 统计重启次数的时间窗口（默认 60 秒）
+    pub window: Duration,
+    /// 重启次数用尽时是否触发运行时停机（默认 true）
+    pub escalate: bool,
+}
+
+impl Default for RestartStrategy {
+    fn default() -> Self {
+        Self {
+            policy: RestartPolicy::Never,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            multiplier: 2.0,
+            max_restarts: 5,
+            window: Duration::from_secs(60),
+            escalate: true,
+        }
+    }
+}
+
+impl RestartStrategy {
+    /// 不重启
+    pub fn never() -> Self {
+        Self::default()
+    }
+
+    /// 失败时重启
+    pub fn on_failure() -> Self {
+        Self::default().with_policy(RestartPolicy::OnFailure)
+    }
+
+    /// 退出即重启
+    pub fn always() -> Self {
+        Self::default().with_policy(RestartPolicy::Always)
+    }
+
+    /// 设置重启条件
+    pub fn with_policy(mut self, policy: RestartPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// 设置首次退避与退避上限
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// 设置退避倍数
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// 设置 `window` 内的最大重启次数
+    pub fn with_max_restarts(mut self, max_restarts: u32, window: Duration) -> Self {
+        self.max_restarts = max_restarts;
+        self.window = window;
+        self
+    }
+
+    /// 设置重启次数用尽时是否触发运行时停机
+    pub fn with_escalation(mut self, escalate: bool) -> Self {
+        self.escalate = escalate;
+        self
+    }
+
+    /// 第 `attempt` 次连续重启（从 0 开始）前的等待
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self
+            .multiplier
+            .max(1.0)
+            .powi(attempt.min(i32::MAX as u32) as i32);
+        Duration::try_from_secs_f64(self.initial_backoff.as_secs_f64() * factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+}
+
+/// 时间窗口内的重启记录
+#[derive(Debug)]
+pub(super) struct RestartBudget {
+    strategy: RestartStrategy,
+    restarts: VecDeque<Instant>,
+}
+
+impl RestartBudget {
+    pub(super) fn new(strategy: RestartStrategy) -> Self {
+        Self {
+            strategy,
+            restarts: VecDeque::new(),
+        }
+    }
+
+    pub(super) fn strategy(&self) -> &RestartStrategy {
+        &self.strategy
+    }
+
+    /// 记录一次重启并返回退避时间；窗口内次数已用尽时返回 `None`
+    pub(super) fn next_restart(&mut self, now: Instant) -> Option<Duration> {
+        while let Some(oldest) = self.restarts.front() {
+            if now.duration_since(*oldest) < self.strategy.window {
+                break;
+            }
+            self.restarts.pop_front();
+        }
+        if self.restarts.len() >= self.strategy.max_restarts as usize {
+            return None;
+        }
+        let backoff = self.strategy.backoff(self.restarts.len() as u32);
+        self.restarts.push_back(now);
+        Some(backoff)
+    }
+}
+
+/// 可重启任务
+///
+/// 与 [SpawnTask](super::SpawnTask) 相同，但保存的是可重复调用的 Future 构建函数，
+/// 每次（重新）运行都会用新的 shutdown 接收器构建新的 Future。
+///
+/// # 示例
+///
+/// ```rust
+/// use flare_core_runtime::task::{RestartStrategy, SupervisedTask};
+/// use std::time::Duration;
+///
+/// let task = SupervisedTask::new("order-consumer", |shutdown_rx| async move {
+///     let _ = shutdown_rx.await;
+///     Ok(())
+/// })
+/// .with_restart(
+///     RestartStrategy::on_failure()
+///         .with_backoff(Duration::from_millis(500), Duration::from_secs(30))
+///         .with_max_restarts(10, Duration::from_secs(300)),
+/// );
+/// ```
+pub struct SupervisedTask {
+    /// 任务名称
+    name: String,
+    /// 任务依赖
+    dependencies: Vec<String>,
+    /// 任务优先级
+    priority: i32,
+    /// 是否关键任务
+    critical: bool,
+    /// 停机排空超时
+    drain_timeout: Option<Duration>,
+    /// 重启策略
+    restart: RestartStrategy,
+    /// Future 构建函数，每次运行调用一次
+    factory: TaskFutureFactory,
+}
+
+impl SupervisedTask {
+    /// 创建可重启任务，默认失败时重启（[RestartStrategy::on_failure]）
+    pub fn new<F, Fut>(name: impl Into<String>, factory: F) -> Self
+    where
+        F: Fn(ShutdownReceiver) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = TaskResult> + Send + 'static,
+    {
+        Self {
+            name: name.into(),
+            dependencies: Vec::new(),
+            priority: 0,
+            critical: false,
+            drain_timeout: None,
+            restart: RestartStrategy::on_failure(),
+            factory: Arc::new(move |shutdown_rx| Box::pin(factory(shutdown_rx))),
+        }
+    }
+
+    /// 设置任务依赖
+    pub fn with_dependencies(mut self, deps: Vec<String>) -> Self {
+        self.dependencies = deps;
+        self
+    }
+
+    /// 设置任务优先级
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// 设置是否为关键任务
+    pub fn with_critical(mut self, critical: bool) -> Self {
+        self.critical = critical;
+        self
+    }
+
+    /// 设置停机时等待任务退出的上限
+    pub fn with_drain_timeout(mut self, timeout: Duration) -> Self {
+        self.drain_timeout = Some(timeout);
+        self
+    }
+
+    /// 设置重启策略
+    pub fn with_restart(mut self, restart: RestartStrategy) -> Self {
+        self.restart = restart;
+        self
+    }
+}
+
+impl Task for SupervisedTask {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn dependencies(&self) -> Vec<String> {
+        self.dependencies.clone()
+    }
+
+    fn run(self: Box<Self>, shutdown_rx: ShutdownReceiver) -> TaskFuture {
+        (self.factory)(shutdown_rx)
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn is_critical(&self) -> bool {
+        self.critical
+    }
+
+    fn drain_timeout(&self) -> Option<Duration> {
+        self.drain_timeout
+    }
+
+    fn restart_strategy(&self) -> RestartStrategy {
+        self.restart.clone()
+    }
+
+    fn respawn(&self) -> Option<Box<dyn Task>> {
+        Some(Box::new(Self {
+            name: self.name.clone(),
+            dependencies: self.dependencies.clone(),
+            priority: self.priority,
+            critical: self.critical,
+            drain_timeout: self.drain_timeout,
+            restart: self.restart.clone(),
+            factory: Arc::clone(&self.factory),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_max() {
+        let strategy = RestartStrategy::on_failure()
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(500));
+
+        assert_eq!(strategy.backoff(0), Duration::from_millis(100));
+        assert_eq!(strategy.backoff(1), Duration::from_millis(200));
+        assert_eq!(strategy.backoff(2), Duration::from_millis(400));
+        assert_eq!(strategy.backoff(3), Duration::from_millis(500));
+        assert_eq!(strategy.backoff(u32::MAX), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn budget_is_exhausted_within_window_and_recovers_after_it() {
+        let mut budget = RestartBudget::new(
+            RestartStrategy::always()
+                .with_backoff(Duration::from_millis(10), Duration::from_secs(1))
+                .with_max_restarts(2, Duration::from_secs(60)),
+        );
+        let start = Instant::now();
+
+        assert_eq!(budget.next_restart(start), Some(Duration::from_millis(10)));
+        assert_eq!(budget.next_restart(start), Some(Duration::from_millis(20)));
+        assert_eq!(budget.next_restart(start), None);
+
+        let later = start + Duration::from_secs(61);
+        assert_eq!(budget.next_restart(later), Some(Duration::from_millis(10)));
+    }
+
+    #[test]
+    fn policy_decides_restart_by_exit_result() {
+        assert!(!RestartPolicy::Never.should_restart(true));
+        assert!(RestartPolicy::OnFailure.should_restart(true));
+        assert!(!RestartPolicy::OnFailure.should_restart(false));
+        assert!(RestartPolicy::Always.should_restart(false));
+    }
+}
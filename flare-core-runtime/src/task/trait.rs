@@ -3,8 +3,10 @@
 //! 任务抽象是运行时的核心扩展点，所有需要在运行时中管理的任务都必须实现此 trait
 
 use super::state::TaskState;
+use super::supervisor::RestartStrategy;
 use std::future::Future;
 use std::pin::Pin;
+use std::time::Duration;
 
 /// 任务执行结果
 pub type TaskResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
@@ -78,7 +80,7 @@ pub trait Task: Send {
     /// # 实现要求
     ///
     /// - 必须响应 shutdown_rx 信号，实现优雅停机
-    /// - 错误时返回 `Err`，运行时会根据 `is_critical()` 决定是否停机
+    /// - 错误时返回 `Err`，运行时按 [Task::restart_strategy] 决定是否重启
     fn run(
         self: Box<Self>,
         shutdown_rx: tokio::sync::oneshot::Receiver<()>,
@@ -106,7 +108,9 @@ pub trait Task: Send {
         0
     }
 
-    /// 是否关键任务 (失败时触发运行时停机)
+    /// 是否关键任务 (重启次数用尽时触发运行时停机，不受 `with_escalation(false)` 影响)
+    ///
+    /// 不重启的任务（[RestartPolicy::Never](super::RestartPolicy::Never)）失败只标记为 Failed，不会停机
     ///
     /// # 默认实现
     ///
@@ -123,4 +127,33 @@ pub trait Task: Send {
     fn initial_state(&self) -> TaskState {
         TaskState::Pending
     }
+
+    /// 停机时等待任务退出的上限，超时后任务被强制中止
+    ///
+    /// # 默认实现
+    ///
+    /// 返回 `None`（使用 `RuntimeConfig::shutdown_timeout`）
+    fn drain_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// 退出后的重启策略
+    ///
+    /// 只有 [Self::respawn] 能构建新实例的任务才会被重启
+    ///
+    /// # 默认实现
+    ///
+    /// 返回 [RestartStrategy::never]
+    fn restart_strategy(&self) -> RestartStrategy {
+        RestartStrategy::never()
+    }
+
+    /// 为下一次运行构建新实例（`run` 会消费 `self`）
+    ///
+    /// # 默认实现
+    ///
+    /// 返回 `None`（不可重启）
+    fn respawn(&self) -> Option<Box<dyn Task>> {
+        None
+    }
 }
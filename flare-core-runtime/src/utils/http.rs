@@ -0,0 +1,511 @@
+//! 运行时内置 HTTP 端点使用的最小 HTTP/1.1 服务（基于 hyper）
+//!
+//! 只处理方法、路径、查询参数与请求头，不读取请求体；每个连接处理一个请求后关闭。
+//! 响应体可以是完整文本，也可以是逐段写出的流（用于 SSE）。
+//! 同时处理的连接数有上限，accept 出错（如文件描述符耗尽）时退避后继续。
+//! 停机时不再接受连接，流式响应立即结束，其余在途请求最多等待 [DRAIN_TIMEOUT]。
+//! 供指标导出等运维端点使用，业务 HTTP 服务请使用 `flare-core-transport`。
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use http_body_util::combinators::UnsyncBoxBody;
+use http_body_util::{BodyExt, Full, StreamBody};
+use hyper::body::{Frame, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper_util::rt::{TokioIo, TokioTimer};
+use std::convert::Infallible;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{Semaphore, oneshot, watch};
+use tokio::task::JoinSet;
+use tracing::{debug, warn};
+
+/// 请求头上限
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+/// 读取请求头的超时
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// 停机后等待在途请求完成的上限
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+/// 同时处理的连接上限，达到后暂停 accept
+const MAX_CONNECTIONS: usize = 256;
+/// accept 失败后的退避时间
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// 解析后的请求
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct HttpRequest {
+    pub(crate) method: String,
+    pub(crate) path: String,
+    pub(crate) query: Vec<(String, String)>,
+    /// 请求头，名称已转为小写
+    pub(crate) headers: Vec<(String, String)>,
+}
+
+impl HttpRequest {
+    /// 查询参数
+    pub(crate) fn query(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// 请求头（名称不区分大小写）
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn from_hyper<B>(request: &http::Request<B>) -> Self {
+        let query = request
+            .uri()
+            .query()
+            .unwrap_or_default()
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (percent_decode(key), percent_decode(value))
+            })
+            .collect();
+        let headers = request
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.as_str().to_string(),
+                    String::from_utf8_lossy(value.as_bytes()).trim().to_string(),
+                )
+            })
+            .collect();
+        Self {
+            method: request.method().as_str().to_string(),
+            path: request.uri().path().to_string(),
+            query,
+            headers,
+        }
+    }
+}
+
+type BodyStream = Pin<Box<dyn Stream<Item = String> + Send>>;
+type ResponseBody = UnsyncBoxBody<Bytes, Infallible>;
+
+enum Body {
+    Full(String),
+    Stream(BodyStream),
+}
+
+/// 响应
+pub(crate) struct HttpResponse {
+    status: u16,
+    content_type: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: Body,
+}
+
+impl HttpResponse {
+    pub(crate) fn new(status: u16, content_type: &'static str, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type,
+            headers: Vec::new(),
+            body: Body::Full(body.into()),
+        }
+    }
+
+    /// 流式响应：逐段写出，流结束、客户端断开或停机时结束
+    pub(crate) fn stream(
+        content_type: &'static str,
+        body: impl Stream<Item = String> + Send + 'static,
+    ) -> Self {
+        Self {
+            status: 200,
+            content_type,
+            headers: Vec::new(),
+            body: Body::Stream(Box::pin(body)),
+        }
+    }
+
+    /// 追加响应头
+    pub(crate) fn with_header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    pub(crate) fn json(status: u16, body: impl Into<String>) -> Self {
+        Self::new(status, "application/json", body)
+    }
+
+    pub(crate) fn text(status: u16, body: impl Into<String>) -> Self {
+        Self::new(status, "text/plain; charset=utf-8", body)
+    }
+
+    pub(crate) fn not_found() -> Self {
+        Self::text(404, "not found\n")
+    }
+
+    /// 转为 hyper 响应；流式响应在 `closing` 变为 true 时结束
+    fn into_hyper(self, mut closing: watch::Receiver<bool>) -> http::Response<ResponseBody> {
+        let mut builder = http::Response::builder()
+            .status(self.status)
+            .header(CONTENT_TYPE, self.content_type);
+        for (name, value) in self.headers {
+            builder = builder.header(name, value);
+        }
+        let body = match self.body {
+            Body::Full(body) => Full::new(Bytes::from(body)).boxed_unsync(),
+            Body::Stream(body) => {
+                builder = builder.header(CACHE_CONTROL, "no-cache");
+                let closed = async move {
+                    let _ = closing.wait_for(|closing| *closing).await;
+                };
+                StreamBody::new(
+                    body.take_until(closed)
+                        .map(|chunk| Ok(Frame::data(Bytes::from(chunk)))),
+                )
+                .boxed_unsync()
+            }
+        };
+        builder.body(body).unwrap_or_else(|e| {
+            warn!(error = %e, "Invalid HTTP response, replying 500");
+            let mut response = http::Response::new(
+                Full::new(Bytes::from_static(b"internal server error\n")).boxed_unsync(),
+            );
+            *response.status_mut() = http::StatusCode::INTERNAL_SERVER_ERROR;
+            response
+        })
+    }
+}
+
+/// 接受连接直到收到 shutdown 信号，每个连接在独立任务中处理；返回前排空在途连接
+pub(crate) async fn serve<H, Fut>(
+    listener: TcpListener,
+    handler: H,
+    shutdown_rx: oneshot::Receiver<()>,
+) -> io::Result<()>
+where
+    H: Fn(HttpRequest) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = HttpResponse> + Send + 'static,
+{
+    serve_with_limit(listener, handler, shutdown_rx, MAX_CONNECTIONS).await
+}
+
+async fn serve_with_limit<H, Fut>(
+    listener: TcpListener,
+    handler: H,
+    mut shutdown_rx: oneshot::Receiver<()>,
+    max_connections: usize,
+) -> io::Result<()>
+where
+    H: Fn(HttpRequest) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = HttpResponse> + Send + 'static,
+{
+    let (closing_tx, closing_rx) = watch::channel(false);
+    let limit = Arc::new(Semaphore::new(max_connections.max(1)));
+    let mut connections = JoinSet::new();
+    loop {
+        // 连接数达到上限时等已有连接结束，新连接留在内核 backlog 中
+        let permit = tokio::select! {
+            permit = limit.clone().acquire_owned() => {
+                permit.expect("connection semaphore is never closed")
+            }
+            _ = &mut shutdown_rx => break,
+        };
+        let (stream, peer) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    // EMFILE、ENFILE、ECONNABORTED 等错误不终止服务，退避后继续
+                    warn!(error = %e, "Failed to accept HTTP connection, backing off");
+                    tokio::select! {
+                        _ = tokio::time::sleep(ACCEPT_BACKOFF) => continue,
+                        _ = &mut shutdown_rx => break,
+                    }
+                }
+            },
+            Some(_) = connections.join_next(), if !connections.is_empty() => continue,
+            _ = &mut shutdown_rx => break,
+        };
+        let handler = handler.clone();
+        let closing = closing_rx.clone();
+        connections.spawn(async move {
+            if let Err(e) = handle_connection(stream, handler, closing).await {
+                debug!(peer = %peer, error = %e, "HTTP connection failed");
+            }
+            drop(permit);
+        });
+    }
+    drop(listener);
+
+    let _ = closing_tx.send(true);
+    let drained = tokio::time::timeout(DRAIN_TIMEOUT, async {
+        while connections.join_next().await.is_some() {}
+    })
+    .await;
+    if drained.is_err() {
+        debug!(
+            connections = connections.len(),
+            "HTTP connections still open after drain timeout, aborting"
+        );
+        connections.shutdown().await;
+    }
+    Ok(())
+}
+
+async fn handle_connection<H, Fut>(
+    stream: TcpStream,
+    handler: H,
+    mut closing: watch::Receiver<bool>,
+) -> hyper::Result<()>
+where
+    H: Fn(HttpRequest) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = HttpResponse> + Send + 'static,
+{
+    let response_closing = closing.clone();
+    let service = service_fn(move |request: http::Request<Incoming>| {
+        let handler = handler.clone();
+        let closing = response_closing.clone();
+        async move {
+            let response = handler(HttpRequest::from_hyper(&request)).await;
+            Ok::<_, Infallible>(response.into_hyper(closing))
+        }
+    });
+    let connection = http1::Builder::new()
+        .timer(TokioTimer::new())
+        .header_read_timeout(READ_TIMEOUT)
+        .max_buf_size(MAX_HEAD_BYTES)
+        .keep_alive(false)
+        .serve_connection(TokioIo::new(stream), service);
+    tokio::pin!(connection);
+
+    tokio::select! {
+        result = connection.as_mut() => return result,
+        _ = closing.wait_for(|closing| *closing) => {}
+    }
+    // 停机：尚未收到请求的连接直接关闭，在途请求写完响应后关闭
+    connection.as_mut().graceful_shutdown();
+    connection.await
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                (Some(high), Some(low)) => {
+                    decoded.push((high << 4) | low);
+                    i += 2;
+                }
+                _ => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn hex(digit: u8) -> Option<u8> {
+    (digit as char).to_digit(16).map(|value| value as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn request(method: &str, target: &str, headers: &[(&str, &str)]) -> HttpRequest {
+        let mut builder = http::Request::builder().method(method).uri(target);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        HttpRequest::from_hyper(&builder.body(()).unwrap())
+    }
+
+    #[test]
+    fn parses_request_line_and_query() {
+        let request = request("POST", "/consumers/orders/pause?topic=a%2Fb&all", &[]);
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/consumers/orders/pause");
+        assert_eq!(request.query("topic"), Some("a/b"));
+        assert_eq!(request.query("all"), Some(""));
+        assert_eq!(request.query("missing"), None);
+    }
+
+    #[test]
+    fn parses_headers_case_insensitively() {
+        let request = request(
+            "GET",
+            "/tasks",
+            &[("Host", "test"), ("Authorization", "Bearer s3cret ")],
+        );
+
+        assert_eq!(request.header("authorization"), Some("Bearer s3cret"));
+        assert_eq!(request.header("HOST"), Some("test"));
+        assert_eq!(request.header("x-missing"), None);
+    }
+
+    #[tokio::test]
+    async fn serves_until_shutdown() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let server = tokio::spawn(serve(
+            listener,
+            |request: HttpRequest| async move { HttpResponse::text(200, request.path) },
+            shutdown_rx,
+        ));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /hello HTTP/1.1\r\nHost: test\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\n/hello"));
+        shutdown_tx.send(()).unwrap();
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn streams_chunks_until_body_ends() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (_shutdown_tx, shutdown_rx) = oneshot::channel();
+        tokio::spawn(serve(
+            listener,
+            |_request: HttpRequest| async {
+                HttpResponse::stream(
+                    "text/event-stream",
+                    futures::stream::iter(["data: 1\n\n".to_string(), "data: 2\n\n".to_string()]),
+                )
+            },
+            shutdown_rx,
+        ));
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /events HTTP/1.1\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.contains("content-type: text/event-stream\r\n"));
+        assert!(response.contains("transfer-encoding: chunked\r\n"));
+        assert!(!response.contains("content-length"));
+        assert!(response.contains("data: 1\n\n"));
+        assert!(response.contains("data: 2\n\n"));
+        assert!(response.ends_with("0\r\n\r\n"));
+    }
+
+    #[tokio::test]
+    async fn shutdown_waits_for_in_flight_requests_and_ends_streams() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let (entered_tx, mut entered_rx) = tokio::sync::mpsc::unbounded_channel();
+        let server = tokio::spawn(serve(
+            listener,
+            move |request: HttpRequest| {
+                let entered_tx = entered_tx.clone();
+                async move {
+                    let _ = entered_tx.send(());
+                    if request.path == "/events" {
+                        let first = futures::stream::iter(["data: 1\n\n".to_string()]);
+                        return HttpResponse::stream(
+                            "text/event-stream",
+                            first.chain(futures::stream::pending()),
+                        );
+                    }
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    HttpResponse::text(200, "done")
+                }
+            },
+            shutdown_rx,
+        ));
+
+        let mut events = TcpStream::connect(addr).await.unwrap();
+        events
+            .write_all(b"GET /events HTTP/1.1\r\n\r\n")
+            .await
+            .unwrap();
+        let mut slow = TcpStream::connect(addr).await.unwrap();
+        slow.write_all(b"GET /slow HTTP/1.1\r\n\r\n").await.unwrap();
+        // 尚未发出请求的空闲连接不应拖慢停机
+        let mut idle = TcpStream::connect(addr).await.unwrap();
+        entered_rx.recv().await.unwrap();
+        entered_rx.recv().await.unwrap();
+        let mut head = Vec::new();
+        let mut buf = [0u8; 256];
+        while !String::from_utf8_lossy(&head).contains("data: 1") {
+            let read = events.read(&mut buf).await.unwrap();
+            head.extend_from_slice(&buf[..read]);
+        }
+
+        shutdown_tx.send(()).unwrap();
+        tokio::time::timeout(Duration::from_secs(1), server)
+            .await
+            .expect("server drained before the drain timeout")
+            .unwrap()
+            .unwrap();
+
+        let mut response = String::new();
+        slow.read_to_string(&mut response).await.unwrap();
+        assert!(response.ends_with("\r\n\r\ndone"));
+        // 流式响应在停机时以结束块收尾，连接随之关闭
+        events.read_to_end(&mut head).await.unwrap();
+        assert!(String::from_utf8_lossy(&head).ends_with("0\r\n\r\n"));
+        let mut rest = Vec::new();
+        idle.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+    }
+
+    #[tokio::test]
+    async fn connections_beyond_the_limit_wait_for_a_free_slot() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (_shutdown_tx, shutdown_rx) = oneshot::channel();
+        tokio::spawn(serve_with_limit(
+            listener,
+            |_request: HttpRequest| async { HttpResponse::text(200, "ok") },
+            shutdown_rx,
+            1,
+        ));
+
+        // 第一个连接占住唯一的名额
+        let first = TcpStream::connect(addr).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let mut second = TcpStream::connect(addr).await.unwrap();
+        second
+            .write_all(b"GET / HTTP/1.1\r\nHost: test\r\n\r\n")
+            .await
+            .unwrap();
+        let mut buf = [0u8; 64];
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), second.read(&mut buf))
+                .await
+                .is_err()
+        );
+
+        drop(first);
+        let mut response = String::new();
+        second.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+}
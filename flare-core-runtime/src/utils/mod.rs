@@ -1,7 +1,8 @@
 //! 工具函数模块
 //!
-//! 提供拓扑排序、就绪检查等工具函数
+//! 提供拓扑排序、就绪检查、内置 HTTP 端点等工具函数
 
+pub(crate) mod http;
 mod topology;
 
 pub use topology::topological_sort;
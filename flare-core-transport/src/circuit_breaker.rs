@@ -0,0 +1,501 @@
+//! 熔断器
+//!
+//! 按目标（实例 ID 或服务名）统计调用结果，故障目标短时间内直接拒绝，避免请求持续打到已经不可用的下游：
+//! - Closed：正常放行，连续失败次数或窗口内错误率超过阈值时转为 Open；
+//! - Open：拒绝全部请求，`open_duration` 后转为 HalfOpen；
+//! - HalfOpen：放行少量探测请求，全部成功后转为 Closed，任一失败重新 Open。
+//!
+//! 失败分类与重试策略一致（见 [`is_retryable_code`]）：Unavailable、DeadlineExceeded、ResourceExhausted
+//! 及传输层错误计为实例故障，业务错误（如 InvalidArgument）不影响熔断。
+//! 状态切换通过 tracing 输出（target 为本模块）。
+//!
+//! `grpc` 与 `discovery` 共用本模块：前者提供单个 Channel 的 `CircuitBreakerLayer`，
+//! 后者由 `ServiceDiscover` 按实例维护熔断器并在选择实例时跳过熔断中的实例。
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+
+use http::{Response as HttpResponse, StatusCode};
+use tonic::Status;
+use tracing::{info, warn};
+
+use crate::status::is_retryable_code;
+
+/// 从 HTTP 响应头推断失败调用的 gRPC 状态
+///
+/// - 仅含 trailers 的响应（服务端直接返回错误）会在响应头中携带 `grpc-status`；
+/// - 网关返回的 HTTP 错误按 gRPC 规范映射：429/502/503/504 视为 Unavailable。
+///
+/// 成功、或状态位于 trailers 中（响应体已开始）时返回 None。
+pub fn status_from_response<B>(response: &HttpResponse<B>) -> Option<Status> {
+    if let Some(status) = Status::from_header_map(response.headers()) {
+        return (status.code() != tonic::Code::Ok).then_some(status);
+    }
+    match response.status() {
+        StatusCode::TOO_MANY_REQUESTS
+        | StatusCode::BAD_GATEWAY
+        | StatusCode::SERVICE_UNAVAILABLE
+        | StatusCode::GATEWAY_TIMEOUT => Some(Status::unavailable(format!(
+            "http status {}",
+            response.status()
+        ))),
+        _ => None,
+    }
+}
+
+/// 熔断配置
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerConfig {
+    /// 连续失败达到该次数后熔断（0 表示不按连续失败熔断）
+    pub consecutive_failures: u32,
+    /// 窗口内错误率阈值（0~1）
+    pub error_rate_threshold: f64,
+    /// 窗口内请求数达到该值后才按错误率判断
+    pub min_requests: u32,
+    /// 错误率统计窗口
+    pub window: Duration,
+    /// Open 状态持续时长，之后进入 HalfOpen
+    pub open_duration: Duration,
+    /// HalfOpen 状态允许的探测请求数，全部成功后恢复
+    pub half_open_max_requests: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            consecutive_failures: 5,
+            error_rate_threshold: 0.5,
+            min_requests: 20,
+            window: Duration::from_secs(10),
+            open_duration: Duration::from_secs(30),
+            half_open_max_requests: 1,
+        }
+    }
+}
+
+/// 熔断器状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl fmt::Display for CircuitState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half_open",
+        })
+    }
+}
+
+#[derive(Debug)]
+struct BreakerState {
+    state: CircuitState,
+    consecutive_failures: u32,
+    window_start: Instant,
+    window_requests: u32,
+    window_failures: u32,
+    opened_at: Instant,
+    half_open_in_flight: u32,
+    half_open_successes: u32,
+}
+
+/// 单个目标的熔断器
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    name: String,
+    config: CircuitBreakerConfig,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    /// 创建熔断器，`name` 用于日志（通常为实例 ID 或服务名）
+    pub fn new(name: impl Into<String>, config: CircuitBreakerConfig) -> Self {
+        let now = Instant::now();
+        Self {
+            name: name.into(),
+            config,
+            state: Mutex::new(BreakerState {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                window_start: now,
+                window_requests: 0,
+                window_failures: 0,
+                opened_at: now,
+                half_open_in_flight: 0,
+                half_open_successes: 0,
+            }),
+        }
+    }
+
+    /// 熔断器名称
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 当前状态（Open 超过 `open_duration` 时返回 HalfOpen）
+    pub fn state(&self) -> CircuitState {
+        let mut state = self.lock();
+        self.maybe_half_open(&mut state);
+        state.state
+    }
+
+    /// 是否处于 Open 状态（拒绝所有请求）
+    pub fn is_open(&self) -> bool {
+        self.state() == CircuitState::Open
+    }
+
+    /// 申请一次调用许可，被熔断时返回 None
+    ///
+    /// 调用结束后通过 [`CircuitPermit::success`] / [`CircuitPermit::failure`] 上报结果；
+    /// 许可未上报直接丢弃时不计入统计。
+    pub fn try_acquire(self: &Arc<Self>) -> Option<CircuitPermit> {
+        let mut state = self.lock();
+        self.maybe_half_open(&mut state);
+        match state.state {
+            CircuitState::Closed => Some(CircuitPermit::new(self.clone(), false)),
+            CircuitState::Open => None,
+            CircuitState::HalfOpen => {
+                if state.half_open_in_flight + state.half_open_successes
+                    >= self.config.half_open_max_requests.max(1)
+                {
+                    return None;
+                }
+                state.half_open_in_flight += 1;
+                Some(CircuitPermit::new(self.clone(), true))
+            }
+        }
+    }
+
+    /// 按 gRPC 状态上报一次调用结果（不经过许可，用于外部统计）
+    pub fn record_status(&self, status: &Status) {
+        self.record(false, !is_retryable_code(status.code()));
+    }
+
+    fn record(&self, probe: bool, success: bool) {
+        let mut state = self.lock();
+        if probe {
+            state.half_open_in_flight = state.half_open_in_flight.saturating_sub(1);
+            if state.state != CircuitState::HalfOpen {
+                return;
+            }
+            if success {
+                state.half_open_successes += 1;
+                if state.half_open_successes >= self.config.half_open_max_requests.max(1) {
+                    self.transition(&mut state, CircuitState::Closed);
+                }
+            } else {
+                self.transition(&mut state, CircuitState::Open);
+            }
+            return;
+        }
+
+        // 熔断前发出的请求在熔断后返回，不再影响状态
+        if state.state != CircuitState::Closed {
+            return;
+        }
+
+        let now = Instant::now();
+        if now.duration_since(state.window_start) >= self.config.window {
+            state.window_start = now;
+            state.window_requests = 0;
+            state.window_failures = 0;
+        }
+        state.window_requests += 1;
+        if success {
+            state.consecutive_failures = 0;
+            return;
+        }
+        state.window_failures += 1;
+        state.consecutive_failures += 1;
+
+        let consecutive_tripped = self.config.consecutive_failures > 0
+            && state.consecutive_failures >= self.config.consecutive_failures;
+        let rate_tripped = state.window_requests >= self.config.min_requests.max(1)
+            && f64::from(state.window_failures) / f64::from(state.window_requests)
+                >= self.config.error_rate_threshold;
+        if consecutive_tripped || rate_tripped {
+            self.transition(&mut state, CircuitState::Open);
+        }
+    }
+
+    fn release_probe(&self) {
+        let mut state = self.lock();
+        state.half_open_in_flight = state.half_open_in_flight.saturating_sub(1);
+    }
+
+    fn maybe_half_open(&self, state: &mut BreakerState) {
+        if state.state == CircuitState::Open
+            && state.opened_at.elapsed() >= self.config.open_duration
+        {
+            self.transition(state, CircuitState::HalfOpen);
+        }
+    }
+
+    fn transition(&self, state: &mut BreakerState, to: CircuitState) {
+        let from = state.state;
+        if from == to {
+            return;
+        }
+        match to {
+            CircuitState::Open => {
+                warn!(
+                    breaker = %self.name,
+                    %from,
+                    %to,
+                    consecutive_failures = state.consecutive_failures,
+                    window_requests = state.window_requests,
+                    window_failures = state.window_failures,
+                    "circuit breaker opened"
+                );
+                state.opened_at = Instant::now();
+            }
+            CircuitState::HalfOpen => {
+                info!(breaker = %self.name, %from, %to, "circuit breaker half-open, probing");
+            }
+            CircuitState::Closed => {
+                info!(breaker = %self.name, %from, %to, "circuit breaker closed");
+            }
+        }
+        state.state = to;
+        state.consecutive_failures = 0;
+        state.window_start = Instant::now();
+        state.window_requests = 0;
+        state.window_failures = 0;
+        state.half_open_successes = 0;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// 调用许可
+pub struct CircuitPermit {
+    breaker: Arc<CircuitBreaker>,
+    probe: bool,
+    done: bool,
+}
+
+impl CircuitPermit {
+    fn new(breaker: Arc<CircuitBreaker>, probe: bool) -> Self {
+        Self {
+            breaker,
+            probe,
+            done: false,
+        }
+    }
+
+    /// 上报成功
+    pub fn success(mut self) {
+        self.done = true;
+        self.breaker.record(self.probe, true);
+    }
+
+    /// 上报失败
+    pub fn failure(mut self) {
+        self.done = true;
+        self.breaker.record(self.probe, false);
+    }
+
+    /// 按 gRPC 状态上报，不属于实例故障的错误码视为成功
+    pub fn record_status(self, status: &Status) {
+        if is_retryable_code(status.code()) {
+            self.failure();
+        } else {
+            self.success();
+        }
+    }
+
+    /// 按 HTTP 调用结果上报：传输层错误为失败，其余按响应头中的 gRPC 状态判断
+    pub fn record_response<B, E>(self, result: &Result<HttpResponse<B>, E>) {
+        match result {
+            Ok(response) => match status_from_response(response) {
+                Some(status) => self.record_status(&status),
+                None => self.success(),
+            },
+            Err(_) => self.failure(),
+        }
+    }
+}
+
+impl Drop for CircuitPermit {
+    fn drop(&mut self) {
+        if !self.done && self.probe {
+            self.breaker.release_probe();
+        }
+    }
+}
+
+/// 熔断器注册表：按目标（实例 ID）维护熔断器，共享同一份配置
+#[derive(Debug, Default)]
+pub struct CircuitBreakerRegistry {
+    config: CircuitBreakerConfig,
+    breakers: RwLock<HashMap<String, Arc<CircuitBreaker>>>,
+}
+
+impl CircuitBreakerRegistry {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            breakers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 获取目标的熔断器，不存在时创建
+    pub fn get_or_create(&self, target: &str) -> Arc<CircuitBreaker> {
+        if let Some(breaker) = self.get(target) {
+            return breaker;
+        }
+        self.breakers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(target.to_string())
+            .or_insert_with(|| Arc::new(CircuitBreaker::new(target, self.config.clone())))
+            .clone()
+    }
+
+    /// 获取目标的熔断器
+    pub fn get(&self, target: &str) -> Option<Arc<CircuitBreaker>> {
+        self.breakers
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(target)
+            .cloned()
+    }
+
+    /// 目标是否被熔断（没有熔断器的目标视为可用）
+    pub fn is_open(&self, target: &str) -> bool {
+        self.get(target).is_some_and(|breaker| breaker.is_open())
+    }
+
+    /// 移除目标的熔断器（实例下线时调用）
+    pub fn remove(&self, target: &str) {
+        self.breakers
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(target);
+    }
+
+    /// 所有目标的当前状态
+    pub fn states(&self) -> Vec<(String, CircuitState)> {
+        self.breakers
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|(target, breaker)| (target.clone(), breaker.state()))
+            .collect()
+    }
+}
+
+/// 熔断被拒绝时返回的状态
+pub fn circuit_open_status(breaker: &CircuitBreaker) -> Status {
+    Status::unavailable(format!("circuit breaker open: {}", breaker.name()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> CircuitBreakerConfig {
+        CircuitBreakerConfig {
+            consecutive_failures: 3,
+            error_rate_threshold: 0.5,
+            min_requests: 10,
+            window: Duration::from_secs(60),
+            open_duration: Duration::from_millis(50),
+            half_open_max_requests: 1,
+        }
+    }
+
+    fn fail(breaker: &Arc<CircuitBreaker>, code: tonic::Code) {
+        breaker
+            .try_acquire()
+            .expect("permit")
+            .record_status(&Status::new(code, "boom"));
+    }
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let breaker = Arc::new(CircuitBreaker::new("a", config()));
+        fail(&breaker, tonic::Code::Unavailable);
+        fail(&breaker, tonic::Code::DeadlineExceeded);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        fail(&breaker, tonic::Code::Unavailable);
+        assert!(breaker.is_open());
+        assert!(breaker.try_acquire().is_none());
+    }
+
+    #[test]
+    fn business_errors_do_not_trip() {
+        let breaker = Arc::new(CircuitBreaker::new("a", config()));
+        for _ in 0..10 {
+            fail(&breaker, tonic::Code::InvalidArgument);
+        }
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn breaker_failures_match_retryable_codes() {
+        let breaker = Arc::new(CircuitBreaker::new("a", config()));
+        for _ in 0..10 {
+            breaker.record_status(&Status::resource_exhausted("overloaded"));
+        }
+        assert!(breaker.is_open());
+        assert!(is_retryable_code(tonic::Code::ResourceExhausted));
+    }
+
+    #[test]
+    fn opens_on_error_rate() {
+        let breaker = Arc::new(CircuitBreaker::new("a", config()));
+        for i in 0..10 {
+            let permit = breaker.try_acquire().expect("permit");
+            // 错误率只在失败时评估：第 10 个请求失败时达到 min_requests
+            if i % 2 == 1 {
+                permit.failure();
+            } else {
+                permit.success();
+            }
+        }
+        assert!(breaker.is_open());
+    }
+
+    #[test]
+    fn half_open_probe_closes_or_reopens() {
+        let breaker = Arc::new(CircuitBreaker::new("a", config()));
+        for _ in 0..3 {
+            fail(&breaker, tonic::Code::Unavailable);
+        }
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+
+        let probe = breaker.try_acquire().expect("probe");
+        assert!(breaker.try_acquire().is_none(), "only one probe at a time");
+        probe.failure();
+        assert!(breaker.is_open());
+
+        std::thread::sleep(Duration::from_millis(60));
+        // 丢弃未上报的探测许可会释放名额
+        drop(breaker.try_acquire().expect("probe"));
+        breaker.try_acquire().expect("probe").success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn registry_tracks_targets_independently() {
+        let registry = CircuitBreakerRegistry::new(config());
+        let a = registry.get_or_create("a");
+        for _ in 0..3 {
+            fail(&a, tonic::Code::Unavailable);
+        }
+        assert!(registry.is_open("a"));
+        assert!(!registry.is_open("b"));
+        registry.remove("a");
+        assert!(!registry.is_open("a"));
+    }
+}
@@ -0,0 +1,77 @@
+//! 按 Context 字段的粘性路由键
+//!
+//! 与 `ServiceClient::get_channel_for_ctx` 配合使用：同一会话 / 用户 / 租户的调用
+//! 总是落到同一后端实例，实例增减时只有约 1/N 的 key 会迁移。
+
+use flare_core_base::context::Context;
+
+/// 粘性路由键来源
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AffinityKey {
+    /// `Context::user_id`，缺失时使用 actor_id
+    UserId,
+    /// `Context::session_id`
+    SessionId,
+    /// `Context::tenant_id`
+    TenantId,
+    /// 调用方直接给出的 key（如 conversation_id）
+    Custom(String),
+}
+
+impl AffinityKey {
+    /// 使用自定义 key
+    pub fn custom(key: impl Into<String>) -> Self {
+        AffinityKey::Custom(key.into())
+    }
+
+    /// 从 Context 中取出路由键，字段缺失或为空时返回 None
+    pub fn resolve<'a>(&'a self, ctx: &'a Context) -> Option<&'a str> {
+        let key = match self {
+            AffinityKey::UserId => ctx
+                .user_id()
+                .filter(|u| !u.is_empty())
+                .or_else(|| ctx.actor().map(|a| a.actor_id())),
+            AffinityKey::SessionId => ctx.session_id(),
+            AffinityKey::TenantId => ctx.tenant_id(),
+            AffinityKey::Custom(key) => Some(key.as_str()),
+        };
+        key.filter(|k| !k.is_empty())
+    }
+
+    /// 键名（用于日志）
+    pub fn name(&self) -> &'static str {
+        match self {
+            AffinityKey::UserId => "user_id",
+            AffinityKey::SessionId => "session_id",
+            AffinityKey::TenantId => "tenant_id",
+            AffinityKey::Custom(_) => "custom",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flare_core_base::context::ActorContext;
+
+    #[test]
+    fn resolves_fields_from_context() {
+        let ctx = Context::root()
+            .with_user_id("u1")
+            .with_session_id("s1")
+            .with_tenant_id("t1");
+
+        assert_eq!(AffinityKey::UserId.resolve(&ctx), Some("u1"));
+        assert_eq!(AffinityKey::SessionId.resolve(&ctx), Some("s1"));
+        assert_eq!(AffinityKey::TenantId.resolve(&ctx), Some("t1"));
+        assert_eq!(AffinityKey::custom("conv-9").resolve(&ctx), Some("conv-9"));
+    }
+
+    #[test]
+    fn user_id_falls_back_to_actor() {
+        let ctx = Context::root().with_actor(ActorContext::new("actor-7"));
+
+        assert_eq!(AffinityKey::UserId.resolve(&ctx), Some("actor-7"));
+        assert_eq!(AffinityKey::SessionId.resolve(&ctx), None);
+    }
+}
@@ -1,15 +1,66 @@
 //! DNS/SRV 服务发现后端
+//!
+//! 解析 `_service._tcp.<namespace>.<domain>` SRV 记录（priority / weight / port），
+//! 没有 SRV 记录时回退到 `<service>.<namespace>.<domain>` 的 A/AAAA 记录。
+//!
+//! # backend_config
+//! - `domain`: 域名后缀，默认 `local`
+//! - `nameservers`: DNS 服务器列表（`ip` 或 `ip:port`），默认读取 `/etc/resolv.conf`
+//! - `port`: A/AAAA 回退时使用的服务端口（未配置则不做 A/AAAA 回退）
+//! - `timeout_ms`: 单次查询超时，默认 2000
+//! - `addresses`: 静态地址列表，DNS 解析无结果或失败时兜底
+//!
+//! 解析结果按记录 TTL 缓存；`watch` 按 `refresh_interval` 轮询，TTL 更短时提前刷新。
+
+mod wire;
 
 use async_trait::async_trait;
 use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::Mutex;
+
+use crate::discovery::{
+    DiscoveryBackend, DiscoveryConfig, ServiceInstance, default_discovery_refresh_interval_secs,
+};
+use wire::{Message, RecordData};
+
+const DEFAULT_DNS_PORT: u16 = 53;
+const DEFAULT_QUERY_TIMEOUT_MS: u64 = 2000;
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+/// 无 SRV weight 时的实例权重（与 `ServiceInstance::new` 默认值一致）
+const DEFAULT_WEIGHT: u32 = 100;
 
-use crate::discovery::{DiscoveryBackend, DiscoveryConfig, ServiceInstance};
+/// (解析目标, 最小 TTL 秒)
+type Resolved = (Vec<ResolvedTarget>, u32);
+
+/// 单个解析目标（SRV 目标展开后的地址）
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ResolvedTarget {
+    address: SocketAddr,
+    weight: u32,
+}
+
+/// 按 TTL 缓存的解析结果
+struct CachedResolution {
+    targets: Vec<ResolvedTarget>,
+    expires_at: Instant,
+}
 
 /// DNS 服务发现后端
+#[derive(Clone)]
 pub struct DnsBackend {
     config: DiscoveryConfig,
     namespace: String,
+    domain: String,
+    nameservers: Vec<SocketAddr>,
+    default_port: Option<u16>,
+    query_timeout: Duration,
+    /// 查询名 -> 解析结果
+    cache: Arc<Mutex<HashMap<String, CachedResolution>>>,
 }
 
 impl DnsBackend {
@@ -23,32 +74,65 @@ impl DnsBackend {
             .and_then(|ns| ns.default.clone())
             .unwrap_or_else(|| "default".to_string());
 
+        let domain = config
+            .backend_config
+            .get("domain")
+            .and_then(|v| v.as_str())
+            .unwrap_or("local")
+            .trim_matches('.')
+            .to_string();
+
+        let mut nameservers: Vec<SocketAddr> = config
+            .backend_config
+            .get("nameservers")
+            .and_then(|v| v.as_array())
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|v| v.as_str().and_then(parse_nameserver))
+                    .collect()
+            })
+            .unwrap_or_default();
+        if nameservers.is_empty() {
+            nameservers = system_nameservers().await;
+        }
+
+        let default_port = config
+            .backend_config
+            .get("port")
+            .and_then(|v| v.as_u64())
+            .and_then(|p| u16::try_from(p).ok());
+
+        let query_timeout = Duration::from_millis(
+            config
+                .backend_config
+                .get("timeout_ms")
+                .and_then(|v| v.as_u64())
+                .unwrap_or(DEFAULT_QUERY_TIMEOUT_MS),
+        );
+
         Ok(Self {
             config: config.clone(),
             namespace,
+            domain,
+            nameservers,
+            default_port,
+            query_timeout,
+            cache: Arc::new(Mutex::new(HashMap::new())),
         })
     }
 
-    async fn resolve_srv(
-        &self,
-        _service_type: &str,
-    ) -> Result<Vec<SocketAddr>, Box<dyn std::error::Error + Send + Sync>> {
-        // DNS SRV 解析
-        // 格式：_service._tcp.namespace.domain
-        let _domain = self
-            .config
-            .backend_config
-            .get("domain")
-            .and_then(|v| v.as_str())
-            .unwrap_or("local");
-
-        // TODO: 实现实际的 DNS SRV 解析
-        // let srv_name = format!("_{}._tcp.{}.{}", service_type, self.namespace, domain);
+    /// 刷新间隔（与 ServiceDiscover 后台刷新保持一致）
+    fn refresh_interval(&self) -> Duration {
+        Duration::from_secs(
+            self.config
+                .refresh_interval
+                .unwrap_or_else(default_discovery_refresh_interval_secs),
+        )
+    }
 
-        // 使用 hickory-dns 或 trust-dns 进行 DNS 查询
-        // 这里简化实现，返回配置的地址
-        let addresses = self
-            .config
+    /// 配置的静态地址（兜底）
+    fn static_addresses(&self) -> Vec<SocketAddr> {
+        self.config
             .backend_config
             .get("addresses")
             .and_then(|v| v.as_array())
@@ -57,9 +141,352 @@ impl DnsBackend {
                     .map(|v| v.as_str().and_then(|s| s.parse::<SocketAddr>().ok()))
                     .collect::<Option<Vec<_>>>()
             })
-            .unwrap_or_default();
+            .unwrap_or_default()
+    }
+
+    /// 解析服务地址，TTL 内命中缓存
+    async fn resolve_srv(
+        &self,
+        service_type: &str,
+        namespace: &str,
+    ) -> Result<Vec<ResolvedTarget>, Box<dyn std::error::Error + Send + Sync>> {
+        // 格式：_service._tcp.namespace.domain
+        let srv_name = format!("_{}._tcp.{}.{}", service_type, namespace, self.domain);
+
+        {
+            let cache = self.cache.lock().await;
+            if let Some(entry) = cache.get(&srv_name)
+                && entry.expires_at > Instant::now()
+            {
+                return Ok(entry.targets.clone());
+            }
+        }
+
+        let resolved = match self.lookup_srv(&srv_name).await {
+            Ok(Some(found)) => Ok(found),
+            Ok(None) => {
+                let host = format!("{}.{}.{}", service_type, namespace, self.domain);
+                match self.default_port {
+                    Some(port) => self.lookup_host(&host, port, DEFAULT_WEIGHT).await,
+                    None => Ok((Vec::new(), 0)),
+                }
+            }
+            Err(e) => Err(e),
+        };
+
+        let (targets, ttl) = match resolved {
+            Ok((targets, ttl)) if !targets.is_empty() => {
+                (targets, Duration::from_secs(u64::from(ttl)))
+            }
+            other => {
+                let statics = self.static_addresses();
+                if statics.is_empty() {
+                    return other.map(|(targets, _)| targets);
+                }
+                if let Err(e) = other {
+                    tracing::warn!(
+                        srv_name = %srv_name,
+                        error = %e,
+                        "DNS resolution failed, using static addresses"
+                    );
+                }
+                let targets = statics
+                    .into_iter()
+                    .map(|address| ResolvedTarget {
+                        address,
+                        weight: DEFAULT_WEIGHT,
+                    })
+                    .collect();
+                (targets, self.refresh_interval())
+            }
+        };
+
+        let mut cache = self.cache.lock().await;
+        cache.insert(
+            srv_name,
+            CachedResolution {
+                targets: targets.clone(),
+                expires_at: Instant::now() + ttl,
+            },
+        );
+
+        Ok(targets)
+    }
+
+    /// 查询 SRV 记录，返回 (目标列表, 最小 TTL)；无 SRV 记录时返回 None
+    ///
+    /// 按 priority 从小到大逐组解析（RFC 2782），使用第一组能解析出地址的目标；
+    /// SRV weight 映射为实例权重。
+    async fn lookup_srv(
+        &self,
+        srv_name: &str,
+    ) -> Result<Option<Resolved>, Box<dyn std::error::Error + Send + Sync>> {
+        let message = self.query(srv_name, wire::TYPE_SRV).await?;
+
+        let mut srv_records: Vec<(u16, u16, u16, String, u32)> = message
+            .answers
+            .iter()
+            .filter_map(|r| match &r.data {
+                RecordData::Srv {
+                    priority,
+                    weight,
+                    port,
+                    target,
+                } => Some((*priority, *weight, *port, target.clone(), r.ttl)),
+                _ => None,
+            })
+            .collect();
+        if srv_records.is_empty() {
+            return Ok(None);
+        }
+        srv_records.sort_by_key(|r| r.0);
+
+        let mut min_ttl = u32::MAX;
+        for group in srv_records.chunk_by(|a, b| a.0 == b.0) {
+            let (targets, ttl) = self.resolve_srv_group(&message, group).await;
+            min_ttl = min_ttl.min(ttl);
+            if !targets.is_empty() {
+                return Ok(Some((targets, min_ttl)));
+            }
+            tracing::warn!(
+                srv_name = %srv_name,
+                priority = group[0].0,
+                "No SRV target resolved at this priority, trying the next one"
+            );
+        }
+
+        Ok(Some((Vec::new(), min_ttl)))
+    }
+
+    /// 解析同一 priority 的 SRV 目标，返回 (目标列表, 最小 TTL)
+    async fn resolve_srv_group(
+        &self,
+        message: &Message,
+        group: &[(u16, u16, u16, String, u32)],
+    ) -> Resolved {
+        let mut targets = Vec::new();
+        let mut min_ttl = u32::MAX;
+        for (_, weight, port, target, ttl) in group {
+            let (port, target) = (*port, target.as_str());
+            min_ttl = min_ttl.min(*ttl);
+
+            // 优先使用附加段里的地址，避免额外查询
+            let glued: Vec<(IpAddr, u32)> = message
+                .additionals
+                .iter()
+                .filter(|r| r.name.eq_ignore_ascii_case(target))
+                .filter_map(|r| match r.data {
+                    RecordData::A(ip) => Some((IpAddr::V4(ip), r.ttl)),
+                    RecordData::Aaaa(ip) => Some((IpAddr::V6(ip), r.ttl)),
+                    _ => None,
+                })
+                .collect();
+
+            let weight = u32::from((*weight).max(1));
+            if glued.is_empty() {
+                match self.lookup_host(target, port, weight).await {
+                    Ok((resolved, ttl)) => {
+                        if !resolved.is_empty() {
+                            min_ttl = min_ttl.min(ttl);
+                        }
+                        targets.extend(resolved);
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            srv_target = %target,
+                            error = %e,
+                            "Failed to resolve SRV target"
+                        );
+                    }
+                }
+            } else {
+                for (ip, ttl) in glued {
+                    min_ttl = min_ttl.min(ttl);
+                    targets.push(ResolvedTarget {
+                        address: SocketAddr::new(ip, port),
+                        weight,
+                    });
+                }
+            }
+        }
+        (targets, min_ttl)
+    }
+
+    /// 查询 A 与 AAAA 记录，返回 (目标列表, 最小 TTL)
+    ///
+    /// 任一类型查询失败时记录告警并保留另一类型的结果（如只支持 IPv4 的解析器拒绝 AAAA），
+    /// 两者都失败时才返回错误。
+    async fn lookup_host(
+        &self,
+        host: &str,
+        port: u16,
+        weight: u32,
+    ) -> Result<Resolved, Box<dyn std::error::Error + Send + Sync>> {
+        let mut targets = Vec::new();
+        let mut min_ttl = u32::MAX;
+        let mut failures = Vec::new();
+
+        for qtype in [wire::TYPE_A, wire::TYPE_AAAA] {
+            let message = match self.query(host, qtype).await {
+                Ok(message) => message,
+                Err(e) => {
+                    tracing::warn!(
+                        host = %host,
+                        qtype,
+                        error = %e,
+                        "DNS address lookup failed"
+                    );
+                    failures.push(e);
+                    continue;
+                }
+            };
+            for record in &message.answers {
+                let ip = match record.data {
+                    RecordData::A(ip) => IpAddr::V4(ip),
+                    RecordData::Aaaa(ip) => IpAddr::V6(ip),
+                    _ => continue,
+                };
+                min_ttl = min_ttl.min(record.ttl);
+                targets.push(ResolvedTarget {
+                    address: SocketAddr::new(ip, port),
+                    weight,
+                });
+            }
+        }
+
+        if failures.len() == 2 {
+            return Err(failures.pop().expect("two lookup failures"));
+        }
+        let ttl = if targets.is_empty() { 0 } else { min_ttl };
+        Ok((targets, ttl))
+    }
+
+    /// 依次向各 DNS 服务器查询，UDP 应答被截断时改用 TCP
+    async fn query(
+        &self,
+        name: &str,
+        qtype: u16,
+    ) -> Result<Message, Box<dyn std::error::Error + Send + Sync>> {
+        let id = uuid::Uuid::new_v4().as_u128() as u16;
+        let packet = wire::encode_query(id, name, qtype)?;
+        let mut last_error: Box<dyn std::error::Error + Send + Sync> =
+            "No DNS nameserver configured".into();
 
-        Ok(addresses)
+        for server in &self.nameservers {
+            let result = match query_udp(*server, &packet, self.query_timeout).await {
+                Ok(bytes) => wire::decode_message(&bytes).map_err(Into::into),
+                Err(e) => Err(e),
+            };
+            let result = match result {
+                Ok(message) if message.truncated => {
+                    match query_tcp(*server, &packet, self.query_timeout).await {
+                        Ok(bytes) => wire::decode_message(&bytes).map_err(Into::into),
+                        Err(e) => Err(e),
+                    }
+                }
+                other => other,
+            };
+
+            match result {
+                Ok(message) if message.id != id || !message.answers_query(name, qtype) => {
+                    last_error =
+                        format!("DNS response from {} does not match the query", server).into();
+                }
+                Ok(message) if message.rcode == 0 => return Ok(message),
+                Ok(message) if message.rcode == wire::RCODE_NX_DOMAIN => {
+                    return Ok(Message {
+                        answers: Vec::new(),
+                        additionals: Vec::new(),
+                        ..message
+                    });
+                }
+                Ok(message) => {
+                    last_error = format!(
+                        "DNS server {} returned rcode {} for {}",
+                        server, message.rcode, name
+                    )
+                    .into();
+                }
+                Err(e) => last_error = e,
+            }
+        }
+
+        Err(last_error)
+    }
+}
+
+async fn query_udp(
+    server: SocketAddr,
+    packet: &[u8],
+    timeout: Duration,
+) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    let bind_addr: SocketAddr = if server.is_ipv4() {
+        SocketAddr::from(([0, 0, 0, 0], 0))
+    } else {
+        SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0))
+    };
+    let socket = UdpSocket::bind(bind_addr).await?;
+    socket.connect(server).await?;
+    socket.send(packet).await?;
+
+    let mut buf = vec![0u8; 4096];
+    let len = tokio::time::timeout(timeout, socket.recv(&mut buf))
+        .await
+        .map_err(|_| format!("DNS query to {} timed out", server))??;
+    buf.truncate(len);
+    Ok(buf)
+}
+
+async fn query_tcp(
+    server: SocketAddr,
+    packet: &[u8],
+    timeout: Duration,
+) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    let exchange = async {
+        let mut stream = TcpStream::connect(server).await?;
+        let mut framed = Vec::with_capacity(packet.len() + 2);
+        framed.extend_from_slice(&(packet.len() as u16).to_be_bytes());
+        framed.extend_from_slice(packet);
+        stream.write_all(&framed).await?;
+
+        let mut len = [0u8; 2];
+        stream.read_exact(&mut len).await?;
+        let mut buf = vec![0u8; u16::from_be_bytes(len) as usize];
+        stream.read_exact(&mut buf).await?;
+        Ok::<_, std::io::Error>(buf)
+    };
+
+    Ok(tokio::time::timeout(timeout, exchange)
+        .await
+        .map_err(|_| format!("DNS TCP query to {} timed out", server))??)
+}
+
+/// 解析 `ip` 或 `ip:port` 形式的 DNS 服务器地址
+fn parse_nameserver(s: &str) -> Option<SocketAddr> {
+    s.parse::<SocketAddr>().ok().or_else(|| {
+        s.parse::<IpAddr>()
+            .ok()
+            .map(|ip| SocketAddr::new(ip, DEFAULT_DNS_PORT))
+    })
+}
+
+/// 读取系统 DNS 服务器（`/etc/resolv.conf`），读取失败时使用 127.0.0.1:53
+async fn system_nameservers() -> Vec<SocketAddr> {
+    let servers: Vec<SocketAddr> = tokio::fs::read_to_string(RESOLV_CONF)
+        .await
+        .map(|content| {
+            content
+                .lines()
+                .filter_map(|line| line.trim().strip_prefix("nameserver"))
+                .filter_map(|rest| parse_nameserver(rest.trim()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if servers.is_empty() {
+        vec![SocketAddr::from(([127, 0, 0, 1], DEFAULT_DNS_PORT))]
+    } else {
+        servers
     }
 }
 
@@ -68,20 +495,25 @@ impl DiscoveryBackend for DnsBackend {
     async fn discover(
         &self,
         service_type: &str,
-        _namespace: Option<&str>,
+        namespace: Option<&str>,
         _version: Option<&str>,
         _tags: Option<&HashMap<String, String>>,
     ) -> Result<Vec<ServiceInstance>, Box<dyn std::error::Error + Send + Sync>> {
-        let addresses = self.resolve_srv(service_type).await?;
-
-        let mut instances = Vec::new();
-        for (idx, addr) in addresses.iter().enumerate() {
-            let instance =
-                ServiceInstance::new(service_type, format!("{}-{}", service_type, idx), *addr)
-                    .with_namespace(&self.namespace);
+        let namespace = namespace.unwrap_or(&self.namespace);
+        let targets = self.resolve_srv(service_type, namespace).await?;
 
-            instances.push(instance);
-        }
+        let instances = targets
+            .into_iter()
+            .map(|target| {
+                ServiceInstance::new(
+                    service_type,
+                    format!("{}-{}", service_type, target.address),
+                    target.address,
+                )
+                .with_namespace(namespace)
+                .with_weight(target.weight)
+            })
+            .collect();
 
         Ok(instances)
     }
@@ -112,19 +544,35 @@ impl DiscoveryBackend for DnsBackend {
         // DNS 后端不支持 watch（需要轮询）
         let (tx, rx) = tokio::sync::mpsc::channel(100);
 
-        // 定期轮询 DNS
         let service_type = service_type.to_string();
         let backend = self.clone();
+        let refresh_interval = self.refresh_interval();
 
         tokio::spawn(async move {
-            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(30));
             loop {
-                interval.tick().await;
                 if let Ok(instances) = backend.discover(&service_type, None, None, None).await {
                     for inst in instances {
-                        let _ = tx.send(inst).await;
+                        if tx.send(inst).await.is_err() {
+                            return;
+                        }
                     }
                 }
+
+                // 记录 TTL 早于刷新间隔到期时提前重新解析
+                let srv_name = format!(
+                    "_{}._tcp.{}.{}",
+                    service_type, backend.namespace, backend.domain
+                );
+                let wait = {
+                    let cache = backend.cache.lock().await;
+                    cache
+                        .get(&srv_name)
+                        .map(|entry| entry.expires_at.saturating_duration_since(Instant::now()))
+                        .unwrap_or(refresh_interval)
+                        .min(refresh_interval)
+                        .max(Duration::from_secs(1))
+                };
+                tokio::time::sleep(wait).await;
             }
         });
 
@@ -132,11 +580,310 @@ impl DiscoveryBackend for DnsBackend {
     }
 }
 
-impl Clone for DnsBackend {
-    fn clone(&self) -> Self {
-        Self {
-            config: self.config.clone(),
-            namespace: self.namespace.clone(),
+#[cfg(test)]
+mod tests {
+    use super::wire::{self, Record, RecordData};
+    use super::*;
+    use crate::discovery::{BackendType, LoadBalanceStrategy, NamespaceConfig};
+    use serde_json::json;
+    use std::net::Ipv4Addr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    type Zone = HashMap<(String, u16), (Vec<Record>, Vec<Record>)>;
+
+    const RCODE_SERVER_FAILURE: u8 = 2;
+
+    /// 进程内 DNS 桩服务器：按 (name, qtype) 应答，未知名称返回 NXDOMAIN
+    async fn spawn_stub(zone: Zone) -> (SocketAddr, Arc<AtomicUsize>) {
+        spawn_stub_with_failures(zone, &[]).await
+    }
+
+    /// 同 [`spawn_stub`]，`failing` 中的 (name, qtype) 返回 SERVFAIL
+    async fn spawn_stub_with_failures(
+        zone: Zone,
+        failing: &[(&str, u16)],
+    ) -> (SocketAddr, Arc<AtomicUsize>) {
+        let failing: Vec<(String, u16)> = failing
+            .iter()
+            .map(|(name, qtype)| (name.to_string(), *qtype))
+            .collect();
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let queries = Arc::new(AtomicUsize::new(0));
+        let counter = queries.clone();
+
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            loop {
+                let Ok((len, peer)) = socket.recv_from(&mut buf).await else {
+                    return;
+                };
+                counter.fetch_add(1, Ordering::SeqCst);
+                let query = &buf[..len];
+                let (_, name, qtype) = wire::decode_question(query).unwrap();
+                let question = (name.to_ascii_lowercase(), qtype);
+                let response = match zone.get(&question) {
+                    _ if failing.contains(&question) => {
+                        wire::encode_response(query, RCODE_SERVER_FAILURE, &[], &[])
+                    }
+                    Some((answers, additionals)) => {
+                        wire::encode_response(query, 0, answers, additionals)
+                    }
+                    None => wire::encode_response(query, wire::RCODE_NX_DOMAIN, &[], &[]),
+                };
+                let _ = socket.send_to(&response, peer).await;
+            }
+        });
+
+        (addr, queries)
+    }
+
+    fn record(name: &str, ttl: u32, data: RecordData) -> Record {
+        Record {
+            name: name.to_string(),
+            ttl,
+            data,
+        }
+    }
+
+    fn srv(priority: u16, weight: u16, port: u16, target: &str) -> RecordData {
+        RecordData::Srv {
+            priority,
+            weight,
+            port,
+            target: target.to_string(),
         }
     }
+
+    async fn backend(nameserver: SocketAddr, extra: &[(&str, serde_json::Value)]) -> DnsBackend {
+        let mut backend_config = HashMap::new();
+        backend_config.insert("domain".to_string(), json!("svc.test"));
+        backend_config.insert("nameservers".to_string(), json!([nameserver.to_string()]));
+        backend_config.insert("timeout_ms".to_string(), json!(500));
+        for (key, value) in extra {
+            backend_config.insert(key.to_string(), value.clone());
+        }
+        let config = DiscoveryConfig {
+            backend: BackendType::Dns,
+            backend_config,
+            namespace: Some(NamespaceConfig {
+                default: Some("im".to_string()),
+                separator: None,
+            }),
+            version: None,
+            tag_filters: vec![],
+            load_balance: LoadBalanceStrategy::default(),
+            health_check: None,
+            refresh_interval: Some(30),
+        };
+        DnsBackend::new(&config).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn resolves_srv_records_with_weights() {
+        let srv_name = "_push._tcp.im.svc.test";
+        let mut zone = Zone::new();
+        zone.insert(
+            (srv_name.to_string(), wire::TYPE_SRV),
+            (
+                vec![
+                    record(srv_name, 60, srv(10, 30, 7001, "a.svc.test")),
+                    record(srv_name, 60, srv(10, 70, 7002, "b.svc.test")),
+                    record(srv_name, 60, srv(20, 100, 7003, "backup.svc.test")),
+                ],
+                vec![record(
+                    "a.svc.test",
+                    60,
+                    RecordData::A(Ipv4Addr::new(10, 0, 0, 1)),
+                )],
+            ),
+        );
+        zone.insert(
+            ("b.svc.test".to_string(), wire::TYPE_A),
+            (
+                vec![record(
+                    "b.svc.test",
+                    60,
+                    RecordData::A(Ipv4Addr::new(10, 0, 0, 2)),
+                )],
+                vec![],
+            ),
+        );
+        let (addr, _) = spawn_stub(zone).await;
+        let backend = backend(addr, &[]).await;
+
+        let mut instances = backend.discover("push", None, None, None).await.unwrap();
+        instances.sort_by_key(|i| i.address);
+
+        assert_eq!(instances.len(), 2);
+        assert_eq!(instances[0].address, "10.0.0.1:7001".parse().unwrap());
+        assert_eq!(instances[0].weight, 30);
+        assert_eq!(instances[1].address, "10.0.0.2:7002".parse().unwrap());
+        assert_eq!(instances[1].weight, 70);
+        assert_eq!(instances[0].namespace.as_deref(), Some("im"));
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_next_srv_priority_when_targets_do_not_resolve() {
+        let srv_name = "_push._tcp.im.svc.test";
+        let mut zone = Zone::new();
+        zone.insert(
+            (srv_name.to_string(), wire::TYPE_SRV),
+            (
+                vec![
+                    record(srv_name, 60, srv(10, 50, 7001, "gone.svc.test")),
+                    record(srv_name, 60, srv(20, 100, 7003, "backup.svc.test")),
+                ],
+                vec![],
+            ),
+        );
+        zone.insert(
+            ("backup.svc.test".to_string(), wire::TYPE_A),
+            (
+                vec![record(
+                    "backup.svc.test",
+                    60,
+                    RecordData::A(Ipv4Addr::new(10, 0, 0, 9)),
+                )],
+                vec![],
+            ),
+        );
+        let (addr, _) = spawn_stub(zone).await;
+        let backend = backend(addr, &[]).await;
+
+        let instances = backend.discover("push", None, None, None).await.unwrap();
+
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0].address, "10.0.0.9:7003".parse().unwrap());
+    }
+
+    #[tokio::test]
+    async fn rejects_response_for_a_different_question() {
+        // 沿用查询 id，但问题段换成另一个名称
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            while let Ok((_, peer)) = socket.recv_from(&mut buf).await {
+                let mut spoofed = wire::encode_query(0, "evil.svc.test", wire::TYPE_A).unwrap();
+                spoofed[..2].copy_from_slice(&buf[..2]);
+                let answer = record(
+                    "evil.svc.test",
+                    60,
+                    RecordData::A(Ipv4Addr::new(6, 6, 6, 6)),
+                );
+                let response = wire::encode_response(&spoofed, 0, &[answer], &[]);
+                let _ = socket.send_to(&response, peer).await;
+            }
+        });
+        let backend = backend(addr, &[("port", json!(9000))]).await;
+
+        let result = backend.discover("push", None, None, None).await;
+
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("does not match the query"), "{error}");
+    }
+
+    #[test]
+    fn response_must_echo_the_question() {
+        let query = wire::encode_query(7, "push.im.svc.test", wire::TYPE_A).unwrap();
+        let message = wire::decode_message(&wire::encode_response(&query, 0, &[], &[])).unwrap();
+
+        assert!(message.answers_query("PUSH.im.svc.test.", wire::TYPE_A));
+        assert!(!message.answers_query("push.im.svc.test", wire::TYPE_AAAA));
+        assert!(!message.answers_query("other.im.svc.test", wire::TYPE_A));
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_a_records_without_srv() {
+        let mut zone = Zone::new();
+        zone.insert(
+            ("push.im.svc.test".to_string(), wire::TYPE_A),
+            (
+                vec![record(
+                    "push.im.svc.test",
+                    60,
+                    RecordData::A(Ipv4Addr::new(10, 0, 1, 5)),
+                )],
+                vec![],
+            ),
+        );
+        let (addr, _) = spawn_stub(zone).await;
+        let backend = backend(addr, &[("port", json!(9000))]).await;
+
+        let instances = backend.discover("push", None, None, None).await.unwrap();
+
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0].address, "10.0.1.5:9000".parse().unwrap());
+        assert_eq!(instances[0].weight, DEFAULT_WEIGHT);
+    }
+
+    #[tokio::test]
+    async fn keeps_a_records_when_aaaa_lookup_fails() {
+        let host = "push.im.svc.test";
+        let mut zone = Zone::new();
+        zone.insert(
+            (host.to_string(), wire::TYPE_A),
+            (
+                vec![record(host, 60, RecordData::A(Ipv4Addr::new(10, 0, 1, 5)))],
+                vec![],
+            ),
+        );
+        let (addr, _) = spawn_stub_with_failures(zone, &[(host, wire::TYPE_AAAA)]).await;
+        let ipv4_only = backend(addr, &[("port", json!(9000))]).await;
+
+        let instances = ipv4_only.discover("push", None, None, None).await.unwrap();
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0].address, "10.0.1.5:9000".parse().unwrap());
+
+        // A 与 AAAA 都失败时才报错
+        let (addr, _) = spawn_stub_with_failures(
+            Zone::new(),
+            &[(host, wire::TYPE_A), (host, wire::TYPE_AAAA)],
+        )
+        .await;
+        let failing = backend(addr, &[("port", json!(9000))]).await;
+        let error = failing
+            .lookup_host(host, 9000, DEFAULT_WEIGHT)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("rcode 2"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn caches_results_until_ttl_expires() {
+        let srv_name = "_push._tcp.im.svc.test";
+        let mut zone = Zone::new();
+        zone.insert(
+            (srv_name.to_string(), wire::TYPE_SRV),
+            (
+                vec![record(srv_name, 300, srv(0, 5, 7001, "a.svc.test"))],
+                vec![record(
+                    "a.svc.test",
+                    300,
+                    RecordData::A(Ipv4Addr::new(10, 0, 0, 1)),
+                )],
+            ),
+        );
+        let (addr, queries) = spawn_stub(zone).await;
+        let backend = backend(addr, &[]).await;
+
+        backend.discover("push", None, None, None).await.unwrap();
+        let after_first = queries.load(Ordering::SeqCst);
+        backend.discover("push", None, None, None).await.unwrap();
+
+        assert_eq!(after_first, 1);
+        assert_eq!(queries.load(Ordering::SeqCst), after_first);
+    }
+
+    #[tokio::test]
+    async fn uses_static_addresses_when_nothing_resolves() {
+        let (addr, _) = spawn_stub(Zone::new()).await;
+        let backend = backend(addr, &[("addresses", json!(["127.0.0.1:8080"]))]).await;
+
+        let instances = backend.discover("push", None, None, None).await.unwrap();
+
+        assert_eq!(instances.len(), 1);
+        assert_eq!(instances[0].address, "127.0.0.1:8080".parse().unwrap());
+    }
 }
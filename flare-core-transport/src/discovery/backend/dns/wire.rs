@@ -0,0 +1,292 @@
+//! 最小 DNS 报文编解码（RFC 1035 / RFC 2782）
+//!
+//! 只覆盖服务发现需要的部分：构造单问题查询，解析 A / AAAA / SRV 应答，
+//! 支持名称压缩指针。其余记录类型解析为 `RecordData::Other` 并跳过。
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+pub(super) const TYPE_A: u16 = 1;
+pub(super) const TYPE_AAAA: u16 = 28;
+pub(super) const TYPE_SRV: u16 = 33;
+
+/// NXDOMAIN：名称不存在，按空应答处理
+pub(super) const RCODE_NX_DOMAIN: u8 = 3;
+
+const CLASS_IN: u16 = 1;
+const HEADER_LEN: usize = 12;
+const FLAG_RECURSION_DESIRED: u16 = 0x0100;
+const FLAG_TRUNCATED: u16 = 0x0200;
+/// 压缩指针最大跳转次数，防止恶意报文造成死循环
+const MAX_POINTER_JUMPS: usize = 16;
+
+/// 资源记录数据
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum RecordData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    Srv {
+        priority: u16,
+        weight: u16,
+        port: u16,
+        target: String,
+    },
+    Other,
+}
+
+/// 资源记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Record {
+    pub name: String,
+    pub ttl: u32,
+    pub data: RecordData,
+}
+
+/// 问题段
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Question {
+    pub name: String,
+    pub qtype: u16,
+    pub qclass: u16,
+}
+
+/// 解析后的应答报文
+#[derive(Debug, Default)]
+pub(super) struct Message {
+    pub id: u16,
+    pub truncated: bool,
+    pub rcode: u8,
+    pub questions: Vec<Question>,
+    pub answers: Vec<Record>,
+    pub additionals: Vec<Record>,
+}
+
+impl Message {
+    /// 应答的问题段是否正是本次查询（单问题、名称不区分大小写、IN 类）
+    pub(super) fn answers_query(&self, name: &str, qtype: u16) -> bool {
+        match self.questions.as_slice() {
+            [question] => {
+                question.qtype == qtype
+                    && question.qclass == CLASS_IN
+                    && question
+                        .name
+                        .trim_end_matches('.')
+                        .eq_ignore_ascii_case(name.trim_end_matches('.'))
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 构造单问题的递归查询报文
+pub(super) fn encode_query(id: u16, name: &str, qtype: u16) -> Result<Vec<u8>, String> {
+    let mut buf = Vec::with_capacity(HEADER_LEN + name.len() + 6);
+    buf.extend_from_slice(&id.to_be_bytes());
+    buf.extend_from_slice(&FLAG_RECURSION_DESIRED.to_be_bytes());
+    buf.extend_from_slice(&1u16.to_be_bytes()); // QDCOUNT
+    buf.extend_from_slice(&[0u8; 6]); // ANCOUNT / NSCOUNT / ARCOUNT
+    encode_name(&mut buf, name)?;
+    buf.extend_from_slice(&qtype.to_be_bytes());
+    buf.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(buf)
+}
+
+fn encode_name(buf: &mut Vec<u8>, name: &str) -> Result<(), String> {
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(format!("Invalid DNS name: {}", name));
+        }
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label.as_bytes());
+    }
+    buf.push(0);
+    Ok(())
+}
+
+/// 解析应答报文（授权段丢弃）
+pub(super) fn decode_message(buf: &[u8]) -> Result<Message, String> {
+    if buf.len() < HEADER_LEN {
+        return Err("DNS message too short".to_string());
+    }
+    let id = read_u16(buf, 0)?;
+    let flags = read_u16(buf, 2)?;
+    let qdcount = read_u16(buf, 4)?;
+    let ancount = read_u16(buf, 6)?;
+    let nscount = read_u16(buf, 8)?;
+    let arcount = read_u16(buf, 10)?;
+
+    let mut message = Message {
+        id,
+        truncated: flags & FLAG_TRUNCATED != 0,
+        rcode: (flags & 0x000f) as u8,
+        ..Default::default()
+    };
+
+    let mut pos = HEADER_LEN;
+    for _ in 0..qdcount {
+        let (name, next) = decode_name(buf, pos)?;
+        message.questions.push(Question {
+            name,
+            qtype: read_u16(buf, next)?,
+            qclass: read_u16(buf, next + 2)?,
+        });
+        pos = next + 4;
+    }
+    for _ in 0..ancount {
+        let (record, next) = decode_record(buf, pos)?;
+        message.answers.push(record);
+        pos = next;
+    }
+    for _ in 0..nscount {
+        let (_, next) = decode_record(buf, pos)?;
+        pos = next;
+    }
+    for _ in 0..arcount {
+        let (record, next) = decode_record(buf, pos)?;
+        message.additionals.push(record);
+        pos = next;
+    }
+
+    Ok(message)
+}
+
+fn decode_record(buf: &[u8], start: usize) -> Result<(Record, usize), String> {
+    let (name, pos) = decode_name(buf, start)?;
+    let rtype = read_u16(buf, pos)?;
+    let ttl = read_u32(buf, pos + 4)?;
+    let rdlen = read_u16(buf, pos + 8)? as usize;
+    let rdata = pos + 10;
+    let end = rdata + rdlen;
+    if end > buf.len() {
+        return Err("DNS record data out of bounds".to_string());
+    }
+
+    let data = match rtype {
+        TYPE_A if rdlen == 4 => RecordData::A(Ipv4Addr::new(
+            buf[rdata],
+            buf[rdata + 1],
+            buf[rdata + 2],
+            buf[rdata + 3],
+        )),
+        TYPE_AAAA if rdlen == 16 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&buf[rdata..end]);
+            RecordData::Aaaa(Ipv6Addr::from(octets))
+        }
+        TYPE_SRV if rdlen >= 7 => RecordData::Srv {
+            priority: read_u16(buf, rdata)?,
+            weight: read_u16(buf, rdata + 2)?,
+            port: read_u16(buf, rdata + 4)?,
+            target: decode_name(buf, rdata + 6)?.0,
+        },
+        _ => RecordData::Other,
+    };
+
+    Ok((Record { name, ttl, data }, end))
+}
+
+/// 解析（可能压缩的）域名，返回名称与名称之后的偏移
+fn decode_name(buf: &[u8], start: usize) -> Result<(String, usize), String> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut pos = start;
+    let mut end = None;
+    let mut jumps = 0;
+
+    loop {
+        let len = *buf.get(pos).ok_or("DNS name out of bounds")? as usize;
+        match len & 0xc0 {
+            0xc0 => {
+                let low = *buf.get(pos + 1).ok_or("DNS name pointer out of bounds")? as usize;
+                if end.is_none() {
+                    end = Some(pos + 2);
+                }
+                jumps += 1;
+                if jumps > MAX_POINTER_JUMPS {
+                    return Err("DNS name compression loop".to_string());
+                }
+                pos = ((len & 0x3f) << 8) | low;
+            }
+            0x00 if len == 0 => {
+                pos += 1;
+                break;
+            }
+            0x00 => {
+                let label = buf
+                    .get(pos + 1..pos + 1 + len)
+                    .ok_or("DNS label out of bounds")?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                pos += 1 + len;
+            }
+            _ => return Err("Unsupported DNS label type".to_string()),
+        }
+    }
+
+    Ok((labels.join("."), end.unwrap_or(pos)))
+}
+
+fn read_u16(buf: &[u8], pos: usize) -> Result<u16, String> {
+    buf.get(pos..pos + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .ok_or_else(|| "DNS message truncated".to_string())
+}
+
+fn read_u32(buf: &[u8], pos: usize) -> Result<u32, String> {
+    buf.get(pos..pos + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| "DNS message truncated".to_string())
+}
+
+/// 解析查询报文的问题段：(id, name, qtype)，供测试桩服务器使用
+#[cfg(test)]
+pub(super) fn decode_question(buf: &[u8]) -> Result<(u16, String, u16), String> {
+    let id = read_u16(buf, 0)?;
+    let (name, pos) = decode_name(buf, HEADER_LEN)?;
+    let qtype = read_u16(buf, pos)?;
+    Ok((id, name, qtype))
+}
+
+/// 基于查询报文构造应答（名称不压缩），供测试桩服务器使用
+#[cfg(test)]
+pub(super) fn encode_response(
+    query: &[u8],
+    rcode: u8,
+    answers: &[Record],
+    additionals: &[Record],
+) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&query[0..2]);
+    buf.extend_from_slice(&(0x8180u16 | rcode as u16).to_be_bytes());
+    buf.extend_from_slice(&1u16.to_be_bytes());
+    buf.extend_from_slice(&(answers.len() as u16).to_be_bytes());
+    buf.extend_from_slice(&0u16.to_be_bytes());
+    buf.extend_from_slice(&(additionals.len() as u16).to_be_bytes());
+    buf.extend_from_slice(&query[HEADER_LEN..]);
+
+    for record in answers.iter().chain(additionals) {
+        encode_name(&mut buf, &record.name).expect("valid record name");
+        let (rtype, rdata) = match &record.data {
+            RecordData::A(ip) => (TYPE_A, ip.octets().to_vec()),
+            RecordData::Aaaa(ip) => (TYPE_AAAA, ip.octets().to_vec()),
+            RecordData::Srv {
+                priority,
+                weight,
+                port,
+                target,
+            } => {
+                let mut rdata = Vec::new();
+                rdata.extend_from_slice(&priority.to_be_bytes());
+                rdata.extend_from_slice(&weight.to_be_bytes());
+                rdata.extend_from_slice(&port.to_be_bytes());
+                encode_name(&mut rdata, target).expect("valid SRV target");
+                (TYPE_SRV, rdata)
+            }
+            RecordData::Other => (0, Vec::new()),
+        };
+        buf.extend_from_slice(&rtype.to_be_bytes());
+        buf.extend_from_slice(&CLASS_IN.to_be_bytes());
+        buf.extend_from_slice(&record.ttl.to_be_bytes());
+        buf.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&rdata);
+    }
+
+    buf
+}
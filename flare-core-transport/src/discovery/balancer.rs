@@ -0,0 +1,402 @@
+//! 负载均衡策略
+//!
+//! `ServiceClient` 每次获取 Channel 时通过 [`LoadBalancer`] 从当前实例集合中选择一个。
+//! 内置实现覆盖 [`LoadBalanceStrategy`] 的全部变体，也可以通过
+//! `ServiceDiscover::with_load_balancer` 注入自定义实现。
+
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+
+use crate::discovery::config::LoadBalanceStrategy;
+use crate::discovery::discover::ChannelService;
+use crate::discovery::instance::DEFAULT_INSTANCE_WEIGHT;
+
+/// 参与负载均衡的实例
+#[derive(Clone)]
+pub struct BalanceEndpoint {
+    id: Arc<str>,
+    service: ChannelService,
+}
+
+impl BalanceEndpoint {
+    pub fn new(id: impl Into<Arc<str>>, service: ChannelService) -> Self {
+        Self {
+            id: id.into(),
+            service,
+        }
+    }
+
+    /// 实例 ID
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// 实例权重
+    pub fn weight(&self) -> u32 {
+        self.service.weight()
+    }
+
+    /// 当前在途请求数（通过 `TrackedChannel` 发出的请求）
+    pub fn in_flight(&self) -> usize {
+        self.service.in_flight()
+    }
+
+    /// 实例对应的 Channel 服务
+    pub fn service(&self) -> &ChannelService {
+        &self.service
+    }
+}
+
+/// 负载均衡器
+///
+/// 实现需要是线程安全的：`select` 可能在多个 `ServiceClient` 之间并发调用。
+pub trait LoadBalancer: Send + Sync {
+    /// 实例集合变化后调用，用于重建内部状态（如哈希环）
+    ///
+    /// `endpoints` 按实例 ID 升序排列。
+    fn rebuild(&self, _endpoints: &[BalanceEndpoint]) {}
+
+    /// 从 `endpoints` 中选择一个实例并返回下标，`endpoints` 为空时返回 None
+    ///
+    /// `key` 为调用方提供的路由键（如 conversation_id、user_id），
+    /// 内置实现中只有一致性哈希使用它。
+    fn select(&self, endpoints: &[BalanceEndpoint], key: Option<&str>) -> Option<usize>;
+}
+
+/// 根据配置的策略创建内置负载均衡器
+pub fn load_balancer_for(strategy: LoadBalanceStrategy) -> Arc<dyn LoadBalancer> {
+    match strategy {
+        LoadBalanceStrategy::RoundRobin => Arc::new(RoundRobinBalancer::default()),
+        LoadBalanceStrategy::Random => Arc::new(RandomBalancer),
+        LoadBalanceStrategy::ConsistentHash => Arc::new(ConsistentHashBalancer::default()),
+        LoadBalanceStrategy::LeastConnections => Arc::new(LeastConnectionsBalancer::default()),
+        LoadBalanceStrategy::WeightedRoundRobin => Arc::new(WeightedRoundRobinBalancer::default()),
+        LoadBalanceStrategy::WeightedRandom => Arc::new(WeightedRandomBalancer),
+    }
+}
+
+/// 稳定的 64 位哈希（FNV-1a + splitmix64 终结器）
+///
+/// 不依赖 `DefaultHasher`，保证不同进程、不同版本对同一个 key 得到相同结果。
+pub(crate) fn hash_key(key: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in key {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash ^= hash >> 30;
+    hash = hash.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash ^= hash >> 27;
+    hash = hash.wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+/// 轮询
+#[derive(Default)]
+pub struct RoundRobinBalancer {
+    next: AtomicUsize,
+}
+
+impl LoadBalancer for RoundRobinBalancer {
+    fn select(&self, endpoints: &[BalanceEndpoint], _key: Option<&str>) -> Option<usize> {
+        if endpoints.is_empty() {
+            return None;
+        }
+        Some(self.next.fetch_add(1, Ordering::Relaxed) % endpoints.len())
+    }
+}
+
+/// 随机
+#[derive(Default)]
+pub struct RandomBalancer;
+
+impl LoadBalancer for RandomBalancer {
+    fn select(&self, endpoints: &[BalanceEndpoint], _key: Option<&str>) -> Option<usize> {
+        if endpoints.is_empty() {
+            return None;
+        }
+        Some(rand::thread_rng().gen_range(0..endpoints.len()))
+    }
+}
+
+/// 加权随机：按实例权重比例随机选择
+#[derive(Default)]
+pub struct WeightedRandomBalancer;
+
+impl LoadBalancer for WeightedRandomBalancer {
+    fn select(&self, endpoints: &[BalanceEndpoint], key: Option<&str>) -> Option<usize> {
+        let total: u64 = endpoints.iter().map(|e| u64::from(e.weight())).sum();
+        if total == 0 {
+            return RandomBalancer.select(endpoints, key);
+        }
+
+        let mut point = rand::thread_rng().gen_range(0..total);
+        for (idx, endpoint) in endpoints.iter().enumerate() {
+            let weight = u64::from(endpoint.weight());
+            if point < weight {
+                return Some(idx);
+            }
+            point -= weight;
+        }
+        None
+    }
+}
+
+/// 加权轮询（平滑加权轮询，与 nginx 相同）
+///
+/// 权重 5:1:1 的选择序列为 a a b a c a a，而不是 a a a a a b c。
+#[derive(Default)]
+pub struct WeightedRoundRobinBalancer {
+    current: Mutex<HashMap<Arc<str>, i64>>,
+}
+
+impl LoadBalancer for WeightedRoundRobinBalancer {
+    fn rebuild(&self, _endpoints: &[BalanceEndpoint]) {
+        self.current
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    fn select(&self, endpoints: &[BalanceEndpoint], _key: Option<&str>) -> Option<usize> {
+        let mut current = self.current.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut total = 0i64;
+        let mut best: Option<(usize, i64)> = None;
+
+        for (idx, endpoint) in endpoints.iter().enumerate() {
+            let weight = i64::from(endpoint.weight());
+            total += weight;
+            let entry = current.entry(endpoint.id.clone()).or_insert(0);
+            *entry += weight;
+            if best.is_none_or(|(_, value)| *entry > value) {
+                best = Some((idx, *entry));
+            }
+        }
+
+        let (idx, _) = best?;
+        if let Some(entry) = current.get_mut(endpoints[idx].id()) {
+            *entry -= total;
+        }
+        Some(idx)
+    }
+}
+
+/// 最少连接：选择在途请求最少的实例，相同时轮流选择
+///
+/// 在途请求数只统计通过 `ServiceClient::get_tracked_channel` 发出的请求。
+#[derive(Default)]
+pub struct LeastConnectionsBalancer {
+    next: AtomicUsize,
+}
+
+impl LoadBalancer for LeastConnectionsBalancer {
+    fn select(&self, endpoints: &[BalanceEndpoint], _key: Option<&str>) -> Option<usize> {
+        if endpoints.is_empty() {
+            return None;
+        }
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        (0..endpoints.len())
+            .map(|offset| (start + offset) % endpoints.len())
+            .min_by_key(|idx| endpoints[*idx].in_flight())
+    }
+}
+
+/// 一致性哈希（带虚拟节点的哈希环）
+///
+/// 每个实例按权重放置 `virtual_nodes * weight / 100` 个虚拟节点；
+/// 实例增减时只有约 1/N 的 key 会迁移。未提供 key 时退化为随机选择。
+pub struct ConsistentHashBalancer {
+    virtual_nodes: u32,
+    ring: RwLock<Vec<(u64, Arc<str>)>>,
+}
+
+impl ConsistentHashBalancer {
+    /// 默认每个实例（权重 100）的虚拟节点数
+    pub const DEFAULT_VIRTUAL_NODES: u32 = 160;
+
+    pub fn new(virtual_nodes: u32) -> Self {
+        Self {
+            virtual_nodes: virtual_nodes.max(1),
+            ring: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// 查找 key 落在哈希环上的实例 ID
+    pub fn lookup(&self, key: &str) -> Option<Arc<str>> {
+        let ring = self.ring.read().unwrap_or_else(PoisonError::into_inner);
+        if ring.is_empty() {
+            return None;
+        }
+        let hash = hash_key(key.as_bytes());
+        let idx = ring.partition_point(|(point, _)| *point < hash);
+        let (_, id) = &ring[idx % ring.len()];
+        Some(id.clone())
+    }
+
+    fn nodes_for(&self, weight: u32) -> u64 {
+        let base = u64::from(self.virtual_nodes);
+        (base * u64::from(weight) / u64::from(DEFAULT_INSTANCE_WEIGHT)).clamp(1, base * 10)
+    }
+}
+
+impl Default for ConsistentHashBalancer {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_VIRTUAL_NODES)
+    }
+}
+
+impl LoadBalancer for ConsistentHashBalancer {
+    fn rebuild(&self, endpoints: &[BalanceEndpoint]) {
+        let mut ring = Vec::new();
+        for endpoint in endpoints {
+            for replica in 0..self.nodes_for(endpoint.weight()) {
+                let point = hash_key(format!("{}#{}", endpoint.id(), replica).as_bytes());
+                ring.push((point, endpoint.id.clone()));
+            }
+        }
+        ring.sort_unstable_by_key(|(point, _)| *point);
+        *self.ring.write().unwrap_or_else(PoisonError::into_inner) = ring;
+    }
+
+    fn select(&self, endpoints: &[BalanceEndpoint], key: Option<&str>) -> Option<usize> {
+        if endpoints.is_empty() {
+            return None;
+        }
+        let Some(key) = key else {
+            return RandomBalancer.select(endpoints, None);
+        };
+
+        match self.lookup(key) {
+            Some(id) => endpoints
+                .binary_search_by(|e| e.id().cmp(&*id))
+                .ok()
+                // 哈希环尚未随实例集合重建时按 key 取模，保证同一 key 仍然稳定
+                .or_else(|| Some((hash_key(key.as_bytes()) % endpoints.len() as u64) as usize)),
+            None => Some((hash_key(key.as_bytes()) % endpoints.len() as u64) as usize),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::transport::Endpoint;
+
+    fn endpoints(weights: &[(&str, u32)]) -> Vec<BalanceEndpoint> {
+        let mut endpoints: Vec<BalanceEndpoint> = weights
+            .iter()
+            .map(|(id, weight)| {
+                let channel = Endpoint::from_static("http://127.0.0.1:1").connect_lazy();
+                BalanceEndpoint::new(*id, ChannelService::new(channel).with_weight(*weight))
+            })
+            .collect();
+        endpoints.sort_by(|a, b| a.id().cmp(b.id()));
+        endpoints
+    }
+
+    #[tokio::test]
+    async fn round_robin_cycles_through_endpoints() {
+        let endpoints = endpoints(&[("a", 100), ("b", 100), ("c", 100)]);
+        let balancer = RoundRobinBalancer::default();
+
+        let picks: Vec<usize> = (0..6)
+            .map(|_| balancer.select(&endpoints, None).unwrap())
+            .collect();
+
+        assert_eq!(picks, vec![0, 1, 2, 0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn smooth_weighted_round_robin_interleaves() {
+        let endpoints = endpoints(&[("a", 5), ("b", 1), ("c", 1)]);
+        let balancer = WeightedRoundRobinBalancer::default();
+
+        let picks: String = (0..7)
+            .map(|_| endpoints[balancer.select(&endpoints, None).unwrap()].id())
+            .collect();
+
+        assert_eq!(picks, "aabacaa");
+    }
+
+    #[tokio::test]
+    async fn weighted_random_skips_zero_weight() {
+        let endpoints = endpoints(&[("a", 0), ("b", 10)]);
+        let balancer = WeightedRandomBalancer;
+
+        for _ in 0..100 {
+            assert_eq!(balancer.select(&endpoints, None), Some(1));
+        }
+    }
+
+    #[tokio::test]
+    async fn least_connections_prefers_idle_endpoint() {
+        let endpoints = endpoints(&[("a", 100), ("b", 100)]);
+        let guard = endpoints[0].service().begin_request();
+        let balancer = LeastConnectionsBalancer::default();
+
+        for _ in 0..4 {
+            assert_eq!(balancer.select(&endpoints, None), Some(1));
+        }
+        drop(guard);
+        assert_eq!(endpoints[0].in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn consistent_hash_is_stable_per_key() {
+        let endpoints = endpoints(&[("a", 100), ("b", 100), ("c", 100)]);
+        let balancer = ConsistentHashBalancer::default();
+        balancer.rebuild(&endpoints);
+
+        for key in ["conv-1", "conv-2", "user-42"] {
+            let first = balancer.select(&endpoints, Some(key));
+            for _ in 0..10 {
+                assert_eq!(balancer.select(&endpoints, Some(key)), first);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn consistent_hash_moves_about_one_nth_of_keys() {
+        let ids: Vec<String> = (0..10).map(|i| format!("node-{}", i)).collect();
+        let weights: Vec<(&str, u32)> = ids.iter().map(|id| (id.as_str(), 100)).collect();
+        let before = endpoints(&weights);
+        let mut grown = weights.clone();
+        grown.push(("node-new", 100));
+        let after = endpoints(&grown);
+
+        let ring_before = ConsistentHashBalancer::default();
+        ring_before.rebuild(&before);
+        let ring_after = ConsistentHashBalancer::default();
+        ring_after.rebuild(&after);
+
+        let total = 10_000;
+        let moved = (0..total)
+            .map(|i| format!("user-{}", i))
+            .filter(|key| ring_before.lookup(key) != ring_after.lookup(key))
+            .count();
+
+        // 理想迁移比例为 1/11（约 909 个）
+        assert!(moved > total / 22, "moved {} keys", moved);
+        assert!(moved < total * 2 / 11, "moved {} keys", moved);
+    }
+
+    #[tokio::test]
+    async fn strategy_maps_to_balancer() {
+        let endpoints = endpoints(&[("a", 100)]);
+        for strategy in [
+            LoadBalanceStrategy::RoundRobin,
+            LoadBalanceStrategy::Random,
+            LoadBalanceStrategy::ConsistentHash,
+            LoadBalanceStrategy::LeastConnections,
+            LoadBalanceStrategy::WeightedRoundRobin,
+            LoadBalanceStrategy::WeightedRandom,
+        ] {
+            let balancer = load_balancer_for(strategy);
+            balancer.rebuild(&endpoints);
+            assert_eq!(balancer.select(&endpoints, Some("k")), Some(0));
+            assert_eq!(balancer.select(&[], Some("k")), None);
+        }
+    }
+}
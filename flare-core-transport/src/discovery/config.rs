@@ -124,12 +124,14 @@ pub struct TagFilter {
 /// 负载均衡策略
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
+#[derive(Default)]
 pub enum LoadBalanceStrategy {
     /// 轮询
     RoundRobin,
     /// 随机
     Random,
     /// 一致性哈希
+    #[default]
     ConsistentHash,
     /// 最少连接
     LeastConnections,
@@ -139,12 +141,6 @@ pub enum LoadBalanceStrategy {
     WeightedRandom,
 }
 
-impl Default for LoadBalanceStrategy {
-    fn default() -> Self {
-        LoadBalanceStrategy::ConsistentHash
-    }
-}
-
 impl std::str::FromStr for LoadBalanceStrategy {
     type Err = String;
 
@@ -1,11 +1,13 @@
 //! Tower Discover trait 实现
 //!
-//! 提供 Channel 缓存、P2C 负载均衡和简洁调用接口
+//! 提供 Channel 缓存、可插拔负载均衡和简洁调用接口
 
-use futures::Stream;
+use futures::{FutureExt, Stream, StreamExt};
 use std::collections::HashMap;
+use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::task::{Context, Poll};
 use tokio::sync::RwLock;
 use tokio::sync::mpsc;
@@ -13,11 +15,19 @@ use tonic::transport::Channel;
 use tower::Service;
 use tower::discover::Change;
 
+use crate::circuit_breaker::{
+    CircuitBreaker, CircuitBreakerConfig, CircuitBreakerRegistry, circuit_open_status,
+};
+use crate::discovery::affinity::AffinityKey;
 use crate::discovery::backend::DiscoveryBackend;
+use crate::discovery::balancer::{
+    BalanceEndpoint, ConsistentHashBalancer, LoadBalancer, load_balancer_for,
+};
 use crate::discovery::config::{
     DiscoveryConfig, TagFilter, default_discovery_refresh_interval_secs,
 };
-use crate::discovery::instance::ServiceInstance;
+use crate::discovery::instance::{DEFAULT_INSTANCE_WEIGHT, ServiceInstance};
+use flare_core_base::context::Context as FlareContext;
 
 /// 将配置中的 tag_filters 转为 backend.discover() 所需的 HashMap。
 /// 仅包含 value 存在的过滤器（精确匹配）。
@@ -34,22 +44,129 @@ fn tag_filters_to_map(config: &DiscoveryConfig) -> Option<HashMap<String, String
 /// Channel 服务包装器
 ///
 /// 实现 tower::Service trait，用于与 tower::balance 集成
-/// 每个 Endpoint 对应一个缓存的 Channel
+/// 每个 Endpoint 对应一个缓存的 Channel，并携带实例权重与在途请求计数
 #[derive(Clone)]
 pub struct ChannelService {
     channel: Channel,
+    weight: u32,
+    in_flight: Arc<AtomicUsize>,
 }
 
 impl ChannelService {
     /// 创建新的 Channel 服务
     pub fn new(channel: Channel) -> Self {
-        Self { channel }
+        Self {
+            channel,
+            weight: DEFAULT_INSTANCE_WEIGHT,
+            in_flight: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// 设置实例权重（用于加权负载均衡）
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
     }
 
     /// 获取底层的 Channel
     pub fn channel(&self) -> &Channel {
         &self.channel
     }
+
+    /// 实例权重
+    pub fn weight(&self) -> u32 {
+        self.weight
+    }
+
+    /// 当前在途请求数
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// 获取统计在途请求数的 Channel（用于最少连接负载均衡）
+    pub fn tracked_channel(&self) -> TrackedChannel {
+        TrackedChannel {
+            channel: self.channel.clone(),
+            in_flight: self.in_flight.clone(),
+            breaker: None,
+        }
+    }
+
+    /// 手动登记一个在途请求，guard 释放时计数减一
+    pub fn begin_request(&self) -> InFlightGuard {
+        InFlightGuard::new(self.in_flight.clone())
+    }
+}
+
+/// 在途请求计数守卫
+pub struct InFlightGuard {
+    counter: Arc<AtomicUsize>,
+}
+
+impl InFlightGuard {
+    fn new(counter: Arc<AtomicUsize>) -> Self {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Self { counter }
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.counter.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// 统计在途请求数的 Channel
+///
+/// 可直接传给 tonic 生成的客户端（`YourServiceClient::new(tracked)`）。
+/// 计数覆盖从发出请求到收到响应头的区间，流式响应体不计入。
+/// 启用熔断时同时向实例的熔断器上报调用结果，熔断期间直接返回 Unavailable。
+#[derive(Clone)]
+pub struct TrackedChannel {
+    channel: Channel,
+    in_flight: Arc<AtomicUsize>,
+    breaker: Option<Arc<CircuitBreaker>>,
+}
+
+impl TrackedChannel {
+    /// 关联实例的熔断器
+    pub fn with_circuit_breaker(mut self, breaker: Arc<CircuitBreaker>) -> Self {
+        self.breaker = Some(breaker);
+        self
+    }
+}
+
+impl Service<http::Request<tonic::body::Body>> for TrackedChannel {
+    type Response = http::Response<tonic::body::Body>;
+    type Error = tonic::transport::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Service::<http::Request<tonic::body::Body>>::poll_ready(&mut self.channel, cx)
+    }
+
+    fn call(&mut self, req: http::Request<tonic::body::Body>) -> Self::Future {
+        let permit = match &self.breaker {
+            Some(breaker) => match breaker.try_acquire() {
+                Some(permit) => Some(permit),
+                None => {
+                    let response = circuit_open_status(breaker).into_http();
+                    return Box::pin(async move { Ok(response) });
+                }
+            },
+            None => None,
+        };
+        let guard = InFlightGuard::new(self.in_flight.clone());
+        let fut = self.channel.call(req);
+        Box::pin(async move {
+            let result = fut.await;
+            drop(guard);
+            if let Some(permit) = permit {
+                permit.record_response(&result);
+            }
+            result
+        })
+    }
 }
 
 /// 实现 tower::Service trait
@@ -73,21 +190,19 @@ where
 
 /// 实现 tower::load::Load trait
 ///
-/// 用于负载均衡，返回服务的负载值
-/// 这里返回一个固定值，表示所有服务负载相同
+/// 用于 tower::balance 负载均衡，返回当前在途请求数
 impl tower::load::Load for ChannelService {
     type Metric = u32;
 
     fn load(&self) -> Self::Metric {
-        // 返回固定负载值，表示所有服务负载相同
-        // 可以根据实际需求实现更复杂的负载计算逻辑
-        0
+        u32::try_from(self.in_flight()).unwrap_or(u32::MAX)
     }
 }
 
 /// 实现 tower::discover::Discover 的服务发现器
 ///
 /// 这是与 tower 生态系统集成的核心类型，可以用于：
+/// - `ServiceClient` 按配置的 `LoadBalanceStrategy` 选择实例
 /// - tower::balance::p2c::Balance 负载均衡
 /// - tonic 客户端自动服务发现
 /// - Channel 缓存：每个 Endpoint 对应一个 tonic::Channel
@@ -104,6 +219,10 @@ pub struct ServiceDiscover {
     #[allow(dead_code)] // 在 start_refresh_task 中使用
     config: DiscoveryConfig,
     instances: Arc<RwLock<HashMap<String, ServiceInstance>>>,
+    // 负载均衡器（默认按 config.load_balance 创建）
+    load_balancer: Arc<dyn LoadBalancer>,
+    // 按实例 ID 维护的熔断器（默认不启用）
+    circuit_breakers: Option<Arc<CircuitBreakerRegistry>>,
 }
 
 /// ServiceDiscover 的更新器
@@ -134,6 +253,7 @@ impl ServiceDiscover {
     ) -> (Self, ServiceDiscoverUpdater) {
         let (tx, rx) = mpsc::channel(buffer);
         let channel_cache = Arc::new(RwLock::new(HashMap::new()));
+        let load_balancer = load_balancer_for(config.load_balance);
 
         (
             Self {
@@ -142,6 +262,8 @@ impl ServiceDiscover {
                 backend,
                 config,
                 instances: Arc::new(RwLock::new(HashMap::new())),
+                load_balancer,
+                circuit_breakers: None,
             },
             ServiceDiscoverUpdater { tx, channel_cache },
         )
@@ -299,6 +421,77 @@ impl ServiceDiscover {
         Ok(())
     }
 
+    /// 替换负载均衡器（默认按 `DiscoveryConfig::load_balance` 创建）
+    pub fn with_load_balancer(mut self, load_balancer: Arc<dyn LoadBalancer>) -> Self {
+        self.load_balancer = load_balancer;
+        self
+    }
+
+    /// 当前使用的负载均衡器
+    pub fn load_balancer(&self) -> Arc<dyn LoadBalancer> {
+        self.load_balancer.clone()
+    }
+
+    /// 启用按实例熔断
+    ///
+    /// 选择实例时跳过熔断中的实例，直到其进入 HalfOpen 探测恢复；
+    /// 调用结果通过 [`ServiceDiscover::tracked_channel`] 返回的 `TrackedChannel` 上报，
+    /// 实例下线时其熔断器随 Remove 事件一并移除。
+    pub fn with_circuit_breaker(self, config: CircuitBreakerConfig) -> Self {
+        self.with_circuit_breaker_registry(Arc::new(CircuitBreakerRegistry::new(config)))
+    }
+
+    /// 使用已有的熔断器注册表（可在多个 `ServiceDiscover` 间共享或用于外部观测）
+    pub fn with_circuit_breaker_registry(mut self, registry: Arc<CircuitBreakerRegistry>) -> Self {
+        self.circuit_breakers = Some(registry);
+        self
+    }
+
+    /// 熔断器注册表（未启用熔断时为 None）
+    pub fn circuit_breakers(&self) -> Option<Arc<CircuitBreakerRegistry>> {
+        self.circuit_breakers.clone()
+    }
+
+    /// 过滤熔断中的实例
+    ///
+    /// 未启用熔断或没有实例被熔断时返回 None（直接使用完整实例集合）；
+    /// 否则返回可用实例及其在 `endpoints` 中的下标，全部熔断时返回 Unavailable。
+    #[allow(clippy::type_complexity)]
+    pub fn available_endpoints(
+        &self,
+        endpoints: &[BalanceEndpoint],
+    ) -> Result<Option<(Vec<BalanceEndpoint>, Vec<usize>)>, Box<dyn std::error::Error + Send + Sync>>
+    {
+        let Some(registry) = &self.circuit_breakers else {
+            return Ok(None);
+        };
+        let available: Vec<usize> = (0..endpoints.len())
+            .filter(|&idx| !registry.is_open(endpoints[idx].id()))
+            .collect();
+        if available.len() == endpoints.len() {
+            return Ok(None);
+        }
+        if available.is_empty() {
+            return Err(Box::new(tonic::Status::unavailable(
+                "all service instances are circuit-open",
+            )));
+        }
+        let selected = available
+            .iter()
+            .map(|&idx| endpoints[idx].clone())
+            .collect();
+        Ok(Some((selected, available)))
+    }
+
+    /// 为实例创建统计在途请求数的 Channel，启用熔断时关联该实例的熔断器
+    pub fn tracked_channel(&self, endpoint: &BalanceEndpoint) -> TrackedChannel {
+        let tracked = endpoint.service().tracked_channel();
+        match &self.circuit_breakers {
+            Some(registry) => tracked.with_circuit_breaker(registry.get_or_create(endpoint.id())),
+            None => tracked,
+        }
+    }
+
     /// 获取所有实例
     pub async fn get_instances(&self) -> Vec<ServiceInstance> {
         let instances = self.instances.read().await;
@@ -349,7 +542,7 @@ impl ServiceDiscoverUpdater {
             .map_err(|e| format!("Invalid URI: {}", e))?;
         let channel = endpoint.connect_lazy();
 
-        let service = ChannelService::new(channel);
+        let service = ChannelService::new(channel).with_weight(instance.effective_weight());
 
         // 缓存 Channel
         {
@@ -393,7 +586,13 @@ impl Stream for ServiceDiscover {
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         match self.rx.poll_recv(cx) {
-            Poll::Ready(Some(change)) => Poll::Ready(Some(Ok(change))),
+            Poll::Ready(Some(change)) => {
+                // 实例下线时丢弃其熔断状态，同 ID 重新上线后从 Closed 开始
+                if let (Change::Remove(id), Some(registry)) = (&change, &self.circuit_breakers) {
+                    registry.remove(id);
+                }
+                Poll::Ready(Some(Ok(change)))
+            }
             Poll::Ready(None) => Poll::Ready(None), // channel 关闭
             Poll::Pending => Poll::Pending,
         }
@@ -407,25 +606,33 @@ impl Stream for ServiceDiscover {
 
 /// 服务发现客户端
 ///
-/// 提供简洁的调用接口，集成 Channel 缓存和 P2C 负载均衡
-///
-/// 注意：Balance 的第二个类型参数是请求类型，我们使用 () 作为占位符
+/// 提供简洁的调用接口，集成 Channel 缓存和可插拔负载均衡：
+/// 消费 `ServiceDiscover` 的 Change 事件维护实例集合，
+/// 每次取 Channel 时由 `LoadBalancer`（默认按 `DiscoveryConfig::load_balance` 创建）选择实例。
 ///
 /// 注意：ServiceClient 不支持 Clone，并发调用时请使用 `Arc<Mutex<ServiceClient>>`
 pub struct ServiceClient {
-    balancer: tower::balance::p2c::Balance<
-        Pin<Box<ServiceDiscover>>,
-        (), // 请求类型是 ()，因为我们只需要获取 Channel
-    >,
+    discover: Pin<Box<ServiceDiscover>>,
+    // 按实例 ID 升序排列
+    endpoints: Vec<BalanceEndpoint>,
+    load_balancer: Arc<dyn LoadBalancer>,
+    // 粘性路由专用哈希环，与配置的负载均衡策略无关
+    affinity_ring: ConsistentHashBalancer,
+    // 实例集合变化后置位，下一次粘性路由时重建哈希环
+    affinity_dirty: bool,
 }
 
 impl ServiceClient {
     /// 从 ServiceDiscover 创建客户端
     pub fn new(discover: ServiceDiscover) -> Self {
-        // Balance 的第二个类型参数是请求类型，我们使用 () 作为占位符
-        let balancer: tower::balance::p2c::Balance<Pin<Box<ServiceDiscover>>, ()> =
-            tower::balance::p2c::Balance::new(Box::pin(discover));
-        Self { balancer }
+        let load_balancer = discover.load_balancer();
+        Self {
+            discover: Box::pin(discover),
+            endpoints: Vec::new(),
+            load_balancer,
+            affinity_ring: ConsistentHashBalancer::default(),
+            affinity_dirty: true,
+        }
     }
 
     /// 调用服务（简洁接口）
@@ -439,19 +646,7 @@ impl ServiceClient {
         &mut self,
         _req: Req,
     ) -> Result<Channel, Box<dyn std::error::Error + Send + Sync>> {
-        use tower::Service;
-
-        // Balance 实现了 Service trait
-        // Balance 的 Request 类型是 ()，Response 是 Channel
-        // call() 方法会自动处理 ready 检查，如果没有服务可用会等待
-        // 调用时会自动选择一个 ChannelService，然后调用其 call(()) 方法返回 Channel
-        let channel = self
-            .balancer
-            .call(())
-            .await
-            .map_err(|e| format!("Service call error: {}", e))?;
-
-        Ok(channel)
+        self.get_channel().await
     }
 
     /// 获取 Channel（不发送请求）
@@ -464,53 +659,236 @@ impl ServiceClient {
     pub async fn get_channel(
         &mut self,
     ) -> Result<Channel, Box<dyn std::error::Error + Send + Sync>> {
-        use futures::future::poll_fn;
-        use tower::Service;
-
-        // Balance 实现了 Service trait
-        // 我们需要先确保 Balance 已经准备好（即已经发现了至少一个服务）
-        // 使用 poll_fn 配合 yield_now 来等待 ready 状态
-        // 这样 Balance 内部的 Discover 才能处理 Stream 事件
-
-        // 尝试最多 100 次（10 秒），每次等待 100ms
-        for _attempt in 0..100 {
-            // 让出执行权，允许 Balance 处理 Discover 的 Stream 事件
-            tokio::task::yield_now().await;
-
-            // 使用 poll_fn 来检查是否准备好
-            // poll_fn 会在每次 poll 时调用 poll_ready，如果返回 Pending 会继续等待
-            let ready_result = poll_fn(|cx| self.balancer.poll_ready(cx)).await;
-
-            match ready_result {
-                Ok(()) => {
-                    // 服务已经准备好，可以调用
-                    break;
+        let service = self.select(None).await?;
+        Ok(service.channel().clone())
+    }
+
+    /// 按路由键获取 Channel
+    ///
+    /// 一致性哈希策略下，相同 key（如 conversation_id、user_id）总是落到同一实例；
+    /// 其他策略忽略 key。
+    pub async fn get_channel_by_key(
+        &mut self,
+        key: &str,
+    ) -> Result<Channel, Box<dyn std::error::Error + Send + Sync>> {
+        let service = self.select(Some(key)).await?;
+        Ok(service.channel().clone())
+    }
+
+    /// 获取统计在途请求数的 Channel
+    ///
+    /// 最少连接策略依赖在途请求数，使用该策略时应通过此方法获取 Channel。
+    /// 启用熔断时，只有通过此方法发出的调用会上报到实例的熔断器。
+    pub async fn get_tracked_channel(
+        &mut self,
+        key: Option<&str>,
+    ) -> Result<TrackedChannel, Box<dyn std::error::Error + Send + Sync>> {
+        let idx = self.select_index(key).await?;
+        Ok(self.discover.tracked_channel(&self.endpoints[idx]))
+    }
+
+    /// 按 Context 字段粘性路由获取 Channel
+    ///
+    /// 从 `ctx` 取出 `key` 对应的字段（user_id、session_id、tenant_id 或自定义 key），
+    /// 在带虚拟节点的一致性哈希环上选择实例，与配置的负载均衡策略无关。
+    /// 哈希环随 `ServiceDiscover` 的 Change 事件重建，实例增减时只有约 1/N 的 key 迁移。
+    ///
+    /// 字段缺失时退化为配置的负载均衡策略。
+    pub async fn get_channel_for_ctx<C>(
+        &mut self,
+        ctx: &C,
+        key: &AffinityKey,
+    ) -> Result<Channel, Box<dyn std::error::Error + Send + Sync>>
+    where
+        C: AsRef<FlareContext>,
+    {
+        let Some(hash_key) = key.resolve(ctx.as_ref()) else {
+            tracing::debug!(
+                affinity_key = key.name(),
+                "affinity key missing in context, falling back to load balancer"
+            );
+            return self.get_channel().await;
+        };
+
+        self.wait_for_endpoints().await?;
+        if self.affinity_dirty {
+            self.affinity_ring.rebuild(&self.endpoints);
+            self.affinity_dirty = false;
+        }
+
+        let idx = match self.discover.available_endpoints(&self.endpoints)? {
+            // 熔断中的实例不参与选择，其 key 按取模落到剩余实例
+            Some((available, indices)) => self
+                .affinity_ring
+                .select(&available, Some(hash_key))
+                .and_then(|i| indices.get(i).copied()),
+            None => self.affinity_ring.select(&self.endpoints, Some(hash_key)),
+        }
+        .ok_or("Affinity ring returned no endpoint")?;
+        Ok(self.endpoints[idx].service().channel().clone())
+    }
+
+    /// 当前可选实例数
+    pub fn endpoint_count(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    /// 等待至少一个实例可用，然后通过负载均衡器选择
+    async fn select(
+        &mut self,
+        key: Option<&str>,
+    ) -> Result<ChannelService, Box<dyn std::error::Error + Send + Sync>> {
+        let idx = self.select_index(key).await?;
+        Ok(self.endpoints[idx].service().clone())
+    }
+
+    async fn select_index(
+        &mut self,
+        key: Option<&str>,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        self.wait_for_endpoints().await?;
+
+        let idx = match self.discover.available_endpoints(&self.endpoints)? {
+            Some((available, indices)) => self
+                .load_balancer
+                .select(&available, key)
+                .and_then(|i| indices.get(i).copied()),
+            None => self.load_balancer.select(&self.endpoints, key),
+        };
+        idx.filter(|idx| *idx < self.endpoints.len())
+            .ok_or_else(|| "Load balancer returned no endpoint".into())
+    }
+
+    /// 应用已就绪的 Change 事件，没有实例时等待直到至少一个实例可用
+    async fn wait_for_endpoints(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.poll_changes();
+        while self.endpoints.is_empty() {
+            match self.discover.next().await {
+                Some(Ok(change)) => {
+                    self.apply_change(change);
+                    self.poll_changes();
+                    self.on_endpoints_changed();
                 }
-                Err(e) => {
-                    return Err(format!("Service not ready: {}", e).into());
+                Some(Err(never)) => match never {},
+                None => return Err("Service discovery stream closed".into()),
+            }
+        }
+        Ok(())
+    }
+
+    /// 取出所有已就绪的 Change 事件（不等待），有变化时重建负载均衡器状态
+    fn poll_changes(&mut self) {
+        let mut changed = false;
+        while let Some(Some(Ok(change))) = self.discover.next().now_or_never() {
+            self.apply_change(change);
+            changed = true;
+        }
+        if changed {
+            self.on_endpoints_changed();
+        }
+    }
+
+    fn on_endpoints_changed(&mut self) {
+        self.load_balancer.rebuild(&self.endpoints);
+        self.affinity_dirty = true;
+    }
+
+    fn apply_change(&mut self, change: Change<String, ChannelService>) {
+        match change {
+            Change::Insert(id, service) => {
+                match self.endpoints.binary_search_by(|e| e.id().cmp(id.as_str())) {
+                    Ok(idx) => self.endpoints[idx] = BalanceEndpoint::new(id, service),
+                    Err(idx) => self
+                        .endpoints
+                        .insert(idx, BalanceEndpoint::new(id, service)),
+                }
+            }
+            Change::Remove(id) => {
+                if let Ok(idx) = self.endpoints.binary_search_by(|e| e.id().cmp(id.as_str())) {
+                    self.endpoints.remove(idx);
                 }
             }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::backend::mesh::MeshBackend;
+    use crate::discovery::config::{BackendType, LoadBalanceStrategy};
+
+    async fn discover() -> (ServiceDiscover, ServiceDiscoverUpdater) {
+        let config = DiscoveryConfig {
+            backend: BackendType::Mesh,
+            backend_config: HashMap::new(),
+            namespace: None,
+            version: None,
+            tag_filters: vec![],
+            load_balance: LoadBalanceStrategy::RoundRobin,
+            health_check: None,
+            refresh_interval: None,
+        };
+        let backend = Arc::new(MeshBackend::new(&config).await.unwrap());
+        let (discover, updater) = ServiceDiscover::new_internal(16, backend, config);
+        let breaker = CircuitBreakerConfig {
+            consecutive_failures: 1,
+            ..CircuitBreakerConfig::default()
+        };
+        (discover.with_circuit_breaker(breaker), updater)
+    }
+
+    fn channel_service() -> ChannelService {
+        ChannelService::new(
+            tonic::transport::Endpoint::from_static("http://127.0.0.1:1").connect_lazy(),
+        )
+    }
 
-            // 如果还没准备好（这里实际上不会执行，因为 match 的所有分支都会返回或跳出）
-            // 但保留这个 sleep 以防逻辑变化
-            #[allow(unreachable_code)]
-            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    fn trip(registry: &CircuitBreakerRegistry, id: &str) {
+        registry
+            .get_or_create(id)
+            .try_acquire()
+            .expect("permit")
+            .failure();
+    }
+
+    async fn selected_id(client: &mut ServiceClient) -> Result<String, String> {
+        match client.select_index(None).await {
+            Ok(idx) => Ok(client.endpoints[idx].id().to_string()),
+            Err(err) => Err(err.to_string()),
         }
+    }
 
-        // 循环结束意味着服务已经准备好（通过 break 跳出）
-        // 再次确认服务准备好（虽然理论上已经准备好了）
-        poll_fn(|cx| self.balancer.poll_ready(cx))
-            .await
-            .map_err(|e| format!("Service not ready after waiting: {}", e))?;
+    #[tokio::test]
+    async fn skips_circuit_open_instances() {
+        let (discover, updater) = discover().await;
+        let registry = discover.circuit_breakers().unwrap();
+        updater.insert("a", channel_service()).await;
+        updater.insert("b", channel_service()).await;
+        let mut client = ServiceClient::new(discover);
+
+        trip(&registry, "a");
+        for _ in 0..4 {
+            assert_eq!(selected_id(&mut client).await.unwrap(), "b");
+        }
 
-        // Balance 的 Request 类型是 ()，Response 是 Channel
-        // 调用时会自动选择一个 ChannelService，然后调用其 call(()) 方法返回 Channel
-        let channel = self
-            .balancer
-            .call(())
-            .await
-            .map_err(|e| format!("Service call error: {}", e))?;
+        trip(&registry, "b");
+        let err = selected_id(&mut client).await.unwrap_err();
+        assert!(err.contains("circuit-open"), "{err}");
+    }
 
-        Ok(channel)
+    #[tokio::test]
+    async fn removed_instance_drops_breaker_state() {
+        let (discover, updater) = discover().await;
+        let registry = discover.circuit_breakers().unwrap();
+        updater.insert("a", channel_service()).await;
+        updater.insert("b", channel_service()).await;
+        let mut client = ServiceClient::new(discover);
+        assert!(selected_id(&mut client).await.is_ok());
+
+        trip(&registry, "a");
+        updater.remove("a").await;
+        assert_eq!(selected_id(&mut client).await.unwrap(), "b");
+        assert!(registry.get("a").is_none());
     }
 }
@@ -262,7 +262,7 @@ async fn example_tonic_integration() -> Result<(), Box<dyn std::error::Error + S
 
     let (discover, updater) = DiscoveryFactory::create_discover(config).await?;
 
-    // 创建服务客户端（集成 Channel 缓存和 P2C 负载均衡）
+    // 创建服务客户端（集成 Channel 缓存和按配置策略的负载均衡）
     use crate::discovery::ServiceClient;
     let mut client = ServiceClient::new(discover);
 
@@ -68,7 +68,7 @@ impl DiscoveryFactory {
     /// - TTL：45 秒（Consul，可通过 `CONSUL_TTL_SECONDS` 调整）或 60 秒（etcd，可通过 `ETCD_TTL_SECONDS` 调整）
     /// - 刷新间隔：30 秒
     /// - 健康检查：启用，间隔 10 秒，超时 5 秒
-    /// - 负载均衡：P2C（Power of Two Choices）
+    /// - 负载均衡：一致性哈希（`ServiceClient::get_channel_by_key` 按 key 粘性路由）
     /// - 失败阈值：3 次
     /// - 成功阈值：2 次
     ///
@@ -426,7 +426,7 @@ impl ServiceRegistry {
     pub async fn shutdown(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         if let Some(shutdown_tx) = self.shutdown_tx.take() {
             // 停止心跳任务
-            let _ = shutdown_tx.send(());
+            let _ = shutdown_tx.send(()).await;
 
             // 等待一小段时间，确保心跳任务已停止
             tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
@@ -4,6 +4,12 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::SocketAddr;
 
+/// 默认实例权重
+pub const DEFAULT_INSTANCE_WEIGHT: u32 = 100;
+
+/// 覆盖实例权重的元数据键（`InstanceMetadata::custom`）
+pub const WEIGHT_METADATA_KEY: &str = "weight";
+
 /// 服务实例
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ServiceInstance {
@@ -67,7 +73,7 @@ impl ServiceInstance {
             tags: HashMap::new(),
             metadata: InstanceMetadata::default(),
             healthy: true,
-            weight: 100,
+            weight: DEFAULT_INSTANCE_WEIGHT,
         }
     }
 
@@ -95,6 +101,18 @@ impl ServiceInstance {
         self
     }
 
+    /// 负载均衡使用的权重
+    ///
+    /// `metadata.custom["weight"]` 存在且可解析时优先使用（便于在注册中心元数据中调权），
+    /// 否则使用 `weight` 字段。
+    pub fn effective_weight(&self) -> u32 {
+        self.metadata
+            .custom
+            .get(WEIGHT_METADATA_KEY)
+            .and_then(|w| w.trim().parse::<u32>().ok())
+            .unwrap_or(self.weight)
+    }
+
     /// 设置健康状态
     pub fn with_health(mut self, healthy: bool) -> Self {
         self.healthy = healthy;
@@ -3,18 +3,32 @@
 //! 提供统一的服务发现抽象，支持多种后端（etcd、consul、DNS、Service Mesh），
 //! 完全兼容 tower 生态系统，支持命名空间、版本控制和自定义标签。
 
+pub mod affinity;
 pub mod backend;
+pub mod balancer;
 pub mod config;
 pub mod discover;
 pub mod examples;
 pub mod factory;
 pub mod instance;
 
+pub use crate::circuit_breaker::{CircuitBreakerConfig, CircuitBreakerRegistry, CircuitState};
+pub use affinity::AffinityKey;
 pub use backend::DiscoveryBackend;
+pub use balancer::{
+    BalanceEndpoint, ConsistentHashBalancer, LeastConnectionsBalancer, LoadBalancer,
+    RandomBalancer, RoundRobinBalancer, WeightedRandomBalancer, WeightedRoundRobinBalancer,
+    load_balancer_for,
+};
 pub use config::{
     BackendType, DiscoveryConfig, HealthCheckConfig, LoadBalanceStrategy, NamespaceConfig,
     TagFilter, VersionConfig, default_discovery_refresh_interval_secs,
 };
-pub use discover::{ChannelService, ServiceClient, ServiceDiscover, ServiceDiscoverUpdater};
+pub use discover::{
+    ChannelService, InFlightGuard, ServiceClient, ServiceDiscover, ServiceDiscoverUpdater,
+    TrackedChannel,
+};
 pub use factory::{DiscoveryFactory, ServiceRegistry};
-pub use instance::{InstanceMetadata, ServiceInstance};
+pub use instance::{
+    DEFAULT_INSTANCE_WEIGHT, InstanceMetadata, ServiceInstance, WEIGHT_METADATA_KEY,
+};
@@ -73,7 +73,7 @@ where
         self
     }
 
-    ///! 获取监听地址
+    /// 获取监听地址
     pub fn address(&self) -> SocketAddr {
         self.address
     }
@@ -1,6 +1,7 @@
 //! gRPC 客户端拦截器
 //!
 //! 提供自动添加 Context 的拦截器，与 context/core 对齐。
+//! 编码前以当前 span 替换 `traceparent` 中的 span id，下游看到的父 span 是本次调用所在的 span。
 
 use tonic::service::Interceptor;
 use tonic::{Request, Status};
@@ -8,6 +9,7 @@ use uuid::Uuid;
 
 use crate::grpc::utils::{decode_context_from_metadata, encode_context_to_metadata};
 use flare_core_base::context::Context;
+use flare_core_infra::ctx_with_current_span;
 
 /// 客户端上下文拦截器配置
 #[derive(Debug, Clone)]
@@ -69,8 +71,10 @@ impl ClientContextInterceptor {
     pub fn new(config: ClientContextConfig) -> Self {
         Self { config }
     }
+}
 
-    pub fn default() -> Self {
+impl Default for ClientContextInterceptor {
+    fn default() -> Self {
         Self::new(ClientContextConfig::default())
     }
 }
@@ -82,10 +86,10 @@ impl Interceptor for ClientContextInterceptor {
             .unwrap_or_else(Context::root);
 
         if let Some(ref default) = self.config.default_context {
-            if ctx.tenant_id().is_none() {
-                if let Some(tid) = default.tenant_id() {
-                    ctx = ctx.with_tenant_id(tid);
-                }
+            if ctx.tenant_id().is_none()
+                && let Some(tid) = default.tenant_id()
+            {
+                ctx = ctx.with_tenant_id(tid);
             }
             if ctx.trace_id().is_empty() {
                 let tid = default.trace_id();
@@ -93,37 +97,37 @@ impl Interceptor for ClientContextInterceptor {
                     ctx = ctx.with_trace_id(tid);
                 }
             }
-            if ctx.user_id().is_none() {
-                if let Some(uid) = default.user_id() {
-                    ctx = ctx.with_user_id(uid);
-                }
+            if ctx.user_id().is_none()
+                && let Some(uid) = default.user_id()
+            {
+                ctx = ctx.with_user_id(uid);
             }
-            if ctx.device_id().is_none() {
-                if let Some(did) = default.device_id() {
-                    ctx = ctx.with_device_id(did);
-                }
+            if ctx.device_id().is_none()
+                && let Some(did) = default.device_id()
+            {
+                ctx = ctx.with_device_id(did);
             }
-            if ctx.platform().is_none() {
-                if let Some(p) = default.platform() {
-                    ctx = ctx.with_platform(p);
-                }
+            if ctx.platform().is_none()
+                && let Some(p) = default.platform()
+            {
+                ctx = ctx.with_platform(p);
             }
-            if ctx.session_id().is_none() {
-                if let Some(sid) = default.session_id() {
-                    ctx = ctx.with_session_id(sid);
-                }
+            if ctx.session_id().is_none()
+                && let Some(sid) = default.session_id()
+            {
+                ctx = ctx.with_session_id(sid);
             }
-            if ctx.actor().is_none() {
-                if let Some(actor) = default.actor() {
-                    ctx = ctx.with_actor(actor.clone());
-                }
+            if ctx.actor().is_none()
+                && let Some(actor) = default.actor()
+            {
+                ctx = ctx.with_actor(actor.clone());
             }
         }
 
-        if let Some(ref tenant_id) = self.config.default_tenant_id {
-            if ctx.tenant_id().is_none() {
-                ctx = ctx.with_tenant_id(tenant_id.as_str());
-            }
+        if let Some(ref tenant_id) = self.config.default_tenant_id
+            && ctx.tenant_id().is_none()
+        {
+            ctx = ctx.with_tenant_id(tenant_id.as_str());
         }
 
         if self.config.auto_generate_request_id && ctx.request_id().is_empty() {
@@ -150,7 +154,7 @@ impl Interceptor for ClientContextInterceptor {
             ctx = ctx.with_trace_id(Uuid::new_v4().to_string());
         }
 
-        encode_context_to_metadata(request.metadata_mut(), &ctx);
+        encode_context_to_metadata(request.metadata_mut(), &ctx_with_current_span(&ctx));
         Ok(request)
     }
 }
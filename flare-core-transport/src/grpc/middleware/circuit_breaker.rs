@@ -0,0 +1,135 @@
+//! 客户端熔断中间件
+//!
+//! 为单个 Channel 叠加 [`CircuitBreaker`]（状态机与失败分类见 [`crate::circuit_breaker`]）；
+//! 服务发现场景下改用 `ServiceDiscover::with_circuit_breaker` 按实例熔断。
+//!
+//! ```rust,ignore
+//! use flare_server_core::grpc::middleware::{CircuitBreakerConfig, CircuitBreakerLayer};
+//! use tower::ServiceBuilder;
+//!
+//! let channel = ServiceBuilder::new()
+//!     .layer(CircuitBreakerLayer::from_config("user-service", CircuitBreakerConfig::default()))
+//!     .service(channel);
+//! let client = YourServiceClient::new(channel);
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+
+use http::{Request as HttpRequest, Response as HttpResponse};
+use tower::{Layer, Service};
+
+pub use crate::circuit_breaker::{
+    CircuitBreaker, CircuitBreakerConfig, CircuitBreakerRegistry, CircuitPermit, CircuitState,
+    circuit_open_status,
+};
+
+/// 熔断中间件层（客户端）
+#[derive(Clone)]
+pub struct CircuitBreakerLayer {
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl CircuitBreakerLayer {
+    /// 使用已有熔断器（可与 [`CircuitBreakerRegistry`] 共享）
+    pub fn new(breaker: Arc<CircuitBreaker>) -> Self {
+        Self { breaker }
+    }
+
+    /// 按配置创建独立的熔断器
+    pub fn from_config(name: impl Into<String>, config: CircuitBreakerConfig) -> Self {
+        Self::new(Arc::new(CircuitBreaker::new(name, config)))
+    }
+
+    /// 当前使用的熔断器
+    pub fn breaker(&self) -> &Arc<CircuitBreaker> {
+        &self.breaker
+    }
+}
+
+impl<S> Layer<S> for CircuitBreakerLayer {
+    type Service = CircuitBreakerService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        CircuitBreakerService {
+            inner: service,
+            breaker: self.breaker.clone(),
+        }
+    }
+}
+
+/// 熔断服务
+///
+/// 熔断时直接返回 `grpc-status: 14`（Unavailable）的 trailers-only 响应，
+/// 可与 [`RetryLayer`](super::RetryLayer) 叠加使用。
+#[derive(Clone)]
+pub struct CircuitBreakerService<S> {
+    inner: S,
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl<S, ReqBody, ResBody> Service<HttpRequest<ReqBody>> for CircuitBreakerService<S>
+where
+    S: Service<HttpRequest<ReqBody>, Response = HttpResponse<ResBody>>,
+    S::Future: Send + 'static,
+    ResBody: Default + Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: HttpRequest<ReqBody>) -> Self::Future {
+        let Some(permit) = self.breaker.try_acquire() else {
+            let response = circuit_open_status(&self.breaker).into_http();
+            return Box::pin(async move { Ok(response) });
+        };
+        let fut = self.inner.call(req);
+        Box::pin(async move {
+            let result = fut.await;
+            permit.record_response(&result);
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tonic::Status;
+    use tower::ServiceExt;
+
+    fn config() -> CircuitBreakerConfig {
+        CircuitBreakerConfig {
+            consecutive_failures: 3,
+            open_duration: Duration::from_millis(50),
+            ..CircuitBreakerConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn layer_rejects_while_open() {
+        let layer = CircuitBreakerLayer::from_config("a", config());
+        let breaker = layer.breaker().clone();
+        let svc = layer.layer(tower::service_fn(|_req: HttpRequest<()>| async {
+            let response = Status::unavailable("down").into_http::<String>();
+            Ok::<_, std::convert::Infallible>(response)
+        }));
+
+        for _ in 0..3 {
+            svc.clone().oneshot(HttpRequest::new(())).await.unwrap();
+        }
+        assert!(breaker.is_open());
+
+        let response = svc.clone().oneshot(HttpRequest::new(())).await.unwrap();
+        let status = Status::from_header_map(response.headers()).expect("grpc-status");
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+        assert!(status.message().contains("circuit breaker open"));
+    }
+}
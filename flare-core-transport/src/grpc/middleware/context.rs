@@ -1,6 +1,9 @@
 //! Context 中间件
 //!
 //! 从 gRPC 请求 metadata 解码并注入 `Context`（Ctx）到请求扩展，与 context/core 对齐。
+//! 请求携带 `grpc-timeout` 时转换为 Context 的 deadline，到期后 Context 被取消，
+//! 基于该 Context 发起的下游调用随之携带剩余时间、停止工作。
+//! 每个请求在 `grpc_request` span 内处理，其父 span 为调用方 `traceparent` 中的 span。
 
 use std::convert::Infallible;
 use std::sync::Arc;
@@ -8,10 +11,11 @@ use std::task::{Context as TaskContext, Poll};
 use tonic::body::Body;
 use tonic::{Request as TonicRequest, Status};
 use tower::{Layer, Service};
-use tracing::{debug, warn};
+use tracing::{Instrument, debug, warn};
 
-use crate::grpc::utils::decode_context_from_metadata;
+use crate::grpc::utils::{decode_context_from_metadata, timeout_from_metadata};
 use flare_core_base::context::{Context, Ctx};
+use flare_core_infra::set_span_parent_from_ctx;
 use http::Request as HttpRequest;
 
 /// Context 中间件层
@@ -102,16 +106,22 @@ where
             use tonic::metadata::MetadataMap;
             let mut m = MetadataMap::new();
             for (key, value) in req.headers() {
-                if let Ok(k) = key.as_str().parse::<tonic::metadata::MetadataKey<_>>() {
-                    if let Ok(s) = value.to_str() {
-                        if let Ok(v) = s.parse::<tonic::metadata::MetadataValue<_>>() {
-                            m.insert(k, v);
-                        }
-                    }
+                if let Ok(k) = key.as_str().parse::<tonic::metadata::MetadataKey<_>>()
+                    && let Ok(s) = value.to_str()
+                    && let Ok(v) = s.parse::<tonic::metadata::MetadataValue<_>>()
+                {
+                    m.insert(k, v);
                 }
             }
             m
         };
+        let timeout = timeout_from_metadata(&metadata);
+        let with_deadline = move |ctx: Context| -> Ctx {
+            match timeout {
+                Some(timeout) => Arc::new(ctx.with_timeout(timeout)),
+                None => Arc::new(ctx),
+            }
+        };
 
         Box::pin(async move {
             let ctx_opt = req
@@ -121,43 +131,44 @@ where
                 .or_else(|| decode_context_from_metadata(&metadata));
 
             let ctx = match ctx_opt {
-                Some(arc) => arc,
+                Some(ctx) => {
+                    debug!(
+                        request_id = %ctx.request_id(),
+                        trace_id = %ctx.trace_id(),
+                        tenant_id = ctx.tenant_id().unwrap_or("none"),
+                        "Context from request"
+                    );
+                    match (&default_tenant_id, timeout) {
+                        (Some(tid), _) if ctx.tenant_id().is_none() => {
+                            with_deadline((*ctx).clone().with_tenant_id(tid.as_str()))
+                        }
+                        (_, Some(_)) => with_deadline((*ctx).clone()),
+                        _ => ctx,
+                    }
+                }
                 None if allow_missing => {
                     warn!("Context not found, allow_missing=true, using default");
                     let default_ctx = match &default_tenant_id {
                         Some(tid) => Context::root().with_tenant_id(tid.as_str()),
                         None => Context::root(),
                     };
-                    req.extensions_mut().insert(Arc::new(default_ctx));
-                    return inner.call(req).await;
+                    with_deadline(default_ctx)
                 }
                 None => {
                     warn!("Context required but not found, injecting root");
-                    let default_ctx = Context::root();
-                    req.extensions_mut().insert(Arc::new(default_ctx));
-                    return inner.call(req).await;
+                    with_deadline(Context::root())
                 }
             };
 
-            debug!(
+            let span = tracing::info_span!(
+                "grpc_request",
+                path = %req.uri().path(),
                 request_id = %ctx.request_id(),
-                trace_id = %ctx.trace_id(),
-                tenant_id = ctx.tenant_id().unwrap_or("none"),
-                "Context from request"
             );
+            set_span_parent_from_ctx(&span, &ctx);
+            req.extensions_mut().insert(ctx);
 
-            if ctx.tenant_id().is_none() {
-                if let Some(tid) = &default_tenant_id {
-                    let with_tenant = (*ctx).clone().with_tenant_id(tid.as_str());
-                    req.extensions_mut().insert(Arc::new(with_tenant));
-                } else {
-                    req.extensions_mut().insert(ctx);
-                }
-            } else {
-                req.extensions_mut().insert(ctx);
-            }
-
-            inner.call(req).await
+            inner.call(req).instrument(span).await
         })
     }
 }
@@ -1,6 +1,6 @@
 //! gRPC 中间件
 //!
-//! 提供超时、限流、重试、Context 等中间件。
+//! 提供超时、限流、重试、熔断、Context 等中间件。
 //!
 //! ```rust,ignore
 //! use flare_server_core::middleware::ContextLayer;
@@ -13,16 +13,24 @@
 //!     .await?;
 //! ```
 
+pub mod circuit_breaker;
 pub mod context;
 pub mod rate_limit;
 pub mod retry;
 pub mod timeout;
 
+pub use circuit_breaker::{
+    CircuitBreaker, CircuitBreakerConfig, CircuitBreakerLayer, CircuitBreakerRegistry,
+    CircuitBreakerService, CircuitPermit, CircuitState,
+};
 pub use context::{
     ContextLayer, ContextService, extract_actor_id, extract_context, extract_request_id,
     extract_tenant_id, extract_user_id, get_context, require_actor_id, require_request_id,
     require_tenant_id, require_user_id,
 };
-pub use rate_limit::RateLimitLayer;
-pub use retry::RetryLayer;
-pub use timeout::TimeoutLayer;
+pub use rate_limit::{
+    KeyedRateLimitLayer, KeyedRateLimitService, MissingKeyPolicy, RETRY_AFTER_MS_METADATA,
+    RateLimitKey, RateLimitLayer,
+};
+pub use retry::{RetryBudget, RetryLayer, RetryService};
+pub use timeout::{GrpcTimeoutLayer, GrpcTimeoutService, TimeoutLayer};
@@ -1,7 +1,283 @@
+//! 限流中间件
+//!
+//! - [`RateLimitLayer`]：最大并发数限制。
+//! - [`KeyedRateLimitLayer`]：按租户 / 用户 / 方法维度的每秒令牌桶限流，读取 `ContextLayer` 注入的 Context；
+//!   超限返回 `ResourceExhausted`，metadata 携带 `x-retry-after-ms`，details 携带
+//!   `ErrorCode::MessageRateLimitExceeded`。令牌桶由 `RateLimitStore` 承载（默认进程内，可换 Redis）。
+//!   缺少限流字段的请求按 [`MissingKeyPolicy`] 处理。
+//!
+//! ```rust,ignore
+//! Server::builder()
+//!     .layer(ContextLayer::new().allow_missing())
+//!     .layer(KeyedRateLimitLayer::new(100).keyed_by(RateLimitKey::TenantId))
+//!     .add_service(YourServiceServer::new(handler))
+//!     .serve(addr)
+//!     .await?;
+//! ```
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+use std::task::{Context as TaskContext, Poll};
+use std::time::Duration;
+
+use flare_core_base::context::{Ctx, keys};
+use flare_core_base::error::{ErrorBuilder, ErrorCode};
+use flare_core_infra::ratelimit::{InMemoryRateLimitStore, RateLimitQuota, RateLimitStore};
+use http::{Request as HttpRequest, Response as HttpResponse};
+use tonic::Status;
+use tonic::metadata::MetadataValue;
 use tower::{
-    Layer,
+    Layer, Service,
     limit::{ConcurrencyLimit, ConcurrencyLimitLayer},
 };
+use tracing::{debug, warn};
+
+/// 限流拒绝时携带建议重试间隔（毫秒）的 metadata 键
+pub const RETRY_AFTER_MS_METADATA: &str = "x-retry-after-ms";
+
+/// 缺少限流字段的请求默认共享的桶
+const DEFAULT_SHARED_KEY: &str = "-";
+
+/// 限流维度
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RateLimitKey {
+    /// 按租户（`Context::tenant_id`）
+    TenantId,
+    /// 按用户（`Context::user_id`，缺失时使用 actor_id）
+    UserId,
+    /// 按 gRPC 方法（请求路径 `/pkg.Service/Method`）
+    Method,
+}
+
+/// 请求缺少限流字段（如未携带租户）时的处理方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MissingKeyPolicy {
+    /// 归入同一个共享桶，所有缺少字段的请求共用一份配额（默认桶名 `"-"`）
+    Shared(String),
+    /// 不限流，直接放行
+    Bypass,
+}
+
+impl Default for MissingKeyPolicy {
+    fn default() -> Self {
+        MissingKeyPolicy::Shared(DEFAULT_SHARED_KEY.to_string())
+    }
+}
+
+impl RateLimitKey {
+    /// 提取限流键；字段缺失时返回 `None`
+    fn extract<B>(&self, req: &HttpRequest<B>) -> Option<String> {
+        let ctx = req.extensions().get::<Ctx>();
+        let header = |name: &str| {
+            req.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let value = match self {
+            RateLimitKey::TenantId => ctx
+                .and_then(|c| c.tenant_id().map(str::to_string))
+                .or_else(|| header(keys::TENANT_ID)),
+            RateLimitKey::UserId => ctx
+                .and_then(|c| {
+                    c.user_id()
+                        .filter(|u| !u.is_empty())
+                        .or_else(|| c.actor().map(|a| a.actor_id()))
+                        .map(str::to_string)
+                })
+                .or_else(|| header(keys::USER_ID)),
+            RateLimitKey::Method => Some(req.uri().path().to_string()),
+        };
+        value.filter(|v| !v.is_empty())
+    }
+}
+
+/// 按键每秒限流中间件层
+///
+/// 默认使用进程内令牌桶；多实例部署时通过 [`KeyedRateLimitLayer::with_store`] 换成
+/// `RedisRateLimitStore` 共享配额。存储故障时放行请求（fail-open）并记录告警。
+///
+/// 构建方法与调用顺序无关：进程内存储在首次 `layer` 时按最终配置创建，并由该层构建的所有服务共享。
+#[derive(Clone)]
+pub struct KeyedRateLimitLayer {
+    key: RateLimitKey,
+    missing_key: MissingKeyPolicy,
+    quota: RateLimitQuota,
+    /// 外部存储；未设置时使用进程内存储
+    store: Option<Arc<dyn RateLimitStore>>,
+    /// 进程内存储的空闲桶回收时间（`None` 为默认值）
+    idle_timeout: Option<Duration>,
+    memory: Arc<OnceLock<Arc<InMemoryRateLimitStore>>>,
+}
+
+impl KeyedRateLimitLayer {
+    /// 每个键每秒 `requests_per_second` 个请求，突发容量默认等于速率，默认按租户限流
+    pub fn new(requests_per_second: u32) -> Self {
+        Self {
+            key: RateLimitKey::TenantId,
+            missing_key: MissingKeyPolicy::default(),
+            quota: RateLimitQuota::new(requests_per_second, requests_per_second),
+            store: None,
+            idle_timeout: None,
+            memory: Arc::new(OnceLock::new()),
+        }
+    }
+
+    /// 设置限流维度
+    pub fn keyed_by(mut self, key: RateLimitKey) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// 设置缺少限流字段时的处理方式（默认共享 `"-"` 桶）
+    pub fn with_missing_key(mut self, policy: MissingKeyPolicy) -> Self {
+        self.missing_key = policy;
+        self
+    }
+
+    /// 设置突发容量（桶大小）
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.quota = RateLimitQuota::new(self.quota.requests_per_second, burst);
+        self
+    }
+
+    /// 使用指定的限流存储（如 Redis，多实例共享配额）
+    pub fn with_store(mut self, store: Arc<dyn RateLimitStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// 设置空闲桶回收时间（默认 5 分钟）
+    ///
+    /// 仅作用于默认的进程内存储；设置了外部存储时无效（无论调用顺序），外部存储自行管理过期。
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = Some(idle_timeout);
+        // 已构建过服务的层克隆而来时，新配置使用新的进程内存储
+        self.memory = Arc::new(OnceLock::new());
+        self
+    }
+
+    pub fn requests_per_second(&self) -> u32 {
+        self.quota.requests_per_second
+    }
+
+    pub fn burst(&self) -> u32 {
+        self.quota.burst
+    }
+
+    fn store(&self) -> Arc<dyn RateLimitStore> {
+        match &self.store {
+            Some(store) => Arc::clone(store),
+            None => self.memory_store().clone(),
+        }
+    }
+
+    fn memory_store(&self) -> &Arc<InMemoryRateLimitStore> {
+        self.memory.get_or_init(|| {
+            Arc::new(match self.idle_timeout {
+                Some(idle_timeout) => InMemoryRateLimitStore::with_idle_timeout(idle_timeout),
+                None => InMemoryRateLimitStore::new(),
+            })
+        })
+    }
+}
+
+impl<S> Layer<S> for KeyedRateLimitLayer {
+    type Service = KeyedRateLimitService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        KeyedRateLimitService {
+            inner: service,
+            key: self.key.clone(),
+            missing_key: self.missing_key.clone(),
+            quota: self.quota,
+            store: self.store(),
+        }
+    }
+}
+
+/// 按键每秒限流服务
+#[derive(Clone)]
+pub struct KeyedRateLimitService<S> {
+    inner: S,
+    key: RateLimitKey,
+    missing_key: MissingKeyPolicy,
+    quota: RateLimitQuota,
+    store: Arc<dyn RateLimitStore>,
+}
+
+impl<S> tonic::server::NamedService for KeyedRateLimitService<S>
+where
+    S: tonic::server::NamedService,
+{
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S, ReqBody, ResBody> Service<HttpRequest<ReqBody>> for KeyedRateLimitService<S>
+where
+    S: Service<HttpRequest<ReqBody>, Response = HttpResponse<ResBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    ReqBody: Send + 'static,
+    ResBody: Default + Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: HttpRequest<ReqBody>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let key = match (self.key.extract(&req), &self.missing_key) {
+            (Some(key), _) => key,
+            (None, MissingKeyPolicy::Shared(shared)) => shared.clone(),
+            (None, MissingKeyPolicy::Bypass) => return Box::pin(inner.call(req)),
+        };
+        let dimension = self.key.clone();
+        let quota = self.quota;
+        let store = Arc::clone(&self.store);
+
+        Box::pin(async move {
+            match store.acquire(&key, &quota).await {
+                Ok(decision) if !decision.allowed => {
+                    debug!(
+                        key = %key,
+                        dimension = ?dimension,
+                        path = req.uri().path(),
+                        retry_after_ms = decision.retry_after.as_millis() as u64,
+                        "gRPC request rate limited"
+                    );
+                    return Ok(rate_limited_status(decision.retry_after).into_http());
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    warn!(error = %err, key = %key, "Rate limit store failed, allowing request");
+                }
+            }
+            inner.call(req).await
+        })
+    }
+}
+
+/// 构造限流拒绝状态：ResourceExhausted + 重试提示 + FlareError details
+fn rate_limited_status(retry_after: Duration) -> Status {
+    let retry_after_ms = (retry_after.as_millis() as u64).max(1);
+    let err = ErrorBuilder::new(ErrorCode::MessageRateLimitExceeded, "rate limit exceeded")
+        .details(format!(
+            "rate limit exceeded, retry after {retry_after_ms}ms"
+        ))
+        .param("retry_after_ms", retry_after_ms.to_string())
+        .build_error();
+    let mut status = Status::from(err);
+    status
+        .metadata_mut()
+        .insert(RETRY_AFTER_MS_METADATA, MetadataValue::from(retry_after_ms));
+    status
+}
 
 /// 限流中间件层
 #[derive(Clone)]
@@ -46,10 +322,19 @@ mod tests {
         time::Duration,
     };
 
+    use flare_core_base::context::Context as FlareContext;
+    use http::{Request as HttpRequest, Response as HttpResponse};
     use tokio::{sync::Notify, time::timeout};
+    use tonic::{Code, Status};
     use tower::{Layer, Service};
 
-    use super::RateLimitLayer;
+    use async_trait::async_trait;
+    use flare_core_infra::ratelimit::{RateLimitDecision, RateLimitError};
+
+    use super::{
+        KeyedRateLimitLayer, MissingKeyPolicy, RETRY_AFTER_MS_METADATA, RateLimitKey,
+        RateLimitLayer, RateLimitQuota, RateLimitStore,
+    };
 
     #[derive(Clone)]
     struct BlockingService {
@@ -78,6 +363,29 @@ mod tests {
         }
     }
 
+    #[derive(Clone)]
+    struct OkService;
+
+    impl Service<HttpRequest<()>> for OkService {
+        type Response = HttpResponse<String>;
+        type Error = Infallible;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Infallible>> + Send>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _req: HttpRequest<()>) -> Self::Future {
+            Box::pin(async { Ok(HttpResponse::new("ok".to_string())) })
+        }
+    }
+
+    fn request(path: &str, ctx: FlareContext) -> HttpRequest<()> {
+        let mut req = HttpRequest::builder().uri(path).body(()).unwrap();
+        req.extensions_mut().insert(Arc::new(ctx));
+        req
+    }
+
     #[test]
     fn zero_limit_is_normalized_to_one() {
         let layer = RateLimitLayer::new(0);
@@ -85,6 +393,13 @@ mod tests {
         assert_eq!(layer.max_concurrent(), 1);
     }
 
+    #[test]
+    fn zero_rate_is_normalized_to_one() {
+        let layer = KeyedRateLimitLayer::new(0).with_burst(0);
+        assert_eq!(layer.requests_per_second(), 1);
+        assert_eq!(layer.burst(), 1);
+    }
+
     #[tokio::test]
     async fn enforces_concurrent_request_limit() {
         let started = Arc::new(AtomicUsize::new(0));
@@ -119,4 +434,204 @@ mod tests {
         .unwrap()
         .unwrap();
     }
+
+    #[tokio::test]
+    async fn rejects_over_limit_per_tenant() {
+        let mut service = KeyedRateLimitLayer::new(1)
+            .with_burst(2)
+            .keyed_by(RateLimitKey::TenantId)
+            .layer(OkService);
+        let tenant = |t: &str| FlareContext::root().with_tenant_id(t);
+
+        for _ in 0..2 {
+            let resp = service.call(request("/svc/M", tenant("a"))).await.unwrap();
+            assert_eq!(resp.body(), "ok");
+        }
+
+        let rejected = service.call(request("/svc/M", tenant("a"))).await.unwrap();
+        let status = Status::from_header_map(rejected.headers()).unwrap();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        let retry_after: u64 = rejected
+            .headers()
+            .get(RETRY_AFTER_MS_METADATA)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(retry_after > 0 && retry_after <= 1000);
+
+        // 其他租户不受影响
+        let resp = service.call(request("/svc/M", tenant("b"))).await.unwrap();
+        assert_eq!(resp.body(), "ok");
+    }
+
+    #[tokio::test]
+    async fn keys_by_method() {
+        let mut service = KeyedRateLimitLayer::new(1)
+            .keyed_by(RateLimitKey::Method)
+            .layer(OkService);
+
+        let ctx = FlareContext::root;
+        assert_eq!(
+            service.call(request("/svc/A", ctx())).await.unwrap().body(),
+            "ok"
+        );
+        assert_eq!(
+            service.call(request("/svc/B", ctx())).await.unwrap().body(),
+            "ok"
+        );
+        let rejected = service.call(request("/svc/A", ctx())).await.unwrap();
+        assert!(rejected.body().is_empty());
+    }
+
+    /// 进程内假存储：所有键共用一个计数，模拟多实例共享的外部存储
+    struct SharedCounterStore {
+        limit: usize,
+        used: AtomicUsize,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl RateLimitStore for SharedCounterStore {
+        async fn acquire(
+            &self,
+            _key: &str,
+            quota: &RateLimitQuota,
+        ) -> Result<RateLimitDecision, RateLimitError> {
+            if self.fail {
+                return Err(RateLimitError::Backend("connection refused".into()));
+            }
+            let used = self.used.fetch_add(1, Ordering::SeqCst);
+            Ok(quota.decision(used < self.limit, 0.0))
+        }
+    }
+
+    #[tokio::test]
+    async fn instances_share_quota_through_store() {
+        let store = Arc::new(SharedCounterStore {
+            limit: 2,
+            used: AtomicUsize::new(0),
+            fail: false,
+        });
+        let layer = KeyedRateLimitLayer::new(100).with_store(store.clone());
+        let mut gateway_a = layer.layer(OkService);
+        let mut gateway_b = KeyedRateLimitLayer::new(100)
+            .with_store(store)
+            .layer(OkService);
+
+        let ctx = FlareContext::root;
+        assert_eq!(
+            gateway_a
+                .call(request("/svc/M", ctx()))
+                .await
+                .unwrap()
+                .body(),
+            "ok"
+        );
+        assert_eq!(
+            gateway_b
+                .call(request("/svc/M", ctx()))
+                .await
+                .unwrap()
+                .body(),
+            "ok"
+        );
+        let rejected = gateway_a.call(request("/svc/M", ctx())).await.unwrap();
+        assert_eq!(
+            Status::from_header_map(rejected.headers()).unwrap().code(),
+            Code::ResourceExhausted
+        );
+    }
+
+    #[tokio::test]
+    async fn builder_order_does_not_matter() {
+        let idle = Duration::from_millis(30);
+        for layer in [
+            KeyedRateLimitLayer::new(100).with_idle_timeout(idle),
+            KeyedRateLimitLayer::new(100)
+                .keyed_by(RateLimitKey::Method)
+                .with_idle_timeout(idle)
+                .keyed_by(RateLimitKey::TenantId),
+        ] {
+            let mut service = layer.layer(OkService);
+            let tenant = |t: &str| FlareContext::root().with_tenant_id(t);
+            service.call(request("/svc/M", tenant("a"))).await.unwrap();
+            service.call(request("/svc/M", tenant("b"))).await.unwrap();
+            assert_eq!(layer.memory_store().len(), 2);
+
+            tokio::time::sleep(Duration::from_millis(40)).await;
+            service.call(request("/svc/M", tenant("c"))).await.unwrap();
+            assert_eq!(layer.memory_store().len(), 1, "idle buckets evicted");
+        }
+
+        // 外部存储无论在空闲回收时间之前还是之后设置都生效
+        for external_first in [true, false] {
+            let store = Arc::new(SharedCounterStore {
+                limit: 0,
+                used: AtomicUsize::new(0),
+                fail: false,
+            });
+            let layer = if external_first {
+                KeyedRateLimitLayer::new(100)
+                    .with_store(store.clone())
+                    .with_idle_timeout(idle)
+            } else {
+                KeyedRateLimitLayer::new(100)
+                    .with_idle_timeout(idle)
+                    .with_store(store.clone())
+            };
+            let rejected = layer
+                .layer(OkService)
+                .call(request("/svc/M", FlareContext::root()))
+                .await
+                .unwrap();
+            assert!(rejected.body().is_empty());
+            assert_eq!(store.used.load(Ordering::SeqCst), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn store_failure_fails_open() {
+        let store = Arc::new(SharedCounterStore {
+            limit: 0,
+            used: AtomicUsize::new(0),
+            fail: true,
+        });
+        let mut service = KeyedRateLimitLayer::new(1)
+            .with_store(store)
+            .layer(OkService);
+
+        let resp = service
+            .call(request("/svc/M", FlareContext::root()))
+            .await
+            .unwrap();
+        assert_eq!(resp.body(), "ok");
+    }
+
+    #[tokio::test]
+    async fn requests_without_key_follow_missing_key_policy() {
+        let ctx = FlareContext::root;
+
+        let mut shared = KeyedRateLimitLayer::new(1).layer(OkService);
+        assert_eq!(
+            shared.call(request("/svc/M", ctx())).await.unwrap().body(),
+            "ok"
+        );
+        let rejected = shared.call(request("/svc/N", ctx())).await.unwrap();
+        assert!(
+            rejected.body().is_empty(),
+            "keyless requests share one bucket"
+        );
+
+        let mut bypass = KeyedRateLimitLayer::new(1)
+            .with_missing_key(MissingKeyPolicy::Bypass)
+            .layer(OkService);
+        for _ in 0..3 {
+            assert_eq!(
+                bypass.call(request("/svc/M", ctx())).await.unwrap().body(),
+                "ok"
+            );
+        }
+    }
 }
@@ -1,37 +1,636 @@
-//! 重试中间件
+//! 客户端重试中间件
 //!
-//! 注意：重试中间件主要用于客户端，服务端通常不需要重试
+//! 基于 [`RetryPolicy`] 对 tonic 客户端调用做重放：
+//! - 一元调用的请求体会被缓冲，每次重试都重建一份完全相同的请求；
+//! - 退避时长取 `backoff_duration` 并叠加随机抖动，避免多个客户端同时重试；
+//! - 剩余时间（`grpc-timeout` 头与请求扩展中 `Ctx` 的 deadline 取小）不足以等待下一次退避时放弃重试，
+//!   并在每次重试时按剩余时间改写 `grpc-timeout`；
+//! - [`RetryBudget`] 限制重试占请求量的比例，下游整体故障时不会被重试放大流量；
+//! - 流式调用的请求体无法重放，直接透传，失败立即返回。
+//!
+//! ```rust,ignore
+//! use flare_server_core::grpc::middleware::RetryLayer;
+//! use tower::ServiceBuilder;
+//!
+//! let channel = ServiceBuilder::new()
+//!     .layer(RetryLayer::new(3))
+//!     .service(channel);
+//! let client = YourServiceClient::new(channel);
+//! ```
+
+use std::future::{Future, poll_fn};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context as TaskContext, Poll};
+use std::time::{Duration, Instant};
+
+use bytes::{Bytes, BytesMut};
+use flare_core_base::context::Ctx;
+use flare_core_base::utils::jittered_backoff;
+use http::request::Parts;
+use http::{HeaderValue, Request as HttpRequest, Response as HttpResponse};
+use http_body::{Body as HttpBody, Frame};
+use tonic::Status;
+use tonic::body::Body;
+use tower::{Layer, Service, ServiceExt};
+use tracing::{debug, warn};
+
+use crate::grpc::retry::{ExponentialBackoffPolicy, RetryPolicy};
+use crate::grpc::utils::{
+    GRPC_TIMEOUT_HEADER, encode_grpc_timeout, parse_grpc_timeout, status_from_response,
+};
 
-use tower::Layer;
+const DEFAULT_BASE_DELAY: Duration = Duration::from_millis(50);
+const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(2);
+const DEFAULT_JITTER: f64 = 0.2;
+/// 可重放请求体的默认上限，与 tonic 默认消息大小上限一致
+const DEFAULT_MAX_BUFFER_BYTES: usize = 4 * 1024 * 1024;
 
-/// 重试中间件层
+/// 重试预算
 ///
-/// 注意：此中间件主要用于客户端场景
-/// 服务端通常不需要重试，因为重试应该由客户端处理
-#[derive(Clone)]
-pub struct RetryLayer {
-    max_retries: usize,
+/// 每个原始请求存入 `ratio` 个令牌，每次重试消耗 1 个；另外每秒补充 `min_per_second` 个保底令牌，
+/// 保证低流量时仍可重试。多个 [`RetryLayer`] 可共享同一个预算。
+#[derive(Debug)]
+pub struct RetryBudget {
+    ratio: f64,
+    min_per_second: f64,
+    max_tokens: f64,
+    state: Mutex<BudgetState>,
+}
+
+#[derive(Debug)]
+struct BudgetState {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RetryBudget {
+    /// 创建重试预算
+    ///
+    /// - `ratio`: 每个请求允许的重试比例（0.2 表示重试量最多为请求量的 20%）
+    /// - `min_per_second`: 每秒保底重试次数
+    pub fn new(ratio: f64, min_per_second: u32) -> Self {
+        let ratio = ratio.max(0.0);
+        let min_per_second = f64::from(min_per_second);
+        // 令牌上限约为 10 秒的额度，避免长时间空闲后积攒过多重试
+        let max_tokens = (min_per_second * 10.0 + ratio * 100.0).max(1.0);
+        Self {
+            ratio,
+            min_per_second,
+            max_tokens,
+            state: Mutex::new(BudgetState {
+                tokens: min_per_second.min(max_tokens),
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// 记录一次原始请求
+    pub fn deposit(&self) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        self.refill(&mut state);
+        state.tokens = (state.tokens + self.ratio).min(self.max_tokens);
+    }
+
+    /// 尝试消耗一次重试额度
+    pub fn try_withdraw(&self) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        self.refill(&mut state);
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn refill(&self, state: &mut BudgetState) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(state.refilled_at).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * self.min_per_second).min(self.max_tokens);
+        state.refilled_at = now;
+    }
+}
+
+impl Default for RetryBudget {
+    fn default() -> Self {
+        Self::new(0.2, 10)
+    }
+}
+
+/// 重试中间件层（客户端）
+///
+/// 服务端通常不需要重试，重试应由调用方在 Channel 上叠加本层完成。
+pub struct RetryLayer<P = ExponentialBackoffPolicy> {
+    policy: Arc<P>,
+    budget: Arc<RetryBudget>,
+    jitter: f64,
+    max_buffer_bytes: usize,
 }
 
 impl RetryLayer {
+    /// 指数退避（50ms 起步，最大 2s），最多重试 `max_retries` 次
     pub fn new(max_retries: usize) -> Self {
-        Self { max_retries }
+        Self::with_policy(ExponentialBackoffPolicy::new(
+            max_retries + 1,
+            DEFAULT_BASE_DELAY,
+            DEFAULT_MAX_DELAY,
+        ))
+    }
+}
+
+impl<P: RetryPolicy> RetryLayer<P> {
+    /// 使用自定义重试策略
+    ///
+    /// `should_retry` 的 `attempt` 为已发起的调用次数（首次失败时为 1），
+    /// `backoff_duration` 的参数为已重试次数（首次重试前为 0）。
+    pub fn with_policy(policy: P) -> Self {
+        Self {
+            policy: Arc::new(policy),
+            budget: Arc::new(RetryBudget::default()),
+            jitter: DEFAULT_JITTER,
+            max_buffer_bytes: DEFAULT_MAX_BUFFER_BYTES,
+        }
+    }
+
+    /// 设置重试预算（可在多个 Layer 间共享）
+    pub fn with_budget(mut self, budget: Arc<RetryBudget>) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// 设置抖动比例（0~1），实际退避为 `backoff * (1 - jitter * rand)`
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// 设置可缓冲重放的最大请求体大小，超过则不重试
+    pub fn with_max_buffer_bytes(mut self, max_buffer_bytes: usize) -> Self {
+        self.max_buffer_bytes = max_buffer_bytes;
+        self
+    }
+
+    /// 当前使用的重试预算
+    pub fn budget(&self) -> &Arc<RetryBudget> {
+        &self.budget
     }
 }
 
-// 注意：tower 的重试中间件需要特定的 Service trait 实现
-// 这里提供一个简化的实现，实际使用时需要根据具体场景调整
-// 注意：这里简化了实现，直接返回服务，不进行实际的 Service trait 约束
-impl<S> Layer<S> for RetryLayer
+impl<P> Clone for RetryLayer<P> {
+    fn clone(&self) -> Self {
+        Self {
+            policy: Arc::clone(&self.policy),
+            budget: Arc::clone(&self.budget),
+            jitter: self.jitter,
+            max_buffer_bytes: self.max_buffer_bytes,
+        }
+    }
+}
+
+impl<S, P> Layer<S> for RetryLayer<P> {
+    type Service = RetryService<S, P>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        RetryService {
+            inner: service,
+            policy: Arc::clone(&self.policy),
+            budget: Arc::clone(&self.budget),
+            jitter: self.jitter,
+            max_buffer_bytes: self.max_buffer_bytes,
+        }
+    }
+}
+
+/// 重试服务
+pub struct RetryService<S, P = ExponentialBackoffPolicy> {
+    inner: S,
+    policy: Arc<P>,
+    budget: Arc<RetryBudget>,
+    jitter: f64,
+    max_buffer_bytes: usize,
+}
+
+impl<S: Clone, P> Clone for RetryService<S, P> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            policy: Arc::clone(&self.policy),
+            budget: Arc::clone(&self.budget),
+            jitter: self.jitter,
+            max_buffer_bytes: self.max_buffer_bytes,
+        }
+    }
+}
+
+impl<S, P, ResBody> Service<HttpRequest<Body>> for RetryService<S, P>
 where
-    S: Clone + Send + 'static,
+    S: Service<HttpRequest<Body>, Response = HttpResponse<ResBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    P: RetryPolicy + Send + Sync + 'static,
+    ResBody: Send + 'static,
 {
-    type Service = S;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
 
-    fn layer(&self, service: S) -> Self::Service {
-        // 简化实现：直接返回服务
-        // 实际的重试逻辑应该在客户端实现
-        let _ = self.max_retries;
-        service
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: HttpRequest<Body>) -> Self::Future {
+        // 取走已就绪的 inner，留下克隆体等待下一次 poll_ready
+        let clone = self.inner.clone();
+        let this = RetryService {
+            inner: std::mem::replace(&mut self.inner, clone),
+            policy: Arc::clone(&self.policy),
+            budget: Arc::clone(&self.budget),
+            jitter: self.jitter,
+            max_buffer_bytes: self.max_buffer_bytes,
+        };
+        Box::pin(this.call_with_retry(req))
+    }
+}
+
+impl<S, P, ResBody> RetryService<S, P>
+where
+    S: Service<HttpRequest<Body>, Response = HttpResponse<ResBody>>,
+    P: RetryPolicy,
+{
+    async fn call_with_retry(
+        mut self,
+        req: HttpRequest<Body>,
+    ) -> Result<HttpResponse<ResBody>, S::Error> {
+        let (parts, body) = req.into_parts();
+        let payload = match buffer_body(body, self.max_buffer_bytes).await {
+            Buffered::Complete(payload) => payload,
+            Buffered::Streaming(body) => {
+                debug!(
+                    path = parts.uri.path(),
+                    "Streaming gRPC request, retry disabled"
+                );
+                return self.inner.call(HttpRequest::from_parts(parts, body)).await;
+            }
+        };
+
+        let deadline = request_deadline(&parts);
+        self.budget.deposit();
+
+        let mut attempt = 1;
+        loop {
+            let result = self
+                .inner
+                .call(rebuild_request(&parts, &payload, deadline))
+                .await;
+            let Some(status) = failure_status(&result) else {
+                return result;
+            };
+            if !self.policy.should_retry(attempt, &status) {
+                return result;
+            }
+
+            let delay = self.backoff_for(attempt - 1);
+            if deadline.is_some_and(|d| Instant::now() + delay >= d) {
+                debug!(
+                    path = parts.uri.path(),
+                    attempt, "Deadline too close, giving up retry"
+                );
+                return result;
+            }
+            if !self.budget.try_withdraw() {
+                warn!(
+                    path = parts.uri.path(),
+                    attempt,
+                    code = ?status.code(),
+                    "Retry budget exhausted, giving up retry"
+                );
+                return result;
+            }
+
+            debug!(
+                path = parts.uri.path(),
+                attempt,
+                code = ?status.code(),
+                delay_ms = delay.as_millis() as u64,
+                "Retrying gRPC call"
+            );
+            drop(result);
+            tokio::time::sleep(delay).await;
+            self.inner.ready().await?;
+            attempt += 1;
+        }
+    }
+
+    fn backoff_for(&self, retries: usize) -> Duration {
+        jittered_backoff(self.policy.backoff_duration(retries), self.jitter)
+    }
+}
+
+/// 调用的截止时间：`grpc-timeout` 头与 `Ctx` deadline 取较早者
+fn request_deadline(parts: &Parts) -> Option<Instant> {
+    let now = Instant::now();
+    let from_header = parts
+        .headers
+        .get(GRPC_TIMEOUT_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_grpc_timeout)
+        .map(|t| now + t);
+    let from_ctx = parts.extensions.get::<Ctx>().and_then(|ctx| ctx.deadline());
+    from_header.into_iter().chain(from_ctx).min()
+}
+
+fn rebuild_request(parts: &Parts, payload: &Bytes, deadline: Option<Instant>) -> HttpRequest<Body> {
+    let mut req = HttpRequest::new(Body::new(BufferedBody::replay(payload.clone())));
+    *req.method_mut() = parts.method.clone();
+    *req.uri_mut() = parts.uri.clone();
+    *req.version_mut() = parts.version;
+    *req.headers_mut() = parts.headers.clone();
+    *req.extensions_mut() = parts.extensions.clone();
+
+    if let Some(deadline) = deadline {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if let Ok(value) = HeaderValue::from_str(&encode_grpc_timeout(remaining)) {
+            req.headers_mut().insert(GRPC_TIMEOUT_HEADER, value);
+        }
+    }
+    req
+}
+
+/// 提取失败调用的状态；传输层错误视为 Unavailable
+fn failure_status<B, E>(result: &Result<HttpResponse<B>, E>) -> Option<Status> {
+    match result {
+        Ok(response) => status_from_response(response),
+        Err(_) => Some(Status::unavailable("transport error")),
+    }
+}
+
+enum Buffered {
+    /// 请求体已完整读出，可重放
+    Complete(Bytes),
+    /// 请求体仍在流式产生（或过大），只能透传一次
+    Streaming(Body),
+}
+
+/// 读取已就绪的请求体帧
+///
+/// 一元调用的请求体在发送前即已编码完毕，全部帧立即就绪；一旦遇到 Pending，
+/// 说明是客户端流 / 双向流，把已读部分与剩余部分重新拼接后透传。
+async fn buffer_body(mut body: Body, limit: usize) -> Buffered {
+    let mut buf = BytesMut::new();
+    loop {
+        let polled = poll_fn(|cx| Poll::Ready(Pin::new(&mut body).poll_frame(cx))).await;
+        let pending = match polled {
+            Poll::Ready(None) => return Buffered::Complete(buf.freeze()),
+            Poll::Ready(Some(Ok(frame))) => match frame.into_data() {
+                Ok(data) => {
+                    buf.extend_from_slice(&data);
+                    if buf.len() <= limit {
+                        continue;
+                    }
+                    None
+                }
+                Err(frame) => Some(Ok(frame)),
+            },
+            Poll::Ready(Some(Err(status))) => Some(Err(status)),
+            Poll::Pending => None,
+        };
+        return Buffered::Streaming(Body::new(BufferedBody {
+            prefix: Some(buf.freeze()),
+            pending,
+            inner: body,
+        }));
+    }
+}
+
+/// 已缓冲数据 + 剩余请求体
+struct BufferedBody {
+    prefix: Option<Bytes>,
+    pending: Option<Result<Frame<Bytes>, Status>>,
+    inner: Body,
+}
+
+impl BufferedBody {
+    fn replay(payload: Bytes) -> Self {
+        Self {
+            prefix: Some(payload),
+            pending: None,
+            inner: Body::empty(),
+        }
+    }
+}
+
+impl HttpBody for BufferedBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        if let Some(prefix) = this.prefix.take().filter(|p| !p.is_empty()) {
+            return Poll::Ready(Some(Ok(Frame::data(prefix))));
+        }
+        if let Some(pending) = this.pending.take() {
+            return Poll::Ready(Some(pending));
+        }
+        Pin::new(&mut this.inner).poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.prefix.as_ref().is_none_or(Bytes::is_empty)
+            && self.pending.is_none()
+            && self.inner.is_end_stream()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tonic::Code;
+
+    fn grpc_response(code: Code) -> HttpResponse<Body> {
+        let mut response = HttpResponse::new(Body::empty());
+        response
+            .headers_mut()
+            .insert("grpc-status", HeaderValue::from(code as i32));
+        response
+    }
+
+    /// 前 `failures` 次返回 `code`，之后成功；记录每次收到的请求体
+    #[derive(Clone)]
+    struct Flaky {
+        failures: usize,
+        code: Code,
+        calls: Arc<AtomicUsize>,
+        bodies: Arc<Mutex<Vec<Bytes>>>,
+    }
+
+    impl Flaky {
+        fn new(failures: usize, code: Code) -> Self {
+            Self {
+                failures,
+                code,
+                calls: Arc::new(AtomicUsize::new(0)),
+                bodies: Arc::new(Mutex::new(Vec::new())),
+            }
+        }
+
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    impl Service<HttpRequest<Body>> for Flaky {
+        type Response = HttpResponse<Body>;
+        type Error = Infallible;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Infallible>> + Send>>;
+
+        fn poll_ready(&mut self, _cx: &mut TaskContext<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: HttpRequest<Body>) -> Self::Future {
+            let this = self.clone();
+            Box::pin(async move {
+                if let Buffered::Complete(body) = buffer_body(req.into_body(), usize::MAX).await {
+                    this.bodies.lock().unwrap().push(body);
+                }
+                let n = this.calls.fetch_add(1, Ordering::SeqCst);
+                let code = if n < this.failures {
+                    this.code
+                } else {
+                    Code::Ok
+                };
+                Ok(grpc_response(code))
+            })
+        }
+    }
+
+    /// 永不就绪的请求体，模拟客户端流
+    struct NeverReady;
+
+    impl HttpBody for NeverReady {
+        type Data = Bytes;
+        type Error = Status;
+
+        fn poll_frame(
+            self: Pin<&mut Self>,
+            _cx: &mut TaskContext<'_>,
+        ) -> Poll<Option<Result<Frame<Bytes>, Status>>> {
+            Poll::Pending
+        }
+    }
+
+    fn unary_request(payload: &'static [u8]) -> HttpRequest<Body> {
+        HttpRequest::builder()
+            .uri("http://svc/pkg.Svc/Method")
+            .body(Body::new(BufferedBody::replay(Bytes::from_static(payload))))
+            .unwrap()
+    }
+
+    fn layer(max_retries: usize) -> RetryLayer {
+        RetryLayer::with_policy(ExponentialBackoffPolicy::new(
+            max_retries + 1,
+            Duration::from_millis(1),
+            Duration::from_millis(5),
+        ))
+    }
+
+    fn status_of(response: &HttpResponse<Body>) -> Code {
+        Status::from_header_map(response.headers()).unwrap().code()
+    }
+
+    #[tokio::test]
+    async fn replays_request_until_success() {
+        let inner = Flaky::new(2, Code::Unavailable);
+        let mut svc = layer(3).layer(inner.clone());
+
+        let response = svc
+            .ready()
+            .await
+            .unwrap()
+            .call(unary_request(b"payload"))
+            .await
+            .unwrap();
+
+        assert_eq!(status_of(&response), Code::Ok);
+        assert_eq!(inner.calls(), 3);
+        let bodies = inner.bodies.lock().unwrap();
+        assert!(bodies.iter().all(|b| b.as_ref() == b"payload"));
+    }
+
+    #[tokio::test]
+    async fn stops_at_max_retries_and_on_non_retryable_codes() {
+        let inner = Flaky::new(10, Code::Unavailable);
+        let mut svc = layer(2).layer(inner.clone());
+        let response = svc.call(unary_request(b"x")).await.unwrap();
+        assert_eq!(status_of(&response), Code::Unavailable);
+        assert_eq!(inner.calls(), 3);
+
+        let inner = Flaky::new(10, Code::InvalidArgument);
+        let mut svc = layer(2).layer(inner.clone());
+        let response = svc.call(unary_request(b"x")).await.unwrap();
+        assert_eq!(status_of(&response), Code::InvalidArgument);
+        assert_eq!(inner.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn exhausted_budget_prevents_retry() {
+        let inner = Flaky::new(1, Code::Unavailable);
+        let budget = Arc::new(RetryBudget::new(0.0, 0));
+        let mut svc = layer(3).with_budget(budget).layer(inner.clone());
+
+        let response = svc.call(unary_request(b"x")).await.unwrap();
+
+        assert_eq!(status_of(&response), Code::Unavailable);
+        assert_eq!(inner.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn respects_remaining_deadline() {
+        let inner = Flaky::new(1, Code::Unavailable);
+        let policy =
+            ExponentialBackoffPolicy::new(3, Duration::from_secs(1), Duration::from_secs(1));
+        let mut svc = RetryLayer::with_policy(policy)
+            .with_jitter(0.0)
+            .layer(inner.clone());
+
+        let mut req = unary_request(b"x");
+        req.headers_mut()
+            .insert(GRPC_TIMEOUT_HEADER, HeaderValue::from_static("100m"));
+        let response = svc.call(req).await.unwrap();
+
+        assert_eq!(status_of(&response), Code::Unavailable);
+        assert_eq!(inner.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn streaming_request_is_not_retried() {
+        let inner = Flaky::new(1, Code::Unavailable);
+        let mut svc = layer(3).layer(inner.clone());
+
+        let req = HttpRequest::builder()
+            .uri("http://svc/pkg.Svc/Stream")
+            .body(Body::new(NeverReady))
+            .unwrap();
+        let response = svc.call(req).await.unwrap();
+
+        assert_eq!(status_of(&response), Code::Unavailable);
+        assert_eq!(inner.calls(), 1);
+    }
+
+    #[test]
+    fn budget_limits_retry_ratio() {
+        let budget = RetryBudget::new(0.5, 0);
+        for _ in 0..4 {
+            budget.deposit();
+        }
+        assert!(budget.try_withdraw());
+        assert!(budget.try_withdraw());
+        assert!(!budget.try_withdraw());
     }
 }
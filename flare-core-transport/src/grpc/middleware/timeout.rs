@@ -1,22 +1,163 @@
+//! 超时中间件
+//!
+//! [`GrpcTimeoutLayer`]（别名 [`TimeoutLayer`]）感知调用方截止时间，实际超时取以下三者的最小值：
+//! - 本层配置的超时；
+//! - 请求头 `grpc-timeout`（调用方剩余时间）；
+//! - 请求扩展中 `Ctx` 的剩余时间（放在 `ContextLayer` 之后时生效）。
+//!
+//! 超时后返回 `DeadlineExceeded`，并丢弃内部 future 以停止本请求的后续工作。
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
 use std::time::Duration;
-use tower::Layer;
 
-/// 超时中间件层
+use flare_core_base::context::Ctx;
+use http::{Request as HttpRequest, Response as HttpResponse};
+use tonic::Status;
+use tower::{BoxError, Layer, Service};
+
+use crate::grpc::utils::{GRPC_TIMEOUT_HEADER, parse_grpc_timeout};
+
+/// 超时中间件层，与 [`GrpcTimeoutLayer`] 相同，同样遵守调用方截止时间
+pub type TimeoutLayer = GrpcTimeoutLayer;
+
+/// 感知 `grpc-timeout` 与 Context 截止时间的超时中间件层
 #[derive(Clone)]
-pub struct TimeoutLayer {
+pub struct GrpcTimeoutLayer {
     timeout: Duration,
 }
 
-impl TimeoutLayer {
+impl GrpcTimeoutLayer {
+    /// `timeout` 为服务端允许的最长处理时间，调用方剩余时间更短时以调用方为准
     pub fn new(timeout: Duration) -> Self {
         Self { timeout }
     }
 }
 
-impl<S> Layer<S> for TimeoutLayer {
-    type Service = tower::timeout::Timeout<S>;
+impl<S> Layer<S> for GrpcTimeoutLayer {
+    type Service = GrpcTimeoutService<S>;
 
     fn layer(&self, service: S) -> Self::Service {
-        tower::timeout::Timeout::new(service, self.timeout)
+        GrpcTimeoutService {
+            inner: service,
+            timeout: self.timeout,
+        }
+    }
+}
+
+/// 感知截止时间的 gRPC 超时服务
+#[derive(Clone)]
+pub struct GrpcTimeoutService<S> {
+    inner: S,
+    timeout: Duration,
+}
+
+impl<S> GrpcTimeoutService<S> {
+    /// 计算请求的实际超时
+    fn effective_timeout<B>(&self, req: &HttpRequest<B>) -> Duration {
+        let header = req
+            .headers()
+            .get(GRPC_TIMEOUT_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_grpc_timeout);
+        let ctx = req.extensions().get::<Ctx>().and_then(|ctx| {
+            ctx.deadline()
+                .map(|_| ctx.remaining_time().unwrap_or(Duration::ZERO))
+        });
+        [header, ctx]
+            .into_iter()
+            .flatten()
+            .fold(self.timeout, Duration::min)
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<HttpRequest<ReqBody>> for GrpcTimeoutService<S>
+where
+    S: Service<HttpRequest<ReqBody>, Response = HttpResponse<ResBody>>,
+    S::Error: Into<BoxError>,
+    S::Future: Send + 'static,
+    ResBody: Default + Send + 'static,
+{
+    type Response = S::Response;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: HttpRequest<ReqBody>) -> Self::Future {
+        let timeout = self.effective_timeout(&req);
+        let fut = self.inner.call(req);
+        Box::pin(async move {
+            match tokio::time::timeout(timeout, fut).await {
+                Ok(result) => result.map_err(Into::into),
+                Err(_) => Ok(Status::deadline_exceeded(format!(
+                    "request timed out after {}ms",
+                    timeout.as_millis()
+                ))
+                .into_http()),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flare_core_base::context::Context;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn grpc_timeout_header_wins_when_smaller() {
+        let svc = GrpcTimeoutLayer::new(Duration::from_secs(30)).layer(tower::service_fn(
+            |_req: HttpRequest<()>| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Ok::<_, BoxError>(HttpResponse::new(String::new()))
+            },
+        ));
+        let mut req = HttpRequest::new(());
+        req.headers_mut()
+            .insert(GRPC_TIMEOUT_HEADER, "50m".parse().unwrap());
+        assert_eq!(svc.effective_timeout(&req), Duration::from_millis(50));
+
+        let response = svc.oneshot(req).await.unwrap();
+        let status = Status::from_header_map(response.headers()).expect("grpc-status");
+        assert_eq!(status.code(), tonic::Code::DeadlineExceeded);
+    }
+
+    #[tokio::test]
+    async fn context_deadline_and_layer_timeout_take_minimum() {
+        let svc = GrpcTimeoutLayer::new(Duration::from_secs(1)).layer(tower::service_fn(
+            |_req: HttpRequest<()>| async { Ok::<_, BoxError>(HttpResponse::new(String::new())) },
+        ));
+
+        let mut req = HttpRequest::new(());
+        req.headers_mut()
+            .insert(GRPC_TIMEOUT_HEADER, "5S".parse().unwrap());
+        assert_eq!(svc.effective_timeout(&req), Duration::from_secs(1));
+
+        let ctx: Ctx = Arc::new(Context::root().with_timeout(Duration::from_millis(200)));
+        req.extensions_mut().insert(ctx);
+        assert!(svc.effective_timeout(&req) <= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn timeout_layer_honours_incoming_deadline() {
+        let svc = TimeoutLayer::new(Duration::from_secs(30)).layer(tower::service_fn(
+            |_req: HttpRequest<()>| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Ok::<_, BoxError>(HttpResponse::new(String::new()))
+            },
+        ));
+        let mut req = HttpRequest::new(());
+        let ctx: Ctx = Arc::new(Context::root().with_timeout(Duration::from_millis(50)));
+        req.extensions_mut().insert(ctx);
+
+        let response = svc.oneshot(req).await.unwrap();
+        let status = Status::from_header_map(response.headers()).expect("grpc-status");
+        assert_eq!(status.code(), tonic::Code::DeadlineExceeded);
     }
 }
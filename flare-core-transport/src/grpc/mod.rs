@@ -34,12 +34,4 @@ pub mod adapter;
 pub use adapter::{GrpcAdapter, GrpcAdapterBuilder};
 pub use exponential::ExponentialBackoffPolicy;
 pub use fixed::FixedRetryPolicy;
-
-use std::time::Duration;
-
-/// 重试策略 trait
-pub trait RetryPolicy {
-    fn should_retry(&self, attempt: usize, error: &tonic::Status) -> bool;
-    fn backoff_duration(&self, attempt: usize) -> Duration;
-    fn max_attempts(&self) -> usize;
-}
+pub use retry::RetryPolicy;
@@ -1,4 +1,4 @@
-use super::RetryPolicy;
+use super::{RetryPolicy, is_retryable_code};
 use std::time::Duration;
 use tonic::Status;
 
@@ -26,12 +26,7 @@ impl RetryPolicy for ExponentialBackoffPolicy {
         }
 
         // 只对可重试的错误进行重试
-        matches!(
-            error.code(),
-            tonic::Code::Unavailable
-                | tonic::Code::DeadlineExceeded
-                | tonic::Code::ResourceExhausted
-        )
+        is_retryable_code(error.code())
     }
 
     fn backoff_duration(&self, attempt: usize) -> Duration {
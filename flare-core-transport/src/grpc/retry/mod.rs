@@ -6,6 +6,8 @@ pub mod fixed;
 pub use exponential::ExponentialBackoffPolicy;
 pub use fixed::FixedRetryPolicy;
 
+pub use crate::status::is_retryable_code;
+
 use std::time::Duration;
 
 /// 重试策略 trait
@@ -23,6 +23,9 @@ pub use context::{
     extract_tenant_id, extract_user_id, get_context, require_actor_id, require_request_id,
     require_tenant_id, require_user_id,
 };
-pub use rate_limit::RateLimitLayer;
+pub use rate_limit::{
+    KeyedRateLimitLayer, KeyedRateLimitService, MissingKeyPolicy, RETRY_AFTER_MS_METADATA,
+    RateLimitKey, RateLimitLayer,
+};
 pub use retry::RetryLayer;
-pub use timeout::TimeoutLayer;
+pub use timeout::{GrpcTimeoutLayer, GrpcTimeoutService, TimeoutLayer};
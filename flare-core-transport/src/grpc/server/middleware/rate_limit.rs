@@ -1 +1,4 @@
-pub use crate::grpc::middleware::RateLimitLayer;
+pub use crate::grpc::middleware::rate_limit::{
+    KeyedRateLimitLayer, KeyedRateLimitService, MissingKeyPolicy, RETRY_AFTER_MS_METADATA,
+    RateLimitKey, RateLimitLayer,
+};
@@ -0,0 +1,97 @@
+//! `grpc-timeout` 请求头编解码
+//!
+//! 格式见 gRPC over HTTP/2 协议：最多 8 位十进制数字 + 单位（H/M/S/m/u/n）。
+
+use std::time::Duration;
+
+/// 标准 gRPC 超时请求头
+pub const GRPC_TIMEOUT_HEADER: &str = "grpc-timeout";
+
+/// 协议允许的最大数值（8 位十进制）
+const MAX_TIMEOUT_VALUE: u128 = 99_999_999;
+
+/// 解析 `grpc-timeout` 头，格式非法时返回 None
+pub fn parse_grpc_timeout(value: &str) -> Option<Duration> {
+    if value.len() < 2 || value.len() > 9 || !value.is_ascii() {
+        return None;
+    }
+    let (digits, unit) = value.split_at(value.len() - 1);
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let n: u64 = digits.parse().ok()?;
+    let timeout = match unit {
+        "H" => Duration::from_secs(n * 3600),
+        "M" => Duration::from_secs(n * 60),
+        "S" => Duration::from_secs(n),
+        "m" => Duration::from_millis(n),
+        "u" => Duration::from_micros(n),
+        "n" => Duration::from_nanos(n),
+        _ => return None,
+    };
+    Some(timeout)
+}
+
+/// 编码为 `grpc-timeout` 头，选择能容纳数值的最精细单位
+pub fn encode_grpc_timeout(timeout: Duration) -> String {
+    let nanos = timeout.as_nanos();
+    if nanos <= MAX_TIMEOUT_VALUE {
+        return format!("{}n", nanos);
+    }
+    let micros = timeout.as_micros();
+    if micros <= MAX_TIMEOUT_VALUE {
+        return format!("{}u", micros);
+    }
+    let millis = timeout.as_millis();
+    if millis <= MAX_TIMEOUT_VALUE {
+        return format!("{}m", millis);
+    }
+    let secs = timeout.as_secs() as u128;
+    if secs <= MAX_TIMEOUT_VALUE {
+        return format!("{}S", secs);
+    }
+    let minutes = secs / 60;
+    if minutes <= MAX_TIMEOUT_VALUE {
+        return format!("{}M", minutes);
+    }
+    format!("{}H", (secs / 3600).min(MAX_TIMEOUT_VALUE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_all_units() {
+        assert_eq!(parse_grpc_timeout("1H"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_grpc_timeout("2M"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_grpc_timeout("3S"), Some(Duration::from_secs(3)));
+        assert_eq!(parse_grpc_timeout("250m"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_grpc_timeout("7u"), Some(Duration::from_micros(7)));
+        assert_eq!(parse_grpc_timeout("9n"), Some(Duration::from_nanos(9)));
+    }
+
+    #[test]
+    fn rejects_malformed_values() {
+        assert_eq!(parse_grpc_timeout(""), None);
+        assert_eq!(parse_grpc_timeout("S"), None);
+        assert_eq!(parse_grpc_timeout("123456789S"), None);
+        assert_eq!(parse_grpc_timeout("1.5S"), None);
+        assert_eq!(parse_grpc_timeout("10x"), None);
+    }
+
+    #[test]
+    fn encode_round_trips_within_precision() {
+        for d in [
+            Duration::from_nanos(500),
+            Duration::from_millis(1500),
+            Duration::from_secs(30),
+            Duration::from_secs(86_400 * 400),
+        ] {
+            let encoded = encode_grpc_timeout(d);
+            assert!(encoded.len() <= 9, "{encoded}");
+            let decoded = parse_grpc_timeout(&encoded).unwrap();
+            assert!(decoded <= d && d - decoded < Duration::from_secs(60));
+        }
+    }
+}
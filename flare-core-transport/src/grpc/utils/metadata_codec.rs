@@ -1,73 +1,73 @@
 //! 统一的 Metadata 编解码模块
 //!
 //! 提供高效的 Context <-> gRPC Metadata 双向转换，与 context/core 对齐。
+//! Context 的 deadline 以标准 `grpc-timeout` 头传递剩余时间；
+//! 追踪信息同时写入 `x-trace-id` 与 W3C `traceparent` / `tracestate` / `baggage`。
 
 use std::sync::Arc;
+use std::time::Duration;
 use tonic::metadata::MetadataMap;
 
+use crate::grpc::utils::grpc_timeout::{
+    GRPC_TIMEOUT_HEADER, encode_grpc_timeout, parse_grpc_timeout,
+};
 use flare_core_base::context::{ActorContext, ActorType, Context, Ctx, keys};
+use flare_core_base::utils::{trace_context_from_headers, trace_context_to_headers};
 
 /// 将 Context 编码到 gRPC Metadata
+///
+/// 带 deadline 时写入 `grpc-timeout`（剩余时间，已过期为 0）；
+/// metadata 中已有更小的 `grpc-timeout`（如 `Request::set_timeout`）时保留原值。
 pub fn encode_context_to_metadata(metadata: &mut MetadataMap, ctx: &Context) {
-    if !ctx.request_id().is_empty() {
-        if let Ok(val) = ctx
+    encode_deadline_to_metadata(metadata, ctx);
+    if !ctx.request_id().is_empty()
+        && let Ok(val) = ctx
             .request_id()
             .parse::<tonic::metadata::MetadataValue<tonic::metadata::Ascii>>()
-        {
-            metadata.insert(keys::REQUEST_ID, val);
-        }
+    {
+        metadata.insert(keys::REQUEST_ID, val);
     }
-    if !ctx.trace_id().is_empty() {
-        if let Ok(val) = ctx
+    if !ctx.trace_id().is_empty()
+        && let Ok(val) = ctx
             .trace_id()
             .parse::<tonic::metadata::MetadataValue<tonic::metadata::Ascii>>()
-        {
-            metadata.insert(keys::TRACE_ID, val);
-        }
+    {
+        metadata.insert(keys::TRACE_ID, val);
     }
-    if let Some(tenant_id) = ctx.tenant_id() {
-        if !tenant_id.is_empty() {
-            if let Ok(val) =
-                tenant_id.parse::<tonic::metadata::MetadataValue<tonic::metadata::Ascii>>()
-            {
-                metadata.insert(keys::TENANT_ID, val);
-            }
-        }
+    if let Some(tenant_id) = ctx.tenant_id()
+        && !tenant_id.is_empty()
+        && let Ok(val) = tenant_id.parse::<tonic::metadata::MetadataValue<tonic::metadata::Ascii>>()
+    {
+        metadata.insert(keys::TENANT_ID, val);
     }
-    if let Some(user_id) = ctx.user_id() {
-        if !user_id.is_empty() {
-            if let Ok(val) =
-                user_id.parse::<tonic::metadata::MetadataValue<tonic::metadata::Ascii>>()
-            {
-                metadata.insert(keys::USER_ID, val);
-            }
-        }
+    if let Some(user_id) = ctx.user_id()
+        && !user_id.is_empty()
+        && let Ok(val) = user_id.parse::<tonic::metadata::MetadataValue<tonic::metadata::Ascii>>()
+    {
+        metadata.insert(keys::USER_ID, val);
     }
-    if let Some(device_id) = ctx.device_id() {
-        if !device_id.is_empty() {
-            if let Ok(val) =
-                device_id.parse::<tonic::metadata::MetadataValue<tonic::metadata::Ascii>>()
-            {
-                metadata.insert(keys::DEVICE_ID, val);
-            }
-        }
+    if let Some(device_id) = ctx.device_id()
+        && !device_id.is_empty()
+        && let Ok(val) = device_id.parse::<tonic::metadata::MetadataValue<tonic::metadata::Ascii>>()
+    {
+        metadata.insert(keys::DEVICE_ID, val);
     }
-    if let Some(platform) = ctx.platform() {
-        if !platform.is_empty() {
-            if let Ok(val) =
-                platform.parse::<tonic::metadata::MetadataValue<tonic::metadata::Ascii>>()
-            {
-                metadata.insert(keys::PLATFORM, val);
-            }
-        }
+    if let Some(platform) = ctx.platform()
+        && !platform.is_empty()
+        && let Ok(val) = platform.parse::<tonic::metadata::MetadataValue<tonic::metadata::Ascii>>()
+    {
+        metadata.insert(keys::PLATFORM, val);
+    }
+    if let Some(session_id) = ctx.session_id()
+        && !session_id.is_empty()
+        && let Ok(val) =
+            session_id.parse::<tonic::metadata::MetadataValue<tonic::metadata::Ascii>>()
+    {
+        metadata.insert(keys::SESSION_ID, val);
     }
-    if let Some(session_id) = ctx.session_id() {
-        if !session_id.is_empty() {
-            if let Ok(val) =
-                session_id.parse::<tonic::metadata::MetadataValue<tonic::metadata::Ascii>>()
-            {
-                metadata.insert(keys::SESSION_ID, val);
-            }
+    for (key, value) in trace_context_to_headers(ctx) {
+        if let Ok(val) = value.parse::<tonic::metadata::MetadataValue<tonic::metadata::Ascii>>() {
+            metadata.insert(key, val);
         }
     }
     if let Some(actor) = ctx.actor() {
@@ -135,6 +135,11 @@ pub fn decode_context_from_metadata(metadata: &MetadataMap) -> Option<Ctx> {
     {
         ctx = ctx.with_session_id(session_id);
     }
+    if let Some(trace) =
+        trace_context_from_headers(|key| metadata.get(key).and_then(|v| v.to_str().ok()))
+    {
+        ctx = ctx.with_trace_context(trace);
+    }
     if let Some(actor) = decode_actor_from_metadata(metadata) {
         ctx = ctx.with_actor(actor);
     }
@@ -142,46 +147,67 @@ pub fn decode_context_from_metadata(metadata: &MetadataMap) -> Option<Ctx> {
     Some(Arc::new(ctx))
 }
 
+/// 从 Metadata 读取 `grpc-timeout`
+///
+/// 解码 Context 时不会自动设置 deadline（需要 tokio 运行时启动计时），
+/// 服务端由 `ContextLayer` 调用 `Context::with_timeout` 应用。
+pub fn timeout_from_metadata(metadata: &MetadataMap) -> Option<Duration> {
+    metadata
+        .get(GRPC_TIMEOUT_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_grpc_timeout)
+}
+
+fn encode_deadline_to_metadata(metadata: &mut MetadataMap, ctx: &Context) {
+    if ctx.deadline().is_none() {
+        return;
+    }
+    let remaining = ctx.remaining_time().unwrap_or(Duration::ZERO);
+    if timeout_from_metadata(metadata).is_some_and(|existing| existing <= remaining) {
+        return;
+    }
+    if let Ok(val) = encode_grpc_timeout(remaining)
+        .parse::<tonic::metadata::MetadataValue<tonic::metadata::Ascii>>()
+    {
+        metadata.insert(GRPC_TIMEOUT_HEADER, val);
+    }
+}
+
 fn encode_actor_to_metadata(metadata: &mut MetadataMap, actor: &ActorContext) {
-    if !actor.actor_id().is_empty() {
-        if let Ok(val) = actor
+    if !actor.actor_id().is_empty()
+        && let Ok(val) = actor
             .actor_id()
             .parse::<tonic::metadata::MetadataValue<tonic::metadata::Ascii>>()
-        {
-            metadata.insert(keys::ACTOR_ID, val.clone());
-            if actor.actor_type == ActorType::User {
-                metadata.insert(keys::USER_ID, val);
-            }
+    {
+        metadata.insert(keys::ACTOR_ID, val.clone());
+        if actor.actor_type == ActorType::User {
+            metadata.insert(keys::USER_ID, val);
         }
     }
-    if actor.actor_type != ActorType::Unspecified {
-        if let Ok(val) = format!("{}", i32::from(actor.actor_type))
+    if actor.actor_type != ActorType::Unspecified
+        && let Ok(val) = format!("{}", i32::from(actor.actor_type))
             .parse::<tonic::metadata::MetadataValue<tonic::metadata::Ascii>>()
-        {
-            metadata.insert(keys::ACTOR_TYPE, val);
-        }
+    {
+        metadata.insert(keys::ACTOR_TYPE, val);
     }
     for role in actor.roles().iter() {
-        if !role.is_empty() {
-            if let Ok(val) = role
+        if !role.is_empty()
+            && let Ok(val) = role
                 .as_ref()
                 .parse::<tonic::metadata::MetadataValue<tonic::metadata::Ascii>>()
-            {
-                metadata.append("x-actor-roles", val);
-            }
+        {
+            metadata.append("x-actor-roles", val);
         }
     }
     for (key, value) in actor.attributes().iter() {
         let header_name = format!("x-actor-attr-{}", key.as_ref());
         if let Ok(key_val) =
             header_name.parse::<tonic::metadata::MetadataKey<tonic::metadata::Ascii>>()
-        {
-            if let Ok(val) = value
+            && let Ok(val) = value
                 .as_ref()
                 .parse::<tonic::metadata::MetadataValue<tonic::metadata::Ascii>>()
-            {
-                metadata.insert(key_val, val);
-            }
+        {
+            metadata.insert(key_val, val);
         }
     }
 }
@@ -204,25 +230,87 @@ fn decode_actor_from_metadata(metadata: &MetadataMap) -> Option<ActorContext> {
     let mut actor = ActorContext::new(actor_id).with_type(actor_type);
 
     for val in metadata.get_all("x-actor-roles") {
-        if let Ok(role) = val.to_str() {
-            if !role.is_empty() {
-                actor = actor.with_role(role);
-            }
+        if let Ok(role) = val.to_str()
+            && !role.is_empty()
+        {
+            actor = actor.with_role(role);
         }
     }
 
     for entry in metadata.iter() {
-        match entry {
-            tonic::metadata::KeyAndValueRef::Ascii(key, value) => {
-                if let Some(key_str) = key.as_str().strip_prefix("x-actor-attr-") {
-                    if let Ok(value_str) = value.to_str() {
-                        actor = actor.with_attribute(key_str, value_str);
-                    }
-                }
-            }
-            _ => {}
+        if let tonic::metadata::KeyAndValueRef::Ascii(key, value) = entry
+            && let Some(key_str) = key.as_str().strip_prefix("x-actor-attr-")
+            && let Ok(value_str) = value.to_str()
+        {
+            actor = actor.with_attribute(key_str, value_str);
         }
     }
 
     Some(actor)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn encodes_remaining_time_as_grpc_timeout() {
+        let ctx = Context::with_request_id("req-1").with_timeout(Duration::from_secs(5));
+        let mut metadata = MetadataMap::new();
+        encode_context_to_metadata(&mut metadata, &ctx);
+
+        let timeout = timeout_from_metadata(&metadata).expect("grpc-timeout");
+        assert!(timeout <= Duration::from_secs(5));
+        assert!(timeout > Duration::from_secs(4));
+    }
+
+    #[tokio::test]
+    async fn keeps_smaller_existing_timeout() {
+        let ctx = Context::with_request_id("req-1").with_timeout(Duration::from_secs(5));
+        let mut metadata = MetadataMap::new();
+        metadata.insert(GRPC_TIMEOUT_HEADER, "100m".parse().unwrap());
+        encode_context_to_metadata(&mut metadata, &ctx);
+        assert_eq!(
+            timeout_from_metadata(&metadata),
+            Some(Duration::from_millis(100))
+        );
+    }
+
+    #[test]
+    fn traceparent_roundtrip_through_metadata() {
+        let mut metadata = MetadataMap::new();
+        metadata.insert(
+            keys::TRACEPARENT,
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+                .parse()
+                .unwrap(),
+        );
+        metadata.insert(keys::BAGGAGE, "tenant=t1".parse().unwrap());
+
+        let ctx = decode_context_from_metadata(&metadata).unwrap();
+        assert_eq!(ctx.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(
+            ctx.trace_context().and_then(|t| t.baggage_item("tenant")),
+            Some("t1")
+        );
+
+        let mut out = MetadataMap::new();
+        encode_context_to_metadata(&mut out, &ctx);
+        assert_eq!(
+            out.get(keys::TRACEPARENT).and_then(|v| v.to_str().ok()),
+            Some("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+        );
+        assert_eq!(
+            out.get(keys::TRACE_ID).and_then(|v| v.to_str().ok()),
+            Some("4bf92f3577b34da6a3ce929d0e0e4736")
+        );
+    }
+
+    #[test]
+    fn no_deadline_writes_no_timeout() {
+        let ctx = Context::with_request_id("req-1");
+        let mut metadata = MetadataMap::new();
+        encode_context_to_metadata(&mut metadata, &ctx);
+        assert!(metadata.get(GRPC_TIMEOUT_HEADER).is_none());
+    }
+}
@@ -3,9 +3,14 @@
 //! 提供基础的 gRPC 工具函数,不依赖其他模块
 
 mod context_utils;
+mod grpc_timeout;
 mod metadata_codec;
 
-pub use metadata_codec::{decode_context_from_metadata, encode_context_to_metadata};
+pub use crate::circuit_breaker::status_from_response;
+pub use grpc_timeout::{GRPC_TIMEOUT_HEADER, encode_grpc_timeout, parse_grpc_timeout};
+pub use metadata_codec::{
+    decode_context_from_metadata, encode_context_to_metadata, timeout_from_metadata,
+};
 
 pub use context_utils::{
     create_traced_metadata, error_to_status, extract_ctx_from_request_opt, extract_device_id,
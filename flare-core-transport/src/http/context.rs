@@ -1,9 +1,14 @@
 use axum::http::HeaderMap;
 use flare_core_base::context::{Context, Ctx, keys};
+use flare_core_base::utils::{trace_context_from_headers, trace_context_to_headers};
+use flare_core_infra::ctx_with_current_span;
 use std::sync::Arc;
 use uuid::Uuid;
 
 /// 从 HTTP headers 构建统一 Context。
+///
+/// 带合法 W3C `traceparent` 时以其 trace-id 为准（`x-trace-id` 为同一 trace 的 UUID 形式时保留），
+/// `tracestate` / `baggage` 随 [`TraceContext`](flare_core_base::context::TraceContext) 一并还原。
 pub trait ContextFromHeaders {
     fn from_headers(headers: &HeaderMap) -> Self;
     fn require_user_id(&self) -> Result<&str, &'static str>;
@@ -13,10 +18,17 @@ impl ContextFromHeaders for Ctx {
     fn from_headers(headers: &HeaderMap) -> Self {
         let request_id =
             header_value(headers, keys::REQUEST_ID).unwrap_or_else(|| Uuid::new_v4().to_string());
-        let trace_id =
-            header_value(headers, keys::TRACE_ID).unwrap_or_else(|| Uuid::new_v4().to_string());
+        let trace = trace_context_from_headers(|key| {
+            headers.get(key).and_then(|value| value.to_str().ok())
+        });
+        let trace_id = header_value(headers, keys::TRACE_ID)
+            .or_else(|| trace.as_ref().map(|t| t.trace_id().to_string()))
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
 
         let mut ctx = Context::with_request_id(request_id).with_trace_id(trace_id);
+        if let Some(trace) = trace {
+            ctx = ctx.with_trace_context(trace);
+        }
 
         if let Some(user_id) = header_value(headers, keys::USER_ID) {
             ctx = ctx.with_user_id(user_id);
@@ -46,6 +58,23 @@ impl ContextFromHeaders for Ctx {
     }
 }
 
+/// 将 Context 的追踪信息写入 HTTP headers（`x-trace-id` + W3C traceparent / tracestate / baggage）
+///
+/// 用于发起下游 HTTP 调用，已存在的同名头会被覆盖；traceparent 的 span id 取当前 span。
+pub fn inject_trace_headers(headers: &mut HeaderMap, ctx: &Context) {
+    let ctx = &ctx_with_current_span(ctx);
+    if !ctx.trace_id().is_empty()
+        && let Ok(value) = ctx.trace_id().parse()
+    {
+        headers.insert(keys::TRACE_ID, value);
+    }
+    for (key, value) in trace_context_to_headers(ctx) {
+        if let Ok(value) = value.parse() {
+            headers.insert(key, value);
+        }
+    }
+}
+
 fn header_value(headers: &HeaderMap, name: &str) -> Option<String> {
     headers
         .get(name)
@@ -72,4 +101,29 @@ mod tests {
         assert_eq!(ctx.request_id(), "request-789");
         assert_eq!(ctx.user_id(), Some("user-012"));
     }
+
+    #[test]
+    fn context_from_headers_reads_w3c_trace_context() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            keys::TRACEPARENT,
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00"
+                .parse()
+                .unwrap(),
+        );
+        headers.insert(keys::TRACESTATE, "vendor=abc".parse().unwrap());
+
+        let ctx = Ctx::from_headers(&headers);
+        assert_eq!(ctx.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        let trace = ctx.trace_context().expect("trace context");
+        assert!(!trace.sampled);
+
+        let mut out = HeaderMap::new();
+        inject_trace_headers(&mut out, &ctx);
+        assert_eq!(
+            out.get(keys::TRACEPARENT).unwrap(),
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00"
+        );
+        assert_eq!(out.get(keys::TRACESTATE).unwrap(), "vendor=abc");
+    }
 }
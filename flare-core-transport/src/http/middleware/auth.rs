@@ -10,14 +10,16 @@ use axum::{
 use std::sync::Arc;
 use tracing::{debug, warn};
 
+use crate::http::context::ContextFromHeaders;
 use crate::http::response::ApiResponse;
-use flare_core_base::context::keys;
+use flare_core_base::context::{ActorContext, Ctx, keys};
 use flare_core_base::error::ErrorCode;
 use flare_core_infra::auth::TokenService;
 
 /// 认证中间件
 ///
-/// 使用 TokenService 验证 JWT Token 并将用户信息注入到请求 Header 中
+/// 使用 TokenService 验证 JWT Token 并将用户信息注入到请求 Header 中，
+/// 同时把认证后的 [`Ctx`]（actor 为 token 主体）放入请求 extensions，供限流等内层中间件使用
 ///
 /// # Example
 ///
@@ -88,19 +90,20 @@ pub async fn auth_middleware(
             }
 
             // 注入设备 ID
-            if let Some(device_id) = &claims.device_id {
-                if let Ok(device_id_value) = HeaderValue::from_str(device_id) {
-                    headers.insert(keys::DEVICE_ID, device_id_value);
-                }
+            if let Some(device_id) = &claims.device_id
+                && let Ok(device_id_value) = HeaderValue::from_str(device_id)
+            {
+                headers.insert(keys::DEVICE_ID, device_id_value);
             }
 
             // 注入租户 ID
-            if let Some(tenant_id) = &claims.tenant_id {
-                if let Ok(tenant_id_value) = HeaderValue::from_str(tenant_id) {
-                    headers.insert(keys::TENANT_ID, tenant_id_value);
-                }
+            if let Some(tenant_id) = &claims.tenant_id
+                && let Ok(tenant_id_value) = HeaderValue::from_str(tenant_id)
+            {
+                headers.insert(keys::TENANT_ID, tenant_id_value);
             }
 
+            insert_authenticated_context(&mut request, &claims.sub);
             next.run(request).await
         }
         Err(e) => {
@@ -139,8 +142,8 @@ pub async fn optional_auth_middleware(
     };
 
     // 2. 解析 Bearer Token
-    let token = if auth_header.starts_with("Bearer ") {
-        &auth_header[7..]
+    let token = if let Some(token) = auth_header.strip_prefix("Bearer ") {
+        token
     } else {
         // Token 格式错误,但这是可选认证,所以继续执行
         return next.run(request).await;
@@ -163,18 +166,19 @@ pub async fn optional_auth_middleware(
                 headers.insert(keys::USER_ID, user_id);
             }
 
-            if let Some(device_id) = &claims.device_id {
-                if let Ok(device_id_value) = HeaderValue::from_str(device_id) {
-                    headers.insert(keys::DEVICE_ID, device_id_value);
-                }
+            if let Some(device_id) = &claims.device_id
+                && let Ok(device_id_value) = HeaderValue::from_str(device_id)
+            {
+                headers.insert(keys::DEVICE_ID, device_id_value);
             }
 
-            if let Some(tenant_id) = &claims.tenant_id {
-                if let Ok(tenant_id_value) = HeaderValue::from_str(tenant_id) {
-                    headers.insert(keys::TENANT_ID, tenant_id_value);
-                }
+            if let Some(tenant_id) = &claims.tenant_id
+                && let Ok(tenant_id_value) = HeaderValue::from_str(tenant_id)
+            {
+                headers.insert(keys::TENANT_ID, tenant_id_value);
             }
 
+            insert_authenticated_context(&mut request, &claims.sub);
             next.run(request).await
         }
         Err(e) => {
@@ -184,3 +188,10 @@ pub async fn optional_auth_middleware(
         }
     }
 }
+
+/// 由注入后的身份头构建 Context，以 token 主体作为 actor 放入请求 extensions
+fn insert_authenticated_context(request: &mut Request, subject: &str) {
+    let ctx = Ctx::from_headers(request.headers());
+    let ctx: Ctx = Arc::new(ctx.with_actor(ActorContext::new(subject)));
+    request.extensions_mut().insert(ctx);
+}
@@ -5,5 +5,7 @@ mod rate_limit;
 mod tracing;
 
 pub use auth::{auth_middleware, optional_auth_middleware};
-pub use rate_limit::{RateLimitLayer, RateLimiter};
+pub use rate_limit::{
+    RateLimitDecision, RateLimitKey, RateLimitLayer, RateLimitService, RateLimiter,
+};
 pub use tracing::tracing_middleware;
@@ -1,70 +1,467 @@
 //! HTTP 限流中间件
+//!
+//! 按用户 / 租户 / 客户端 IP 的令牌桶限流。用户与租户只取认证中间件放入请求 extensions 的
+//! [`Ctx`]，从不读取客户端可随意改写的身份头；未认证的请求按客户端 IP 限流。
+//! 超限时通过 `ApiResponse` 返回 429，并附带 `Retry-After` 与 `X-RateLimit-*` 头。
+//!
+//! ```rust,ignore
+//! use flare_server_core::http::middleware::{RateLimitKey, RateLimitLayer, auth_middleware};
+//!
+//! // 限流层需位于认证中间件之内，才能读到认证后的 Context
+//! let app = Router::new()
+//!     .route("/api/messages", post(handler))
+//!     .layer(RateLimitLayer::new(20, 40).keyed_by(RateLimitKey::UserId))
+//!     .layer(middleware::from_fn(auth_middleware))
+//!     .layer(Extension(token_service));
+//!
+//! // 需要按 IP 限流时使用 connect_info 启动，以便读取对端地址
+//! axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
+//! ```
 
-use std::collections::HashMap;
+use axum::{
+    Json,
+    extract::{ConnectInfo, Request},
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use flare_core_base::context::Ctx;
+use flare_core_base::error::ErrorCode;
+use flare_core_infra::ratelimit::{InMemoryRateLimitStore, RateLimitQuota, RateLimitStore};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
 use std::sync::Arc;
-use std::time::Instant;
-use tokio::sync::Mutex;
+use std::task::{Context as TaskContext, Poll};
+use std::time::Duration;
+use tower::{Layer, Service};
+use tracing::{debug, warn};
 
-/// 简单的限流器
-pub struct RateLimiter {
-    requests_per_second: u32,
-    burst_capacity: u32,
-    clients: Arc<Mutex<HashMap<String, ClientState>>>,
-}
+use crate::http::response::ApiResponse;
+
+pub use flare_core_infra::ratelimit::RateLimitDecision;
 
-struct ClientState {
-    tokens: u32,
-    last_update: Instant,
+static X_RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+static X_RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+static X_RATELIMIT_RESET: HeaderName = HeaderName::from_static("x-ratelimit-reset");
+
+/// 令牌桶限流器
+///
+/// 由 [`RateLimitStore`] 承载令牌桶：默认进程内存储，多实例部署时换成
+/// `RedisRateLimitStore` 共享配额。存储故障时放行（fail-open）并记录告警。
+pub struct RateLimiter {
+    quota: RateLimitQuota,
+    store: Arc<dyn RateLimitStore>,
+    /// 默认进程内存储（使用外部存储时为 `None`）
+    memory: Option<Arc<InMemoryRateLimitStore>>,
 }
 
 impl RateLimiter {
     pub fn new(requests_per_second: u32, burst_capacity: u32) -> Self {
+        let memory = Arc::new(InMemoryRateLimitStore::new());
         Self {
-            requests_per_second,
-            burst_capacity,
-            clients: Arc::new(Mutex::new(HashMap::new())),
+            memory: Some(memory.clone()),
+            ..Self::with_store(memory, requests_per_second, burst_capacity)
+        }
+    }
+
+    /// 使用指定的限流存储
+    pub fn with_store(
+        store: Arc<dyn RateLimitStore>,
+        requests_per_second: u32,
+        burst_capacity: u32,
+    ) -> Self {
+        Self {
+            quota: RateLimitQuota::new(requests_per_second, burst_capacity),
+            store,
+            memory: None,
         }
     }
 
+    /// 设置空闲客户端条目的回收时间
+    ///
+    /// 仅作用于默认的进程内存储；外部存储自行管理过期。
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        if self.memory.is_some() {
+            let memory = Arc::new(InMemoryRateLimitStore::with_idle_timeout(idle_timeout));
+            self.store = memory.clone();
+            self.memory = Some(memory);
+        }
+        self
+    }
+
+    /// 当前跟踪的客户端数量（外部存储时为 0）
+    pub async fn tracked_clients(&self) -> usize {
+        self.memory.as_ref().map_or(0, |memory| memory.len())
+    }
+
     pub async fn check(&self, client_id: &str) -> bool {
-        let mut clients = self.clients.lock().await;
-        let now = Instant::now();
-
-        let state = clients.entry(client_id.to_string()).or_insert(ClientState {
-            tokens: self.burst_capacity,
-            last_update: now,
-        });
-
-        // 补充令牌
-        let elapsed = now.duration_since(state.last_update);
-        let tokens_to_add = (elapsed.as_secs_f64() * self.requests_per_second as f64) as u32;
-        state.tokens = (state.tokens + tokens_to_add).min(self.burst_capacity);
-        state.last_update = now;
-
-        // 检查并消耗令牌
-        if state.tokens > 0 {
-            state.tokens -= 1;
-            true
-        } else {
-            false
+        self.acquire(client_id).await.allowed
+    }
+
+    /// 尝试消耗一个令牌并返回完整判定信息
+    pub async fn acquire(&self, client_id: &str) -> RateLimitDecision {
+        match self.store.acquire(client_id, &self.quota).await {
+            Ok(decision) => decision,
+            Err(err) => {
+                warn!(error = %err, client_id, "Rate limit store failed, allowing request");
+                self.quota.decision(true, f64::from(self.quota.burst))
+            }
         }
     }
 }
 
+/// 限流键来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateLimitKey {
+    /// 依次尝试认证用户、认证租户、客户端 IP
+    #[default]
+    Auto,
+    /// 按认证用户（未认证时退化为客户端 IP）
+    UserId,
+    /// 按认证租户（未认证时退化为客户端 IP）
+    TenantId,
+    /// 按客户端 IP
+    RemoteIp,
+}
+
+impl RateLimitKey {
+    fn extract(self, req: &Request, forwarded_hops: usize) -> String {
+        // 只信任认证中间件放入 extensions 的 Context
+        let ctx = req.extensions().get::<Ctx>();
+        let user = || {
+            ctx.and_then(|c| {
+                c.user_id()
+                    .filter(|u| !u.is_empty())
+                    .or_else(|| c.actor().map(|a| a.actor_id()))
+                    .filter(|u| !u.is_empty())
+            })
+            .map(|u| format!("user:{u}"))
+        };
+        let tenant = || {
+            ctx.and_then(|c| c.tenant_id())
+                .filter(|t| !t.is_empty())
+                .map(|t| format!("tenant:{t}"))
+        };
+        let key = match self {
+            RateLimitKey::Auto => user().or_else(tenant),
+            RateLimitKey::UserId => user(),
+            RateLimitKey::TenantId => tenant(),
+            RateLimitKey::RemoteIp => None,
+        };
+        key.unwrap_or_else(|| match remote_ip(req, forwarded_hops) {
+            Some(ip) => format!("ip:{ip}"),
+            None => "ip:unknown".to_string(),
+        })
+    }
+}
+
+/// 客户端 IP：可信代理追加的 `X-Forwarded-For` 条目优先，其次是连接对端地址
+///
+/// 每个可信代理在末尾追加一跳，因此取倒数第 `forwarded_hops` 个条目；更靠左的条目由客户端
+/// 控制，不能作为限流键。条目数不足时说明请求未经过全部可信代理，退回对端地址。
+fn remote_ip(req: &Request, forwarded_hops: usize) -> Option<String> {
+    if forwarded_hops > 0 {
+        let forwarded = req
+            .headers()
+            .get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| {
+                let entries: Vec<&str> = v.split(',').map(str::trim).collect();
+                entries
+                    .len()
+                    .checked_sub(forwarded_hops)
+                    .map(|index| entries[index])
+            })
+            .filter(|v| !v.is_empty());
+        if let Some(ip) = forwarded {
+            return Some(ip.to_string());
+        }
+    }
+    req.extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+}
+
 /// 限流层
 #[derive(Clone)]
 pub struct RateLimitLayer {
     limiter: Arc<RateLimiter>,
+    key: RateLimitKey,
+    /// 信任的 `X-Forwarded-For` 代理跳数（0 表示不信任）
+    forwarded_hops: usize,
 }
 
 impl RateLimitLayer {
     pub fn new(requests_per_second: u32, burst_capacity: u32) -> Self {
+        Self::from_limiter(Arc::new(RateLimiter::new(
+            requests_per_second,
+            burst_capacity,
+        )))
+    }
+
+    /// 使用已有的限流器（可在多个路由间共享）
+    pub fn from_limiter(limiter: Arc<RateLimiter>) -> Self {
         Self {
-            limiter: Arc::new(RateLimiter::new(requests_per_second, burst_capacity)),
+            limiter,
+            key: RateLimitKey::default(),
+            forwarded_hops: 0,
         }
     }
 
+    /// 设置限流键来源
+    pub fn keyed_by(mut self, key: RateLimitKey) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// 信任 `X-Forwarded-For` 最右侧条目（即可信反向代理追加的一跳）作为客户端 IP
+    ///
+    /// 仅在单层可信反向代理之后启用；多层代理使用 [`Self::trust_forwarded_hops`]。
+    pub fn trust_forwarded_for(self) -> Self {
+        self.trust_forwarded_hops(1)
+    }
+
+    /// 信任最后 `hops` 个代理追加的 `X-Forwarded-For` 条目，取倒数第 `hops` 个作为客户端 IP
+    pub fn trust_forwarded_hops(mut self, hops: usize) -> Self {
+        self.forwarded_hops = hops;
+        self
+    }
+
     pub fn limiter(&self) -> Arc<RateLimiter> {
         self.limiter.clone()
     }
 }
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        RateLimitService {
+            inner: service,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// 限流服务
+#[derive(Clone)]
+pub struct RateLimitService<S> {
+    inner: S,
+    layer: RateLimitLayer,
+}
+
+impl<S> Service<Request> for RateLimitService<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let limiter = self.layer.limiter.clone();
+        let key = self.layer.key.extract(&req, self.layer.forwarded_hops);
+
+        Box::pin(async move {
+            let decision = limiter.acquire(&key).await;
+            if !decision.allowed {
+                debug!(
+                    key = %key,
+                    path = %req.uri().path(),
+                    retry_after_ms = decision.retry_after.as_millis() as u64,
+                    "HTTP request rate limited"
+                );
+                let body: ApiResponse<()> = ApiResponse::from_code(ErrorCode::HttpTooManyRequests);
+                let mut response = (StatusCode::TOO_MANY_REQUESTS, Json(body)).into_response();
+                let headers = response.headers_mut();
+                headers.insert(
+                    axum::http::header::RETRY_AFTER,
+                    HeaderValue::from(ceil_secs(decision.retry_after)),
+                );
+                insert_rate_limit_headers(headers, &decision);
+                return Ok(response);
+            }
+
+            let mut response = inner.call(req).await?;
+            insert_rate_limit_headers(response.headers_mut(), &decision);
+            Ok(response)
+        })
+    }
+}
+
+fn insert_rate_limit_headers(headers: &mut HeaderMap, decision: &RateLimitDecision) {
+    headers.insert(X_RATELIMIT_LIMIT.clone(), HeaderValue::from(decision.limit));
+    headers.insert(
+        X_RATELIMIT_REMAINING.clone(),
+        HeaderValue::from(decision.remaining),
+    );
+    headers.insert(
+        X_RATELIMIT_RESET.clone(),
+        HeaderValue::from(ceil_secs(decision.reset_after)),
+    );
+}
+
+/// 向上取整到秒（`Retry-After` 只支持整秒）
+fn ceil_secs(d: Duration) -> u64 {
+    d.as_secs() + u64::from(d.subsec_nanos() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, body::Body, routing::get};
+    use flare_core_base::context::{ActorContext, Context, keys};
+    use tower::ServiceExt;
+
+    /// 模拟认证中间件：认证用户以 Context 形式放入 extensions
+    fn request(user: Option<&str>) -> Request {
+        let mut req = Request::builder().uri("/").body(Body::empty()).unwrap();
+        if let Some(user) = user {
+            let ctx = Context::root()
+                .with_user_id(user)
+                .with_actor(ActorContext::new(user));
+            req.extensions_mut().insert(Arc::new(ctx) as Ctx);
+        }
+        req
+    }
+
+    #[tokio::test]
+    async fn fractional_refill_is_not_truncated() {
+        let limiter = RateLimiter::new(4, 1);
+        assert!(limiter.check("c").await);
+        assert!(!limiter.check("c").await);
+
+        // 每次只补充 0.6 个令牌，累计后应可放行
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(!limiter.check("c").await);
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(limiter.check("c").await);
+    }
+
+    #[tokio::test]
+    async fn evicts_idle_clients() {
+        let limiter = RateLimiter::new(10, 1).with_idle_timeout(Duration::from_millis(30));
+        limiter.check("a").await;
+        limiter.check("b").await;
+        assert_eq!(limiter.tracked_clients().await, 2);
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        limiter.check("c").await;
+        assert_eq!(limiter.tracked_clients().await, 1);
+    }
+
+    struct FailingStore;
+
+    #[async_trait::async_trait]
+    impl RateLimitStore for FailingStore {
+        async fn acquire(
+            &self,
+            _key: &str,
+            _quota: &RateLimitQuota,
+        ) -> Result<RateLimitDecision, flare_core_infra::ratelimit::RateLimitError> {
+            Err(flare_core_infra::ratelimit::RateLimitError::Backend(
+                "connection refused".into(),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn store_failure_fails_open() {
+        let limiter = RateLimiter::with_store(Arc::new(FailingStore), 1, 2);
+        assert_eq!(limiter.tracked_clients().await, 0);
+        for _ in 0..3 {
+            let decision = limiter.acquire("a").await;
+            assert!(decision.allowed);
+            assert_eq!(decision.limit, 2);
+            assert_eq!(decision.remaining, 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn middleware_returns_429_with_headers() {
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(RateLimitLayer::new(1, 1).keyed_by(RateLimitKey::UserId));
+
+        let ok = app.clone().oneshot(request(Some("u1"))).await.unwrap();
+        assert_eq!(ok.status(), StatusCode::OK);
+        assert_eq!(ok.headers()[&X_RATELIMIT_LIMIT], "1");
+        assert_eq!(ok.headers()[&X_RATELIMIT_REMAINING], "0");
+
+        let limited = app.clone().oneshot(request(Some("u1"))).await.unwrap();
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(limited.headers()[axum::http::header::RETRY_AFTER], "1");
+        let body = axum::body::to_bytes(limited.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], ErrorCode::HttpTooManyRequests as i32);
+
+        // 其他用户使用独立的桶
+        let other = app.oneshot(request(Some("u2"))).await.unwrap();
+        assert_eq!(other.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn auto_key_falls_back_to_remote_ip() {
+        let mut req = request(None);
+        req.extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 7], 4000))));
+        assert_eq!(RateLimitKey::Auto.extract(&req, 0), "ip:10.0.0.7");
+
+        req.headers_mut().insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("1.2.3.4, 10.0.0.1"),
+        );
+        assert_eq!(RateLimitKey::Auto.extract(&req, 0), "ip:10.0.0.7");
+        assert_eq!(RateLimitKey::Auto.extract(&req, 1), "ip:10.0.0.1");
+        assert_eq!(RateLimitKey::Auto.extract(&req, 2), "ip:1.2.3.4");
+        // 条目少于可信跳数：请求未经过全部代理，退回对端地址
+        assert_eq!(RateLimitKey::Auto.extract(&req, 3), "ip:10.0.0.7");
+
+        let ctx = Context::root().with_tenant_id("t1");
+        req.extensions_mut().insert(Arc::new(ctx) as Ctx);
+        assert_eq!(RateLimitKey::Auto.extract(&req, 0), "tenant:t1");
+    }
+
+    #[test]
+    fn spoofed_forwarded_for_does_not_change_key() {
+        let mut req = request(None);
+        req.headers_mut().insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("1.1.1.1, 203.0.113.9"),
+        );
+        let key = RateLimitKey::Auto.extract(&req, 1);
+        assert_eq!(key, "ip:203.0.113.9");
+
+        // 客户端改写最左侧条目，代理追加的一跳不变
+        req.headers_mut().insert(
+            "x-forwarded-for",
+            HeaderValue::from_static("9.9.9.9, 203.0.113.9"),
+        );
+        assert_eq!(RateLimitKey::Auto.extract(&req, 1), key);
+    }
+
+    #[test]
+    fn unauthenticated_identity_headers_are_ignored() {
+        let mut req = request(None);
+        req.extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 7], 4000))));
+        req.headers_mut()
+            .insert(keys::USER_ID, HeaderValue::from_static("forged"));
+        req.headers_mut()
+            .insert(keys::TENANT_ID, HeaderValue::from_static("forged"));
+        assert_eq!(RateLimitKey::Auto.extract(&req, 0), "ip:10.0.0.7");
+        assert_eq!(RateLimitKey::UserId.extract(&req, 0), "ip:10.0.0.7");
+
+        let authed = request(Some("u1"));
+        assert_eq!(RateLimitKey::Auto.extract(&authed, 0), "user:u1");
+    }
+}
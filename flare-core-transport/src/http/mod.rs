@@ -7,7 +7,7 @@ pub mod middleware;
 pub mod response;
 
 pub use adapter::{HttpAdapter, HttpAdapterBuilder};
-pub use context::ContextFromHeaders;
+pub use context::{ContextFromHeaders, inject_trace_headers};
 pub use error::{HttpApiError, Result};
 pub use middleware::*;
 pub use response::*;
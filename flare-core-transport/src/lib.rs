@@ -25,6 +25,14 @@ pub mod grpc;
 #[cfg(feature = "http")]
 pub mod http;
 
+// gRPC status classification shared by retry policies and the circuit breaker.
+#[cfg(any(feature = "grpc", feature = "discovery"))]
+pub mod status;
+
+// Circuit breaker shared by gRPC middleware and service discovery.
+#[cfg(any(feature = "grpc", feature = "discovery"))]
+pub mod circuit_breaker;
+
 // Service discovery integration.
 #[cfg(feature = "discovery")]
 pub mod discovery;
@@ -0,0 +1,12 @@
+//! gRPC 状态码分类
+//!
+//! 重试策略（`grpc::retry`）与熔断器（[`crate::circuit_breaker`]）共用同一套瞬时故障判定，
+//! 两者对同一个状态码的结论保持一致。
+
+/// 可重试（瞬时故障）的状态码：Unavailable、DeadlineExceeded、ResourceExhausted
+pub fn is_retryable_code(code: tonic::Code) -> bool {
+    matches!(
+        code,
+        tonic::Code::Unavailable | tonic::Code::DeadlineExceeded | tonic::Code::ResourceExhausted
+    )
+}